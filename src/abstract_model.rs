@@ -1,20 +1,30 @@
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
 use tracing::debug;
 
-use stateright::{Model, Property};
+use stateright::{Expectation, Model, Property};
 
 use crate::arbitrary_client::ArbitraryClient;
 use crate::arbitrary_client::ArbitraryClientAction;
 use crate::controller::util::get_node_condition;
 use crate::controller::{Controller, Controllers};
+use crate::controller_properties::Properties;
+use crate::coverage::Coverage;
+use crate::reduction;
 use crate::resources::Node;
 use crate::resources::{
-    ConditionStatus, ControllerRevision, Deployment, Job, NodeConditionType, PersistentVolumeClaim,
-    Pod, ReplicaSet, ResourceQuantities, StatefulSet,
+    ConditionStatus, ControllerRevision, CronJob, DaemonSet, Deployment, HorizontalPodAutoscaler,
+    Job, Lease, Meta, Namespace, NodeConditionType, PersistentVolumeClaim, Pod, PropagationPolicy,
+    ReplicaSet, ResourceQuantities, StatefulSet,
 };
 use crate::state::RawState;
-use crate::state::{history::ConsistencySetup, revision::Revision, State};
+use crate::state::{
+    history::{ConsistencySetup, ReadMode},
+    revision::Revision,
+    State,
+};
+use crate::vacuity::Vacuity;
 
 #[derive(derivative::Derivative)]
 #[derivative(Debug)]
@@ -25,17 +35,39 @@ pub struct AbstractModelCfg {
     pub initial_state: RawState,
     /// The consistency level of the state.
     pub consistency_level: ConsistencySetup,
+    /// Read modes for individual controllers (by index), defaulting to any valid revision.
+    pub read_modes: BTreeMap<usize, ReadMode>,
+    /// Maximum number of writes controllers can issue after the last perturbation before they
+    /// are considered to be fighting, if checking for quiescence.
+    pub quiescence_bound: Option<usize>,
+    /// Whether to only explore one interleaving of independent actions.
+    pub partial_order_reduction: bool,
+    /// The number of times the clock can tick.
+    pub clock_ticks: u32,
     #[derivative(Debug = "ignore")]
-    pub properties: Vec<Property<AbstractModel>>,
+    pub properties: Properties,
 }
 
+/// Name of the property checking that controllers settle after perturbations stop.
+pub const QUIESCENCE_PROPERTY: &str =
+    "steady state: controllers stop writing after perturbations stop";
+
 #[derive(derivative::Derivative)]
 #[derivative(Debug)]
 pub struct AbstractModel {
     pub controllers: Vec<Controllers>,
+    pub read_modes: BTreeMap<usize, ReadMode>,
+    pub quiescence_bound: Option<usize>,
+    pub partial_order_reduction: bool,
+    pub clock_ticks: u32,
     pub initial_states: Vec<State>,
+    /// The properties to check, including the built-in ones.
     #[derivative(Debug = "ignore")]
     pub properties: Vec<Property<Self>>,
+    /// Whether the properties that are implications have had their antecedent hold while checking.
+    pub vacuity: Arc<Vacuity>,
+    /// What the transitions taken while checking exercised.
+    pub coverage: Arc<Coverage>,
 }
 
 impl AbstractModel {
@@ -44,13 +76,302 @@ impl AbstractModel {
         for c in &cfg.controllers {
             state.add_controller(c.new_state());
         }
+        if let Some(bound) = cfg.quiescence_bound {
+            state.track_quiescence(bound);
+        }
         let initial_states = vec![state];
+        let mut properties = cfg.properties;
+        properties.add(
+            Expectation::Always,
+            "all resources have unique names",
+            |_model, state| {
+                let state = state.latest();
+                fn key<T: Meta>(r: &T) -> (&str, &str) {
+                    (&r.metadata().namespace, &r.metadata().name)
+                }
+                all_unique(state.nodes.iter().map(key))
+                    && all_unique(state.pods.iter().map(key))
+                    && all_unique(state.replicasets.iter().map(key))
+                    && all_unique(state.deployments.iter().map(key))
+                    && all_unique(state.statefulsets.iter().map(key))
+                    && all_unique(state.daemonsets.iter().map(key))
+                    && all_unique(state.controller_revisions.iter().map(key))
+                    && all_unique(state.persistent_volume_claims.iter().map(key))
+                    && all_unique(state.jobs.iter().map(key))
+                    && all_unique(state.cronjobs.iter().map(key))
+                    && all_unique(state.horizontal_pod_autoscalers.iter().map(key))
+                    && all_unique(state.leases.iter().map(key))
+                    && all_unique(state.namespaces.iter().map(key))
+            },
+        );
+        if cfg.quiescence_bound.is_some() {
+            properties.add(
+                Expectation::Always,
+                QUIESCENCE_PROPERTY,
+                |model, state| match (model.quiescence_bound, state.writes_since_perturbation()) {
+                    (Some(bound), Some(writes)) => writes <= bound,
+                    _ => true,
+                },
+            );
+        }
+        let vacuity = Arc::new(Vacuity::new(&properties));
+        let coverage = Arc::new(Coverage::new(&cfg.controllers, vacuity.clone()));
         Self {
             controllers: cfg.controllers,
+            read_modes: cfg.read_modes,
+            quiescence_bound: cfg.quiescence_bound,
+            partial_order_reduction: cfg.partial_order_reduction,
+            clock_ticks: cfg.clock_ticks,
             initial_states,
-            properties: cfg.properties,
+            properties: properties.into_iter().collect(),
+            vacuity,
+            coverage,
         }
     }
+
+    /// The read mode that the controller at the given index uses.
+    pub fn read_mode(&self, controller: usize) -> ReadMode {
+        self.read_modes
+            .get(&controller)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// The name of the controller that takes the action, if any.
+    pub fn actor(&self, action: &Action) -> Option<String> {
+        match action {
+            Action::ControllerStep(_, i)
+            | Action::ControllerRestart(i)
+            | Action::NodeRestart(i)
+            | Action::InformerEvent(i)
+            | Action::InformerRelist(i) => Some(self.controllers[*i].name()),
+            Action::ArbitraryStep(_)
+            | Action::Replicate(_)
+            | Action::Partition(_)
+            | Action::Heal(_)
+            | Action::TickClock => None,
+        }
+    }
+
+    /// All of the actions enabled in the state, before any reduction.
+    pub fn enabled_actions(&self, state: &State, actions: &mut Vec<Action>) {
+        for (i, controller) in self.controllers.iter().enumerate() {
+            let cstate = state.get_controller(i);
+            let min_revision = controller.min_revision_accepted(cstate);
+            for revision in state.revisions(i, self.read_mode(i), min_revision) {
+                debug!(?revision, "Adding revision choice");
+                actions.push(Action::ControllerStep(revision, i));
+            }
+        }
+
+        // arbitrary client
+        let latest_view = state.latest();
+        let arbitrary_actions = ArbitraryClient::actions(&latest_view)
+            .into_iter()
+            .map(Action::ArbitraryStep);
+        actions.extend(arbitrary_actions);
+
+        for (i, controller) in self.controllers.iter().enumerate() {
+            if matches!(controller, Controllers::Node(_)) {
+                // skip nodes for now
+                continue;
+            }
+            if state.get_controller(i) != &controller.new_state() {
+                actions.push(Action::ControllerRestart(i));
+            }
+        }
+
+        // at max revision as this isn't a controller event
+        for node in latest_view.nodes.iter() {
+            if let Some(cond) =
+                get_node_condition(&node.status.conditions, NodeConditionType::Ready)
+            {
+                if cond.status == ConditionStatus::True {
+                    // find the controller index for the corresponding node
+                    for (i, controller) in self.controllers.iter().enumerate() {
+                        if let Controllers::Node(n) = controller {
+                            if n.name == node.metadata.name {
+                                // match
+                                actions.push(Action::NodeRestart(i));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        for replica in state.lagging_replicas() {
+            actions.push(Action::Replicate(replica));
+        }
+        for replica in state.partitionable_replicas() {
+            actions.push(Action::Partition(replica));
+        }
+        for replica in state.partitioned_replicas() {
+            actions.push(Action::Heal(replica));
+        }
+
+        for controller in state.pending_informers() {
+            actions.push(Action::InformerEvent(controller));
+            actions.push(Action::InformerRelist(controller));
+        }
+
+        if state.clock_ticks() < self.clock_ticks {
+            actions.push(Action::TickClock);
+        }
+    }
+
+    /// The operation that taking the action in the state writes to the datastore, if any.
+    pub fn operation(&self, state: &State, action: &Action) -> Option<ControllerAction> {
+        match action {
+            Action::ControllerStep(revision, i) => {
+                let view = state.view_for(*i, self.read_mode(*i), revision);
+                let mut cstate = state.get_controller(*i).clone();
+                self.controllers[*i].step(&view, &mut cstate)
+            }
+            Action::ArbitraryStep(a) => Some(ArbitraryClient::controller_action(
+                &state.latest(),
+                a.clone(),
+            )),
+            Action::NodeRestart(i) => match &self.controllers[*i] {
+                Controllers::Node(n) => state
+                    .latest()
+                    .nodes
+                    .get("", &n.name)
+                    .map(|node| ControllerAction::DeleteNode(node.clone())),
+                _ => None,
+            },
+            Action::ControllerRestart(_)
+            | Action::Replicate(_)
+            | Action::Partition(_)
+            | Action::Heal(_)
+            | Action::InformerEvent(_)
+            | Action::InformerRelist(_)
+            | Action::TickClock => None,
+        }
+    }
+
+    /// The state after taking the action, tallying any write it makes in the coverage if given.
+    ///
+    /// Checks that only look ahead, rather than explore, leave the coverage out.
+    pub fn transition(
+        &self,
+        last_state: &State,
+        action: Action,
+        coverage: Option<&Coverage>,
+    ) -> Option<State> {
+        let mut state = last_state.clone();
+        // only a write from this step can be rejected
+        state.clear_rejection();
+        match action {
+            Action::ControllerStep(revision, controller_index) => {
+                let controller = &self.controllers[controller_index];
+                let mut cstate = last_state.get_controller(controller_index).clone();
+                let view = &last_state.view_for(
+                    controller_index,
+                    self.read_mode(controller_index),
+                    &revision,
+                );
+                state.observe(controller_index);
+                if let Some(action) = controller.step(view, &mut cstate) {
+                    state.record_controller_write();
+                    push_change(
+                        &mut state,
+                        Change {
+                            revision,
+                            controller: Some(controller_index),
+                            operation: action,
+                        },
+                        coverage,
+                    );
+                }
+                state.update_controller(controller_index, cstate);
+                Some(state)
+            }
+            Action::ArbitraryStep(action) => {
+                state.record_perturbation();
+                let controller_action = ArbitraryClient::controller_action(&state.latest(), action);
+                let revision = state.max_revision();
+                push_change(
+                    &mut state,
+                    Change {
+                        revision,
+                        controller: None,
+                        operation: controller_action,
+                    },
+                    coverage,
+                );
+                Some(state)
+            }
+            Action::ControllerRestart(controller_index) => {
+                state.record_perturbation();
+                let controller_state = self.controllers[controller_index].new_state();
+                state.update_controller(controller_index, controller_state);
+                state.relist(controller_index);
+                Some(state)
+            }
+            Action::NodeRestart(controller_index) => {
+                state.record_perturbation();
+                let controller_state = self.controllers[controller_index].new_state();
+                state.update_controller(controller_index, controller_state);
+                state.relist(controller_index);
+                let s = state.latest();
+                if let Controllers::Node(n) = &self.controllers[controller_index] {
+                    if let Some(node) = s.nodes.get("", &n.name) {
+                        push_change(
+                            &mut state,
+                            Change {
+                                revision: s.revision.clone(),
+                                controller: None,
+                                operation: ControllerAction::DeleteNode(node.clone()),
+                            },
+                            coverage,
+                        );
+                    }
+                }
+                Some(state)
+            }
+            Action::Replicate(replica) => {
+                state.replicate(replica);
+                Some(state)
+            }
+            Action::Partition(replica) => {
+                state.record_perturbation();
+                state.partition(replica);
+                Some(state)
+            }
+            Action::Heal(replica) => {
+                state.record_perturbation();
+                state.heal(replica);
+                Some(state)
+            }
+            Action::InformerEvent(controller_index) => {
+                state.deliver_event(controller_index);
+                Some(state)
+            }
+            Action::InformerRelist(controller_index) => {
+                state.record_perturbation();
+                state.relist(controller_index);
+                Some(state)
+            }
+            Action::TickClock => {
+                // time-driven controllers, like cronjobs, legitimately write after the clock moves
+                // on, and the ticks are bounded so fighting after the last one is still flagged
+                state.record_perturbation();
+                state.tick_clock();
+                Some(state)
+            }
+        }
+    }
+}
+
+/// Push the change, tallying it against its controller (or the client) if coverage is given.
+fn push_change(state: &mut State, change: Change, coverage: Option<&Coverage>) {
+    let controller = change.controller;
+    let kind = change.operation.kind();
+    state.push_change(change);
+    if let Some(coverage) = coverage {
+        coverage.record(controller, kind, state.rejection().is_some());
+    }
 }
 
 /// Changes to a state.
@@ -58,6 +379,8 @@ impl AbstractModel {
 pub struct Change {
     /// The revision of the state that this change was generated from.
     pub revision: Revision,
+    /// The controller that generated this change, if any.
+    pub controller: Option<usize>,
     /// The operation to perform on the state.
     pub operation: ControllerAction,
 }
@@ -79,6 +402,7 @@ pub enum ControllerAction {
     RequeueDeployment(Deployment),
     // Update just the status part of the resource, not triggering more reconciliations (I think)
     UpdateDeploymentStatus(Deployment),
+    DeleteDeployment(Deployment, PropagationPolicy),
 
     // ReplicaSets
     CreateReplicaSet(ReplicaSet),
@@ -87,12 +411,16 @@ pub enum ControllerAction {
     // a batch update of multiple replicasets that should cause a new reconciliation if it fails to
     // have this
     UpdateReplicaSets(Vec<ReplicaSet>),
-    DeleteReplicaSet(ReplicaSet),
+    DeleteReplicaSet(ReplicaSet, PropagationPolicy),
 
     // StatefulSets
     UpdateStatefulSet(StatefulSet),
     UpdateStatefulSetStatus(StatefulSet),
 
+    // DaemonSets
+    UpdateDaemonSet(DaemonSet),
+    UpdateDaemonSetStatus(DaemonSet),
+
     // ControllerRevisions
     CreateControllerRevision(ControllerRevision),
     UpdateControllerRevision(ControllerRevision),
@@ -103,11 +431,122 @@ pub enum ControllerAction {
     UpdatePersistentVolumeClaim(PersistentVolumeClaim),
 
     // Jobs
+    CreateJob(Job),
     UpdateJob(Job),
     UpdateJobStatus(Job),
+    DeleteJob(Job),
+
+    // CronJobs
+    UpdateCronJob(CronJob),
+    UpdateCronJobStatus(CronJob),
+
+    // HorizontalPodAutoscalers
+    UpdateHorizontalPodAutoscalerStatus(HorizontalPodAutoscaler),
+
+    // Leases
+    CreateLease(Lease),
+    UpdateLease(Lease),
+
+    // Namespaces
+    DeleteNamespace(Namespace),
+
+    /// An action made by a leader-elected controller whilst it believed it held the given lease.
+    Fenced {
+        /// The namespace of the lease, which is looked up there when the write is committed.
+        namespace: String,
+        lease: String,
+        holder: String,
+        transitions: u32,
+        /// Reject the action if the lease is no longer held, rather than just recording it.
+        enforce: bool,
+        action: Box<ControllerAction>,
+    },
+}
+
+impl ControllerAction {
+    /// The names of every kind of operation, as given by [`Self::kind`].
+    pub const KINDS: [&'static str; 34] = [
+        "NodeJoin",
+        "DeleteNode",
+        "CreatePod",
+        "SoftDeletePod",
+        "HardDeletePod",
+        "UpdatePod",
+        "UpdateDeployment",
+        "RequeueDeployment",
+        "UpdateDeploymentStatus",
+        "DeleteDeployment",
+        "CreateReplicaSet",
+        "UpdateReplicaSet",
+        "UpdateReplicaSetStatus",
+        "UpdateReplicaSets",
+        "DeleteReplicaSet",
+        "UpdateStatefulSet",
+        "UpdateStatefulSetStatus",
+        "UpdateDaemonSet",
+        "UpdateDaemonSetStatus",
+        "CreateControllerRevision",
+        "UpdateControllerRevision",
+        "DeleteControllerRevision",
+        "CreatePersistentVolumeClaim",
+        "UpdatePersistentVolumeClaim",
+        "CreateJob",
+        "UpdateJob",
+        "UpdateJobStatus",
+        "DeleteJob",
+        "UpdateCronJob",
+        "UpdateCronJobStatus",
+        "UpdateHorizontalPodAutoscalerStatus",
+        "CreateLease",
+        "UpdateLease",
+        "DeleteNamespace",
+    ];
+
+    /// The name of the kind of operation, looking through fencing to the operation being fenced.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ControllerAction::NodeJoin(..) => "NodeJoin",
+            ControllerAction::DeleteNode(_) => "DeleteNode",
+            ControllerAction::CreatePod(_) => "CreatePod",
+            ControllerAction::SoftDeletePod(_) => "SoftDeletePod",
+            ControllerAction::HardDeletePod(_) => "HardDeletePod",
+            ControllerAction::UpdatePod(_) => "UpdatePod",
+            ControllerAction::UpdateDeployment(_) => "UpdateDeployment",
+            ControllerAction::RequeueDeployment(_) => "RequeueDeployment",
+            ControllerAction::UpdateDeploymentStatus(_) => "UpdateDeploymentStatus",
+            ControllerAction::DeleteDeployment(..) => "DeleteDeployment",
+            ControllerAction::CreateReplicaSet(_) => "CreateReplicaSet",
+            ControllerAction::UpdateReplicaSet(_) => "UpdateReplicaSet",
+            ControllerAction::UpdateReplicaSetStatus(_) => "UpdateReplicaSetStatus",
+            ControllerAction::UpdateReplicaSets(_) => "UpdateReplicaSets",
+            ControllerAction::DeleteReplicaSet(..) => "DeleteReplicaSet",
+            ControllerAction::UpdateStatefulSet(_) => "UpdateStatefulSet",
+            ControllerAction::UpdateStatefulSetStatus(_) => "UpdateStatefulSetStatus",
+            ControllerAction::UpdateDaemonSet(_) => "UpdateDaemonSet",
+            ControllerAction::UpdateDaemonSetStatus(_) => "UpdateDaemonSetStatus",
+            ControllerAction::CreateControllerRevision(_) => "CreateControllerRevision",
+            ControllerAction::UpdateControllerRevision(_) => "UpdateControllerRevision",
+            ControllerAction::DeleteControllerRevision(_) => "DeleteControllerRevision",
+            ControllerAction::CreatePersistentVolumeClaim(_) => "CreatePersistentVolumeClaim",
+            ControllerAction::UpdatePersistentVolumeClaim(_) => "UpdatePersistentVolumeClaim",
+            ControllerAction::CreateJob(_) => "CreateJob",
+            ControllerAction::UpdateJob(_) => "UpdateJob",
+            ControllerAction::UpdateJobStatus(_) => "UpdateJobStatus",
+            ControllerAction::DeleteJob(_) => "DeleteJob",
+            ControllerAction::UpdateCronJob(_) => "UpdateCronJob",
+            ControllerAction::UpdateCronJobStatus(_) => "UpdateCronJobStatus",
+            ControllerAction::UpdateHorizontalPodAutoscalerStatus(_) => {
+                "UpdateHorizontalPodAutoscalerStatus"
+            }
+            ControllerAction::CreateLease(_) => "CreateLease",
+            ControllerAction::UpdateLease(_) => "UpdateLease",
+            ControllerAction::DeleteNamespace(_) => "DeleteNamespace",
+            ControllerAction::Fenced { action, .. } => action.kind(),
+        }
+    }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Action {
     ControllerStep(Revision, usize),
     ArbitraryStep(ArbitraryClientAction),
@@ -115,6 +554,21 @@ pub enum Action {
     /// The controller at the given index restarts, losing its state.
     ControllerRestart(usize),
     NodeRestart(usize),
+
+    /// The datastore replica at the given index applies the next committed change.
+    Replicate(usize),
+    /// The datastore replica at the given index is partitioned from the rest.
+    Partition(usize),
+    /// The datastore replica at the given index rejoins the rest.
+    Heal(usize),
+
+    /// The informer for the controller at the given index receives the next watch event.
+    InformerEvent(usize),
+    /// The informer for the controller at the given index has its watch dropped and relists.
+    InformerRelist(usize),
+
+    /// Time passes, moving the clock on by a tick.
+    TickClock,
 }
 
 impl Model for AbstractModel {
@@ -127,123 +581,22 @@ impl Model for AbstractModel {
     }
 
     fn actions(&self, state: &Self::State, actions: &mut Vec<Self::Action>) {
-        for (i, controller) in self.controllers.iter().enumerate() {
-            let cstate = state.get_controller(i);
-            let min_revision = controller.min_revision_accepted(cstate);
-            for revision in state.revisions(min_revision) {
-                debug!(?revision, "Adding revision choice");
-                actions.push(Action::ControllerStep(revision, i));
-            }
-        }
-
-        // arbitrary client
-        let latest_view = state.latest();
-        let arbitrary_actions = ArbitraryClient::actions(&latest_view)
-            .into_iter()
-            .map(Action::ArbitraryStep);
-        actions.extend(arbitrary_actions);
-
-        for (i, controller) in self.controllers.iter().enumerate() {
-            if matches!(controller, Controllers::Node(_)) {
-                // skip nodes for now
-                continue;
-            }
-            if state.get_controller(i) != &controller.new_state() {
-                actions.push(Action::ControllerRestart(i));
-            }
-        }
-
-        // at max revision as this isn't a controller event
-        for node in latest_view.nodes.iter() {
-            if let Some(cond) =
-                get_node_condition(&node.status.conditions, NodeConditionType::Ready)
-            {
-                if cond.status == ConditionStatus::True {
-                    // find the controller index for the corresponding node
-                    for (i, controller) in self.controllers.iter().enumerate() {
-                        if let Controllers::Node(n) = controller {
-                            if n.name == node.metadata.name {
-                                // match
-                                actions.push(Action::NodeRestart(i));
-                            }
-                        }
-                    }
-                }
+        // the checker expands each state after evaluating the properties on it
+        self.vacuity.record(self, state);
+        self.enabled_actions(state, actions);
+        if self.partial_order_reduction {
+            if let Some(ample) = reduction::ample_set(self, state, actions) {
+                *actions = ample;
             }
         }
     }
 
     fn next_state(&self, last_state: &Self::State, action: Self::Action) -> Option<Self::State> {
-        match action {
-            Action::ControllerStep(revision, controller_index) => {
-                let controller = &self.controllers[controller_index];
-                let mut cstate = last_state.get_controller(controller_index).clone();
-                let view = &last_state.view_at(&revision);
-                let mut state = last_state.clone();
-                if let Some(action) = controller.step(view, &mut cstate) {
-                    state.push_change(Change {
-                        revision,
-                        operation: action,
-                    });
-                }
-                state.update_controller(controller_index, cstate);
-                Some(state)
-            }
-            Action::ArbitraryStep(action) => {
-                let mut state = last_state.clone();
-                let controller_action = ArbitraryClient::controller_action(&state.latest(), action);
-                state.push_change(Change {
-                    revision: state.max_revision(),
-                    operation: controller_action,
-                });
-                Some(state)
-            }
-            Action::ControllerRestart(controller_index) => {
-                let mut state = last_state.clone();
-                let controller_state = self.controllers[controller_index].new_state();
-                state.update_controller(controller_index, controller_state);
-                Some(state)
-            }
-            Action::NodeRestart(controller_index) => {
-                let mut state = last_state.clone();
-                let controller_state = self.controllers[controller_index].new_state();
-                state.update_controller(controller_index, controller_state);
-                let s = state.latest();
-                if let Controllers::Node(n) = &self.controllers[controller_index] {
-                    if let Some(node) = s.nodes.get(&n.name) {
-                        state.push_change(Change {
-                            revision: s.revision.clone(),
-                            operation: ControllerAction::DeleteNode(node.clone()),
-                        });
-                    }
-                }
-                Some(state)
-            }
-        }
+        self.transition(last_state, action, Some(&self.coverage))
     }
 
     fn properties(&self) -> Vec<stateright::Property<Self>> {
-        let mut p = self.properties.clone();
-        p.append(&mut vec![Property::<Self>::always(
-            "all resources have unique names",
-            |_model, state| {
-                let state = state.latest();
-                all_unique(state.nodes.iter().map(|n| &n.metadata.name))
-                    && all_unique(state.pods.iter().map(|n| &n.metadata.name))
-                    && all_unique(state.replicasets.iter().map(|n| &n.metadata.name))
-                    && all_unique(state.deployments.iter().map(|n| &n.metadata.name))
-                    && all_unique(state.statefulsets.iter().map(|n| &n.metadata.name))
-                    && all_unique(state.controller_revisions.iter().map(|n| &n.metadata.name))
-                    && all_unique(
-                        state
-                            .persistent_volume_claims
-                            .iter()
-                            .map(|n| &n.metadata.name),
-                    )
-                    && all_unique(state.jobs.iter().map(|n| &n.metadata.name))
-            },
-        )]);
-        p
+        self.properties.clone()
     }
 
     fn format_action(&self, last_state: &Self::State, action: &Self::Action) -> String
@@ -253,7 +606,7 @@ impl Model for AbstractModel {
         match action {
             Action::ControllerStep(rev, i) => {
                 let controller = &self.controllers[*i];
-                let view = last_state.view_at(rev);
+                let view = last_state.view_for(*i, self.read_mode(*i), rev);
                 let mut cstate = last_state.get_controller(*i).clone();
                 let caction = controller
                     .step(&view, &mut cstate)
@@ -263,11 +616,15 @@ impl Model for AbstractModel {
                 format!("{:?}: {} {}", action, name, caction)
             }
             Action::ArbitraryStep(_) => format!("{:?}", action),
-            Action::ControllerRestart(i) => {
+            Action::ControllerRestart(i) | Action::InformerEvent(i) | Action::InformerRelist(i) => {
                 let name = self.controllers[*i].name();
                 format!("{:?}: {}", action, name)
             }
-            Action::NodeRestart(_) => format!("{:?}", action),
+            Action::NodeRestart(_)
+            | Action::Replicate(_)
+            | Action::Partition(_)
+            | Action::Heal(_)
+            | Action::TickClock => format!("{:?}", action),
         }
     }
 
@@ -276,8 +633,9 @@ impl Model for AbstractModel {
         Self::State: std::fmt::Debug,
     {
         let last = format!("{:#?}", last_state);
+        // not tallied, as formatting isn't exploring
         let next = self
-            .next_state(last_state, action)
+            .transition(last_state, action, None)
             .map(|next_state| format!("{:#?}", next_state))
             .unwrap_or_default();
         let textdiff = similar::TextDiff::from_lines(&last, &next);
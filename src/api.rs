@@ -1,9 +1,12 @@
 use k8s_openapi::apimachinery::pkg::apis::meta::v1::APIResource;
+use k8s_openapi::ClusterResourceScope;
 use k8s_openapi::NamespaceResourceScope;
 use k8s_openapi::Resource;
 use serde::Serialize;
 
 use crate::resources::Deployment;
+use crate::resources::Meta;
+use crate::resources::Namespace;
 use crate::resources::Node;
 use crate::resources::Pod;
 use crate::resources::ReplicaSet;
@@ -59,13 +62,22 @@ impl_resource!(
 // impl_resource!(PersistentVolumeClaim, "PersistentVolumeClaimList");
 impl_resource!(
     Node,
-    NamespaceResourceScope,
+    ClusterResourceScope,
     "v1",
     "core",
     "Node",
     "v1",
     "nodes"
 );
+impl_resource!(
+    Namespace,
+    ClusterResourceScope,
+    "v1",
+    "core",
+    "Namespace",
+    "v1",
+    "namespaces"
+);
 
 macro_rules! impl_listable {
     ($r:ident, $kind:expr) => {
@@ -82,6 +94,7 @@ impl_listable!(ReplicaSet, "ReplicaSetList");
 // impl_listable!(StatefulSet, "StatefulSetList");
 // impl_listable!(PersistentVolumeClaim, "PersistentVolumeClaimList");
 impl_listable!(Node, "NodeList");
+impl_listable!(Namespace, "NamespaceList");
 //
 macro_rules! impl_api_object {
     ($r:ident) => {
@@ -95,7 +108,7 @@ macro_rules! impl_api_object {
                     group: None,
                     kind: $r::KIND.to_owned(),
                     name: plural_name,
-                    namespaced: true,
+                    namespaced: !<$r as Meta>::CLUSTER_SCOPED,
                     short_names: None,
                     singular_name: $r::KIND.to_lowercase(),
                     storage_version_hash: None,
@@ -122,6 +135,7 @@ impl_api_object!(ReplicaSet);
 // impl_api_object!(StatefulSet);
 // impl_api_object!(PersistentVolumeClaim);
 impl_api_object!(Node);
+impl_api_object!(Namespace);
 
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
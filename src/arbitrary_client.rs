@@ -1,27 +1,51 @@
+use serde::{Deserialize, Serialize};
+
 use crate::{
     abstract_model::ControllerAction,
-    resources::{ContainerState, ContainerStateTerminated},
+    controller::hpa::target_cpu_utilization,
+    resources::{
+        ContainerState, ContainerStateTerminated, NamespacePhase, PropagationPolicy, LABEL_HOSTNAME,
+    },
     state::StateView,
 };
 
 pub struct ArbitraryClient;
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Changes a client can make, each to the resource with the given namespace and name.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum ArbitraryClientAction {
-    ScaleDeployment(String, i32),
-    ScaleStatefulSet(String, i32),
-    ScaleReplicaSet(String, i32),
+    ScaleDeployment(String, String, i32),
+    ScaleStatefulSet(String, String, i32),
+    ScaleReplicaSet(String, String, i32),
+
+    ChangeImageDeployment(String, String, String),
+    ChangeImageStatefulSet(String, String, String),
+    ChangeImageReplicaSet(String, String, String),
+    ChangeImageDaemonSet(String, String, String),
+
+    /// Restrict the daemonset to run only on the named node, or on all nodes again if none is
+    /// given.
+    SelectNodeDaemonSet(String, String, Option<String>),
+
+    TogglePauseDeployment(String, String),
+
+    ToggleSuspendJob(String, String),
 
-    ChangeImageDeployment(String, String),
-    ChangeImageStatefulSet(String, String),
-    ChangeImageReplicaSet(String, String),
+    MarkSucceededContainer(String, String),
+    MarkFailedContainer(String, String),
 
-    TogglePauseDeployment(String),
+    /// Time passes without the holder renewing the lease.
+    ExpireLease(String, String),
 
-    ToggleSuspendJob(String),
+    /// The load on the autoscaler's target changes, giving a new cpu utilization percentage.
+    ChangeMetricHorizontalPodAutoscaler(String, String, u32),
 
-    MarkSucceededContainer(String),
-    MarkFailedContainer(String),
+    /// Delete the resource, propagating the deletion to its dependents by the policy.
+    DeleteDeployment(String, String, PropagationPolicy),
+    DeleteReplicaSet(String, String, PropagationPolicy),
+
+    /// Delete the namespace with the given name, along with everything in it.
+    DeleteNamespace(String),
 }
 
 impl ArbitraryClient {
@@ -31,7 +55,11 @@ impl ArbitraryClient {
         macro_rules! scale_up {
             ($kind:ident, $update:expr) => {
                 for res in view.$kind.iter() {
-                    actions.push($update(res.metadata.name.clone(), 1));
+                    actions.push($update(
+                        res.metadata.namespace.clone(),
+                        res.metadata.name.clone(),
+                        1,
+                    ));
                 }
             };
         }
@@ -40,7 +68,11 @@ impl ArbitraryClient {
         macro_rules! scale_up_option {
             ($kind:ident, $update:expr) => {
                 for res in view.$kind.iter() {
-                    actions.push($update(res.metadata.name.clone(), 1));
+                    actions.push($update(
+                        res.metadata.namespace.clone(),
+                        res.metadata.name.clone(),
+                        1,
+                    ));
                 }
             };
         }
@@ -52,7 +84,11 @@ impl ArbitraryClient {
             ($kind:ident, $update:expr) => {
                 for res in view.$kind.iter() {
                     if res.spec.replicas > 0 {
-                        actions.push($update(res.metadata.name.clone(), -1));
+                        actions.push($update(
+                            res.metadata.namespace.clone(),
+                            res.metadata.name.clone(),
+                            -1,
+                        ));
                     }
                 }
             };
@@ -63,7 +99,11 @@ impl ArbitraryClient {
             ($kind:ident, $update:expr) => {
                 for res in view.$kind.iter() {
                     if res.spec.replicas.unwrap() > 0 {
-                        actions.push($update(res.metadata.name.clone(), -1));
+                        actions.push($update(
+                            res.metadata.namespace.clone(),
+                            res.metadata.name.clone(),
+                            -1,
+                        ));
                     }
                 }
             };
@@ -80,19 +120,54 @@ impl ArbitraryClient {
                     }
                     let image = &res.spec.template.spec.containers[0].image;
                     let new_image = format!("{}1", image);
-                    actions.push($update(res.metadata.name.clone(), new_image));
+                    actions.push($update(
+                        res.metadata.namespace.clone(),
+                        res.metadata.name.clone(),
+                        new_image,
+                    ));
                 }
             };
         }
         change_image!(deployments, ArbitraryClientAction::ChangeImageDeployment);
         change_image!(statefulsets, ArbitraryClientAction::ChangeImageStatefulSet);
         change_image!(replicasets, ArbitraryClientAction::ChangeImageReplicaSet);
+        change_image!(daemonsets, ArbitraryClientAction::ChangeImageDaemonSet);
+
+        // move daemonsets between a single node and all of them
+        for ds in view.daemonsets.iter() {
+            let namespace = &ds.metadata.namespace;
+            let name = &ds.metadata.name;
+            if ds
+                .spec
+                .template
+                .spec
+                .node_selector
+                .contains_key(LABEL_HOSTNAME)
+            {
+                actions.push(ArbitraryClientAction::SelectNodeDaemonSet(
+                    namespace.clone(),
+                    name.clone(),
+                    None,
+                ));
+            } else {
+                for node in view.nodes.iter() {
+                    actions.push(ArbitraryClientAction::SelectNodeDaemonSet(
+                        namespace.clone(),
+                        name.clone(),
+                        Some(node.metadata.name.clone()),
+                    ));
+                }
+            }
+        }
 
         // toggle deployments paused status
         macro_rules! toggle_pause {
             ($kind:ident, $update:expr) => {
                 for res in view.$kind.iter() {
-                    actions.push($update(res.metadata.name.clone()));
+                    actions.push($update(
+                        res.metadata.namespace.clone(),
+                        res.metadata.name.clone(),
+                    ));
                 }
             };
         }
@@ -102,59 +177,138 @@ impl ArbitraryClient {
         macro_rules! toggle_suspension {
             ($kind:ident, $update:expr) => {
                 for res in view.$kind.iter() {
-                    actions.push($update(res.metadata.name.clone()));
+                    actions.push($update(
+                        res.metadata.namespace.clone(),
+                        res.metadata.name.clone(),
+                    ));
                 }
             };
         }
         toggle_suspension!(jobs, ArbitraryClientAction::ToggleSuspendJob);
 
+        // expire held leases
+        for lease in view.leases.iter() {
+            if lease.spec.holder_identity.is_some() && !lease.spec.expired {
+                actions.push(ArbitraryClientAction::ExpireLease(
+                    lease.metadata.namespace.clone(),
+                    lease.metadata.name.clone(),
+                ));
+            }
+        }
+
+        // change the load on autoscaled resources, keeping the metric to a small domain around
+        // the target so that it is scaled down, left alone or scaled up
+        for hpa in view.horizontal_pod_autoscalers.iter() {
+            let target = target_cpu_utilization(hpa);
+            for utilization in [target / 2, target, target * 2] {
+                if hpa.status.current_cpu_utilization_percentage != Some(utilization) {
+                    actions.push(ArbitraryClientAction::ChangeMetricHorizontalPodAutoscaler(
+                        hpa.metadata.namespace.clone(),
+                        hpa.metadata.name.clone(),
+                        utilization,
+                    ));
+                }
+            }
+        }
+
+        // delete resources with each propagation policy, unless they are already being deleted
+        macro_rules! delete {
+            ($kind:ident, $delete:expr) => {
+                for res in view.$kind.iter() {
+                    if res.metadata.deletion_timestamp.is_some() {
+                        continue;
+                    }
+                    for policy in [
+                        PropagationPolicy::Background,
+                        PropagationPolicy::Foreground,
+                        PropagationPolicy::Orphan,
+                    ] {
+                        actions.push($delete(
+                            res.metadata.namespace.clone(),
+                            res.metadata.name.clone(),
+                            policy,
+                        ));
+                    }
+                }
+            };
+        }
+        delete!(deployments, ArbitraryClientAction::DeleteDeployment);
+        delete!(replicasets, ArbitraryClientAction::DeleteReplicaSet);
+
+        // delete namespaces that aren't already being deleted
+        for namespace in view.namespaces.iter() {
+            if namespace.status.phase == NamespacePhase::Active {
+                actions.push(ArbitraryClientAction::DeleteNamespace(
+                    namespace.metadata.name.clone(),
+                ));
+            }
+        }
+
         actions
     }
 
     pub fn controller_action(state: &StateView, action: ArbitraryClientAction) -> ControllerAction {
         match action {
-            ArbitraryClientAction::ScaleDeployment(name, by) => {
-                let mut res = state.deployments.get(&name).unwrap().clone();
+            ArbitraryClientAction::ScaleDeployment(namespace, name, by) => {
+                let mut res = state.deployments.get(&namespace, &name).unwrap().clone();
                 res.spec.replicas = (res.spec.replicas as i32 + by) as u32;
                 ControllerAction::UpdateDeployment(res)
             }
-            ArbitraryClientAction::ScaleStatefulSet(name, by) => {
-                let mut res = state.statefulsets.get(&name).unwrap().clone();
+            ArbitraryClientAction::ScaleStatefulSet(namespace, name, by) => {
+                let mut res = state.statefulsets.get(&namespace, &name).unwrap().clone();
                 res.spec.replicas = Some((res.spec.replicas.unwrap_or(1) as i32 + by) as u32);
                 ControllerAction::UpdateStatefulSet(res)
             }
-            ArbitraryClientAction::ScaleReplicaSet(name, by) => {
-                let mut res = state.replicasets.get(&name).unwrap().clone();
+            ArbitraryClientAction::ScaleReplicaSet(namespace, name, by) => {
+                let mut res = state.replicasets.get(&namespace, &name).unwrap().clone();
                 res.spec.replicas = Some((res.spec.replicas.unwrap_or(1) as i32 + by) as u32);
                 ControllerAction::UpdateReplicaSet(res)
             }
-            ArbitraryClientAction::ChangeImageDeployment(name, image) => {
-                let mut res = state.deployments.get(&name).unwrap().clone();
+            ArbitraryClientAction::ChangeImageDeployment(namespace, name, image) => {
+                let mut res = state.deployments.get(&namespace, &name).unwrap().clone();
                 res.spec.template.spec.containers[0].image = image;
                 ControllerAction::UpdateDeployment(res)
             }
-            ArbitraryClientAction::ChangeImageStatefulSet(name, image) => {
-                let mut res = state.statefulsets.get(&name).unwrap().clone();
+            ArbitraryClientAction::ChangeImageStatefulSet(namespace, name, image) => {
+                let mut res = state.statefulsets.get(&namespace, &name).unwrap().clone();
                 res.spec.template.spec.containers[0].image = image;
                 ControllerAction::UpdateStatefulSet(res)
             }
-            ArbitraryClientAction::ChangeImageReplicaSet(name, image) => {
-                let mut res = state.replicasets.get(&name).unwrap().clone();
+            ArbitraryClientAction::ChangeImageReplicaSet(namespace, name, image) => {
+                let mut res = state.replicasets.get(&namespace, &name).unwrap().clone();
                 res.spec.template.spec.containers[0].image = image;
                 ControllerAction::UpdateReplicaSet(res)
             }
-            ArbitraryClientAction::TogglePauseDeployment(name) => {
-                let mut res = state.deployments.get(&name).unwrap().clone();
+            ArbitraryClientAction::ChangeImageDaemonSet(namespace, name, image) => {
+                let mut res = state.daemonsets.get(&namespace, &name).unwrap().clone();
+                res.spec.template.spec.containers[0].image = image;
+                ControllerAction::UpdateDaemonSet(res)
+            }
+            ArbitraryClientAction::SelectNodeDaemonSet(namespace, name, node) => {
+                let mut res = state.daemonsets.get(&namespace, &name).unwrap().clone();
+                let node_selector = &mut res.spec.template.spec.node_selector;
+                match node {
+                    Some(node) => {
+                        node_selector.insert(LABEL_HOSTNAME.to_owned(), node);
+                    }
+                    None => {
+                        node_selector.remove(LABEL_HOSTNAME);
+                    }
+                }
+                ControllerAction::UpdateDaemonSet(res)
+            }
+            ArbitraryClientAction::TogglePauseDeployment(namespace, name) => {
+                let mut res = state.deployments.get(&namespace, &name).unwrap().clone();
                 res.spec.paused = !res.spec.paused;
                 ControllerAction::UpdateDeployment(res)
             }
-            ArbitraryClientAction::ToggleSuspendJob(name) => {
-                let mut res = state.jobs.get(&name).unwrap().clone();
+            ArbitraryClientAction::ToggleSuspendJob(namespace, name) => {
+                let mut res = state.jobs.get(&namespace, &name).unwrap().clone();
                 res.spec.suspend = !res.spec.suspend;
                 ControllerAction::UpdateJob(res)
             }
-            ArbitraryClientAction::MarkSucceededContainer(name) => {
-                let mut res = state.pods.get(&name).unwrap().clone();
+            ArbitraryClientAction::MarkSucceededContainer(namespace, name) => {
+                let mut res = state.pods.get(&namespace, &name).unwrap().clone();
                 for cs in &mut res.status.container_statuses {
                     cs.last_state = cs.state.clone();
                     cs.state = ContainerState::Terminated(ContainerStateTerminated {
@@ -164,8 +318,8 @@ impl ArbitraryClient {
                 }
                 ControllerAction::UpdatePod(res)
             }
-            ArbitraryClientAction::MarkFailedContainer(name) => {
-                let mut res = state.pods.get(&name).unwrap().clone();
+            ArbitraryClientAction::MarkFailedContainer(namespace, name) => {
+                let mut res = state.pods.get(&namespace, &name).unwrap().clone();
                 for cs in &mut res.status.container_statuses {
                     cs.last_state = cs.state.clone();
                     cs.state = ContainerState::Terminated(ContainerStateTerminated {
@@ -175,6 +329,36 @@ impl ArbitraryClient {
                 }
                 ControllerAction::UpdatePod(res)
             }
+            ArbitraryClientAction::ExpireLease(namespace, name) => {
+                let mut res = state.leases.get(&namespace, &name).unwrap().clone();
+                res.spec.expired = true;
+                ControllerAction::UpdateLease(res)
+            }
+            ArbitraryClientAction::ChangeMetricHorizontalPodAutoscaler(
+                namespace,
+                name,
+                utilization,
+            ) => {
+                let mut res = state
+                    .horizontal_pod_autoscalers
+                    .get(&namespace, &name)
+                    .unwrap()
+                    .clone();
+                res.status.current_cpu_utilization_percentage = Some(utilization);
+                ControllerAction::UpdateHorizontalPodAutoscalerStatus(res)
+            }
+            ArbitraryClientAction::DeleteDeployment(namespace, name, policy) => {
+                let res = state.deployments.get(&namespace, &name).unwrap().clone();
+                ControllerAction::DeleteDeployment(res, policy)
+            }
+            ArbitraryClientAction::DeleteReplicaSet(namespace, name, policy) => {
+                let res = state.replicasets.get(&namespace, &name).unwrap().clone();
+                ControllerAction::DeleteReplicaSet(res, policy)
+            }
+            ArbitraryClientAction::DeleteNamespace(name) => {
+                let res = state.namespaces.get("", &name).unwrap().clone();
+                ControllerAction::DeleteNamespace(res)
+            }
         }
     }
 }
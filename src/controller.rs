@@ -10,16 +10,26 @@ pub use replicaset::ReplicaSetController;
 pub use scheduler::SchedulerController;
 pub use statefulset::StatefulSetController;
 
+pub use self::cronjob::{CronJobController, CronJobControllerState};
+pub use self::daemonset::{DaemonSetController, DaemonSetControllerState};
 pub use self::deployment::DeploymentControllerState;
+pub use self::garbage_collector::{GarbageCollectorController, GarbageCollectorControllerState};
+pub use self::hpa::{HPAController, HPAControllerState};
 pub use self::job::{JobController, JobControllerState};
+pub use self::leader_election::{LeaderElected, LeaderElectedState};
 pub use self::node::NodeControllerState;
 pub use self::podgc::{PodGCController, PodGCControllerState};
 pub use self::replicaset::ReplicaSetControllerState;
 pub use self::scheduler::SchedulerControllerState;
 pub use self::statefulset::StatefulSetControllerState;
 
+pub mod cronjob;
+pub mod daemonset;
 pub mod deployment;
+pub mod garbage_collector;
+pub mod hpa;
 pub mod job;
+pub mod leader_election;
 pub mod node;
 pub mod podgc;
 pub mod replicaset;
@@ -53,8 +63,13 @@ pub enum Controllers {
     ReplicaSet(ReplicaSetController),
     Deployment(DeploymentController),
     StatefulSet(StatefulSetController),
+    DaemonSet(DaemonSetController),
     Job(JobController),
+    CronJob(CronJobController),
+    HPA(HPAController),
     PodGC(PodGCController),
+    GarbageCollector(GarbageCollectorController),
+    LeaderElected(Box<LeaderElected<Controllers>>),
 }
 
 #[derive(Debug, Hash, Clone, PartialEq, Eq)]
@@ -64,8 +79,13 @@ pub enum ControllerStates {
     ReplicaSet(ReplicaSetControllerState),
     Deployment(DeploymentControllerState),
     StatefulSet(StatefulSetControllerState),
+    DaemonSet(DaemonSetControllerState),
     Job(JobControllerState),
+    CronJob(CronJobControllerState),
+    HPA(HPAControllerState),
     PodGC(PodGCControllerState),
+    GarbageCollector(GarbageCollectorControllerState),
+    LeaderElected(Box<LeaderElectedState<ControllerStates>>),
 }
 
 impl Default for ControllerStates {
@@ -100,12 +120,27 @@ impl Controller for Controllers {
             (Controllers::StatefulSet(c), ControllerStates::StatefulSet(s)) => {
                 c.step(global_state, s).map(|a| a.into())
             }
+            (Controllers::DaemonSet(c), ControllerStates::DaemonSet(s)) => {
+                c.step(global_state, s).map(|a| a.into())
+            }
             (Controllers::Job(c), ControllerStates::Job(s)) => {
                 c.step(global_state, s).map(|a| a.into())
             }
+            (Controllers::CronJob(c), ControllerStates::CronJob(s)) => {
+                c.step(global_state, s).map(|a| a.into())
+            }
+            (Controllers::HPA(c), ControllerStates::HPA(s)) => {
+                c.step(global_state, s).map(|a| a.into())
+            }
             (Controllers::PodGC(c), ControllerStates::PodGC(s)) => {
                 c.step(global_state, s).map(|a| a.into())
             }
+            (Controllers::GarbageCollector(c), ControllerStates::GarbageCollector(s)) => {
+                c.step(global_state, s).map(|a| a.into())
+            }
+            (Controllers::LeaderElected(c), ControllerStates::LeaderElected(s)) => {
+                c.step(global_state, s)
+            }
             _ => unreachable!(),
         }
     }
@@ -137,16 +172,41 @@ impl Controller for Controllers {
                 .into_iter()
                 .map(ControllerStates::StatefulSet)
                 .collect(),
+            (Controllers::DaemonSet(c), ControllerStates::DaemonSet(s)) => c
+                .arbitrary_steps(s)
+                .into_iter()
+                .map(ControllerStates::DaemonSet)
+                .collect(),
             (Controllers::Job(c), ControllerStates::Job(s)) => c
                 .arbitrary_steps(s)
                 .into_iter()
                 .map(ControllerStates::Job)
                 .collect(),
+            (Controllers::CronJob(c), ControllerStates::CronJob(s)) => c
+                .arbitrary_steps(s)
+                .into_iter()
+                .map(ControllerStates::CronJob)
+                .collect(),
+            (Controllers::HPA(c), ControllerStates::HPA(s)) => c
+                .arbitrary_steps(s)
+                .into_iter()
+                .map(ControllerStates::HPA)
+                .collect(),
             (Controllers::PodGC(c), ControllerStates::PodGC(s)) => c
                 .arbitrary_steps(s)
                 .into_iter()
                 .map(ControllerStates::PodGC)
                 .collect(),
+            (Controllers::GarbageCollector(c), ControllerStates::GarbageCollector(s)) => c
+                .arbitrary_steps(s)
+                .into_iter()
+                .map(ControllerStates::GarbageCollector)
+                .collect(),
+            (Controllers::LeaderElected(c), ControllerStates::LeaderElected(s)) => c
+                .arbitrary_steps(s)
+                .into_iter()
+                .map(|s| ControllerStates::LeaderElected(Box::new(s)))
+                .collect(),
             _ => unreachable!(),
         }
     }
@@ -158,8 +218,13 @@ impl Controller for Controllers {
             Controllers::ReplicaSet(c) => c.name(),
             Controllers::Deployment(c) => c.name(),
             Controllers::StatefulSet(c) => c.name(),
+            Controllers::DaemonSet(c) => c.name(),
             Controllers::Job(c) => c.name(),
+            Controllers::CronJob(c) => c.name(),
+            Controllers::HPA(c) => c.name(),
             Controllers::PodGC(c) => c.name(),
+            Controllers::GarbageCollector(c) => c.name(),
+            Controllers::LeaderElected(c) => c.name(),
         }
     }
 
@@ -178,8 +243,19 @@ impl Controller for Controllers {
             (Controllers::StatefulSet(c), ControllerStates::StatefulSet(s)) => {
                 c.min_revision_accepted(s)
             }
+            (Controllers::DaemonSet(c), ControllerStates::DaemonSet(s)) => {
+                c.min_revision_accepted(s)
+            }
             (Controllers::Job(c), ControllerStates::Job(s)) => c.min_revision_accepted(s),
+            (Controllers::CronJob(c), ControllerStates::CronJob(s)) => c.min_revision_accepted(s),
+            (Controllers::HPA(c), ControllerStates::HPA(s)) => c.min_revision_accepted(s),
             (Controllers::PodGC(c), ControllerStates::PodGC(s)) => c.min_revision_accepted(s),
+            (Controllers::GarbageCollector(c), ControllerStates::GarbageCollector(s)) => {
+                c.min_revision_accepted(s)
+            }
+            (Controllers::LeaderElected(c), ControllerStates::LeaderElected(s)) => {
+                c.min_revision_accepted(s)
+            }
             _ => unreachable!(),
         }
     }
@@ -201,8 +277,19 @@ impl Controllers {
             Controllers::StatefulSet(_) => {
                 ControllerStates::StatefulSet(StatefulSetControllerState::default())
             }
+            Controllers::DaemonSet(_) => {
+                ControllerStates::DaemonSet(DaemonSetControllerState::default())
+            }
             Controllers::Job(_) => ControllerStates::Job(JobControllerState::default()),
+            Controllers::CronJob(_) => ControllerStates::CronJob(CronJobControllerState::default()),
+            Controllers::HPA(_) => ControllerStates::HPA(HPAControllerState::default()),
             Controllers::PodGC(_) => ControllerStates::PodGC(PodGCControllerState::default()),
+            Controllers::GarbageCollector(_) => {
+                ControllerStates::GarbageCollector(GarbageCollectorControllerState::default())
+            }
+            Controllers::LeaderElected(c) => ControllerStates::LeaderElected(Box::new(
+                LeaderElectedState::new(c.inner.new_state()),
+            )),
         }
     }
 }
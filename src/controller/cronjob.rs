@@ -0,0 +1,466 @@
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+
+use time::format_description::well_known::Rfc3339;
+use time::{Duration, OffsetDateTime};
+use tracing::debug;
+
+use crate::abstract_model::ControllerAction;
+use crate::controller::Controller;
+use crate::resources::{
+    ConcurrencyPolicy, ConditionStatus, CronJob, Job, JobConditionType, Metadata, ObjectReference,
+    Time,
+};
+use crate::state::revision::Revision;
+use crate::state::StateView;
+
+use super::util::new_controller_ref;
+
+// CronJobScheduledTimestampAnnotation is the scheduled timestamp annotation for the Job.
+// It records the original/expected scheduled timestamp for the running job, represented in RFC3339.
+const CRONJOB_SCHEDULED_TIMESTAMP_ANNOTATION: &str =
+    "batch.kubernetes.io/cronjob-scheduled-timestamp";
+
+const DEFAULT_SUCCESSFUL_JOBS_HISTORY_LIMIT: u32 = 3;
+const DEFAULT_FAILED_JOBS_HISTORY_LIMIT: u32 = 1;
+
+#[derive(Clone, Debug)]
+pub struct CronJobController;
+
+#[derive(Debug, Default, Hash, Clone, PartialEq, Eq)]
+pub struct CronJobControllerState {
+    revision: Option<Revision>,
+}
+
+#[derive(Debug)]
+pub enum CronJobControllerAction {
+    CreateJob(Job),
+    DeleteJob(Job),
+
+    UpdateCronJobStatus(CronJob),
+}
+
+impl From<CronJobControllerAction> for ControllerAction {
+    fn from(value: CronJobControllerAction) -> Self {
+        match value {
+            CronJobControllerAction::CreateJob(job) => ControllerAction::CreateJob(job),
+            CronJobControllerAction::DeleteJob(job) => ControllerAction::DeleteJob(job),
+            CronJobControllerAction::UpdateCronJobStatus(cj) => {
+                ControllerAction::UpdateCronJobStatus(cj)
+            }
+        }
+    }
+}
+
+impl Controller for CronJobController {
+    type State = CronJobControllerState;
+    type Action = CronJobControllerAction;
+
+    fn step(
+        &self,
+        global_state: &StateView,
+        local_state: &mut Self::State,
+    ) -> Option<Self::Action> {
+        local_state.revision = Some(global_state.revision.clone());
+        for cronjob in global_state.cronjobs.iter() {
+            let jobs = global_state
+                .jobs
+                .for_controller(&cronjob.metadata.namespace, &cronjob.metadata.uid)
+                .collect::<Vec<_>>();
+            if let Some(op) = sync_cronjob(cronjob, &jobs, global_state.now) {
+                return Some(op);
+            }
+        }
+        None
+    }
+
+    fn arbitrary_steps(&self, _local_state: &Self::State) -> Vec<Self::State> {
+        Vec::new()
+    }
+
+    fn name(&self) -> String {
+        "CronJob".to_owned()
+    }
+
+    fn min_revision_accepted<'a>(&self, state: &'a Self::State) -> Option<&'a Revision> {
+        state.revision.as_ref()
+    }
+}
+
+// syncCronJob reconciles a CronJob with a list of any Jobs that it created.
+// All known jobs created by "cronJob" should be included in "jobs".
+fn sync_cronjob(cronjob: &CronJob, jobs: &[&Job], now: Time) -> Option<CronJobControllerAction> {
+    let mut cronjob = cronjob.clone();
+    let mut update_status = false;
+
+    let children_jobs = jobs
+        .iter()
+        .map(|j| j.metadata.uid.as_str())
+        .collect::<BTreeSet<_>>();
+    for job in jobs {
+        let found = in_active_list(&cronjob, &job.metadata.uid);
+        let finished = finished_condition(job);
+        if !found && finished.is_none() {
+            // THEMELIOS: we can't update the status in the same step as creating the job, so
+            // record the job we created for a schedule after the last one recorded, as the status
+            // update following its creation would have.
+            let scheduled_time = get_scheduled_time(job);
+            let created_by_us = scheduled_time.map_or(false, |t| {
+                cronjob
+                    .status
+                    .last_schedule_time
+                    .map_or(true, |last| t > last)
+            });
+            if created_by_us {
+                cronjob.status.active.push(get_ref(job));
+                cronjob.status.last_schedule_time = scheduled_time;
+                update_status = true;
+            } else {
+                // We found an unfinished job that has us as the parent, but it is not in our Active list.
+                // This could happen if we crashed right after creating the Job and before updating the status,
+                // or if our jobs list is newer than our cj status after a relist, or if someone intentionally created
+                // a job that they wanted us to adopt.
+                debug!(
+                    job = %job.metadata.name,
+                    "Saw a job that the controller did not create or forgot"
+                );
+            }
+        } else if found && finished.is_some() {
+            delete_from_active_list(&mut cronjob, &job.metadata.uid);
+            debug!(job = %job.metadata.name, status = ?finished, "Saw completed job");
+            update_status = true;
+        } else if finished == Some(JobConditionType::Complete) {
+            // a job does not have to be in active list, as long as it has completed successfully, we will process the timestamp
+            if let Some(completion_time) = job.status.completion_time {
+                if cronjob
+                    .status
+                    .last_successful_time
+                    .map_or(true, |t| completion_time > t)
+                {
+                    cronjob.status.last_successful_time = Some(completion_time);
+                    update_status = true;
+                }
+            }
+        }
+    }
+
+    // Remove any job reference from the active list if the corresponding job does not exist any more.
+    // Otherwise, the cronjob may be stuck in active mode forever even though there is no matching
+    // job running.
+    // THEMELIOS: we don't get the job from the datastore to check that it is really missing.
+    let active_len = cronjob.status.active.len();
+    cronjob
+        .status
+        .active
+        .retain(|j| children_jobs.contains(j.uid.as_str()));
+    if cronjob.status.active.len() != active_len {
+        update_status = true;
+    }
+
+    if update_status {
+        return Some(CronJobControllerAction::UpdateCronJobStatus(cronjob));
+    }
+
+    if let Some(op) = schedule_job(&cronjob, jobs, now) {
+        return Some(op);
+    }
+
+    cleanup_finished_jobs(&cronjob, jobs)
+}
+
+// Start the job for the most recent unmet scheduled time, if there is one that the concurrency
+// policy allows to run.
+fn schedule_job(cronjob: &CronJob, jobs: &[&Job], now: Time) -> Option<CronJobControllerAction> {
+    if cronjob.metadata.deletion_timestamp.is_some() {
+        // The CronJob is being deleted.
+        // Don't do anything other than updating status.
+        return None;
+    }
+
+    if cronjob.spec.suspend {
+        debug!(
+            cronjob = %cronjob.metadata.name,
+            "Not starting job because the cron is suspended"
+        );
+        return None;
+    }
+
+    let Some(schedule) = parse_schedule(&cronjob.spec.schedule) else {
+        // this is likely a user error in defining the spec value
+        // we should log the error and not reconcile this cronjob until an update to spec
+        debug!(
+            cronjob = %cronjob.metadata.name,
+            schedule = %cronjob.spec.schedule,
+            "Unparseable schedule"
+        );
+        return None;
+    };
+
+    let scheduled_time = most_recent_schedule_time(cronjob, &schedule, now)?;
+
+    let too_late = cronjob
+        .spec
+        .starting_deadline_seconds
+        .map_or(false, |deadline| {
+            scheduled_time.0 + Duration::seconds(deadline as i64) < now.0
+        });
+    if too_late {
+        debug!(
+            cronjob = %cronjob.metadata.name,
+            "Missed starting window for cronjob"
+        );
+        return None;
+    }
+
+    let job_name = get_job_name(cronjob, scheduled_time);
+    if cronjob.status.active.iter().any(|j| j.name == job_name)
+        || cronjob.status.last_schedule_time == Some(scheduled_time)
+    {
+        debug!(
+            cronjob = %cronjob.metadata.name,
+            job = %job_name,
+            "Not starting job because the scheduled time is already processed"
+        );
+        return None;
+    }
+
+    match cronjob.spec.concurrency_policy {
+        ConcurrencyPolicy::Forbid if !cronjob.status.active.is_empty() => {
+            // Regardless which source of information we use for the set of active jobs,
+            // there is some risk that we won't see an active job when there is one.
+            // (because we haven't seen the status update to the SJ or the created pod).
+            // So it is theoretically possible to have concurrency with Forbid.
+            // As long the as the invocations are "far enough apart in time", this usually won't happen.
+            //
+            // TODO: for Forbid, we could use the same name for every execution, as a lock.
+            debug!(
+                cronjob = %cronjob.metadata.name,
+                "Not starting job because prior execution is still running and concurrency policy is Forbid"
+            );
+            return None;
+        }
+        ConcurrencyPolicy::Replace => {
+            if let Some(job) = cronjob
+                .status
+                .active
+                .iter()
+                .find_map(|j| jobs.iter().find(|job| job.metadata.uid == j.uid))
+            {
+                debug!(
+                    cronjob = %cronjob.metadata.name,
+                    job = %job.metadata.name,
+                    "Deleting job that was still running at next scheduled start time"
+                );
+                return Some(CronJobControllerAction::DeleteJob((*job).clone()));
+            }
+        }
+        ConcurrencyPolicy::Allow | ConcurrencyPolicy::Forbid => {}
+    }
+
+    let job = get_job_from_template(cronjob, scheduled_time);
+    Some(CronJobControllerAction::CreateJob(job))
+}
+
+// cleanupFinishedJobs cleanups finished jobs created by a CronJob
+fn cleanup_finished_jobs(cronjob: &CronJob, jobs: &[&Job]) -> Option<CronJobControllerAction> {
+    let (successful_jobs, failed_jobs): (Vec<_>, Vec<_>) = jobs
+        .iter()
+        .copied()
+        .filter_map(|job| finished_condition(job).map(|c| (job, c)))
+        .partition(|(_, c)| *c == JobConditionType::Complete);
+    let successful_jobs = successful_jobs.into_iter().map(|(j, _)| j).collect();
+    let failed_jobs = failed_jobs.into_iter().map(|(j, _)| j).collect();
+
+    let successful_limit = cronjob
+        .spec
+        .successful_jobs_history_limit
+        .unwrap_or(DEFAULT_SUCCESSFUL_JOBS_HISTORY_LIMIT);
+    let failed_limit = cronjob
+        .spec
+        .failed_jobs_history_limit
+        .unwrap_or(DEFAULT_FAILED_JOBS_HISTORY_LIMIT);
+
+    remove_oldest_jobs(successful_jobs, successful_limit)
+        .or_else(|| remove_oldest_jobs(failed_jobs, failed_limit))
+}
+
+// removeOldestJobs removes the oldest jobs from a list of jobs
+fn remove_oldest_jobs(mut jobs: Vec<&Job>, max_jobs: u32) -> Option<CronJobControllerAction> {
+    if jobs.len() <= max_jobs as usize {
+        return None;
+    }
+    jobs.sort_by(|a, b| by_job_start_time(a, b));
+    jobs.first()
+        .map(|job| CronJobControllerAction::DeleteJob((*job).clone()))
+}
+
+// byJobStartTime sorts a list of jobs by start timestamp, using their names as a tie breaker.
+fn by_job_start_time(a: &Job, b: &Job) -> Ordering {
+    match (a.status.start_time, b.status.start_time) {
+        (None, Some(_)) => Ordering::Greater,
+        (Some(_), None) => Ordering::Less,
+        (a_start, b_start) if a_start == b_start => a.metadata.name.cmp(&b.metadata.name),
+        (a_start, b_start) => a_start.cmp(&b_start),
+    }
+}
+
+// finishedCondition returns the type of the condition the job finished with, if it has finished.
+fn finished_condition(job: &Job) -> Option<JobConditionType> {
+    job.status
+        .conditions
+        .iter()
+        .find(|c| {
+            (c.r#type == JobConditionType::Complete || c.r#type == JobConditionType::Failed)
+                && c.status == ConditionStatus::True
+        })
+        .map(|c| c.r#type)
+}
+
+fn in_active_list(cronjob: &CronJob, uid: &str) -> bool {
+    cronjob.status.active.iter().any(|j| j.uid == uid)
+}
+
+fn delete_from_active_list(cronjob: &mut CronJob, uid: &str) {
+    cronjob.status.active.retain(|j| j.uid != uid)
+}
+
+// getRef gets the reference to the job.
+fn get_ref(job: &Job) -> ObjectReference {
+    ObjectReference {
+        api_version: Job::GVK.group_version().to_string(),
+        kind: Job::GVK.kind.to_owned(),
+        namespace: job.metadata.namespace.clone(),
+        name: job.metadata.name.clone(),
+        uid: job.metadata.uid.clone(),
+        resource_version: job.metadata.resource_version.clone(),
+    }
+}
+
+// getJobFromTemplate2 makes a Job from a CronJob. It converts the unix time into minutes from
+// epoch time and concatenates that to the job name, because the cronjob_controller v2 has the lowest
+// granularity of 1 minute for scheduling job.
+fn get_job_from_template(cronjob: &CronJob, scheduled_time: Time) -> Job {
+    let template = &cronjob.spec.job_template;
+    let mut annotations = template.metadata.annotations.clone();
+    annotations.insert(
+        CRONJOB_SCHEDULED_TIMESTAMP_ANNOTATION.to_owned(),
+        scheduled_time.0.format(&Rfc3339).unwrap(),
+    );
+    Job {
+        metadata: Metadata {
+            name: get_job_name(cronjob, scheduled_time),
+            namespace: cronjob.metadata.namespace.clone(),
+            labels: template.metadata.labels.clone(),
+            annotations,
+            owner_references: vec![new_controller_ref(&cronjob.metadata, &CronJob::GVK)],
+            ..Default::default()
+        },
+        spec: template.spec.clone(),
+        status: Default::default(),
+    }
+}
+
+fn get_job_name(cronjob: &CronJob, scheduled_time: Time) -> String {
+    format!(
+        "{}-{}",
+        cronjob.metadata.name,
+        get_time_hash_in_minutes(scheduled_time)
+    )
+}
+
+// getTimeHashInMinutes returns Unix Epoch Time in minutes
+fn get_time_hash_in_minutes(scheduled_time: Time) -> i64 {
+    scheduled_time.0.unix_timestamp().div_euclid(60)
+}
+
+fn get_scheduled_time(job: &Job) -> Option<Time> {
+    job.metadata
+        .annotations
+        .get(CRONJOB_SCHEDULED_TIMESTAMP_ANNOTATION)
+        .and_then(|t| OffsetDateTime::parse(t, &Rfc3339).ok())
+        .map(Time)
+}
+
+// mostRecentScheduleTime returns the latest schedule time between earliestTime and the count of number of
+// schedules in between them
+fn most_recent_schedule_time(cronjob: &CronJob, schedule: &Schedule, now: Time) -> Option<Time> {
+    let mut earliest_time = cronjob
+        .status
+        .last_schedule_time
+        .or(cronjob.metadata.creation_timestamp)
+        .unwrap_or_default();
+    if let Some(deadline) = cronjob.spec.starting_deadline_seconds {
+        // Controller is not going to schedule anything below this point
+        let scheduling_deadline = Time(now.0 - Duration::seconds(deadline as i64));
+        if scheduling_deadline > earliest_time {
+            earliest_time = scheduling_deadline;
+        }
+    }
+
+    let mut t = schedule.next(earliest_time);
+    if t > now {
+        return None;
+    }
+    loop {
+        let next = schedule.next(t);
+        if next > now {
+            return Some(t);
+        }
+        t = next;
+    }
+}
+
+/// The times that a cron schedule runs at.
+///
+/// THEMELIOS: only the minute field is modelled, the others must be `*`, as the clock only covers
+/// a few minutes.
+#[derive(Debug, PartialEq, Eq)]
+struct Schedule {
+    /// The minutes past the hour to run at.
+    minutes: BTreeSet<u32>,
+}
+
+impl Schedule {
+    /// The first time on the schedule strictly after the given time.
+    fn next(&self, t: Time) -> Time {
+        let mut minute = get_time_hash_in_minutes(t) + 1;
+        while !self.minutes.contains(&(minute.rem_euclid(60) as u32)) {
+            minute += 1;
+        }
+        Time(OffsetDateTime::UNIX_EPOCH + Duration::minutes(minute))
+    }
+}
+
+/// Parse a standard cron schedule, with the minute field being a list of values, ranges or `*`,
+/// each with an optional step.
+fn parse_schedule(schedule: &str) -> Option<Schedule> {
+    let fields = schedule.split_whitespace().collect::<Vec<_>>();
+    let [minute, rest @ ..] = fields.as_slice() else {
+        return None;
+    };
+    if rest.len() != 4 || rest.iter().any(|f| *f != "*") {
+        return None;
+    }
+    let mut minutes = BTreeSet::new();
+    for part in minute.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<usize>().ok().filter(|s| *s > 0)?),
+            None => (part, 1),
+        };
+        let (start, end) = if range == "*" {
+            (0, 59)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (start.parse::<u32>().ok()?, end.parse::<u32>().ok()?)
+        } else {
+            let start = range.parse::<u32>().ok()?;
+            // a single value with a step runs from that value to the end of the range
+            let end = if part.contains('/') { 59 } else { start };
+            (start, end)
+        };
+        if start > end || end > 59 {
+            return None;
+        }
+        minutes.extend((start..=end).step_by(step));
+    }
+    Some(Schedule { minutes })
+}
@@ -0,0 +1,963 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+
+use tracing::debug;
+
+use crate::abstract_model::ControllerAction;
+use crate::controller::Controller;
+use crate::resources::{
+    ControllerRevision, DaemonSet, DaemonSetSpec, DaemonSetStatus, DaemonSetUpdateStrategyType,
+    Metadata, Node, Operator, Pod, PodPhase, PodSpec, PodTemplateSpec, Taint, TaintEffect, Time,
+    Toleration,
+};
+use crate::state::revision::Revision;
+use crate::state::StateView;
+
+use super::util::{
+    compute_hash, get_pod_from_template, is_pod_available, is_pod_ready, new_controller_ref, subset,
+};
+
+// DefaultDaemonSetUniqueLabelKey is the default label key that is added
+// to existing DaemonSet pods to distinguish between old and new
+// DaemonSet pods during DaemonSet template updates.
+const DEFAULT_DAEMONSET_UNIQUE_LABEL_KEY: &str = "controller-revision-hash";
+
+const TAINT_NODE_NOT_READY: &str = "node.kubernetes.io/not-ready";
+const TAINT_NODE_UNREACHABLE: &str = "node.kubernetes.io/unreachable";
+const TAINT_NODE_DISK_PRESSURE: &str = "node.kubernetes.io/disk-pressure";
+const TAINT_NODE_MEMORY_PRESSURE: &str = "node.kubernetes.io/memory-pressure";
+const TAINT_NODE_PID_PRESSURE: &str = "node.kubernetes.io/pid-pressure";
+const TAINT_NODE_UNSCHEDULABLE: &str = "node.kubernetes.io/unschedulable";
+
+const DEFAULT_REVISION_HISTORY_LIMIT: u32 = 10;
+
+type ValOrOp<V> = super::util::ValOrOp<V, DaemonSetControllerAction>;
+
+#[derive(Clone, Debug)]
+pub struct DaemonSetController;
+
+#[derive(Debug, Default, Hash, Clone, PartialEq, Eq)]
+pub struct DaemonSetControllerState {
+    revision: Option<Revision>,
+}
+
+#[derive(Debug)]
+pub enum DaemonSetControllerAction {
+    CreatePod(Pod),
+    DeletePod(Pod),
+
+    CreateControllerRevision(ControllerRevision),
+    UpdateControllerRevision(ControllerRevision),
+    DeleteControllerRevision(ControllerRevision),
+
+    UpdateDaemonSetStatus(DaemonSet),
+}
+
+impl From<DaemonSetControllerAction> for ControllerAction {
+    fn from(value: DaemonSetControllerAction) -> Self {
+        match value {
+            DaemonSetControllerAction::CreatePod(p) => ControllerAction::CreatePod(p),
+            DaemonSetControllerAction::DeletePod(p) => ControllerAction::SoftDeletePod(p),
+            DaemonSetControllerAction::CreateControllerRevision(cr) => {
+                ControllerAction::CreateControllerRevision(cr)
+            }
+            DaemonSetControllerAction::UpdateControllerRevision(cr) => {
+                ControllerAction::UpdateControllerRevision(cr)
+            }
+            DaemonSetControllerAction::DeleteControllerRevision(cr) => {
+                ControllerAction::DeleteControllerRevision(cr)
+            }
+            DaemonSetControllerAction::UpdateDaemonSetStatus(ds) => {
+                ControllerAction::UpdateDaemonSetStatus(ds)
+            }
+        }
+    }
+}
+
+impl Controller for DaemonSetController {
+    type State = DaemonSetControllerState;
+    type Action = DaemonSetControllerAction;
+
+    fn step(
+        &self,
+        global_state: &StateView,
+        local_state: &mut Self::State,
+    ) -> Option<Self::Action> {
+        local_state.revision = Some(global_state.revision.clone());
+        let nodes = global_state.nodes.iter().collect::<Vec<_>>();
+        for daemonset in global_state.daemonsets.iter() {
+            let namespace = &daemonset.metadata.namespace;
+            let pods = global_state
+                .pods
+                .for_controller(namespace, &daemonset.metadata.uid)
+                .collect::<Vec<_>>();
+            let revisions = global_state
+                .controller_revisions
+                .in_namespace(namespace)
+                .collect::<Vec<_>>();
+            if let Some(op) = sync_daemonset(
+                daemonset,
+                &nodes,
+                &pods,
+                &revisions,
+                &global_state.revision,
+                global_state.now,
+            ) {
+                return Some(op);
+            }
+        }
+        None
+    }
+
+    fn arbitrary_steps(&self, _local_state: &Self::State) -> Vec<Self::State> {
+        Vec::new()
+    }
+
+    fn name(&self) -> String {
+        "DaemonSet".to_owned()
+    }
+
+    fn min_revision_accepted<'a>(&self, state: &'a Self::State) -> Option<&'a Revision> {
+        state.revision.as_ref()
+    }
+}
+
+fn sync_daemonset(
+    ds: &DaemonSet,
+    nodes: &[&Node],
+    pods: &[&Pod],
+    all_revisions: &[&ControllerRevision],
+    state_revision: &Revision,
+    now: Time,
+) -> Option<DaemonSetControllerAction> {
+    // Construct histories of the DaemonSet, and get the hash of current history
+    let (cur, old) = match construct_history(ds, all_revisions) {
+        ValOrOp::Resource(r) => r,
+        ValOrOp::Op(op) => return Some(op),
+    };
+    let hash = cur
+        .metadata
+        .labels
+        .get(DEFAULT_DAEMONSET_UNIQUE_LABEL_KEY)
+        .cloned()
+        .unwrap_or_default();
+
+    let node_to_daemon_pods = get_nodes_to_daemon_pods(pods);
+
+    if ds.metadata.deletion_timestamp.is_some() {
+        return update_daemonset_status(
+            ds,
+            nodes,
+            &node_to_daemon_pods,
+            &hash,
+            false,
+            state_revision,
+            now,
+        );
+    }
+
+    if let Some(op) = manage(ds, nodes, &node_to_daemon_pods, &hash, now) {
+        return Some(op);
+    }
+
+    // Process rolling updates if we're ready.
+    if ds.spec.update_strategy.r#type == DaemonSetUpdateStrategyType::RollingUpdate {
+        if let Some(op) = rolling_update(ds, nodes, &node_to_daemon_pods, &hash, now) {
+            return Some(op);
+        }
+    }
+
+    if let Some(op) = cleanup_history(ds, &node_to_daemon_pods, &old) {
+        return Some(op);
+    }
+
+    update_daemonset_status(
+        ds,
+        nodes,
+        &node_to_daemon_pods,
+        &hash,
+        true,
+        state_revision,
+        now,
+    )
+}
+
+// getNodesToDaemonPods returns a map from nodes to daemon pods (corresponding to ds) created for the nodes.
+fn get_nodes_to_daemon_pods<'a>(pods: &[&'a Pod]) -> BTreeMap<&'a str, Vec<&'a Pod>> {
+    let mut node_to_daemon_pods = BTreeMap::<_, Vec<_>>::new();
+    for pod in pods {
+        // skip terminal pods that have been deleted
+        if is_pod_terminal(pod) && pod.metadata.deletion_timestamp.is_some() {
+            continue;
+        }
+        if let Some(node_name) = &pod.spec.node_name {
+            node_to_daemon_pods
+                .entry(node_name.as_str())
+                .or_default()
+                .push(*pod);
+        }
+    }
+    node_to_daemon_pods
+}
+
+// manage manages the scheduling and running of Pods of ds on nodes.
+// After figuring out which nodes should run a Pod of ds but not yet running one and
+// which nodes should not run a Pod of ds but currently running one, it calls function
+// syncNodes with a list of pods to remove and a list of nodes to run a Pod of ds.
+fn manage(
+    ds: &DaemonSet,
+    nodes: &[&Node],
+    node_to_daemon_pods: &BTreeMap<&str, Vec<&Pod>>,
+    hash: &str,
+    now: Time,
+) -> Option<DaemonSetControllerAction> {
+    // For each node, if the node is running the daemon pod but isn't supposed to, kill the daemon
+    // pod. If the node is supposed to run the daemon pod, but isn't, create the daemon pod on the node.
+    let mut nodes_needing_daemon_pods = Vec::new();
+    let mut pods_to_delete = Vec::new();
+    for node in nodes {
+        let (nodes_needing_daemon_pods_on_node, pods_to_delete_on_node) =
+            pods_should_be_on_node(node, node_to_daemon_pods, ds, hash, now);
+        nodes_needing_daemon_pods.extend(nodes_needing_daemon_pods_on_node);
+        pods_to_delete.extend(pods_to_delete_on_node);
+    }
+
+    // THEMELIOS: daemon pods are bound to their node when created so there are no unscheduled
+    // pods on nodes that no longer exist to remove here, the pod GC cleans up those that were
+    // bound.
+
+    sync_nodes(ds, &pods_to_delete, &nodes_needing_daemon_pods, hash)
+}
+
+// podsShouldBeOnNode figures out the DaemonSet pods to be created and deleted on the given node:
+//   - nodesNeedingDaemonPods: the pods need to start on the node
+//   - podsToDelete: the Pods need to be deleted on the node
+fn pods_should_be_on_node<'a>(
+    node: &'a Node,
+    node_to_daemon_pods: &BTreeMap<&str, Vec<&'a Pod>>,
+    ds: &DaemonSet,
+    hash: &str,
+    now: Time,
+) -> (Option<&'a str>, Vec<&'a Pod>) {
+    let (should_run, should_continue_running) = node_should_run_daemon_pod(node, ds);
+    let daemon_pods = node_to_daemon_pods
+        .get(node.metadata.name.as_str())
+        .cloned()
+        .unwrap_or_default();
+    let exists = !daemon_pods.is_empty();
+
+    let mut nodes_needing_daemon_pods = None;
+    let mut pods_to_delete = Vec::new();
+
+    if should_run && !exists {
+        // If daemon pod is supposed to be running on node, but isn't, create daemon pod.
+        nodes_needing_daemon_pods = Some(node.metadata.name.as_str());
+    } else if should_continue_running {
+        // If a daemon pod failed, delete it
+        // If there's non-daemon pods left on this node, we will create it in the next sync loop
+        // THEMELIOS: failed pods are deleted without backing off, the model explores them being
+        // recreated and failing again anyway.
+        let mut daemon_pods_running = Vec::new();
+        for pod in daemon_pods {
+            if pod.metadata.deletion_timestamp.is_some() {
+                continue;
+            }
+            if is_pod_terminal(pod) {
+                debug!(
+                    pod = pod.metadata.name,
+                    "Found terminal daemon pod, deleting"
+                );
+                pods_to_delete.push(pod);
+            } else {
+                daemon_pods_running.push(pod);
+            }
+        }
+
+        sort_by_creation_timestamp_and_phase(&mut daemon_pods_running);
+
+        if !allows_surge(ds) {
+            // When surge is not enabled, if there is more than 1 running pod on a node delete all but the oldest
+            pods_to_delete.extend(daemon_pods_running.iter().skip(1));
+        } else if daemon_pods_running.is_empty() {
+            if should_run {
+                // We are surging so we need to have at least one non-deleted pod on the node
+                nodes_needing_daemon_pods = Some(node.metadata.name.as_str());
+            }
+        } else if daemon_pods_running.len() > 1 {
+            // When surge is enabled, we allow 2 pods if and only if the oldest pod matching the current hash state
+            // is not ready AND the oldest pod that doesn't match the current hash state is ready. All other pods are
+            // deleted. If neither pod is ready, only the one matching the current hash revision is kept.
+            let mut oldest_new_pod = None;
+            let mut oldest_old_pod = None;
+            for pod in daemon_pods_running {
+                if is_pod_updated(pod, hash) {
+                    if oldest_new_pod.is_none() {
+                        oldest_new_pod = Some(pod);
+                        continue;
+                    }
+                } else if oldest_old_pod.is_none() {
+                    oldest_old_pod = Some(pod);
+                    continue;
+                }
+                pods_to_delete.push(pod);
+            }
+            if let (Some(oldest_new_pod), Some(oldest_old_pod)) = (oldest_new_pod, oldest_old_pod) {
+                if !is_pod_ready(oldest_old_pod)
+                    || is_pod_available(oldest_new_pod, ds.spec.min_ready_seconds, now)
+                {
+                    debug!(
+                        old = oldest_old_pod.metadata.name,
+                        new = oldest_new_pod.metadata.name,
+                        "Replacing old daemon pod with newer pod"
+                    );
+                    pods_to_delete.push(oldest_old_pod);
+                }
+            }
+        }
+    } else if exists {
+        // If daemon pod isn't supposed to run on node, but it is, delete all daemon pods on node.
+        for pod in daemon_pods {
+            if pod.metadata.deletion_timestamp.is_some() {
+                continue;
+            }
+            pods_to_delete.push(pod);
+        }
+    }
+
+    (nodes_needing_daemon_pods, pods_to_delete)
+}
+
+// syncNodes deletes given pods and creates new daemon set pods on the given nodes
+fn sync_nodes(
+    ds: &DaemonSet,
+    pods_to_delete: &[&Pod],
+    nodes_needing_daemon_pods: &[&str],
+    hash: &str,
+) -> Option<DaemonSetControllerAction> {
+    // THEMELIOS: rather than batching the creations and deletions we make one of them per step,
+    // creations first.
+    if let Some(node_name) = nodes_needing_daemon_pods.first() {
+        let template = create_pod_template(&ds.spec.template, hash);
+        let mut pod = get_pod_from_template(&ds.metadata, &template, &DaemonSet::GVK);
+        // THEMELIOS: we don't model node affinity so bind the pod to the node directly, as the
+        // controller did before daemon pods were scheduled by the default scheduler.
+        pod.spec.node_name = Some((*node_name).to_owned());
+        return Some(DaemonSetControllerAction::CreatePod(pod));
+    }
+
+    pods_to_delete
+        .first()
+        .map(|pod| DaemonSetControllerAction::DeletePod((*pod).clone()))
+}
+
+// CreatePodTemplate returns copy of provided template with additional
+// label which contains templateGeneration (for backward compatibility),
+// hash of provided template and sets default daemon tolerations.
+fn create_pod_template(template: &PodTemplateSpec, hash: &str) -> PodTemplateSpec {
+    let mut new_template = template.clone();
+
+    add_or_update_daemon_pod_tolerations(&mut new_template.spec);
+
+    if !hash.is_empty() {
+        new_template.metadata.labels.insert(
+            DEFAULT_DAEMONSET_UNIQUE_LABEL_KEY.to_owned(),
+            hash.to_owned(),
+        );
+    }
+    new_template
+}
+
+// AddOrUpdateDaemonPodTolerations apply necessary tolerations to DaemonSet Pods, e.g. node.kubernetes.io/not-ready:NoExecute.
+fn add_or_update_daemon_pod_tolerations(spec: &mut PodSpec) {
+    let tolerations = [
+        // DaemonSet pods shouldn't be deleted by NodeController in case of node problems.
+        (TAINT_NODE_NOT_READY, TaintEffect::NoExecute),
+        (TAINT_NODE_UNREACHABLE, TaintEffect::NoExecute),
+        // According to TaintNodesByCondition feature, all DaemonSet pods should tolerate
+        // MemoryPressure, DiskPressure, PIDPressure and Unschedulable taints.
+        (TAINT_NODE_DISK_PRESSURE, TaintEffect::NoSchedule),
+        (TAINT_NODE_MEMORY_PRESSURE, TaintEffect::NoSchedule),
+        (TAINT_NODE_PID_PRESSURE, TaintEffect::NoSchedule),
+        (TAINT_NODE_UNSCHEDULABLE, TaintEffect::NoSchedule),
+    ];
+    for (key, effect) in tolerations {
+        add_or_update_toleration(
+            spec,
+            Toleration {
+                key: key.to_owned(),
+                operator: Some(Operator::Exists),
+                value: None,
+                effect: Some(effect),
+                toleration_seconds: None,
+            },
+        );
+    }
+}
+
+// AddOrUpdateTolerationInPodSpec tries to add a toleration to the toleration list in PodSpec.
+fn add_or_update_toleration(spec: &mut PodSpec, toleration: Toleration) {
+    let existing = spec.tolerations.iter_mut().find(|t| {
+        t.key == toleration.key
+            && t.operator == toleration.operator
+            && t.value == toleration.value
+            && t.effect == toleration.effect
+    });
+    match existing {
+        Some(t) => *t = toleration,
+        None => spec.tolerations.push(toleration),
+    }
+}
+
+// NodeShouldRunDaemonPod checks a set of preconditions against a (node,daemonset) and returns a
+// summary. Returned booleans are:
+//   - shouldRun:
+//     Returns true when a daemonset should run on the node if a daemonset pod is not already
+//     running on that node.
+//   - shouldContinueRunning:
+//     Returns true when a daemonset should continue running on a node if a daemonset pod is already
+//     running on that node.
+pub fn node_should_run_daemon_pod(node: &Node, ds: &DaemonSet) -> (bool, bool) {
+    let template = create_pod_template(&ds.spec.template, "");
+    let spec = &template.spec;
+
+    // If the daemon set specifies a node name, check that it matches with node.Name.
+    if spec
+        .node_name
+        .as_ref()
+        .map_or(false, |n| n != &node.metadata.name)
+    {
+        return (false, false);
+    }
+
+    let fits_node_affinity = subset(&spec.node_selector, &node.metadata.labels);
+    if !fits_node_affinity {
+        return (false, false);
+    }
+
+    let fits_taints = node
+        .spec
+        .taints
+        .iter()
+        .filter(|t| matches!(t.effect, TaintEffect::NoSchedule | TaintEffect::NoExecute))
+        .all(|t| spec.tolerations.iter().any(|tol| tolerates_taint(tol, t)));
+    if !fits_taints {
+        // Scheduled daemon pods should continue running if they tolerate NoExecute taint.
+        let tolerates_no_execute = node
+            .spec
+            .taints
+            .iter()
+            .filter(|t| t.effect == TaintEffect::NoExecute)
+            .all(|t| spec.tolerations.iter().any(|tol| tolerates_taint(tol, t)));
+        return (false, tolerates_no_execute);
+    }
+
+    (true, true)
+}
+
+// ToleratesTaint checks if the toleration tolerates the taint.
+// The matching follows the rules below:
+//
+//  1. Empty toleration.effect means to match all taint effects,
+//     otherwise taint effect must equal to toleration.effect.
+//  2. If toleration.operator is 'Exists', it means to match all taint values.
+//  3. Empty toleration.key means to match all taint keys.
+//     If toleration.key is empty, toleration.operator must be 'Exists';
+//     this combination means to match all taint values and all taint keys.
+fn tolerates_taint(toleration: &Toleration, taint: &Taint) -> bool {
+    if toleration
+        .effect
+        .as_ref()
+        .map_or(false, |e| *e != taint.effect)
+    {
+        return false;
+    }
+    if !toleration.key.is_empty() && toleration.key != taint.key {
+        return false;
+    }
+    match toleration.operator {
+        Some(Operator::Exists) => true,
+        Some(Operator::Equal) | None => {
+            toleration.value.as_deref().unwrap_or_default() == taint.value
+        }
+    }
+}
+
+fn is_pod_terminal(pod: &Pod) -> bool {
+    pod.status.phase == PodPhase::Failed || pod.status.phase == PodPhase::Succeeded
+}
+
+// IsPodUpdated checks if pod contains label value that either matches templateGeneration or hash
+fn is_pod_updated(pod: &Pod, hash: &str) -> bool {
+    pod.metadata
+        .labels
+        .get(DEFAULT_DAEMONSET_UNIQUE_LABEL_KEY)
+        .map_or(false, |h| h == hash)
+}
+
+// podByCreationTimestampAndPhase, scheduled pods first, then the oldest.
+fn sort_by_creation_timestamp_and_phase(pods: &mut [&Pod]) {
+    pods.sort_by(|p1, p2| {
+        match (p1.spec.node_name.is_some(), p2.spec.node_name.is_some()) {
+            (true, false) => return Ordering::Less,
+            (false, true) => return Ordering::Greater,
+            _ => {}
+        }
+        if p1.metadata.creation_timestamp == p2.metadata.creation_timestamp {
+            return p1.metadata.name.cmp(&p2.metadata.name);
+        }
+        p1.metadata
+            .creation_timestamp
+            .cmp(&p2.metadata.creation_timestamp)
+    })
+}
+
+// AllowsSurge returns true if the daemonset allows more than a single pod on any node.
+fn allows_surge(ds: &DaemonSet) -> bool {
+    surge_count(ds, 1) != 0
+}
+
+// SurgeCount returns 0 if surge is not requested, the expected surge number to allow
+// out of numberToSchedule if surge is configured, or an error if the surge percentage
+// requested is invalid.
+fn surge_count(ds: &DaemonSet, number_to_schedule: usize) -> usize {
+    if ds.spec.update_strategy.r#type != DaemonSetUpdateStrategyType::RollingUpdate {
+        return 0;
+    }
+    ds.spec
+        .update_strategy
+        .rolling_update
+        .as_ref()
+        .and_then(|r| r.max_surge.as_ref())
+        .map_or(0, |s| {
+            s.scaled_value(number_to_schedule as u32, true) as usize
+        })
+}
+
+// UnavailableCount returns 0 if unavailability is not requested, the expected
+// unavailability number to allow out of numberToSchedule if requested, or an error if
+// the unavailability percentage requested is invalid.
+fn unavailable_count(ds: &DaemonSet, number_to_schedule: usize) -> usize {
+    if ds.spec.update_strategy.r#type != DaemonSetUpdateStrategyType::RollingUpdate {
+        return 0;
+    }
+    ds.spec
+        .update_strategy
+        .rolling_update
+        .as_ref()
+        .and_then(|r| r.max_unavailable.as_ref())
+        .map_or(0, |u| {
+            u.scaled_value(number_to_schedule as u32, true) as usize
+        })
+}
+
+// rollingUpdate identifies the set of old pods to in-place update, delete, or additional pods to create on nodes,
+// remaining within the constraints imposed by the update strategy.
+fn rolling_update(
+    ds: &DaemonSet,
+    nodes: &[&Node],
+    node_to_daemon_pods: &BTreeMap<&str, Vec<&Pod>>,
+    hash: &str,
+    now: Time,
+) -> Option<DaemonSetControllerAction> {
+    let mut node_to_daemon_pods = node_to_daemon_pods.clone();
+    let (max_surge, max_unavailable) =
+        updated_desired_node_counts(ds, nodes, &mut node_to_daemon_pods);
+
+    let min_ready_seconds = ds.spec.min_ready_seconds;
+
+    // When not surging, we delete just enough pods to stay under the maxUnavailable limit, if any
+    // are necessary, and let the core loop create new instances on those nodes.
+    //
+    // Assumptions:
+    // * Expect manage loop to allow no more than one pod per node
+    // * Expect manage loop will create new pods
+    // * Expect manage loop will handle failed pods
+    // * Deleted pods do not count as unavailable so that updates make progress when nodes are down
+    // Invariants:
+    // * The number of new pods that are unavailable must be less than maxUnavailable
+    // * A node with an available old pod is a candidate for deletion if it does not violate other invariants
+    if max_surge == 0 {
+        let mut num_unavailable = 0;
+        let mut allowed_replacement_pods = Vec::new();
+        let mut candidate_pods_to_delete = Vec::new();
+        for pods in node_to_daemon_pods.values() {
+            let Some(updated_pods) = find_updated_pods_on_node(pods, hash) else {
+                // let the manage loop clean up this node, and treat it as an unavailable node
+                num_unavailable += 1;
+                continue;
+            };
+            match updated_pods {
+                (None, None) | (Some(_), Some(_)) => {
+                    // the manage loop will handle creating or deleting the appropriate pod, consider this unavailable
+                    num_unavailable += 1;
+                }
+                (Some(new_pod), None) => {
+                    // this pod is up to date, check its availability
+                    if !is_pod_available(new_pod, min_ready_seconds, now) {
+                        // an unavailable new pod is counted against maxUnavailable
+                        num_unavailable += 1;
+                    }
+                }
+                (None, Some(old_pod)) => {
+                    // this pod is old, it is an update candidate
+                    if !is_pod_available(old_pod, min_ready_seconds, now) {
+                        // the old pod isn't available, so it needs to be replaced
+                        allowed_replacement_pods.push(old_pod);
+                    } else if num_unavailable < max_unavailable {
+                        candidate_pods_to_delete.push(old_pod);
+                    }
+                }
+            }
+        }
+
+        // use any of the candidates we can, including the allowedReplacemnntPods
+        let remaining_unavailable = max_unavailable
+            .saturating_sub(num_unavailable)
+            .min(candidate_pods_to_delete.len());
+        let mut old_pods_to_delete = allowed_replacement_pods;
+        old_pods_to_delete.extend(&candidate_pods_to_delete[..remaining_unavailable]);
+
+        return sync_nodes(ds, &old_pods_to_delete, &[], hash);
+    }
+
+    // When surging, we create new pods whenever an old pod is unavailable, and we can create up
+    // to maxSurge extra pods
+    //
+    // Assumptions:
+    // * Expect manage loop to allow no more than two pods per node, one old, one new
+    // * Expect manage loop will create new pods if there are no pods on node
+    // * Expect manage loop will handle failed pods
+    // * Deleted pods do not count as unavailable so that updates make progress when nodes are down
+    // Invariants:
+    // * A node with an unavailable old pod is a candidate for immediate new pod creation
+    // * An old available pod is deleted if a new pod is available
+    // * No more than maxSurge new pods are created for old available pods at any one time
+    let mut old_pods_to_delete = Vec::new();
+    let mut should_not_run_pods_to_delete = Vec::new();
+    let mut candidate_new_nodes = Vec::new();
+    let mut allowed_new_nodes = Vec::new();
+    let mut num_surge = 0;
+    for (node_name, pods) in &node_to_daemon_pods {
+        let Some(updated_pods) = find_updated_pods_on_node(pods, hash) else {
+            // let the manage loop clean up this node, and treat it as a surge node
+            num_surge += 1;
+            continue;
+        };
+        match updated_pods {
+            (_, None) => {
+                // we don't need to do anything to this node, the manage loop will handle it
+            }
+            (None, Some(old_pod)) => {
+                // this is a surge candidate
+                if !is_pod_available(old_pod, min_ready_seconds, now) {
+                    let should_run = nodes
+                        .iter()
+                        .find(|n| n.metadata.name == *node_name)
+                        .map_or(false, |n| node_should_run_daemon_pod(n, ds).0);
+                    if should_run {
+                        // the old pod isn't available, allow it to become a replacement
+                        allowed_new_nodes.push(*node_name);
+                    } else {
+                        should_not_run_pods_to_delete.push(old_pod);
+                    }
+                } else if num_surge < max_surge {
+                    // record the candidate
+                    candidate_new_nodes.push(*node_name);
+                }
+            }
+            (Some(new_pod), Some(old_pod)) => {
+                // we have already surged onto this node, determine our state
+                if is_pod_available(new_pod, min_ready_seconds, now) {
+                    // we're available, delete the old pod
+                    old_pods_to_delete.push(old_pod);
+                } else {
+                    // we're waiting to go available here
+                    num_surge += 1;
+                }
+            }
+        }
+    }
+
+    // use any of the candidates we can, including the allowedNewNodes
+    let remaining_surge = max_surge
+        .saturating_sub(num_surge)
+        .min(candidate_new_nodes.len());
+    let mut new_nodes_to_create = allowed_new_nodes;
+    new_nodes_to_create.extend(&candidate_new_nodes[..remaining_surge]);
+
+    old_pods_to_delete.extend(should_not_run_pods_to_delete);
+    sync_nodes(ds, &old_pods_to_delete, &new_nodes_to_create, hash)
+}
+
+// findUpdatedPodsOnNode looks at non-deleted pods on a given node and returns true if there
+// is at most one of each old and new pods, or false if there are multiples. We can skip
+// processing the particular node in those scenarios and let the manage loop prune the
+// excess pods for our next time around.
+#[allow(clippy::type_complexity)]
+fn find_updated_pods_on_node<'a>(
+    pods_on_node: &[&'a Pod],
+    hash: &str,
+) -> Option<(Option<&'a Pod>, Option<&'a Pod>)> {
+    let mut new_pod = None;
+    let mut old_pod = None;
+    for pod in pods_on_node {
+        if pod.metadata.deletion_timestamp.is_some() {
+            continue;
+        }
+        let slot = if is_pod_updated(pod, hash) {
+            &mut new_pod
+        } else {
+            &mut old_pod
+        };
+        if slot.is_some() {
+            return None;
+        }
+        *slot = Some(*pod);
+    }
+    Some((new_pod, old_pod))
+}
+
+// updatedDesiredNodeCounts calculates the true number of allowed unavailable or surge pods and
+// updates the nodeToDaemonPods array to include an empty array for every node that is not scheduled.
+fn updated_desired_node_counts<'a>(
+    ds: &DaemonSet,
+    nodes: &[&'a Node],
+    node_to_daemon_pods: &mut BTreeMap<&'a str, Vec<&Pod>>,
+) -> (usize, usize) {
+    let mut desired_number_scheduled = 0;
+    for node in nodes {
+        let (want_to_run, _) = node_should_run_daemon_pod(node, ds);
+        if !want_to_run {
+            continue;
+        }
+        desired_number_scheduled += 1;
+        node_to_daemon_pods
+            .entry(node.metadata.name.as_str())
+            .or_default();
+    }
+
+    let mut max_unavailable = unavailable_count(ds, desired_number_scheduled);
+    let max_surge = surge_count(ds, desired_number_scheduled);
+
+    // if the daemonset returned with an impossible configuration, obey the default of unavailable=1 (in the
+    // event the apiserver returns 0 for both surge and unavailability)
+    if desired_number_scheduled > 0 && max_unavailable == 0 && max_surge == 0 {
+        max_unavailable = 1;
+    }
+    (max_surge, max_unavailable)
+}
+
+// constructHistory finds all histories controlled by the given DaemonSet, and
+// update current history revision number, or create current history if need to.
+// It also deduplicates current history, and adds missing unique labels to existing histories.
+fn construct_history<'a>(
+    ds: &DaemonSet,
+    all_revisions: &[&'a ControllerRevision],
+) -> ValOrOp<(&'a ControllerRevision, Vec<&'a ControllerRevision>)> {
+    let patch = get_patch(ds);
+    let (mut cur_histories, old_histories): (Vec<_>, Vec<_>) = all_revisions
+        .iter()
+        .copied()
+        .filter(|r| {
+            r.metadata
+                .owner_references
+                .iter()
+                .any(|or| or.controller && or.uid == ds.metadata.uid)
+        })
+        .partition(|r| r.data == patch);
+
+    let curr_revision = old_histories
+        .iter()
+        .map(|r| r.revision)
+        .max()
+        .unwrap_or_default()
+        + 1;
+
+    // Create a new history if the current one isn't found
+    if cur_histories.is_empty() {
+        return ValOrOp::Op(snapshot(ds, curr_revision, all_revisions, patch));
+    }
+
+    // dedupCurHistories: keep the history with the highest revision, deleting the rest
+    // THEMELIOS: pods aren't relabelled to the kept history, those of the duplicates get replaced
+    // by the rolling update instead.
+    cur_histories.sort_by_key(|r| r.revision);
+    let cur = cur_histories.pop().unwrap();
+    if let Some(duplicate) = cur_histories.first() {
+        return ValOrOp::Op(DaemonSetControllerAction::DeleteControllerRevision(
+            (*duplicate).clone(),
+        ));
+    }
+
+    // Update revision number if necessary
+    if cur.revision < curr_revision {
+        let mut to_update = cur.clone();
+        to_update.revision = curr_revision;
+        return ValOrOp::Op(DaemonSetControllerAction::UpdateControllerRevision(
+            to_update,
+        ));
+    }
+
+    ValOrOp::Resource((cur, old_histories))
+}
+
+// getPatch returns a strategic merge patch that can be applied to restore a Daemonset to a
+// previous version. If the returned error is nil the patch is valid. The current state that we save is just the
+// PodSpecTemplate. We can modify this later to encompass more state (or less) and remain compatible with previously
+// recorded patches.
+fn get_patch(ds: &DaemonSet) -> String {
+    let patch_ds = DaemonSet {
+        spec: DaemonSetSpec {
+            template: ds.spec.template.clone(),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    serde_json::to_string(&patch_ds).unwrap()
+}
+
+fn snapshot(
+    ds: &DaemonSet,
+    revision: u64,
+    all_revisions: &[&ControllerRevision],
+    patch: String,
+) -> DaemonSetControllerAction {
+    let hash = compute_hash(&ds.spec.template, ds.status.collision_count);
+    let name = format!("{}-{}", ds.metadata.name, hash);
+
+    if all_revisions.iter().any(|r| r.metadata.name == name) {
+        // Handle name collisions between different history
+        // We'll bump the collision count and try again next time
+        debug!(
+            name,
+            "Found a hash collision for daemonset, bumping collision count"
+        );
+        let mut ds = ds.clone();
+        ds.status.collision_count += 1;
+        return DaemonSetControllerAction::UpdateDaemonSetStatus(ds);
+    }
+
+    let mut labels = ds.spec.template.metadata.labels.clone();
+    labels.insert(DEFAULT_DAEMONSET_UNIQUE_LABEL_KEY.to_owned(), hash);
+    let history = ControllerRevision {
+        metadata: Metadata {
+            name,
+            namespace: ds.metadata.namespace.clone(),
+            labels,
+            annotations: ds.metadata.annotations.clone(),
+            owner_references: vec![new_controller_ref(&ds.metadata, &DaemonSet::GVK)],
+            ..Default::default()
+        },
+        data: patch,
+        revision,
+    };
+    DaemonSetControllerAction::CreateControllerRevision(history)
+}
+
+fn cleanup_history(
+    ds: &DaemonSet,
+    node_to_daemon_pods: &BTreeMap<&str, Vec<&Pod>>,
+    old: &[&ControllerRevision],
+) -> Option<DaemonSetControllerAction> {
+    let to_keep = ds
+        .spec
+        .revision_history_limit
+        .unwrap_or(DEFAULT_REVISION_HISTORY_LIMIT) as usize;
+    if old.len() <= to_keep {
+        return None;
+    }
+
+    // Find all hashes of live pods
+    let live_hashes = node_to_daemon_pods
+        .values()
+        .flatten()
+        .filter_map(|pod| pod.metadata.labels.get(DEFAULT_DAEMONSET_UNIQUE_LABEL_KEY))
+        .collect::<BTreeSet<_>>();
+
+    // Clean up old history from smallest to highest revision (from oldest to newest)
+    let mut old = old.to_vec();
+    old.sort_by_key(|r| r.revision);
+    old.into_iter()
+        .find(|history| {
+            history
+                .metadata
+                .labels
+                .get(DEFAULT_DAEMONSET_UNIQUE_LABEL_KEY)
+                .map_or(true, |hash| !live_hashes.contains(hash))
+        })
+        .map(|history| DaemonSetControllerAction::DeleteControllerRevision(history.clone()))
+}
+
+fn update_daemonset_status(
+    ds: &DaemonSet,
+    nodes: &[&Node],
+    node_to_daemon_pods: &BTreeMap<&str, Vec<&Pod>>,
+    hash: &str,
+    update_observed_gen: bool,
+    state_revision: &Revision,
+    now: Time,
+) -> Option<DaemonSetControllerAction> {
+    let mut status = DaemonSetStatus {
+        collision_count: ds.status.collision_count,
+        observed_generation: ds.status.observed_generation,
+        observed_revision: ds.status.observed_revision.clone(),
+        ..Default::default()
+    };
+    for node in nodes {
+        let (want_to_run, _) = node_should_run_daemon_pod(node, ds);
+        let daemon_pods = node_to_daemon_pods
+            .get(node.metadata.name.as_str())
+            .filter(|pods| !pods.is_empty());
+
+        if !want_to_run {
+            if daemon_pods.is_some() {
+                status.number_misscheduled += 1;
+            }
+            continue;
+        }
+
+        status.desired_number_scheduled += 1;
+        let Some(daemon_pods) = daemon_pods else {
+            continue;
+        };
+        status.current_number_scheduled += 1;
+        // Sort the daemon pods by creation time, so that the oldest is first.
+        let mut daemon_pods = daemon_pods.clone();
+        sort_by_creation_timestamp_and_phase(&mut daemon_pods);
+        let pod = daemon_pods[0];
+        if is_pod_ready(pod) {
+            status.number_ready += 1;
+            if is_pod_available(pod, ds.spec.min_ready_seconds, now) {
+                status.number_available += 1;
+            }
+        }
+        if is_pod_updated(pod, hash) {
+            status.updated_number_scheduled += 1;
+        }
+    }
+    status.number_unavailable = status
+        .desired_number_scheduled
+        .saturating_sub(status.number_available);
+
+    store_daemonset_status(ds, status, update_observed_gen, state_revision)
+}
+
+fn store_daemonset_status(
+    ds: &DaemonSet,
+    mut status: DaemonSetStatus,
+    update_observed_gen: bool,
+    state_revision: &Revision,
+) -> Option<DaemonSetControllerAction> {
+    if ds.status == status && ds.status.observed_generation >= ds.metadata.generation {
+        return None;
+    }
+
+    if update_observed_gen {
+        status.observed_generation = ds.metadata.generation;
+    }
+    status.observed_revision = state_revision.clone();
+
+    let mut ds = ds.clone();
+    ds.status = status;
+    Some(DaemonSetControllerAction::UpdateDaemonSetStatus(ds))
+}
@@ -1,16 +1,14 @@
-use std::{collections::BTreeMap, hash::Hash};
+use std::collections::BTreeMap;
 
 use crate::{
     abstract_model::ControllerAction,
-    controller::util::new_controller_ref,
-    hasher::FnvHasher,
+    controller::util::{compute_hash, new_controller_ref},
     resources::{
         ConditionStatus, Deployment, DeploymentCondition, DeploymentConditionType,
-        DeploymentStatus, DeploymentStrategyType, LabelSelector, Pod, PodTemplateSpec, ReplicaSet,
-        ReplicaSetCondition, ReplicaSetConditionType,
+        DeploymentStatus, DeploymentStrategyType, LabelSelector, Pod, PodTemplateSpec,
+        PropagationPolicy, ReplicaSet, ReplicaSetCondition, ReplicaSetConditionType, Time,
     },
     state::{revision::Revision, StateView},
-    utils::now,
 };
 use tracing::debug;
 
@@ -115,7 +113,7 @@ impl From<DeploymentControllerAction> for ControllerAction {
                 ControllerAction::UpdateReplicaSet(rs)
             }
             DeploymentControllerAction::DeleteReplicaSet(rs) => {
-                ControllerAction::DeleteReplicaSet(rs)
+                ControllerAction::DeleteReplicaSet(rs, PropagationPolicy::Background)
             }
             DeploymentControllerAction::UpdateReplicaSets(rss) => {
                 ControllerAction::UpdateReplicaSets(rss)
@@ -138,10 +136,18 @@ impl Controller for DeploymentController {
     ) -> Option<DeploymentControllerAction> {
         local_state.revision = Some(global_state.revision.clone());
         for deployment in global_state.deployments.iter() {
-            let replicasets = global_state.replicasets.iter().collect::<Vec<_>>();
+            let replicasets = global_state
+                .replicasets
+                .in_namespace(&deployment.metadata.namespace)
+                .collect::<Vec<_>>();
             let pod_map = BTreeMap::new();
-            if let Some(op) = reconcile(deployment, &replicasets, &pod_map, &global_state.revision)
-            {
+            if let Some(op) = reconcile(
+                deployment,
+                &replicasets,
+                &pod_map,
+                &global_state.revision,
+                global_state.now,
+            ) {
                 return Some(op);
             }
         }
@@ -166,6 +172,7 @@ fn reconcile(
     all_replicasets: &[&ReplicaSet],
     pod_map: &BTreeMap<String, Vec<Pod>>,
     state_revision: &Revision,
+    now: Time,
 ) -> Option<DeploymentControllerAction> {
     let everything = LabelSelector::default();
     if deployment.spec.selector == everything {
@@ -194,13 +201,14 @@ fn reconcile(
             &replicasets,
             all_replicasets,
             state_revision,
+            now,
         );
     }
 
     // Update deployment conditions with an Unknown condition when pausing/resuming
     // a deployment. In this way, we can be sure that we won't timeout when a user
     // resumes a Deployment with a set progressDeadlineSeconds.
-    if let Some(op) = check_paused_conditions(&mut deployment.clone()) {
+    if let Some(op) = check_paused_conditions(&mut deployment.clone(), now) {
         return Some(op);
     }
 
@@ -210,6 +218,7 @@ fn reconcile(
             &replicasets,
             all_replicasets,
             state_revision,
+            now,
         );
     }
 
@@ -217,10 +226,11 @@ fn reconcile(
     // revision so we should ensure that we won't proceed to update replica sets until we
     // make sure that the deployment has cleaned up its rollback spec in subsequent enqueues.
     if get_rollback_to(deployment).is_some() {
-        return rollback(&mut deployment.clone(), &replicasets, all_replicasets);
+        return rollback(&mut deployment.clone(), &replicasets, all_replicasets, now);
     }
 
-    let scaling_event = is_scaling_event(&mut deployment.clone(), &replicasets, all_replicasets);
+    let scaling_event =
+        is_scaling_event(&mut deployment.clone(), &replicasets, all_replicasets, now);
     let scaling_event = match scaling_event {
         ValOrOp::Resource(r) => r,
         ValOrOp::Op(op) => return Some(op),
@@ -231,6 +241,7 @@ fn reconcile(
             &replicasets,
             all_replicasets,
             state_revision,
+            now,
         );
     }
 
@@ -247,12 +258,14 @@ fn reconcile(
             all_replicasets,
             pod_map,
             state_revision,
+            now,
         ),
         DeploymentStrategyType::RollingUpdate => rollout_rolling(
             &mut deployment.clone(),
             &replicasets,
             all_replicasets,
             state_revision,
+            now,
         ),
     }
 }
@@ -328,9 +341,15 @@ fn sync_status_only(
     replicasets: &[&ReplicaSet],
     replicasets_in_ns: &[&ReplicaSet],
     state_revision: &Revision,
+    now: Time,
 ) -> Option<DeploymentControllerAction> {
-    let (new_replicaset, old_replicasets) =
-        get_all_replicasets_and_sync_revision(deployment, replicasets, replicasets_in_ns, false);
+    let (new_replicaset, old_replicasets) = get_all_replicasets_and_sync_revision(
+        deployment,
+        replicasets,
+        replicasets_in_ns,
+        false,
+        now,
+    );
     let new_replicaset = match new_replicaset {
         Some(ValOrOp::Resource(r)) => Some(r),
         Some(ValOrOp::Op(op)) => return Some(op),
@@ -340,13 +359,16 @@ fn sync_status_only(
     if let Some(new_replicaset) = &new_replicaset {
         all_rss.push(new_replicaset);
     }
-    sync_deployment_status(&all_rss, &new_replicaset, deployment, state_revision)
+    sync_deployment_status(&all_rss, &new_replicaset, deployment, state_revision, now)
 }
 
 // checkPausedConditions checks if the given deployment is paused or not and adds an appropriate condition.
 // These conditions are needed so that we won't accidentally report lack of progress for resumed deployments
 // that were paused for longer than progressDeadlineSeconds.
-fn check_paused_conditions(deployment: &mut Deployment) -> Option<DeploymentControllerAction> {
+fn check_paused_conditions(
+    deployment: &mut Deployment,
+    now: Time,
+) -> Option<DeploymentControllerAction> {
     debug!("Checking paused conditions");
     if has_progress_deadline(deployment) {
         return None;
@@ -366,6 +388,7 @@ fn check_paused_conditions(deployment: &mut Deployment) -> Option<DeploymentCont
             ConditionStatus::Unknown,
             PAUSED_DEPLOY_REASON.to_owned(),
             "Deployment is paused".to_owned(),
+            now,
         );
         set_deployment_condition(&mut deployment.status, cond);
         Some(DeploymentControllerAction::UpdateDeploymentStatus(
@@ -377,6 +400,7 @@ fn check_paused_conditions(deployment: &mut Deployment) -> Option<DeploymentCont
             ConditionStatus::Unknown,
             RESUMED_DEPLOY_REASON.to_owned(),
             "Deployment is resumed".to_owned(),
+            now,
         );
         set_deployment_condition(&mut deployment.status, cond);
         Some(DeploymentControllerAction::UpdateDeploymentStatus(
@@ -393,10 +417,16 @@ fn sync(
     replicasets: &[&ReplicaSet],
     replicasets_in_ns: &[&ReplicaSet],
     state_revision: &Revision,
+    now: Time,
 ) -> Option<DeploymentControllerAction> {
     debug!("Syncing deployment");
-    let (new_replicaset, old_replicasets) =
-        get_all_replicasets_and_sync_revision(deployment, replicasets, replicasets_in_ns, false);
+    let (new_replicaset, old_replicasets) = get_all_replicasets_and_sync_revision(
+        deployment,
+        replicasets,
+        replicasets_in_ns,
+        false,
+        now,
+    );
     let new_replicaset = match new_replicaset {
         Some(ValOrOp::Resource(r)) => Some(r),
         Some(ValOrOp::Op(op)) => return Some(op),
@@ -423,6 +453,7 @@ fn sync(
         &new_replicaset,
         deployment,
         state_revision,
+        now,
     ) {
         return Some(op);
     }
@@ -445,6 +476,7 @@ fn get_all_replicasets_and_sync_revision<'a>(
     replicasets: &[&'a ReplicaSet],
     replicasets_in_ns: &[&ReplicaSet],
     create_if_not_existed: bool,
+    now: Time,
 ) -> (Option<ValOrOp<ReplicaSet>>, Vec<&'a ReplicaSet>) {
     debug!("getting all replicasets and sync revision");
     let (_, all_old_replicasets) = find_old_replicasets(deployment, replicasets);
@@ -455,6 +487,7 @@ fn get_all_replicasets_and_sync_revision<'a>(
         &all_old_replicasets,
         replicasets_in_ns,
         create_if_not_existed,
+        now,
     );
 
     (new_replicaset, all_old_replicasets)
@@ -515,6 +548,7 @@ fn get_new_replicaset(
     old_replicasets: &[&ReplicaSet],
     replicasets_in_ns: &[&ReplicaSet],
     create_if_not_existed: bool,
+    now: Time,
 ) -> Option<ValOrOp<ReplicaSet>> {
     let existing_new_rs = find_new_replicaset(deployment, replicasets);
 
@@ -568,6 +602,7 @@ fn get_new_replicaset(
                 ConditionStatus::True,
                 FOUND_NEW_RSREASON.to_owned(),
                 message,
+                now,
             );
             set_deployment_condition(&mut deployment.status, condition);
             needs_update = true;
@@ -672,9 +707,16 @@ fn sync_deployment_status(
     new_replicaset: &Option<ReplicaSet>,
     deployment: &Deployment,
     state_revision: &Revision,
+    now: Time,
 ) -> Option<DeploymentControllerAction> {
     debug!("Syncing deployment status");
-    let new_status = calculate_status(all_replicasets, new_replicaset, deployment, state_revision);
+    let new_status = calculate_status(
+        all_replicasets,
+        new_replicaset,
+        deployment,
+        state_revision,
+        now,
+    );
     if deployment.status != new_status {
         debug!("Setting new status");
         let mut new_deployment = deployment.clone();
@@ -693,6 +735,7 @@ fn calculate_status(
     new_replicaset: &Option<ReplicaSet>,
     deployment: &Deployment,
     state_revision: &Revision,
+    now: Time,
 ) -> DeploymentStatus {
     let available_replicas = get_available_replica_count_for_replicasets(all_replicasets);
     let total_replicas = get_replica_count_for_replicasets(all_replicasets);
@@ -726,6 +769,7 @@ fn calculate_status(
             ConditionStatus::True,
             MINIMUM_REPLICAS_AVAILABLE.to_owned(),
             "Deployment has minimum availability.".to_owned(),
+            now,
         );
         set_deployment_condition(&mut status, min_availability);
     } else {
@@ -738,6 +782,7 @@ fn calculate_status(
             ConditionStatus::False,
             MINIMUM_REPLICAS_UNAVAILABLE.to_owned(),
             "Deployment does not have minimum availability.".to_owned(),
+            now,
         );
         set_deployment_condition(&mut status, no_min_availability);
     }
@@ -1210,12 +1255,13 @@ fn new_deployment_condition(
     status: ConditionStatus,
     reason: String,
     message: String,
+    now: Time,
 ) -> DeploymentCondition {
     DeploymentCondition {
         r#type: cond_type,
         status,
-        last_update_time: Some(now()),
-        last_transition_time: Some(now()),
+        last_update_time: Some(now),
+        last_transition_time: Some(now),
         reason: Some(reason),
         message: Some(message),
     }
@@ -1521,9 +1567,15 @@ fn rollback(
     deployment: &mut Deployment,
     replicasets: &[&ReplicaSet],
     replicasets_in_ns: &[&ReplicaSet],
+    now: Time,
 ) -> Option<DeploymentControllerAction> {
-    let (new_rs, all_old_rss) =
-        get_all_replicasets_and_sync_revision(deployment, replicasets, replicasets_in_ns, true);
+    let (new_rs, all_old_rss) = get_all_replicasets_and_sync_revision(
+        deployment,
+        replicasets,
+        replicasets_in_ns,
+        true,
+        now,
+    );
 
     let new_rs = match new_rs {
         Some(ValOrOp::Resource(r)) => Some(r),
@@ -1648,9 +1700,15 @@ fn is_scaling_event(
     deployment: &mut Deployment,
     replicasets: &[&ReplicaSet],
     replicasets_in_ns: &[&ReplicaSet],
+    now: Time,
 ) -> ValOrOp<bool> {
-    let (new_rs, old_rss) =
-        get_all_replicasets_and_sync_revision(deployment, replicasets, replicasets_in_ns, false);
+    let (new_rs, old_rss) = get_all_replicasets_and_sync_revision(
+        deployment,
+        replicasets,
+        replicasets_in_ns,
+        false,
+        now,
+    );
     let new_rs = match new_rs {
         Some(ValOrOp::Resource(r)) => Some(r),
         Some(ValOrOp::Op(op)) => return ValOrOp::Op(op),
@@ -1756,30 +1814,6 @@ fn set_from_replicaset_template(deployment: &mut Deployment, template: &PodTempl
         .remove(DEFAULT_DEPLOYMENT_UNIQUE_LABEL_KEY);
 }
 
-// ComputeHash returns a hash value calculated from pod template and
-// a collisionCount to avoid hash collision. The hash will be safe encoded to
-// avoid bad words.
-fn compute_hash(template: &PodTemplateSpec, collision_count: u32) -> String {
-    let mut hasher = FnvHasher::new_32a();
-    template.hash(&mut hasher);
-
-    // Add collisionCount in the hash
-    let bytes = collision_count.to_le_bytes();
-    hasher.write(&bytes);
-
-    safe_encode_string(&hasher.finish_32().to_string())
-}
-
-fn safe_encode_string(s: &str) -> String {
-    const ALPHA_NUMS: &[char] = &[
-        'b', 'c', 'd', 'f', 'g', 'h', 'j', 'k', 'l', 'm', 'n', 'p', 'q', 'r', 's', 't', 'v', 'w',
-        'x', 'z', '2', '4', '5', '6', '7', '8', '9',
-    ];
-    s.chars()
-        .map(|c| ALPHA_NUMS[c as usize % ALPHA_NUMS.len()])
-        .collect()
-}
-
 // rolloutRolling implements the logic for rolling a new replica set.
 #[tracing::instrument(skip_all)]
 fn rollout_rolling(
@@ -1787,10 +1821,16 @@ fn rollout_rolling(
     replicasets: &[&ReplicaSet],
     replicasets_in_ns: &[&ReplicaSet],
     state_revision: &Revision,
+    now: Time,
 ) -> Option<DeploymentControllerAction> {
     debug!("Rolling out an update");
-    let (new_replicaset, old_replicasets) =
-        get_all_replicasets_and_sync_revision(deployment, replicasets, replicasets_in_ns, true);
+    let (new_replicaset, old_replicasets) = get_all_replicasets_and_sync_revision(
+        deployment,
+        replicasets,
+        replicasets_in_ns,
+        true,
+        now,
+    );
     let new_replicaset = match new_replicaset {
         Some(ValOrOp::Resource(r)) => r,
         Some(ValOrOp::Op(op)) => return Some(op),
@@ -1831,6 +1871,7 @@ fn rollout_rolling(
         &Some(new_replicaset.clone()),
         deployment,
         state_revision,
+        now,
     )
 }
 
@@ -1844,8 +1885,9 @@ fn sync_rollout_status(
     new_rs: &Option<ReplicaSet>,
     deployment: &Deployment,
     state_revision: &Revision,
+    now: Time,
 ) -> Option<DeploymentControllerAction> {
-    let mut new_status = calculate_status(all_rss, new_rs, deployment, state_revision);
+    let mut new_status = calculate_status(all_rss, new_rs, deployment, state_revision, now);
     debug!("Checking new status");
 
     if !has_progress_deadline(deployment) {
@@ -1869,6 +1911,7 @@ fn sync_rollout_status(
                 ConditionStatus::True,
                 NEW_RSAVAILABLE_REASON.to_owned(),
                 msg,
+                now,
             );
             set_deployment_condition(&mut new_status, condition);
         } else if deployment_progressing(deployment, &new_status) {
@@ -1878,6 +1921,7 @@ fn sync_rollout_status(
                 ConditionStatus::True,
                 REPLICASET_UPDATED_REASON.to_owned(),
                 msg,
+                now,
             );
             if let Some(current_cond) = current_cond {
                 if current_cond.status == ConditionStatus::True {
@@ -1886,7 +1930,7 @@ fn sync_rollout_status(
                 remove_deployment_condition(&mut new_status, DeploymentConditionType::Progressing);
             }
             set_deployment_condition(&mut new_status, condition);
-        } else if deployment_timed_out(deployment, &new_status) {
+        } else if deployment_timed_out(deployment, &new_status, now) {
             let msg = format!(
                 "Deployment {} has timed out progressing.",
                 deployment.metadata.name
@@ -1896,6 +1940,7 @@ fn sync_rollout_status(
                 ConditionStatus::False,
                 TIMED_OUT_REASON.to_owned(),
                 msg,
+                now,
             );
             set_deployment_condition(&mut new_status, condition);
         }
@@ -2130,10 +2175,16 @@ fn rollout_recreate(
     replicasets_in_ns: &[&ReplicaSet],
     pod_map: &BTreeMap<String, Vec<Pod>>,
     state_revision: &Revision,
+    now: Time,
 ) -> Option<DeploymentControllerAction> {
     // Don't create a new RS if not already existed, so that we avoid scaling up before scaling down.
-    let (new_replicaset, old_replicasets) =
-        get_all_replicasets_and_sync_revision(deployment, replicasets, replicasets_in_ns, false);
+    let (new_replicaset, old_replicasets) = get_all_replicasets_and_sync_revision(
+        deployment,
+        replicasets,
+        replicasets_in_ns,
+        false,
+        now,
+    );
     let new_replicaset = match new_replicaset {
         Some(ValOrOp::Resource(r)) => Some(r),
         Some(ValOrOp::Op(op)) => return Some(op),
@@ -2158,15 +2209,20 @@ fn rollout_recreate(
 
     if old_pods_running(&new_replicaset, &old_replicasets, pod_map) {
         let all_rss = all_rss.iter().collect::<Vec<_>>();
-        return sync_rollout_status(&all_rss, &new_replicaset, deployment, state_revision);
+        return sync_rollout_status(&all_rss, &new_replicaset, deployment, state_revision, now);
     }
 
     // If we need to create a new RS, create it now.
     let (new_replicaset, old_replicasets) = if let Some(new_replicaset) = new_replicaset {
         (new_replicaset, old_replicasets)
     } else {
-        let (new_replicaset, old_replicasets) =
-            get_all_replicasets_and_sync_revision(deployment, replicasets, replicasets_in_ns, true);
+        let (new_replicaset, old_replicasets) = get_all_replicasets_and_sync_revision(
+            deployment,
+            replicasets,
+            replicasets_in_ns,
+            true,
+            now,
+        );
         let new_replicaset = match new_replicaset {
             Some(ValOrOp::Resource(r)) => r,
             Some(ValOrOp::Op(op)) => return Some(op),
@@ -2188,7 +2244,13 @@ fn rollout_recreate(
     }
 
     let all_rss = all_rss.iter().collect::<Vec<_>>();
-    sync_rollout_status(&all_rss, &Some(new_replicaset), deployment, state_revision)
+    sync_rollout_status(
+        &all_rss,
+        &Some(new_replicaset),
+        deployment,
+        state_revision,
+        now,
+    )
 }
 
 fn scale_down_old_replicasets_for_recreate(
@@ -2235,7 +2297,7 @@ fn deployment_progressing(deployment: &Deployment, new_status: &DeploymentStatus
         || new_status.available_replicas > old_status.available_replicas
 }
 
-fn deployment_timed_out(deployment: &Deployment, new_status: &DeploymentStatus) -> bool {
+fn deployment_timed_out(deployment: &Deployment, new_status: &DeploymentStatus, now: Time) -> bool {
     if !has_progress_deadline(deployment) {
         return false;
     }
@@ -2253,7 +2315,6 @@ fn deployment_timed_out(deployment: &Deployment, new_status: &DeploymentStatus)
     }
 
     let from = cond.last_update_time.unwrap();
-    let now = now();
     let delta = std::time::Duration::from_secs(
         deployment
             .spec
@@ -0,0 +1,352 @@
+use crate::{
+    abstract_model::ControllerAction,
+    resources::{
+        ControllerRevision, CronJob, DaemonSet, Deployment, Job, Metadata, OwnerReference, Pod,
+        PropagationPolicy, ReplicaSet, StatefulSet, FOREGROUND_DELETION_FINALIZER,
+        ORPHAN_FINALIZER,
+    },
+    state::{revision::Revision, StateView},
+};
+
+use super::Controller;
+
+/// Deletes dependents whose owners have all gone, and propagates the deletion of owners being
+/// deleted in the foreground or with their dependents orphaned.
+///
+/// Owners are looked up in the view the controller steps on, so a stale view can make an owner
+/// that has just been created look like it has gone.
+#[derive(Clone, Debug)]
+pub struct GarbageCollectorController;
+
+#[derive(Debug, Default, Hash, Clone, PartialEq, Eq)]
+pub struct GarbageCollectorControllerState {
+    pub revision: Option<Revision>,
+}
+
+#[derive(Debug)]
+pub enum GarbageCollectorAction {
+    // delete dependents
+    SoftDeletePod(Pod),
+    DeleteReplicaSet(ReplicaSet),
+    DeleteControllerRevision(ControllerRevision),
+    DeleteJob(Job),
+
+    // change the owner references of dependents
+    UpdatePod(Pod),
+    UpdateReplicaSet(ReplicaSet),
+    UpdateControllerRevision(ControllerRevision),
+    UpdateJob(Job),
+
+    // remove finalizers from owners
+    UpdateDeployment(Deployment),
+    UpdateStatefulSet(StatefulSet),
+    UpdateDaemonSet(DaemonSet),
+    UpdateCronJob(CronJob),
+}
+
+impl From<GarbageCollectorAction> for ControllerAction {
+    fn from(value: GarbageCollectorAction) -> Self {
+        match value {
+            GarbageCollectorAction::SoftDeletePod(pod) => ControllerAction::SoftDeletePod(pod),
+            GarbageCollectorAction::DeleteReplicaSet(rs) => {
+                ControllerAction::DeleteReplicaSet(rs, PropagationPolicy::Background)
+            }
+            GarbageCollectorAction::DeleteControllerRevision(cr) => {
+                ControllerAction::DeleteControllerRevision(cr)
+            }
+            GarbageCollectorAction::DeleteJob(job) => ControllerAction::DeleteJob(job),
+            GarbageCollectorAction::UpdatePod(pod) => ControllerAction::UpdatePod(pod),
+            GarbageCollectorAction::UpdateReplicaSet(rs) => ControllerAction::UpdateReplicaSet(rs),
+            GarbageCollectorAction::UpdateControllerRevision(cr) => {
+                ControllerAction::UpdateControllerRevision(cr)
+            }
+            GarbageCollectorAction::UpdateDeployment(dep) => {
+                ControllerAction::UpdateDeployment(dep)
+            }
+            GarbageCollectorAction::UpdateStatefulSet(sts) => {
+                ControllerAction::UpdateStatefulSet(sts)
+            }
+            GarbageCollectorAction::UpdateDaemonSet(ds) => ControllerAction::UpdateDaemonSet(ds),
+            GarbageCollectorAction::UpdateJob(job) => ControllerAction::UpdateJob(job),
+            GarbageCollectorAction::UpdateCronJob(cj) => ControllerAction::UpdateCronJob(cj),
+        }
+    }
+}
+
+impl Controller for GarbageCollectorController {
+    type Action = GarbageCollectorAction;
+    type State = GarbageCollectorControllerState;
+
+    // https://kubernetes.io/docs/concepts/architecture/garbage-collection/
+    fn step(
+        &self,
+        global_state: &StateView,
+        local_state: &mut Self::State,
+    ) -> Option<Self::Action> {
+        local_state.revision = Some(global_state.revision.clone());
+        for owner in owners(global_state) {
+            if let Some(op) = propagate_deletion(global_state, &owner) {
+                return Some(op);
+            }
+        }
+        for dependent in dependents(global_state) {
+            if let Some(op) = collect(global_state, &dependent) {
+                return Some(op);
+            }
+        }
+        None
+    }
+
+    fn arbitrary_steps(&self, _local_state: &Self::State) -> Vec<Self::State> {
+        Vec::new()
+    }
+
+    fn name(&self) -> String {
+        "GarbageCollector".to_owned()
+    }
+
+    fn min_revision_accepted<'a>(&self, state: &'a Self::State) -> Option<&'a Revision> {
+        state.revision.as_ref()
+    }
+}
+
+/// A resource that can own others.
+enum Owner<'a> {
+    Deployment(&'a Deployment),
+    ReplicaSet(&'a ReplicaSet),
+    StatefulSet(&'a StatefulSet),
+    DaemonSet(&'a DaemonSet),
+    Job(&'a Job),
+    CronJob(&'a CronJob),
+}
+
+impl<'a> Owner<'a> {
+    fn metadata(&self) -> &'a Metadata {
+        match self {
+            Owner::Deployment(d) => &d.metadata,
+            Owner::ReplicaSet(rs) => &rs.metadata,
+            Owner::StatefulSet(sts) => &sts.metadata,
+            Owner::DaemonSet(ds) => &ds.metadata,
+            Owner::Job(job) => &job.metadata,
+            Owner::CronJob(cj) => &cj.metadata,
+        }
+    }
+
+    fn remove_finalizer(&self, finalizer: &str) -> GarbageCollectorAction {
+        macro_rules! without_finalizer {
+            ($r:expr) => {{
+                let mut r = (*$r).clone();
+                r.metadata.finalizers.retain(|f| f != finalizer);
+                r
+            }};
+        }
+        match self {
+            Owner::Deployment(d) => GarbageCollectorAction::UpdateDeployment(without_finalizer!(d)),
+            Owner::ReplicaSet(rs) => {
+                GarbageCollectorAction::UpdateReplicaSet(without_finalizer!(rs))
+            }
+            Owner::StatefulSet(sts) => {
+                GarbageCollectorAction::UpdateStatefulSet(without_finalizer!(sts))
+            }
+            Owner::DaemonSet(ds) => GarbageCollectorAction::UpdateDaemonSet(without_finalizer!(ds)),
+            Owner::Job(job) => GarbageCollectorAction::UpdateJob(without_finalizer!(job)),
+            Owner::CronJob(cj) => GarbageCollectorAction::UpdateCronJob(without_finalizer!(cj)),
+        }
+    }
+}
+
+/// A resource that can be owned by others.
+enum Dependent<'a> {
+    Pod(&'a Pod),
+    ReplicaSet(&'a ReplicaSet),
+    ControllerRevision(&'a ControllerRevision),
+    Job(&'a Job),
+}
+
+impl<'a> Dependent<'a> {
+    fn metadata(&self) -> &'a Metadata {
+        match self {
+            Dependent::Pod(pod) => &pod.metadata,
+            Dependent::ReplicaSet(rs) => &rs.metadata,
+            Dependent::ControllerRevision(cr) => &cr.metadata,
+            Dependent::Job(job) => &job.metadata,
+        }
+    }
+
+    fn delete(&self) -> GarbageCollectorAction {
+        match self {
+            Dependent::Pod(pod) => GarbageCollectorAction::SoftDeletePod((*pod).clone()),
+            Dependent::ReplicaSet(rs) => GarbageCollectorAction::DeleteReplicaSet((*rs).clone()),
+            Dependent::ControllerRevision(cr) => {
+                GarbageCollectorAction::DeleteControllerRevision((*cr).clone())
+            }
+            Dependent::Job(job) => GarbageCollectorAction::DeleteJob((*job).clone()),
+        }
+    }
+
+    fn with_owner_references(&self, owners: Vec<OwnerReference>) -> GarbageCollectorAction {
+        macro_rules! with_owners {
+            ($r:expr) => {{
+                let mut r = (*$r).clone();
+                r.metadata.owner_references = owners;
+                r
+            }};
+        }
+        match self {
+            Dependent::Pod(pod) => GarbageCollectorAction::UpdatePod(with_owners!(pod)),
+            Dependent::ReplicaSet(rs) => GarbageCollectorAction::UpdateReplicaSet(with_owners!(rs)),
+            Dependent::ControllerRevision(cr) => {
+                GarbageCollectorAction::UpdateControllerRevision(with_owners!(cr))
+            }
+            Dependent::Job(job) => GarbageCollectorAction::UpdateJob(with_owners!(job)),
+        }
+    }
+}
+
+fn owners(state: &StateView) -> impl Iterator<Item = Owner<'_>> {
+    state
+        .deployments
+        .iter()
+        .map(Owner::Deployment)
+        .chain(state.replicasets.iter().map(Owner::ReplicaSet))
+        .chain(state.statefulsets.iter().map(Owner::StatefulSet))
+        .chain(state.daemonsets.iter().map(Owner::DaemonSet))
+        .chain(state.jobs.iter().map(Owner::Job))
+        .chain(state.cronjobs.iter().map(Owner::CronJob))
+}
+
+fn dependents(state: &StateView) -> impl Iterator<Item = Dependent<'_>> {
+    state
+        .pods
+        .iter()
+        .map(Dependent::Pod)
+        .chain(state.replicasets.iter().map(Dependent::ReplicaSet))
+        .chain(
+            state
+                .controller_revisions
+                .iter()
+                .map(Dependent::ControllerRevision),
+        )
+        .chain(state.jobs.iter().map(Dependent::Job))
+}
+
+/// The dependents that refer to the owner.
+fn dependents_of<'a>(state: &'a StateView, owner: &'a Metadata) -> Vec<Dependent<'a>> {
+    let (namespace, uid) = (&owner.namespace, &owner.uid);
+    state
+        .pods
+        .owned_by(namespace, uid)
+        .map(Dependent::Pod)
+        .chain(
+            state
+                .replicasets
+                .owned_by(namespace, uid)
+                .map(Dependent::ReplicaSet),
+        )
+        .chain(
+            state
+                .controller_revisions
+                .owned_by(namespace, uid)
+                .map(Dependent::ControllerRevision),
+        )
+        .chain(state.jobs.owned_by(namespace, uid).map(Dependent::Job))
+        .collect()
+}
+
+/// Whether the owner that the reference is to still exists, rather than having been replaced by
+/// one with the same name.
+pub fn owner_exists(state: &StateView, namespace: &str, owner: &OwnerReference) -> bool {
+    let uid = |m: &Metadata| m.uid == owner.uid;
+    match owner.kind.as_str() {
+        "Deployment" => state
+            .deployments
+            .get(namespace, &owner.name)
+            .map_or(false, |d| uid(&d.metadata)),
+        "ReplicaSet" => state
+            .replicasets
+            .get(namespace, &owner.name)
+            .map_or(false, |rs| uid(&rs.metadata)),
+        "StatefulSet" => state
+            .statefulsets
+            .get(namespace, &owner.name)
+            .map_or(false, |sts| uid(&sts.metadata)),
+        "DaemonSet" => state
+            .daemonsets
+            .get(namespace, &owner.name)
+            .map_or(false, |ds| uid(&ds.metadata)),
+        "Job" => state
+            .jobs
+            .get(namespace, &owner.name)
+            .map_or(false, |job| uid(&job.metadata)),
+        "CronJob" => state
+            .cronjobs
+            .get(namespace, &owner.name)
+            .map_or(false, |cj| uid(&cj.metadata)),
+        // we don't model owners of other kinds so leave their dependents alone
+        _ => true,
+    }
+}
+
+/// Carry out the propagation policy of an owner being deleted, given by its finalizers.
+fn propagate_deletion(state: &StateView, owner: &Owner) -> Option<GarbageCollectorAction> {
+    let metadata = owner.metadata();
+    if metadata.deletion_timestamp.is_none() {
+        return None;
+    }
+    let has_finalizer = |finalizer: &str| metadata.finalizers.iter().any(|f| f == finalizer);
+    let dependents = dependents_of(state, metadata);
+    if has_finalizer(ORPHAN_FINALIZER) {
+        // orphan the dependents by removing their references to the owner before letting the
+        // owner go
+        if let Some(dependent) = dependents.first() {
+            let owners = dependent
+                .metadata()
+                .owner_references
+                .iter()
+                .filter(|or| or.uid != metadata.uid)
+                .cloned()
+                .collect();
+            return Some(dependent.with_owner_references(owners));
+        }
+        return Some(owner.remove_finalizer(ORPHAN_FINALIZER));
+    }
+    if has_finalizer(FOREGROUND_DELETION_FINALIZER) {
+        // delete the dependents, keeping the owner until those blocking its deletion have gone
+        if let Some(dependent) = dependents
+            .iter()
+            .find(|d| d.metadata().deletion_timestamp.is_none())
+        {
+            return Some(dependent.delete());
+        }
+        let blocked = dependents.iter().any(|d| {
+            d.metadata()
+                .owner_references
+                .iter()
+                .any(|or| or.uid == metadata.uid && or.block_owner_deletion)
+        });
+        if !blocked {
+            return Some(owner.remove_finalizer(FOREGROUND_DELETION_FINALIZER));
+        }
+    }
+    None
+}
+
+/// Delete the dependent if all of its owners have gone, or drop the references to those that have
+/// if some remain.
+fn collect(state: &StateView, dependent: &Dependent) -> Option<GarbageCollectorAction> {
+    let metadata = dependent.metadata();
+    if metadata.deletion_timestamp.is_some() || metadata.owner_references.is_empty() {
+        return None;
+    }
+    let (present, absent): (Vec<_>, Vec<_>) = metadata
+        .owner_references
+        .iter()
+        .partition(|or| owner_exists(state, &metadata.namespace, or));
+    if absent.is_empty() {
+        None
+    } else if present.is_empty() {
+        Some(dependent.delete())
+    } else {
+        Some(dependent.with_owner_references(present.into_iter().cloned().collect()))
+    }
+}
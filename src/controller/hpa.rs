@@ -0,0 +1,271 @@
+use tracing::debug;
+
+use crate::abstract_model::ControllerAction;
+use crate::controller::Controller;
+use crate::resources::{
+    Deployment, HorizontalPodAutoscaler, ReplicaSet, Scale, ScaleSpec, ScaleStatus, StatefulSet,
+};
+use crate::state::revision::Revision;
+use crate::state::StateView;
+
+// The target CPU utilization that autoscalers default to when they don't set one.
+pub const DEFAULT_TARGET_CPU_UTILIZATION_PERCENTAGE: u32 = 80;
+
+// The minimum change (from 1.0) in the ratio of current to target utilization before the
+// autoscaler considers scaling, as a fraction out of TOLERANCE_DENOMINATOR.
+const TOLERANCE_NUMERATOR: u32 = 1;
+const TOLERANCE_DENOMINATOR: u32 = 10;
+
+const SCALE_UP_LIMIT_FACTOR: u32 = 2;
+const SCALE_UP_LIMIT_MINIMUM: u32 = 4;
+
+#[derive(Clone, Debug)]
+pub struct HPAController;
+
+#[derive(Debug, Default, Hash, Clone, PartialEq, Eq)]
+pub struct HPAControllerState {
+    revision: Option<Revision>,
+}
+
+#[derive(Debug)]
+pub enum HPAControllerAction {
+    UpdateDeployment(Deployment),
+    UpdateReplicaSet(ReplicaSet),
+    UpdateStatefulSet(StatefulSet),
+
+    UpdateHorizontalPodAutoscalerStatus(HorizontalPodAutoscaler),
+}
+
+impl From<HPAControllerAction> for ControllerAction {
+    fn from(value: HPAControllerAction) -> Self {
+        match value {
+            HPAControllerAction::UpdateDeployment(d) => ControllerAction::UpdateDeployment(d),
+            HPAControllerAction::UpdateReplicaSet(rs) => ControllerAction::UpdateReplicaSet(rs),
+            HPAControllerAction::UpdateStatefulSet(sts) => ControllerAction::UpdateStatefulSet(sts),
+            HPAControllerAction::UpdateHorizontalPodAutoscalerStatus(hpa) => {
+                ControllerAction::UpdateHorizontalPodAutoscalerStatus(hpa)
+            }
+        }
+    }
+}
+
+impl Controller for HPAController {
+    type State = HPAControllerState;
+    type Action = HPAControllerAction;
+
+    fn step(
+        &self,
+        global_state: &StateView,
+        local_state: &mut Self::State,
+    ) -> Option<Self::Action> {
+        local_state.revision = Some(global_state.revision.clone());
+        for hpa in global_state.horizontal_pod_autoscalers.iter() {
+            if let Some(op) = reconcile_autoscaler(hpa, global_state) {
+                return Some(op);
+            }
+        }
+        None
+    }
+
+    fn arbitrary_steps(&self, _local_state: &Self::State) -> Vec<Self::State> {
+        Vec::new()
+    }
+
+    fn name(&self) -> String {
+        "HPA".to_owned()
+    }
+
+    fn min_revision_accepted<'a>(&self, state: &'a Self::State) -> Option<&'a Revision> {
+        state.revision.as_ref()
+    }
+}
+
+fn reconcile_autoscaler(
+    hpa: &HorizontalPodAutoscaler,
+    state: &StateView,
+) -> Option<HPAControllerAction> {
+    let namespace = &hpa.metadata.namespace;
+    let Some(scale) = get_scale(state, namespace, hpa) else {
+        debug!(
+            hpa = %hpa.metadata.name,
+            target = %hpa.spec.scale_target_ref.name,
+            "Failed to get the scale of the target"
+        );
+        return None;
+    };
+    let current_replicas = scale.spec.replicas;
+    let desired_replicas = desired_replicas(hpa, current_replicas);
+    let rescale = desired_replicas != current_replicas;
+
+    // THEMELIOS: we can't scale the target and update the status in the same step, so record the
+    // decision in the status first and scale the target once the status reflects it.
+    if hpa.status.current_replicas != current_replicas
+        || hpa.status.desired_replicas != desired_replicas
+        || hpa.status.observed_generation != hpa.metadata.generation
+    {
+        let mut hpa = hpa.clone();
+        hpa.status.current_replicas = current_replicas;
+        hpa.status.desired_replicas = desired_replicas;
+        hpa.status.observed_generation = hpa.metadata.generation;
+        hpa.status.observed_revision = state.revision.clone();
+        if rescale {
+            hpa.status.last_scale_time = Some(state.now);
+        }
+        return Some(HPAControllerAction::UpdateHorizontalPodAutoscalerStatus(
+            hpa,
+        ));
+    }
+
+    if rescale {
+        debug!(
+            hpa = %hpa.metadata.name,
+            current_replicas,
+            desired_replicas,
+            "Scaling the target"
+        );
+        let mut scale = scale;
+        scale.spec.replicas = desired_replicas;
+        return update_scale(state, namespace, hpa, &scale);
+    }
+
+    None
+}
+
+/// The number of replicas the autoscaler wants its target to have, given the target currently
+/// has `current_replicas`.
+pub fn desired_replicas(hpa: &HorizontalPodAutoscaler, current_replicas: u32) -> u32 {
+    let min_replicas = hpa.spec.min_replicas.unwrap_or(1);
+    if current_replicas == 0 && min_replicas != 0 {
+        // Autoscaling is disabled for this resource
+        0
+    } else if current_replicas > hpa.spec.max_replicas {
+        hpa.spec.max_replicas
+    } else if current_replicas < min_replicas {
+        min_replicas
+    } else {
+        let metric_desired_replicas = compute_replicas_for_cpu_utilization(hpa, current_replicas);
+        normalize_desired_replicas(hpa, current_replicas, metric_desired_replicas)
+    }
+}
+
+// computeReplicasForCPUUtilization scales the current replicas by the ratio of the current to
+// the target utilization, unless that ratio is within the tolerance.
+fn compute_replicas_for_cpu_utilization(
+    hpa: &HorizontalPodAutoscaler,
+    current_replicas: u32,
+) -> u32 {
+    let Some(utilization) = hpa.status.current_cpu_utilization_percentage else {
+        // no metrics yet so stay where we are
+        return current_replicas;
+    };
+    let target_utilization = target_cpu_utilization(hpa);
+    if within_tolerance(utilization, target_utilization) {
+        return current_replicas;
+    }
+    // ceil(usageRatio * currentReplicas)
+    (current_replicas * utilization + target_utilization - 1) / target_utilization
+}
+
+/// The target CPU utilization of the autoscaler, with the default applied.
+pub fn target_cpu_utilization(hpa: &HorizontalPodAutoscaler) -> u32 {
+    hpa.spec
+        .target_cpu_utilization_percentage
+        .unwrap_or(DEFAULT_TARGET_CPU_UTILIZATION_PERCENTAGE)
+}
+
+/// Whether the ratio of the utilization to the target is close enough to 1 to not scale.
+pub fn within_tolerance(utilization: u32, target_utilization: u32) -> bool {
+    utilization.abs_diff(target_utilization) * TOLERANCE_DENOMINATOR
+        <= target_utilization * TOLERANCE_NUMERATOR
+}
+
+// normalizeDesiredReplicas takes the metrics desired replicas value and normalizes it based on
+// the appropriate conditions (i.e. < maxReplicas, > minReplicas, etc...)
+fn normalize_desired_replicas(
+    hpa: &HorizontalPodAutoscaler,
+    current_replicas: u32,
+    prenormalized_desired_replicas: u32,
+) -> u32 {
+    let min_replicas = hpa.spec.min_replicas.unwrap_or(1);
+    let scale_up_limit = calculate_scale_up_limit(current_replicas);
+    // We don't want to go above the scale up limit, or the max replicas
+    let maximum_allowed_replicas = hpa.spec.max_replicas.min(scale_up_limit);
+    if prenormalized_desired_replicas < min_replicas {
+        min_replicas
+    } else if prenormalized_desired_replicas > maximum_allowed_replicas {
+        maximum_allowed_replicas
+    } else {
+        prenormalized_desired_replicas
+    }
+}
+
+fn calculate_scale_up_limit(current_replicas: u32) -> u32 {
+    (SCALE_UP_LIMIT_FACTOR * current_replicas).max(SCALE_UP_LIMIT_MINIMUM)
+}
+
+/// Read the scale subresource of the autoscaler's target, if it exists.
+pub fn get_scale(
+    state: &StateView,
+    namespace: &str,
+    hpa: &HorizontalPodAutoscaler,
+) -> Option<Scale> {
+    let target = &hpa.spec.scale_target_ref;
+    let (metadata, replicas, status_replicas) = match target.kind.as_str() {
+        "Deployment" => {
+            let d = state.deployments.get(namespace, &target.name)?;
+            (&d.metadata, d.spec.replicas, d.status.replicas)
+        }
+        "ReplicaSet" => {
+            let rs = state.replicasets.get(namespace, &target.name)?;
+            (
+                &rs.metadata,
+                rs.spec.replicas.unwrap_or(1),
+                rs.status.replicas,
+            )
+        }
+        "StatefulSet" => {
+            let sts = state.statefulsets.get(namespace, &target.name)?;
+            (
+                &sts.metadata,
+                sts.spec.replicas.unwrap_or(1),
+                sts.status.replicas,
+            )
+        }
+        _ => return None,
+    };
+    Some(Scale {
+        metadata: metadata.clone(),
+        spec: ScaleSpec { replicas },
+        status: ScaleStatus {
+            replicas: status_replicas,
+        },
+    })
+}
+
+/// Write the scale subresource of the autoscaler's target back to it.
+fn update_scale(
+    state: &StateView,
+    namespace: &str,
+    hpa: &HorizontalPodAutoscaler,
+    scale: &Scale,
+) -> Option<HPAControllerAction> {
+    let target = &hpa.spec.scale_target_ref;
+    match target.kind.as_str() {
+        "Deployment" => {
+            let mut d = state.deployments.get(namespace, &target.name)?.clone();
+            d.spec.replicas = scale.spec.replicas;
+            Some(HPAControllerAction::UpdateDeployment(d))
+        }
+        "ReplicaSet" => {
+            let mut rs = state.replicasets.get(namespace, &target.name)?.clone();
+            rs.spec.replicas = Some(scale.spec.replicas);
+            Some(HPAControllerAction::UpdateReplicaSet(rs))
+        }
+        "StatefulSet" => {
+            let mut sts = state.statefulsets.get(namespace, &target.name)?.clone();
+            sts.spec.replicas = Some(scale.spec.replicas);
+            Some(HPAControllerAction::UpdateStatefulSet(sts))
+        }
+        _ => None,
+    }
+}
@@ -18,7 +18,6 @@ use crate::{
     },
     resources::{Job, PodConditionType},
     state::{revision::Revision, StateView},
-    utils::now,
 };
 
 use super::{
@@ -94,11 +93,17 @@ impl Controller for JobController {
         for job in global_state.jobs.iter() {
             let mut pods = global_state
                 .pods
-                .iter()
-                .filter(|p| job.spec.selector.matches(&p.metadata.labels))
+                .matching(&job.metadata.namespace, &job.spec.selector)
                 .collect::<Vec<_>>();
             let mut job = job.clone();
-            if let Some(op) = reconcile(&mut job, &mut pods, &global_state.revision).0 {
+            if let Some(op) = reconcile(
+                &mut job,
+                &mut pods,
+                &global_state.revision,
+                global_state.now,
+            )
+            .0
+            {
                 return Some(op);
             }
         }
@@ -122,6 +127,7 @@ fn reconcile(
     job: &mut Job,
     pods: &mut [&Pod],
     state_revision: &Revision,
+    now: Time,
 ) -> OptionalJobControllerAction {
     let active_pods = util::filter_active_pods(pods);
     let active = active_pods.len();
@@ -138,7 +144,7 @@ fn reconcile(
 
     // Job first start. Set StartTime only if the job is not in the suspended state.
     if job.status.start_time.is_none() && !job.spec.suspend {
-        job.status.start_time = Some(now());
+        job.status.start_time = Some(now);
     }
 
     let exceeds_backoff_limit = failed > job.spec.backoff_limit.unwrap_or_default() as usize;
@@ -151,7 +157,7 @@ fn reconcile(
             ConditionStatus::True,
             failure_target_condition.reason.clone(),
             failure_target_condition.message.clone(),
-            now(),
+            now,
         ))
     } else if let Some(fail_job_message) = get_fail_job_message(job, pods) {
         // Prepare the interim FailureTarget condition to record the failure message before the finalizers (allowing removal of the pods) are removed.
@@ -160,7 +166,7 @@ fn reconcile(
             ConditionStatus::True,
             JOB_REASON_POD_FAILURE_POLICY.to_owned(),
             fail_job_message,
-            now(),
+            now,
         ))
     } else if exceeds_backoff_limit || past_backoff_limit_on_failure(job, pods) {
         // check if the number of pod restart exceeds backoff (for restart OnFailure only)
@@ -170,20 +176,20 @@ fn reconcile(
             ConditionStatus::True,
             JOB_REASON_BACKOFF_LIMIT_EXCEEDED.to_owned(),
             "Job has reached the specified backoff limit".to_owned(),
-            now(),
+            now,
         ))
-    } else if past_active_deadline(job) {
+    } else if past_active_deadline(job, now) {
         Some(new_condition(
             JobConditionType::Failed,
             ConditionStatus::True,
             JOB_REASON_DEADLINE_EXCEEDED.to_owned(),
             "Job was active longer than specified deadline".to_owned(),
-            now(),
+            now,
         ))
     } else if job.spec.active_deadline_seconds.is_some() && !job.spec.suspend {
-        // let sync_duration = job.spec.active_deadline_seconds - (now() - job.status.start_time);
-        // TODO: requeue
-        todo!()
+        // THEMELIOS: controllers are stepped again as the clock ticks so there is no need to requeue
+        // the job for when its deadline passes.
+        None
     } else {
         None
     };
@@ -245,7 +251,7 @@ fn reconcile(
                 ConditionStatus::True,
                 String::new(),
                 String::new(),
-                now(),
+                now,
             ));
         } else if manage_job_called {
             debug!("Manage job called");
@@ -260,7 +266,7 @@ fn reconcile(
                     ConditionStatus::True,
                     "JobSuspended".to_owned(),
                     "Job suspended".to_owned(),
-                    now(),
+                    now,
                 ) {
                     job.status.conditions = new_conditions;
                     debug!("Suspend condition changed");
@@ -274,7 +280,7 @@ fn reconcile(
                     ConditionStatus::False,
                     "JobResumed".to_owned(),
                     "Job resumed".to_owned(),
-                    now(),
+                    now,
                 ) {
                     job.status.conditions = new_conditions;
                     debug!("Suspend condition changed");
@@ -285,7 +291,7 @@ fn reconcile(
                     // consistent with resuming a Job created in the suspended state.
                     // (ActiveDeadlineSeconds is interpreted as the number of seconds a
                     // Job is continuously active.)
-                    job.status.start_time = Some(now());
+                    job.status.start_time = Some(now);
                 }
             }
         }
@@ -312,6 +318,7 @@ fn reconcile(
         succeeded_indexes,
         prev_succeeded_indexes,
         finished_condition,
+        now,
     )
 }
 
@@ -616,14 +623,14 @@ fn past_backoff_limit_on_failure(job: &Job, pods: &[&Pod]) -> bool {
 // pastActiveDeadline checks if job has ActiveDeadlineSeconds field set and if
 // it is exceeded. If the job is currently suspended, the function will always
 // return false.
-fn past_active_deadline(job: &Job) -> bool {
+fn past_active_deadline(job: &Job, now: Time) -> bool {
     if job.spec.active_deadline_seconds.is_none()
         || job.status.start_time.is_none()
         || job.spec.suspend
     {
         return false;
     }
-    let duration = job.status.start_time.unwrap().0 - now().0;
+    let duration = now.0 - job.status.start_time.unwrap().0;
     let allowed_duration =
         Duration::from_secs(job.spec.active_deadline_seconds.unwrap_or_default());
     duration >= allowed_duration
@@ -723,6 +730,7 @@ fn ensure_job_condition_status(
 //
 // It does this up to a limited number of Pods so that the size of .status
 // doesn't grow too much and this sync doesn't starve other Jobs.
+#[allow(clippy::too_many_arguments)]
 fn track_job_status_and_remove_finalizers(
     mut needs_flush: bool,
     job: &mut Job,
@@ -731,6 +739,7 @@ fn track_job_status_and_remove_finalizers(
     mut succeeded_indexes: OrderedIntervals,
     prev_succeeded_indexes: OrderedIntervals,
     mut finished_condition: Option<JobCondition>,
+    now: Time,
 ) -> OptionalJobControllerAction {
     let is_indexed = job.spec.completion_mode == JobCompletionMode::Indexed;
 
@@ -869,7 +878,7 @@ fn track_job_status_and_remove_finalizers(
         // It is also used in the enactJobFinished function for reporting.
         finished_condition = Some(new_failed_condition_for_failure_target(
             &finished_condition.unwrap(),
-            now(),
+            now,
         ));
     }
 
@@ -885,7 +894,7 @@ fn track_job_status_and_remove_finalizers(
     }
 
     let job_finished =
-        !reached_max_uncounted_pods && enact_job_finished(&mut job.status, finished_condition);
+        !reached_max_uncounted_pods && enact_job_finished(&mut job.status, finished_condition, now);
     if job_finished {
         debug!("needs flush job finished");
         needs_flush = true;
@@ -1372,6 +1381,7 @@ fn count_terminating_pods(pods: &[&Pod]) -> usize {
 fn enact_job_finished(
     job_status: &mut JobStatus,
     finished_condition: Option<JobCondition>,
+    now: Time,
 ) -> bool {
     if let Some(fc) = finished_condition {
         let uncounted = &job_status.uncounted_terminated_pods;
@@ -1385,7 +1395,7 @@ fn enact_job_finished(
             fc.status,
             fc.reason,
             fc.message,
-            now(),
+            now,
         );
         job_status.conditions = conditions.unwrap_or_default();
         if fc.r#type == JobConditionType::Complete {
@@ -0,0 +1,127 @@
+use crate::{
+    abstract_model::ControllerAction,
+    resources::{Lease, LeaseSpec},
+    state::{resources::DEFAULT_NAMESPACE, revision::Revision, StateView},
+    utils,
+};
+
+use super::Controller;
+
+/// Run a controller only whilst it holds a lease.
+///
+/// Candidates acquire the lease when it is free or has expired, and renew it when they see it
+/// expired whilst still holding it.
+/// Writes from the inner controller carry the lease they were made under so the datastore can
+/// fence them, or at least record when they were made by a candidate that had lost the lease.
+#[derive(Clone, Debug)]
+pub struct LeaderElected<C> {
+    pub inner: C,
+    /// The identity of this candidate, unique amongst those contending for the lease.
+    pub identity: String,
+    /// The name of the lease to contend for.
+    pub lease: String,
+    /// Whether the datastore rejects writes made under a lease that is no longer held.
+    pub fencing: bool,
+}
+
+#[derive(Debug, Default, Hash, Clone, PartialEq, Eq)]
+pub struct LeaderElectedState<S> {
+    pub inner: S,
+    /// The lease transitions when this candidate last saw itself holding the lease.
+    pub leading: Option<u32>,
+    revision: Option<Revision>,
+}
+
+impl<S> LeaderElectedState<S> {
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            leading: None,
+            revision: None,
+        }
+    }
+}
+
+impl<C: Controller> LeaderElected<C> {
+    fn acquire(&self, lease: &Lease) -> Lease {
+        let mut lease = lease.clone();
+        lease.spec.holder_identity = Some(self.identity.clone());
+        lease.spec.lease_transitions += 1;
+        lease.spec.expired = false;
+        lease
+    }
+}
+
+impl<C: Controller> Controller for LeaderElected<C> {
+    type State = LeaderElectedState<C::State>;
+
+    type Action = ControllerAction;
+
+    fn step(
+        &self,
+        global_state: &StateView,
+        local_state: &mut Self::State,
+    ) -> Option<Self::Action> {
+        local_state.revision = Some(global_state.revision.clone());
+        match global_state.leases.get(DEFAULT_NAMESPACE, &self.lease) {
+            None => {
+                local_state.leading = None;
+                return Some(ControllerAction::CreateLease(Lease {
+                    metadata: utils::metadata(self.lease.clone()),
+                    spec: LeaseSpec {
+                        holder_identity: Some(self.identity.clone()),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                }));
+            }
+            Some(lease) => {
+                if lease.spec.holder_identity.as_ref() == Some(&self.identity) {
+                    if lease.spec.expired {
+                        // renew it before another candidate takes it
+                        let mut lease = lease.clone();
+                        lease.spec.expired = false;
+                        return Some(ControllerAction::UpdateLease(lease));
+                    }
+                    local_state.leading = Some(lease.spec.lease_transitions);
+                } else if lease.spec.holder_identity.is_none() || lease.spec.expired {
+                    local_state.leading = None;
+                    return Some(ControllerAction::UpdateLease(self.acquire(lease)));
+                } else {
+                    // someone else holds the lease
+                    local_state.leading = None;
+                }
+            }
+        }
+
+        let transitions = local_state.leading?;
+        let action = self.inner.step(global_state, &mut local_state.inner)?;
+        Some(ControllerAction::Fenced {
+            namespace: DEFAULT_NAMESPACE.to_owned(),
+            lease: self.lease.clone(),
+            holder: self.identity.clone(),
+            transitions,
+            enforce: self.fencing,
+            action: Box::new(action.into()),
+        })
+    }
+
+    fn arbitrary_steps(&self, local_state: &Self::State) -> Vec<Self::State> {
+        self.inner
+            .arbitrary_steps(&local_state.inner)
+            .into_iter()
+            .map(|inner| LeaderElectedState {
+                inner,
+                ..local_state.clone()
+            })
+            .collect()
+    }
+
+    fn name(&self) -> String {
+        self.inner.name()
+    }
+
+    fn min_revision_accepted<'a>(&self, state: &'a Self::State) -> Option<&'a Revision> {
+        state.revision.as_ref()
+    }
+}
@@ -9,7 +9,6 @@ use crate::resources::{
 };
 use crate::state::revision::Revision;
 use crate::state::StateView;
-use crate::utils::now;
 
 use super::util::is_pod_active;
 
@@ -53,7 +52,7 @@ impl Controller for NodeController {
         local_state: &mut Self::State,
     ) -> Option<NodeControllerAction> {
         local_state.revision = Some(global_state.revision.clone());
-        if let Some(_node) = global_state.nodes.get(&self.name) {
+        if let Some(_node) = global_state.nodes.get("", &self.name) {
             let pods_for_this_node = global_state
                 .pods
                 .iter()
@@ -64,7 +63,7 @@ impl Controller for NodeController {
                 if is_pod_active(pod) {
                     if !local_state.running.contains_key(&pod.metadata.name) {
                         let cs = ContainerState::Running(ContainerStateRunning {
-                            started_at: Some(now()),
+                            started_at: Some(global_state.now),
                         });
                         local_state
                             .running
@@ -124,7 +123,7 @@ impl Controller for NodeController {
                                 status: ConditionStatus::True,
                                 r#type: PodConditionType::Ready,
                                 last_probe_time: None,
-                                last_transition_time: None,
+                                last_transition_time: Some(global_state.now),
                                 message: None,
                                 reason: None,
                             });
@@ -161,7 +160,8 @@ impl Controller for NodeController {
                     let term = ContainerStateTerminated {
                         exit_code: 0,
                         started_at: *started_at,
-                        finished_at: Some(now()),
+                        // there is no view of the clock here, so it finishes as it starts
+                        finished_at: *started_at,
                         ..Default::default()
                     };
                     // a running container could fail
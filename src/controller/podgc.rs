@@ -44,7 +44,7 @@ impl Controller for PodGCController {
             // PodGC cleans up any Pods which satisfy any of the following conditions:
             // - are orphan Pods - bound to a node which no longer exists,
             if let Some(node_name) = &pod.spec.node_name {
-                if !global_state.nodes.has(node_name) {
+                if !global_state.nodes.has("", node_name) {
                     if pod.metadata.deletion_timestamp.is_none() {
                         return Some(PodGCAction::SoftDeletePod(pod.clone()));
                     } else {
@@ -1,25 +1,23 @@
 use std::cmp::Ordering;
 use std::collections::BTreeMap;
 use std::collections::BTreeSet;
-use std::time::Duration;
 
 use tracing::debug;
 
 use crate::abstract_model::ControllerAction;
 use crate::controller::util::new_controller_ref;
 use crate::controller::Controller;
-use crate::resources::ConditionStatus;
 use crate::resources::{
-    LabelSelector, Pod, PodConditionType, ReplicaSet, ReplicaSetCondition, ReplicaSetConditionType,
-    ReplicaSetStatus, Time,
+    LabelSelector, Pod, ReplicaSet, ReplicaSetCondition, ReplicaSetConditionType, ReplicaSetStatus,
+    Time,
 };
 use crate::state::revision::Revision;
 use crate::state::StateView;
-use crate::utils::now;
 
 use super::util;
 use super::util::get_pod_from_template;
 use super::util::is_pod_active;
+use super::util::is_pod_available;
 use super::util::is_pod_ready;
 use super::util::ValOrOp;
 
@@ -65,8 +63,12 @@ impl Controller for ReplicaSetController {
     ) -> Option<Self::Action> {
         local_state.revision = Some(global_state.revision.clone());
         for replicaset in global_state.replicasets.iter() {
-            let pods = global_state.pods.iter().collect::<Vec<_>>();
-            if let Some(op) = reconcile(replicaset, &pods, &global_state.revision) {
+            let pods = global_state
+                .pods
+                .in_namespace(&replicaset.metadata.namespace)
+                .collect::<Vec<_>>();
+            if let Some(op) = reconcile(replicaset, &pods, &global_state.revision, global_state.now)
+            {
                 return Some(op);
             }
         }
@@ -90,6 +92,7 @@ fn reconcile(
     replicaset: &ReplicaSet,
     all_pods: &[&Pod],
     state_revision: &Revision,
+    now: Time,
 ) -> Option<ReplicaSetControllerAction> {
     let filtered_pods = util::filter_active_pods(all_pods);
     let filtered_pods = claim_pods(replicaset, &filtered_pods);
@@ -105,7 +108,7 @@ fn reconcile(
         }
     }
 
-    let new_status = calculate_status(replicaset, &filtered_pods);
+    let new_status = calculate_status(replicaset, &filtered_pods, now);
     if let Some(op) = update_replicaset_status(replicaset, new_status, state_revision) {
         return Some(op);
     }
@@ -179,7 +182,7 @@ fn claim_pods<'a>(
     ValOrOp::Resource(pods)
 }
 
-fn calculate_status(replicaset: &ReplicaSet, pods: &[&Pod]) -> ReplicaSetStatus {
+fn calculate_status(replicaset: &ReplicaSet, pods: &[&Pod], now: Time) -> ReplicaSetStatus {
     let mut new_status = replicaset.status.clone();
 
     // Count the number of pods that have labels matching the labels of the pod
@@ -199,7 +202,7 @@ fn calculate_status(replicaset: &ReplicaSet, pods: &[&Pod]) -> ReplicaSetStatus
         }
         if is_pod_ready(pod) {
             ready_replicas_count += 1;
-            if is_pod_available(pod, replicaset.spec.min_ready_seconds, now()) {
+            if is_pod_available(pod, replicaset.spec.min_ready_seconds, now) {
                 available_replicas_count += 1;
             }
         }
@@ -244,24 +247,6 @@ fn remove_condition(status: &mut ReplicaSetStatus, cond_type: ReplicaSetConditio
     status.conditions.retain(|c| c.r#type != cond_type)
 }
 
-fn is_pod_available(pod: &Pod, min_ready_seconds: u32, now: Time) -> bool {
-    if let Some(c) = pod
-        .status
-        .conditions
-        .iter()
-        .find(|c| c.r#type == PodConditionType::Ready && c.status == ConditionStatus::True)
-    {
-        if min_ready_seconds == 0
-            || c.last_transition_time.map_or(false, |ltt| {
-                ltt.0 + Duration::from_secs(min_ready_seconds.into()) < now.0
-            })
-        {
-            return true;
-        }
-    }
-    false
-}
-
 // updateReplicaSetStatus attempts to update the Status.Replicas of the given ReplicaSet, with a single GET/PUT retry.
 fn update_replicaset_status(
     rs: &ReplicaSet,
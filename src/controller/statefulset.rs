@@ -13,10 +13,9 @@ use crate::{
         ControllerRevision, GroupVersionKind, Metadata, OwnerReference, PersistentVolumeClaim,
         PersistentVolumeClaimVolumeSource, Pod, PodConditionType, PodManagementPolicyType,
         PodPhase, StatefulSet, StatefulSetPersistentVolumeClaimRetentionPolicyType,
-        StatefulSetSpec, StatefulSetStatus, Volume,
+        StatefulSetSpec, StatefulSetStatus, Time, Volume,
     },
     state::{revision::Revision, StateView},
-    utils::now,
 };
 
 const STATEFULSET_REVISION_LABEL: &str = "controller-revision-hash";
@@ -90,11 +89,18 @@ impl Controller for StatefulSetController {
     ) -> Option<StatefulSetControllerAction> {
         local_state.revision = Some(global_state.revision.clone());
         for statefulset in global_state.statefulsets.iter() {
-            let pods = global_state.pods.iter().collect::<Vec<_>>();
-            let revisions = global_state.controller_revisions.iter().collect::<Vec<_>>();
+            let namespace = &statefulset.metadata.namespace;
+            let pods = global_state
+                .pods
+                .in_namespace(namespace)
+                .collect::<Vec<_>>();
+            let revisions = global_state
+                .controller_revisions
+                .in_namespace(namespace)
+                .collect::<Vec<_>>();
             let pvcs = global_state
                 .persistent_volume_claims
-                .iter()
+                .in_namespace(namespace)
                 .collect::<Vec<_>>();
             if let Some(op) = reconcile(
                 statefulset,
@@ -102,6 +108,7 @@ impl Controller for StatefulSetController {
                 &revisions,
                 &pvcs,
                 &global_state.revision,
+                global_state.now,
             ) {
                 return Some(op);
             }
@@ -128,6 +135,7 @@ fn reconcile(
     all_revisions: &[&ControllerRevision],
     all_pvcs: &[&PersistentVolumeClaim],
     state_revision: &Revision,
+    now: Time,
 ) -> Option<StatefulSetControllerAction> {
     // TODO: claim things
 
@@ -145,7 +153,7 @@ fn reconcile(
 
     let pvcs = all_pvcs;
 
-    sync(statefulset, &pods, &revisions, pvcs, state_revision)
+    sync(statefulset, &pods, &revisions, pvcs, state_revision, now)
 }
 
 fn sync(
@@ -154,8 +162,9 @@ fn sync(
     revisions: &[&ControllerRevision],
     pvcs: &[&PersistentVolumeClaim],
     state_revision: &Revision,
+    now: Time,
 ) -> Option<StatefulSetControllerAction> {
-    if let Some(op) = update_statefulset(statefulset, pods, revisions, pvcs, state_revision) {
+    if let Some(op) = update_statefulset(statefulset, pods, revisions, pvcs, state_revision, now) {
         return Some(op);
     }
     None
@@ -167,12 +176,13 @@ fn update_statefulset(
     revisions: &[&ControllerRevision],
     pvcs: &[&PersistentVolumeClaim],
     state_revision: &Revision,
+    now: Time,
 ) -> Option<StatefulSetControllerAction> {
     // list all revisions and sort them
     let mut revisions = revisions.to_vec();
     sort_controller_revisions(&mut revisions);
 
-    let rop = perform_update(statefulset, pods, &revisions, pvcs, state_revision);
+    let rop = perform_update(statefulset, pods, &revisions, pvcs, state_revision, now);
     let (current_revision, update_revision, _status) = match rop {
         ValOrOp::Op(op) => return Some(op),
         ValOrOp::Resource(r) => r,
@@ -210,6 +220,7 @@ fn perform_update(
     revisions: &[&ControllerRevision],
     pvcs: &[&PersistentVolumeClaim],
     state_revision: &Revision,
+    now: Time,
 ) -> ValOrOp<(ControllerRevision, ControllerRevision, StatefulSetStatus)> {
     debug!("perform_update");
     let (current_revision, update_revision, collision_count) =
@@ -226,6 +237,7 @@ fn perform_update(
         pods,
         pvcs,
         state_revision,
+        now,
     );
     let mut current_status = match current_status {
         ValOrOp::Resource(r) => r,
@@ -256,6 +268,7 @@ fn do_update_statefulset(
     pods: &[&Pod],
     pvcs: &[&PersistentVolumeClaim],
     state_revision: &Revision,
+    now: Time,
 ) -> ValOrOp<StatefulSetStatus> {
     debug!("do_update_statefulset");
     let current_sts = apply_revision(sts, current_revision);
@@ -277,6 +290,7 @@ fn do_update_statefulset(
         current_revision,
         update_revision,
         &[pods.to_vec()],
+        now,
     );
 
     // if status != sts.status {
@@ -368,6 +382,7 @@ fn do_update_statefulset(
             monotonic,
             replica,
             pvcs,
+            now,
         )
     };
     debug!("Processing replicas");
@@ -391,6 +406,7 @@ fn do_update_statefulset(
                         replicas.iter().filter_map(|i| i.as_ref()).collect(),
                         condemned,
                     ],
+                    now,
                 );
                 return ValOrOp::Resource(status);
             }
@@ -421,6 +437,7 @@ fn do_update_statefulset(
                         replicas.iter().filter_map(|i| i.as_ref()).collect(),
                         condemned,
                     ],
+                    now,
                 );
                 return ValOrOp::Resource(status);
             }
@@ -434,7 +451,7 @@ fn do_update_statefulset(
     // Note that we do not resurrect Pods in this interval. Also note that scaling will take precedence over
     // updates.
     let process_condemned_fn =
-        |replica| process_condemned(sts, first_unhealthy_pod.as_ref(), monotonic, replica);
+        |replica| process_condemned(sts, first_unhealthy_pod.as_ref(), monotonic, replica, now);
 
     debug!("Processing condemned pods");
     match run_for_all(&condemned, process_condemned_fn, monotonic) {
@@ -450,6 +467,7 @@ fn do_update_statefulset(
                         replicas.iter().filter_map(|i| i.as_ref()).collect(),
                         condemned,
                     ],
+                    now,
                 );
                 return ValOrOp::Resource(status);
             }
@@ -465,6 +483,7 @@ fn do_update_statefulset(
             replicas.iter().filter_map(|i| i.as_ref()).collect(),
             condemned,
         ],
+        now,
     );
 
     // for the OnDelete strategy we short circuit. Pods will be updated when they are manually deleted.
@@ -620,7 +639,7 @@ fn is_running_and_ready(pod: &Pod) -> bool {
     pod.status.phase == PodPhase::Running && is_pod_ready(pod)
 }
 
-fn is_running_and_available(pod: &Pod, min_ready_seconds: u32) -> bool {
+fn is_running_and_available(pod: &Pod, min_ready_seconds: u32, now: Time) -> bool {
     if !is_pod_ready(pod) {
         return false;
     }
@@ -635,7 +654,7 @@ fn is_running_and_available(pod: &Pod, min_ready_seconds: u32) -> bool {
             || (c.last_transition_time.is_some()
                 && c.last_transition_time.unwrap().0
                     + Duration::from_secs(min_ready_seconds as u64)
-                    < now().0)
+                    < now.0)
         {
             return true;
         }
@@ -694,6 +713,7 @@ fn update_status(
     current_revision: &ControllerRevision,
     update_revision: &ControllerRevision,
     podlists: &[Vec<&Pod>],
+    now: Time,
 ) {
     let num_pods = podlists.iter().map(|l| l.len()).sum::<usize>();
     debug!(num_pods, "Updating status");
@@ -704,8 +724,13 @@ fn update_status(
     status.updated_replicas = 0;
 
     for list in podlists {
-        let replica_status =
-            compute_replica_status(list, min_ready_seconds, current_revision, update_revision);
+        let replica_status = compute_replica_status(
+            list,
+            min_ready_seconds,
+            current_revision,
+            update_revision,
+            now,
+        );
         status.replicas += replica_status.replicas;
         status.ready_replicas += replica_status.ready_replicas;
         status.available_replicas += replica_status.available_replicas;
@@ -728,6 +753,7 @@ fn compute_replica_status(
     min_ready_seconds: u32,
     current_revision: &ControllerRevision,
     update_revision: &ControllerRevision,
+    now: Time,
 ) -> ReplicaStatus {
     debug!("compute_replica_status");
     let mut status = ReplicaStatus::default();
@@ -739,7 +765,7 @@ fn compute_replica_status(
         // count the number of running and ready replicas
         if is_running_and_ready(pod) {
             status.ready_replicas += 1;
-            if is_running_and_available(pod, min_ready_seconds) {
+            if is_running_and_available(pod, min_ready_seconds, now) {
                 status.available_replicas += 1;
             }
         }
@@ -797,6 +823,7 @@ pub fn pod_in_ordinal_range(pod: &Pod, sts: &StatefulSet) -> bool {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn process_replica(
     sts: &StatefulSet,
     _current_revision: &ControllerRevision,
@@ -806,6 +833,7 @@ fn process_replica(
     monotonic: bool,
     replica: &Pod,
     pvcs: &[&PersistentVolumeClaim],
+    now: Time,
 ) -> ValOrOp<bool> {
     debug!(
         name = replica.metadata.name,
@@ -866,7 +894,7 @@ fn process_replica(
     // If we have a Pod that has been created but is not available we can not make progress.
     // We must ensure that all for each Pod, when we create it, all of its predecessors, with respect to its
     // ordinal, are Available.
-    if !is_running_and_available(replica, sts.spec.min_ready_seconds.unwrap_or_default())
+    if !is_running_and_available(replica, sts.spec.min_ready_seconds.unwrap_or_default(), now)
         && monotonic
     {
         return ValOrOp::Resource(true);
@@ -954,6 +982,7 @@ fn process_condemned(
     first_unhealthy_pod: Option<&Pod>,
     monotonic: bool,
     condemned: &Pod,
+    now: Time,
 ) -> ValOrOp<bool> {
     if is_terminating(condemned) {
         // if we are in monotonic mode, block and wait for terminating pods to expire
@@ -969,8 +998,11 @@ fn process_condemned(
     }
 
     // if we are in monotonic mode and the condemned target is not the first unhealthy Pod, block.
-    if !is_running_and_available(condemned, sts.spec.min_ready_seconds.unwrap_or_default())
-        && monotonic
+    if !is_running_and_available(
+        condemned,
+        sts.spec.min_ready_seconds.unwrap_or_default(),
+        now,
+    ) && monotonic
         && Some(condemned) != first_unhealthy_pod
     {
         return ValOrOp::Resource(true);
@@ -1,8 +1,11 @@
 use std::collections::BTreeMap;
+use std::hash::Hash;
+use std::time::Duration;
 
+use crate::hasher::FnvHasher;
 use crate::resources::{
     ConditionStatus, GroupVersionKind, Meta, Metadata, NodeCondition, NodeConditionType,
-    OwnerReference, Pod, PodConditionType, PodPhase, PodStatus, PodTemplateSpec,
+    OwnerReference, Pod, PodConditionType, PodPhase, PodStatus, PodTemplateSpec, Time,
 };
 
 pub enum ValOrOp<V, O> {
@@ -78,6 +81,24 @@ pub fn is_pod_ready(pod: &Pod) -> bool {
         && pod.metadata.deletion_timestamp.is_none()
 }
 
+pub fn is_pod_available(pod: &Pod, min_ready_seconds: u32, now: Time) -> bool {
+    if let Some(c) = pod
+        .status
+        .conditions
+        .iter()
+        .find(|c| c.r#type == PodConditionType::Ready && c.status == ConditionStatus::True)
+    {
+        if min_ready_seconds == 0
+            || c.last_transition_time.map_or(false, |ltt| {
+                ltt.0 + Duration::from_secs(min_ready_seconds.into()) < now.0
+            })
+        {
+            return true;
+        }
+    }
+    false
+}
+
 pub fn is_pod_active(pod: &Pod) -> bool {
     pod.status.phase != PodPhase::Succeeded
         && pod.status.phase != PodPhase::Failed
@@ -108,3 +129,27 @@ where
 pub fn subset(m1: &BTreeMap<String, String>, m2: &BTreeMap<String, String>) -> bool {
     m1.iter().all(|(k, v)| m2.get(k).map_or(false, |w| v == w))
 }
+
+// ComputeHash returns a hash value calculated from pod template and
+// a collisionCount to avoid hash collision. The hash will be safe encoded to
+// avoid bad words.
+pub fn compute_hash(template: &PodTemplateSpec, collision_count: u32) -> String {
+    let mut hasher = FnvHasher::new_32a();
+    template.hash(&mut hasher);
+
+    // Add collisionCount in the hash
+    let bytes = collision_count.to_le_bytes();
+    hasher.write(&bytes);
+
+    safe_encode_string(&hasher.finish_32().to_string())
+}
+
+fn safe_encode_string(s: &str) -> String {
+    const ALPHA_NUMS: &[char] = &[
+        'b', 'c', 'd', 'f', 'g', 'h', 'j', 'k', 'l', 'm', 'n', 'p', 'q', 'r', 's', 't', 'v', 'w',
+        'x', 'z', '2', '4', '5', '6', '7', '8', '9',
+    ];
+    s.chars()
+        .map(|c| ALPHA_NUMS[c as usize % ALPHA_NUMS.len()])
+        .collect()
+}
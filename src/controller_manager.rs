@@ -7,11 +7,13 @@ use std::{
 };
 
 use futures::TryStreamExt;
+use k8s_openapi::NamespaceResourceScope;
 use kube::{
-    api::PostParams,
+    api::{DeleteParams, PostParams},
     runtime::{watcher, watcher::Event},
     Api, Client,
 };
+use serde::{de::DeserializeOwned, Serialize};
 use tokio::{sync::Mutex, task::JoinHandle};
 use tracing::debug;
 use tracing::info;
@@ -20,8 +22,11 @@ use tracing::warn;
 use crate::{
     abstract_model::ControllerAction,
     controller::{job::JobController, Controller, DeploymentController, ReplicaSetController},
+    resources::{Meta, PropagationPolicy},
+    state::resources::namespace_of,
     state::revision::Revision,
     state::StateView,
+    utils,
 };
 
 type AppState = Arc<Mutex<StateView>>;
@@ -58,7 +63,7 @@ pub async fn run() -> (Arc<AtomicBool>, Vec<JoinHandle<()>>) {
                                 let local_dep =
                                     serde_json::from_value(serde_json::to_value(dep).unwrap())
                                         .unwrap();
-                                let _ = state.$field.upsert(local_dep, revision);
+                                let _ = state.$field.upsert(local_dep, revision, utils::now());
                             }
                             Event::Deleted(dep) => {
                                 info!(
@@ -72,8 +77,8 @@ pub async fn run() -> (Arc<AtomicBool>, Vec<JoinHandle<()>>) {
                                 .unwrap();
                                 state.revision = std::cmp::max(state.revision.clone(), revision);
                                 // TODO: should map the given resource to our types and use that
-                                let r = state.$field.get(dep.metadata.name.as_ref().unwrap()).unwrap().clone();
-                                state.$field.remove(&r);
+                                let r = state.$field.get(dep.metadata.namespace.as_deref().unwrap_or_default(), dep.metadata.name.as_ref().unwrap()).unwrap().clone();
+                                let _ = state.$field.remove(&r);
                             }
                             Event::Restarted(deps) => {
                                 let dep_names: Vec<_> = deps
@@ -92,7 +97,7 @@ pub async fn run() -> (Arc<AtomicBool>, Vec<JoinHandle<()>>) {
                                     let local_dep =
                                         serde_json::from_value(serde_json::to_value(dep).unwrap())
                                             .unwrap();
-                                    let _ = state.$field.upsert(local_dep, revision.clone());
+                                    let _ = state.$field.upsert(local_dep, revision.clone(), utils::now());
                                 }
                             }
                         }
@@ -155,18 +160,25 @@ async fn controller_loop<C: Controller>(
 
         tokio::time::sleep(rate_limit).await;
 
-        let s = state.lock().await;
+        let mut s = state.lock().await;
 
         if s.revision == last_revision {
             continue;
         }
 
         debug!(name = controller.name(), "Checking for steps");
+        s.now = utils::now();
         if let Some(operation) = controller.step(&s, &mut cstate) {
             info!(name = controller.name(), "Got operation to perform");
             // let revision = s.revision.clone();
             // s.apply_operation(operation.into(), revision.increment());
-            handle_action(operation.into(), client.clone()).await;
+            if let Err(err) = handle_action(operation.into(), client.clone()).await {
+                warn!(
+                    name = controller.name(),
+                    ?err,
+                    "Failed to perform operation"
+                );
+            }
         }
         last_revision = s.revision.clone();
         debug!(name = controller.name(), "Finished processing step");
@@ -174,7 +186,58 @@ async fn controller_loop<C: Controller>(
     info!(name = controller.name(), "Stopping controller");
 }
 
-async fn handle_action(action: ControllerAction, client: Client) {
+/// Why an operation couldn't be performed against the cluster.
+#[derive(Debug)]
+enum ActionError {
+    Kube(kube::Error),
+    /// Our resource couldn't be converted to the Kubernetes one.
+    Convert(serde_json::Error),
+    /// The operation was fenced on a lease that the controller no longer holds.
+    LeaseNotHeld(String),
+}
+
+impl From<kube::Error> for ActionError {
+    fn from(value: kube::Error) -> Self {
+        ActionError::Kube(value)
+    }
+}
+
+impl From<serde_json::Error> for ActionError {
+    fn from(value: serde_json::Error) -> Self {
+        ActionError::Convert(value)
+    }
+}
+
+async fn handle_action(mut action: ControllerAction, client: Client) -> Result<(), ActionError> {
+    // the API server doesn't fence writes on leases so check the lease ourselves before making the
+    // write, there is still a window for the lease to change in between
+    while let ControllerAction::Fenced {
+        namespace,
+        lease,
+        holder,
+        transitions,
+        enforce,
+        action: fenced,
+    } = action
+    {
+        let api = Api::<k8s_openapi::api::coordination::v1::Lease>::namespaced(
+            client.clone(),
+            &namespace,
+        );
+        let held = api.get_opt(&lease).await?.map_or(false, |l| {
+            l.spec.map_or(false, |spec| {
+                spec.holder_identity.as_ref() == Some(&holder)
+                    && spec.lease_transitions == Some(transitions as i32)
+            })
+        });
+        if !held {
+            if enforce {
+                return Err(ActionError::LeaseNotHeld(lease));
+            }
+            warn!(%lease, %holder, "Writing under a lease that is no longer held");
+        }
+        action = *fenced;
+    }
     match action {
         ControllerAction::NodeJoin(_, _) => todo!(),
         ControllerAction::DeleteNode(_) => todo!(),
@@ -228,6 +291,11 @@ async fn handle_action(action: ControllerAction, client: Client) {
             .await
             .unwrap();
         }
+        ControllerAction::DeleteDeployment(dep, policy) => {
+            let api = api_for::<k8s_openapi::api::apps::v1::Deployment, _>(client, &dep);
+            api.delete(&dep.metadata.name, &delete_params(policy))
+                .await?;
+        }
         ControllerAction::CreateReplicaSet(mut rs) => {
             if rs.metadata.namespace.is_empty() {
                 rs.metadata.namespace = "default".to_owned();
@@ -277,15 +345,138 @@ async fn handle_action(action: ControllerAction, client: Client) {
             .unwrap();
         }
         ControllerAction::UpdateReplicaSets(_) => todo!(),
-        ControllerAction::DeleteReplicaSet(_) => todo!(),
+        ControllerAction::DeleteReplicaSet(rs, policy) => {
+            let api = api_for::<k8s_openapi::api::apps::v1::ReplicaSet, _>(client, &rs);
+            api.delete(&rs.metadata.name, &delete_params(policy))
+                .await?;
+        }
         ControllerAction::UpdateStatefulSet(_) => todo!(),
         ControllerAction::UpdateStatefulSetStatus(_) => todo!(),
+        ControllerAction::UpdateDaemonSet(ds) => {
+            replace::<k8s_openapi::api::apps::v1::DaemonSet, _>(client, ds).await?;
+        }
+        ControllerAction::UpdateDaemonSetStatus(ds) => {
+            replace_status::<k8s_openapi::api::apps::v1::DaemonSet, _>(client, ds).await?;
+        }
         ControllerAction::CreateControllerRevision(_) => todo!(),
         ControllerAction::UpdateControllerRevision(_) => todo!(),
         ControllerAction::DeleteControllerRevision(_) => todo!(),
         ControllerAction::CreatePersistentVolumeClaim(_) => todo!(),
         ControllerAction::UpdatePersistentVolumeClaim(_) => todo!(),
-        ControllerAction::UpdateJob(_) => todo!(),
-        ControllerAction::UpdateJobStatus(_) => todo!(),
+        ControllerAction::CreateJob(job) => {
+            create::<k8s_openapi::api::batch::v1::Job, _>(client, job).await?;
+        }
+        ControllerAction::UpdateJob(job) => {
+            replace::<k8s_openapi::api::batch::v1::Job, _>(client, job).await?;
+        }
+        ControllerAction::UpdateJobStatus(job) => {
+            replace_status::<k8s_openapi::api::batch::v1::Job, _>(client, job).await?;
+        }
+        ControllerAction::DeleteJob(job) => {
+            let api =
+                Api::<k8s_openapi::api::batch::v1::Job>::namespaced(client, namespace_of(&job));
+            api.delete(&job.metadata.name, &DeleteParams::background())
+                .await?;
+        }
+        ControllerAction::UpdateCronJob(cj) => {
+            replace::<k8s_openapi::api::batch::v1::CronJob, _>(client, cj).await?;
+        }
+        ControllerAction::UpdateCronJobStatus(cj) => {
+            replace_status::<k8s_openapi::api::batch::v1::CronJob, _>(client, cj).await?;
+        }
+        ControllerAction::UpdateHorizontalPodAutoscalerStatus(hpa) => {
+            replace_status::<k8s_openapi::api::autoscaling::v2::HorizontalPodAutoscaler, _>(
+                client, hpa,
+            )
+            .await?;
+        }
+        ControllerAction::CreateLease(lease) => {
+            create::<k8s_openapi::api::coordination::v1::Lease, _>(client, lease).await?;
+        }
+        ControllerAction::UpdateLease(lease) => {
+            replace::<k8s_openapi::api::coordination::v1::Lease, _>(client, lease).await?;
+        }
+        ControllerAction::DeleteNamespace(ns) => {
+            let api = Api::<k8s_openapi::api::core::v1::Namespace>::all(client);
+            api.delete(&ns.metadata.name, &DeleteParams::background())
+                .await?;
+        }
+        ControllerAction::Fenced { .. } => unreachable!("fencing is unwrapped above"),
+    }
+    Ok(())
+}
+
+/// The parameters for deleting with the propagation policy.
+fn delete_params(policy: PropagationPolicy) -> DeleteParams {
+    match policy {
+        PropagationPolicy::Background => DeleteParams::background(),
+        PropagationPolicy::Foreground => DeleteParams::foreground(),
+        PropagationPolicy::Orphan => DeleteParams::orphan(),
     }
 }
+
+/// The API for the kind of resource, in the namespace of our resource.
+fn api_for<K, T: Meta>(client: Client, resource: &T) -> Api<K>
+where
+    K: kube::Resource<Scope = NamespaceResourceScope>,
+    K::DynamicType: Default,
+{
+    Api::namespaced(client, namespace_of(resource))
+}
+
+/// Convert our resource into the Kubernetes one, which share a JSON representation.
+fn to_remote<K: DeserializeOwned, T: Serialize>(resource: &T) -> Result<K, ActionError> {
+    Ok(serde_json::from_value(serde_json::to_value(resource)?)?)
+}
+
+async fn create<K, T>(client: Client, resource: T) -> Result<(), ActionError>
+where
+    K: kube::Resource<Scope = NamespaceResourceScope>
+        + Clone
+        + std::fmt::Debug
+        + DeserializeOwned
+        + Serialize,
+    K::DynamicType: Default,
+    T: Meta + Serialize,
+{
+    let api = api_for::<K, _>(client, &resource);
+    api.create(&PostParams::default(), &to_remote(&resource)?)
+        .await?;
+    Ok(())
+}
+
+async fn replace<K, T>(client: Client, resource: T) -> Result<(), ActionError>
+where
+    K: kube::Resource<Scope = NamespaceResourceScope>
+        + Clone
+        + std::fmt::Debug
+        + DeserializeOwned
+        + Serialize,
+    K::DynamicType: Default,
+    T: Meta + Serialize,
+{
+    let api = api_for::<K, _>(client, &resource);
+    api.replace(
+        &resource.metadata().name,
+        &PostParams::default(),
+        &to_remote(&resource)?,
+    )
+    .await?;
+    Ok(())
+}
+
+async fn replace_status<K, T>(client: Client, resource: T) -> Result<(), ActionError>
+where
+    K: kube::Resource<Scope = NamespaceResourceScope> + Clone + std::fmt::Debug + DeserializeOwned,
+    K::DynamicType: Default,
+    T: Meta + Serialize,
+{
+    let api = api_for::<K, _>(client, &resource);
+    api.replace_status(
+        &resource.metadata().name,
+        &PostParams::default(),
+        serde_json::to_vec(&resource)?,
+    )
+    .await?;
+    Ok(())
+}
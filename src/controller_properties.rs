@@ -3,14 +3,21 @@ use stateright::{Expectation, Property};
 use crate::{
     abstract_model::AbstractModel,
     controller::{
-        job::JobController, podgc::PodGCController, Controllers, DeploymentController,
-        NodeController, ReplicaSetController, SchedulerController, StatefulSetController,
+        job::JobController, podgc::PodGCController, Controllers, CronJobController,
+        DaemonSetController, DeploymentController, GarbageCollectorController, HPAController,
+        LeaderElected, NodeController, ReplicaSetController, SchedulerController,
+        StatefulSetController,
     },
     state::State,
 };
 
+pub mod cronjob;
+pub mod daemonset;
 pub mod deployment;
+pub mod garbage_collector;
+pub mod hpa;
 pub mod job;
+pub mod leader_election;
 pub mod node;
 pub mod podgc;
 pub mod replicaset;
@@ -29,31 +36,89 @@ impl ControllerProperties for Controllers {
         properties.append(&mut ReplicaSetController::properties());
         properties.append(&mut DeploymentController::properties());
         properties.append(&mut StatefulSetController::properties());
+        properties.append(&mut DaemonSetController::properties());
         properties.append(&mut JobController::properties());
+        properties.append(&mut CronJobController::properties());
+        properties.append(&mut HPAController::properties());
         properties.append(&mut PodGCController::properties());
+        properties.append(&mut GarbageCollectorController::properties());
+        properties.append(&mut LeaderElected::<Controllers>::properties());
         properties
     }
 }
 
-#[derive(Default)]
-pub struct Properties(Vec<Property<AbstractModel>>);
+/// A condition on a state of the model, as checked by a property.
+pub type Condition = fn(&AbstractModel, &State) -> bool;
+
+/// A property that only says something about states where its antecedent holds, as built by
+/// [`implication!`](crate::implication).
+#[derive(Clone, Copy)]
+pub struct Implication {
+    pub antecedent: Condition,
+    /// The antecedent implying the consequent, which is what gets checked.
+    pub condition: Condition,
+}
+
+/// Build an [`Implication`] from an antecedent and the consequent that must hold wherever it
+/// does.
+///
+/// Conditions are plain functions, so the two are combined here where they are written rather
+/// than when the property is added.
+#[macro_export]
+macro_rules! implication {
+    ($antecedent:expr, $consequent:expr $(,)?) => {
+        $crate::controller_properties::Implication {
+            antecedent: $antecedent,
+            condition: |model, state| {
+                let antecedent: $crate::controller_properties::Condition = $antecedent;
+                let consequent: $crate::controller_properties::Condition = $consequent;
+                !antecedent(model, state) || consequent(model, state)
+            },
+        }
+    };
+}
+
+#[derive(Clone, Default)]
+pub struct Properties {
+    /// The properties, with their antecedent if they are an implication.
+    pub(crate) properties: Vec<(Property<AbstractModel>, Option<Condition>)>,
+}
 
 impl Properties {
-    pub fn add(
+    pub fn add(&mut self, expectation: Expectation, name: &'static str, condition: Condition) {
+        self.properties.push((
+            Property {
+                expectation,
+                name,
+                condition,
+            },
+            None,
+        ))
+    }
+
+    /// Add a property that only says something about states where the antecedent holds, such as
+    /// one checking every stable resource when there may be none.
+    ///
+    /// The antecedent is kept with the property to report properties that only ever held
+    /// vacuously.
+    pub fn add_implication(
         &mut self,
         expectation: Expectation,
         name: &'static str,
-        condition: fn(&AbstractModel, &State) -> bool,
+        implication: Implication,
     ) {
-        self.0.push(Property {
-            expectation,
-            name,
-            condition,
-        })
+        self.properties.push((
+            Property {
+                expectation,
+                name,
+                condition: implication.condition,
+            },
+            Some(implication.antecedent),
+        ))
     }
 
     pub fn append(&mut self, other: &mut Properties) {
-        self.0.append(&mut other.0)
+        self.properties.append(&mut other.properties);
     }
 }
 
@@ -63,6 +128,10 @@ impl IntoIterator for Properties {
     type IntoIter = std::vec::IntoIter<Self::Item>;
 
     fn into_iter(self) -> Self::IntoIter {
-        self.0.into_iter()
+        self.properties
+            .into_iter()
+            .map(|(property, _)| property)
+            .collect::<Vec<_>>()
+            .into_iter()
     }
 }
@@ -0,0 +1,39 @@
+use stateright::Expectation;
+
+use crate::{
+    controller::CronJobController,
+    resources::{ConcurrencyPolicy, ConditionStatus, JobConditionType},
+};
+
+use super::{ControllerProperties, Properties};
+
+impl ControllerProperties for CronJobController {
+    fn properties() -> Properties {
+        let mut properties = Properties::default();
+        properties.add(
+            Expectation::Always,
+            "cronjob: Forbid never has two active jobs",
+            |_model, state| {
+                let s = state.latest();
+                s.cronjobs
+                    .iter()
+                    .filter(|cj| cj.spec.concurrency_policy == ConcurrencyPolicy::Forbid)
+                    .all(|cj| {
+                        let active = s
+                            .jobs
+                            .for_controller(&cj.metadata.namespace, &cj.metadata.uid)
+                            .filter(|job| {
+                                !job.status.conditions.iter().any(|c| {
+                                    (c.r#type == JobConditionType::Complete
+                                        || c.r#type == JobConditionType::Failed)
+                                        && c.status == ConditionStatus::True
+                                })
+                            })
+                            .count();
+                        active <= 1
+                    })
+            },
+        );
+        properties
+    }
+}
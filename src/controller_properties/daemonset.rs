@@ -0,0 +1,104 @@
+use stateright::Expectation;
+
+use crate::{
+    abstract_model::AbstractModel,
+    controller::{daemonset::node_should_run_daemon_pod, DaemonSetController},
+    implication,
+    state::{revision::Revision, State},
+    utils::LogicalBoolExt,
+};
+
+use super::{ControllerProperties, Properties};
+
+impl ControllerProperties for DaemonSetController {
+    fn properties() -> Properties {
+        let mut properties = Properties::default();
+        properties.add_implication(
+            Expectation::Always,
+            "ds: daemonset status.desired_number_scheduled is correct",
+            implication!(observed_daemonset_stable, |_model, state| {
+                let s = state.latest();
+                s.daemonsets
+                    .iter()
+                    .filter(|ds| ds.status.observed_revision != Revision::default())
+                    .all(|ds| {
+                        let observed = state.view_at(&ds.status.observed_revision);
+                        let desired = observed
+                            .nodes
+                            .iter()
+                            .filter(|n| node_should_run_daemon_pod(n, ds).0)
+                            .count() as u32;
+                        let stable = s.resource_stable(ds);
+                        stable.implies(ds.status.desired_number_scheduled == desired)
+                    })
+            }),
+        );
+        properties.add_implication(
+            Expectation::Always,
+            "ds: daemonset status.number_misscheduled is correct",
+            implication!(observed_daemonset_stable, |_model, state| {
+                let s = state.latest();
+                s.daemonsets
+                    .iter()
+                    .filter(|ds| ds.status.observed_revision != Revision::default())
+                    .all(|ds| {
+                        let observed = state.view_at(&ds.status.observed_revision);
+                        let pods = observed
+                            .pods
+                            .for_controller(&ds.metadata.namespace, &ds.metadata.uid)
+                            .collect::<Vec<_>>();
+                        let misscheduled = observed
+                            .nodes
+                            .iter()
+                            .filter(|n| !node_should_run_daemon_pod(n, ds).0)
+                            .filter(|n| {
+                                pods.iter()
+                                    .any(|p| p.spec.node_name.as_ref() == Some(&n.metadata.name))
+                            })
+                            .count() as u32;
+                        let stable = s.resource_stable(ds);
+                        stable.implies(ds.status.number_misscheduled == misscheduled)
+                    })
+            }),
+        );
+        properties.add_implication(
+            Expectation::Always,
+            "ds: when stable, every eligible node runs a daemon pod",
+            implication!(daemonset_stable, |_model, state| {
+                let s = state.latest();
+                s.daemonsets.iter().all(|ds| {
+                    let stable = s.resource_stable(ds);
+                    let pods = s
+                        .pods
+                        .for_controller(&ds.metadata.namespace, &ds.metadata.uid)
+                        .collect::<Vec<_>>();
+                    let all_running = s
+                        .nodes
+                        .iter()
+                        .filter(|n| node_should_run_daemon_pod(n, ds).0)
+                        .all(|n| {
+                            pods.iter()
+                                .any(|p| p.spec.node_name.as_ref() == Some(&n.metadata.name))
+                        });
+                    stable.implies(all_running)
+                })
+            }),
+        );
+        properties
+    }
+}
+
+/// Whether any daemonset that has been observed is stable.
+fn observed_daemonset_stable(_model: &AbstractModel, state: &State) -> bool {
+    let s = state.latest();
+    s.daemonsets
+        .iter()
+        .filter(|ds| ds.status.observed_revision != Revision::default())
+        .any(|ds| s.resource_stable(ds))
+}
+
+/// Whether any daemonset is stable.
+fn daemonset_stable(_model: &AbstractModel, state: &State) -> bool {
+    let s = state.latest();
+    s.daemonsets.iter().any(|ds| s.resource_stable(ds))
+}
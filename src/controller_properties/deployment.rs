@@ -1,3 +1,4 @@
+use crate::abstract_model::AbstractModel;
 use crate::controller::deployment::deployment_complete;
 use crate::controller::deployment::find_old_replicasets;
 use crate::controller::deployment::skip_copy_annotation;
@@ -6,10 +7,12 @@ use crate::controller::util::subset;
 use crate::resources::Pod;
 use crate::resources::ReplicaSet;
 use crate::state::revision::Revision;
+use crate::state::State;
 use crate::utils::LogicalBoolExt;
 use stateright::Expectation;
 
 use crate::controller::DeploymentController;
+use crate::implication;
 
 use super::ControllerProperties;
 use super::Properties;
@@ -27,10 +30,10 @@ impl ControllerProperties for DeploymentController {
                     .all(|d| deployment_complete(d, &d.status))
             },
         );
-        properties.add(
+        properties.add_implication(
             Expectation::Always,
             "dep: replicaset has annotations from deployment",
-            |_m, state| {
+            implication!(deployment_stable, |_m, state| {
                 let s = state.latest();
                 s.deployments
                     .iter()
@@ -43,16 +46,16 @@ impl ControllerProperties for DeploymentController {
                         d_annotations.retain(|k, _| !skip_copy_annotation(k));
                         let correct_annotations = observed
                             .replicasets
-                            .for_controller(&d.metadata.uid)
+                            .for_controller(&d.metadata.namespace, &d.metadata.uid)
                             .all(|rs| subset(&d_annotations, &rs.metadata.annotations));
                         stable.implies(correct_annotations)
                     })
-            },
+            }),
         );
-        properties.add(
+        properties.add_implication(
             Expectation::Always,
             "dep: rs has pod-template-hash in selector, label and template label",
-            |_m, state| {
+            implication!(deployment_stable, |_m, state| {
                 let s = state.latest();
                 s.deployments
                     .iter()
@@ -63,16 +66,16 @@ impl ControllerProperties for DeploymentController {
                         let stable = s.resource_stable(d);
                         let correct_hash = observed
                             .replicasets
-                            .for_controller(&d.metadata.uid)
+                            .for_controller(&d.metadata.namespace, &d.metadata.uid)
                             .all(check_rs_hash_labels);
                         stable.implies(correct_hash)
                     })
-            },
+            }),
         );
-        properties.add(
+        properties.add_implication(
             Expectation::Always,
             "dep: all pods for the rs should have the pod-template-hash in their labels",
-            |_m, state| {
+            implication!(deployment_stable, |_m, state| {
                 let s = state.latest();
                 s.deployments
                     .iter()
@@ -81,16 +84,19 @@ impl ControllerProperties for DeploymentController {
                         let observed_revision = &d.status.observed_revision;
                         let observed = state.view_at(observed_revision);
                         let stable = s.resource_stable(d);
-                        let correct_hash =
-                            check_pods_hash_label(observed.pods.for_controller(&d.metadata.uid));
+                        let correct_hash = check_pods_hash_label(
+                            observed
+                                .pods
+                                .for_controller(&d.metadata.namespace, &d.metadata.uid),
+                        );
                         stable.implies(correct_hash)
                     })
-            },
+            }),
         );
-        properties.add(
+        properties.add_implication(
             Expectation::Always,
             "dep: old rss do not have pods",
-            |_model, state| {
+            implication!(unpaused_deployment_stable, |_model, state| {
                 let s = state.latest();
                 s.deployments
                     .iter()
@@ -109,7 +115,7 @@ impl ControllerProperties for DeploymentController {
                             .all(|rs| rs.spec.replicas.map_or(false, |r| r == 0));
                         stable.implies(empty_old_rss)
                     })
-            },
+            }),
         );
         properties.add(
             Expectation::Always,
@@ -173,3 +179,22 @@ fn check_pods_hash_label<'a>(pods: impl IntoIterator<Item = &'a Pod>) -> bool {
     }
     true
 }
+
+/// Whether any deployment that has been observed is stable.
+fn deployment_stable(_model: &AbstractModel, state: &State) -> bool {
+    let s = state.latest();
+    s.deployments
+        .iter()
+        .filter(|d| d.status.observed_revision != Revision::default())
+        .any(|d| s.resource_stable(d))
+}
+
+/// Whether any deployment that has been observed and isn't paused is stable.
+fn unpaused_deployment_stable(_model: &AbstractModel, state: &State) -> bool {
+    let s = state.latest();
+    s.deployments
+        .iter()
+        .filter(|d| d.status.observed_revision != Revision::default())
+        .filter(|d| !d.spec.paused)
+        .any(|d| s.resource_stable(d))
+}
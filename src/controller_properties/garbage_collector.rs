@@ -0,0 +1,99 @@
+use stateright::Expectation;
+
+use crate::{
+    abstract_model::AbstractModel,
+    controller::{garbage_collector::owner_exists, ControllerStates, GarbageCollectorController},
+    implication,
+    resources::{Metadata, ORPHAN_FINALIZER},
+    state::{State, StateView},
+};
+
+use super::{ControllerProperties, Properties};
+
+impl ControllerProperties for GarbageCollectorController {
+    fn properties() -> Properties {
+        let mut properties = Properties::default();
+        properties.add_implication(
+            Expectation::Always,
+            "gc: when stable, no live object has a dangling controller ownerReference",
+            implication!(collectors_stable, |_model, state| {
+                let s = state.latest();
+                let dangling = |m: &Metadata| {
+                    m.deletion_timestamp.is_none()
+                        && m.owner_references
+                            .iter()
+                            .any(|or| or.controller && !owner_exists(&s, &m.namespace, or))
+                };
+                let any_dangling = s.pods.iter().any(|p| dangling(&p.metadata))
+                    || s.replicasets.iter().any(|rs| dangling(&rs.metadata))
+                    || s.controller_revisions
+                        .iter()
+                        .any(|cr| dangling(&cr.metadata))
+                    || s.jobs.iter().any(|job| dangling(&job.metadata));
+                !any_dangling
+            }),
+        );
+        properties.add_implication(
+            Expectation::Always,
+            "gc: when stable, owners being orphaned have no dependents",
+            implication!(
+                |model, state| {
+                    let s = state.latest();
+                    collectors_stable(model, state) && owners(&s).any(orphaning)
+                },
+                |_model, state| {
+                    let s = state.latest();
+                    let has_dependents = |m: &Metadata| {
+                        s.pods.owned_by(&m.namespace, &m.uid).next().is_some()
+                            || s.replicasets
+                                .owned_by(&m.namespace, &m.uid)
+                                .next()
+                                .is_some()
+                            || s.controller_revisions
+                                .owned_by(&m.namespace, &m.uid)
+                                .next()
+                                .is_some()
+                            || s.jobs.owned_by(&m.namespace, &m.uid).next().is_some()
+                    };
+                    owners(&s)
+                        .filter(|m| orphaning(m))
+                        .all(|m| !has_dependents(m))
+                },
+            ),
+        );
+        properties
+    }
+}
+
+/// The metadata of the resources that can own others.
+fn owners(s: &StateView) -> impl Iterator<Item = &Metadata> {
+    s.deployments
+        .iter()
+        .map(|d| &d.metadata)
+        .chain(s.replicasets.iter().map(|rs| &rs.metadata))
+        .chain(s.statefulsets.iter().map(|sts| &sts.metadata))
+        .chain(s.daemonsets.iter().map(|ds| &ds.metadata))
+        .chain(s.jobs.iter().map(|job| &job.metadata))
+        .chain(s.cronjobs.iter().map(|cj| &cj.metadata))
+}
+
+/// Whether the owner is being deleted with its dependents orphaned.
+fn orphaning(m: &Metadata) -> bool {
+    m.deletion_timestamp.is_some() && m.finalizers.iter().any(|f| f == ORPHAN_FINALIZER)
+}
+
+/// Whether every garbage collector has stepped on the latest state.
+fn collectors_stable(model: &AbstractModel, state: &State) -> bool {
+    let max_revision = state.max_revision();
+    let mut collectors = (0..model.controllers.len())
+        .filter_map(|i| match state.get_controller(i) {
+            ControllerStates::GarbageCollector(s) => Some(s),
+            ControllerStates::LeaderElected(le) => match &le.inner {
+                ControllerStates::GarbageCollector(s) => Some(s),
+                _ => None,
+            },
+            _ => None,
+        })
+        .peekable();
+    collectors.peek().is_some() && collectors.all(|s| s.revision.as_ref() == Some(&max_revision))
+}
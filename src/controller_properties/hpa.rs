@@ -0,0 +1,105 @@
+use stateright::Expectation;
+
+use crate::{
+    abstract_model::AbstractModel,
+    controller::{
+        hpa::{get_scale, target_cpu_utilization, within_tolerance},
+        HPAController,
+    },
+    implication,
+    resources::HorizontalPodAutoscaler,
+    state::{revision::Revision, State},
+    utils::LogicalBoolExt,
+};
+
+use super::{ControllerProperties, Properties};
+
+impl ControllerProperties for HPAController {
+    fn properties() -> Properties {
+        let mut properties = Properties::default();
+        properties.add_implication(
+            Expectation::Always,
+            "hpa: never scales its target outside min and max replicas",
+            implication!(any_autoscaled_target, |_model, state| {
+                let s = state.latest();
+                s.horizontal_pod_autoscalers.iter().all(|hpa| {
+                    let min_replicas = hpa.spec.min_replicas.unwrap_or(1);
+                    autoscaled_target(state, hpa).map_or(true, |replicas| {
+                        min_replicas <= replicas && replicas <= hpa.spec.max_replicas
+                    })
+                })
+            }),
+        );
+        properties.add_implication(
+            Expectation::Always,
+            "hpa: doesn't fight the client scaling its target within bounds",
+            implication!(any_client_scaled_within_bounds, |_model, state| {
+                let s = state.latest();
+                s.horizontal_pod_autoscalers
+                    .iter()
+                    .filter(|hpa| hpa.status.observed_revision != Revision::default())
+                    .all(|hpa| {
+                        client_scaled_within_bounds(state, hpa)
+                            .implies(hpa.status.desired_replicas == hpa.status.current_replicas)
+                    })
+            }),
+        );
+        properties
+    }
+}
+
+/// Whether any autoscaler was the last to set the replicas of its target.
+fn any_autoscaled_target(_model: &AbstractModel, state: &State) -> bool {
+    let s = state.latest();
+    s.horizontal_pod_autoscalers
+        .iter()
+        .any(|hpa| autoscaled_target(state, hpa).is_some())
+}
+
+/// Whether the client has scaled the target of any autoscaler that has observed it within its
+/// bounds.
+fn any_client_scaled_within_bounds(_model: &AbstractModel, state: &State) -> bool {
+    let s = state.latest();
+    s.horizontal_pod_autoscalers
+        .iter()
+        .filter(|hpa| hpa.status.observed_revision != Revision::default())
+        .any(|hpa| client_scaled_within_bounds(state, hpa))
+}
+
+/// Whether the autoscaler is acting on its target, as a target scaled to zero has autoscaling
+/// disabled.
+fn autoscaling_enabled(hpa: &HorizontalPodAutoscaler) -> bool {
+    hpa.status.current_replicas != 0
+}
+
+/// The replicas of the autoscaler's target, if the autoscaler was the last to set them and is
+/// still acting on it.
+fn autoscaled_target(state: &State, hpa: &HorizontalPodAutoscaler) -> Option<u32> {
+    let target = &hpa.spec.scale_target_ref;
+    let namespace = &hpa.metadata.namespace;
+    if !autoscaling_enabled(hpa) || !state.autoscaled(&target.kind, namespace, &target.name) {
+        return None;
+    }
+    get_scale(&state.latest(), namespace, hpa).map(|scale| scale.spec.replicas)
+}
+
+/// Whether the client has scaled the target of the autoscaler within its bounds, with the metric
+/// the autoscaler last observed already on target.
+fn client_scaled_within_bounds(state: &State, hpa: &HorizontalPodAutoscaler) -> bool {
+    // the metric may have changed since, so judge against the one the autoscaler saw
+    let observed = state.view_at(&hpa.status.observed_revision);
+    let Some(observed_hpa) = observed
+        .horizontal_pod_autoscalers
+        .get(&hpa.metadata.namespace, &hpa.metadata.name)
+    else {
+        return false;
+    };
+    let min_replicas = hpa.spec.min_replicas.unwrap_or(1);
+    let current = hpa.status.current_replicas;
+    let in_bounds = min_replicas <= current && current <= hpa.spec.max_replicas;
+    let on_target = observed_hpa
+        .status
+        .current_cpu_utilization_percentage
+        .map_or(true, |u| within_tolerance(u, target_cpu_utilization(hpa)));
+    in_bounds && on_target
+}
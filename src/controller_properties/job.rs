@@ -1,12 +1,15 @@
+use crate::abstract_model::AbstractModel;
 use crate::controller::job::JOB_TRACKING_FINALIZER;
 use crate::controller::util::is_pod_active;
 use crate::controller::util::is_pod_ready;
 use crate::resources::PodPhase;
 use crate::state::revision::Revision;
+use crate::state::State;
 use crate::utils::LogicalBoolExt;
 use stateright::Expectation;
 
 use crate::controller::JobController;
+use crate::implication;
 
 use super::ControllerProperties;
 use super::Properties;
@@ -14,10 +17,10 @@ use super::Properties;
 impl ControllerProperties for JobController {
     fn properties() -> Properties {
         let mut properties = Properties::default();
-        properties.add(
+        properties.add_implication(
             Expectation::Always,
             "job: when synced, status.active is correct",
-            |_model, state| {
+            implication!(job_stable, |_model, state| {
                 let s = state.latest();
                 s.jobs
                     .iter()
@@ -27,7 +30,7 @@ impl ControllerProperties for JobController {
                         let observed = state.view_at(observed_revision);
                         let active_pods = observed
                             .pods
-                            .for_controller(&r.metadata.uid)
+                            .for_controller(&r.metadata.namespace, &r.metadata.uid)
                             .filter(|p| is_pod_active(p))
                             .count();
                         // when the resource has finished processing towards the desired state the
@@ -38,12 +41,12 @@ impl ControllerProperties for JobController {
                         let active_correct = active_pods as u32 == r.status.active;
                         stable.implies(active_correct)
                     })
-            },
+            }),
         );
-        properties.add(
+        properties.add_implication(
             Expectation::Always,
             "job: when synced, status.ready is correct",
-            |_model, state| {
+            implication!(job_stable, |_model, state| {
                 let s = state.latest();
                 s.jobs
                     .iter()
@@ -53,7 +56,7 @@ impl ControllerProperties for JobController {
                         let observed = state.view_at(observed_revision);
                         let ready_pods = observed
                             .pods
-                            .for_controller(&r.metadata.uid)
+                            .for_controller(&r.metadata.namespace, &r.metadata.uid)
                             .filter(|p| is_pod_ready(p))
                             .count();
                         // when the resource has finished processing towards the desired state the
@@ -64,7 +67,7 @@ impl ControllerProperties for JobController {
                         let ready_correct = ready_pods as u32 == r.status.ready;
                         stable.implies(ready_correct)
                     })
-            },
+            }),
         );
         // properties.add(
         //     Expectation::Always,
@@ -83,10 +86,10 @@ impl ControllerProperties for JobController {
         //         })
         //     },
         // );
-        properties.add(
+        properties.add_implication(
             Expectation::Always,
             "job: observed finished pods have no finalizer",
-            |_model, state| {
+            implication!(job_stable, |_model, state| {
                 let s = state.latest();
                 s.jobs
                     .iter()
@@ -97,7 +100,7 @@ impl ControllerProperties for JobController {
                         let stable = s.resource_stable(r);
                         let old_pods_dont_have_finalizer = observed
                             .pods
-                            .for_controller(&r.metadata.uid)
+                            .for_controller(&r.metadata.namespace, &r.metadata.uid)
                             .filter(|p| p.metadata.resource_version < r.metadata.resource_version)
                             .filter(|p| {
                                 matches!(p.status.phase, PodPhase::Succeeded | PodPhase::Failed)
@@ -109,8 +112,17 @@ impl ControllerProperties for JobController {
                             });
                         stable.implies(old_pods_dont_have_finalizer)
                     })
-            },
+            }),
         );
         properties
     }
 }
+
+/// Whether any job that has been observed is stable.
+fn job_stable(_model: &AbstractModel, state: &State) -> bool {
+    let s = state.latest();
+    s.jobs
+        .iter()
+        .filter(|r| r.status.observed_revision != Revision::default())
+        .any(|r| s.resource_stable(r))
+}
@@ -0,0 +1,17 @@
+use stateright::Expectation;
+
+use crate::controller::LeaderElected;
+
+use super::{ControllerProperties, Properties};
+
+impl<C> ControllerProperties for LeaderElected<C> {
+    fn properties() -> Properties {
+        let mut properties = Properties::default();
+        properties.add(
+            Expectation::Always,
+            "lease: no writes from candidates that lost the lease",
+            |_model, state| state.split_brain_leases().is_empty(),
+        );
+        properties
+    }
+}
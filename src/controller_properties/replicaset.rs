@@ -1,8 +1,10 @@
 use stateright::Expectation;
 
 use crate::{
+    abstract_model::AbstractModel,
     controller::{util::is_pod_active, ReplicaSetController},
-    state::revision::Revision,
+    implication,
+    state::{revision::Revision, State},
     utils::LogicalBoolExt,
 };
 
@@ -11,10 +13,10 @@ use super::{ControllerProperties, Properties};
 impl ControllerProperties for ReplicaSetController {
     fn properties() -> Properties {
         let mut properties = Properties::default();
-        properties.add(
+        properties.add_implication(
             Expectation::Always,
             "rs: when stable, status.replicas == count(active_pods)",
-            |_model, state| {
+            implication!(observed_replicaset_stable, |_model, state| {
                 let s = state.latest();
                 s.replicasets
                     .iter()
@@ -29,7 +31,7 @@ impl ControllerProperties for ReplicaSetController {
                         // implementation.
                         let pod_count = observed
                             .pods
-                            .for_controller(&r.metadata.uid)
+                            .for_controller(&r.metadata.namespace, &r.metadata.uid)
                             .filter(|p| is_pod_active(p))
                             .count();
                         // when the resource has finished processing towards the desired state the
@@ -40,12 +42,12 @@ impl ControllerProperties for ReplicaSetController {
                             pod_count as u32 == r.status.replicas,
                         )
                     })
-            },
+            }),
         );
-        properties.add(
+        properties.add_implication(
             Expectation::Always,
             "rs: when stable, status replicas == spec replicas",
-            |_model, state| {
+            implication!(replicaset_stable, |_model, state| {
                 let s = state.latest();
                 let mut replicasets_iter = s.replicasets.iter();
                 replicasets_iter.all(|r| {
@@ -53,8 +55,23 @@ impl ControllerProperties for ReplicaSetController {
                     let replicas_equal = r.spec.replicas.unwrap() == r.status.replicas;
                     stable.implies(replicas_equal)
                 })
-            },
+            }),
         );
         properties
     }
 }
+
+/// Whether any replicaset that has been observed is stable.
+fn observed_replicaset_stable(_model: &AbstractModel, state: &State) -> bool {
+    let s = state.latest();
+    s.replicasets
+        .iter()
+        .filter(|r| r.status.observed_revision != Revision::default())
+        .any(|r| s.resource_stable(r))
+}
+
+/// Whether any replicaset is stable.
+fn replicaset_stable(_model: &AbstractModel, state: &State) -> bool {
+    let s = state.latest();
+    s.replicasets.iter().any(|r| s.resource_stable(r))
+}
@@ -1,12 +1,14 @@
 use stateright::Expectation;
 
 use crate::{
+    abstract_model::AbstractModel,
     controller::{
         statefulset::{get_ordinal, pod_in_ordinal_range},
         util::is_pod_ready,
         StatefulSetController,
     },
-    state::revision::Revision,
+    implication,
+    state::{revision::Revision, State},
     utils::LogicalBoolExt,
 };
 
@@ -15,10 +17,10 @@ use super::{ControllerProperties, Properties};
 impl ControllerProperties for StatefulSetController {
     fn properties() -> Properties {
         let mut properties = Properties::default();
-        properties.add(
+        properties.add_implication(
             Expectation::Always,
             "sts: statefulset status.replicas is correct",
-            |_model, state| {
+            implication!(statefulset_stable, |_model, state| {
                 let s = state.latest();
                 s.statefulsets
                     .iter()
@@ -26,16 +28,19 @@ impl ControllerProperties for StatefulSetController {
                     .all(|sts| {
                         let observed_revision = &sts.status.observed_revision;
                         let observed = state.view_at(observed_revision);
-                        let pod_count = observed.pods.matching(&sts.spec.selector).count() as u32;
+                        let pod_count = observed
+                            .pods
+                            .matching(&sts.metadata.namespace, &sts.spec.selector)
+                            .count() as u32;
                         let stable = s.resource_stable(sts);
                         stable.implies(sts.status.replicas == pod_count)
                     })
-            },
+            }),
         );
-        properties.add(
+        properties.add_implication(
             Expectation::Always,
             "sts: statefulset status.ready_replicas is correct",
-            |_model, state| {
+            implication!(statefulset_stable, |_model, state| {
                 let s = state.latest();
                 s.statefulsets
                     .iter()
@@ -45,18 +50,18 @@ impl ControllerProperties for StatefulSetController {
                         let observed = state.view_at(observed_revision);
                         let pod_count = observed
                             .pods
-                            .matching(&sts.spec.selector)
+                            .matching(&sts.metadata.namespace, &sts.spec.selector)
                             .filter(|p| is_pod_ready(p))
                             .count() as u32;
                         let stable = s.resource_stable(sts);
                         stable.implies(sts.status.ready_replicas == pod_count)
                     })
-            },
+            }),
         );
-        properties.add(
+        properties.add_implication(
             Expectation::Always,
             "sts: statefulset status.available_replicas is correct",
-            |_model, state| {
+            implication!(statefulset_stable, |_model, state| {
                 let s = state.latest();
                 s.statefulsets
                     .iter()
@@ -66,18 +71,18 @@ impl ControllerProperties for StatefulSetController {
                         let observed = state.view_at(observed_revision);
                         let pod_count = observed
                             .pods
-                            .matching(&sts.spec.selector)
+                            .matching(&sts.metadata.namespace, &sts.spec.selector)
                             .filter(|p| is_pod_ready(p))
                             .count() as u32;
                         let stable = s.resource_stable(sts);
                         stable.implies(sts.status.available_replicas == pod_count)
                     })
-            },
+            }),
         );
-        properties.add(
+        properties.add_implication(
             Expectation::Always,
             "sts: when stable, the first statefulset pod has the correct start ordinal",
-            |_model, state| {
+            implication!(statefulset_stable, |_model, state| {
                 // point one and two from https://kubernetes.io/docs/concepts/workloads/controllers/statefulset/#deployment-and-scaling-guarantees
                 let s = state.latest();
                 s.statefulsets
@@ -88,7 +93,7 @@ impl ControllerProperties for StatefulSetController {
                         let observed = state.view_at(observed_revision);
                         let mut ordinals = observed
                             .pods
-                            .for_controller(&sts.metadata.uid)
+                            .for_controller(&sts.metadata.namespace, &sts.metadata.uid)
                             .filter(|p| pod_in_ordinal_range(p, sts))
                             .map(|p| get_ordinal(p).unwrap())
                             .collect::<Vec<_>>();
@@ -99,7 +104,7 @@ impl ControllerProperties for StatefulSetController {
                         });
                         s.resource_stable(sts).implies(correct_start)
                     })
-            },
+            }),
         );
         // properties.add(
         //     Expectation::Always,
@@ -129,3 +134,12 @@ impl ControllerProperties for StatefulSetController {
         properties
     }
 }
+
+/// Whether any statefulset that has been observed is stable.
+fn statefulset_stable(_model: &AbstractModel, state: &State) -> bool {
+    let s = state.latest();
+    s.statefulsets
+        .iter()
+        .filter(|sts| sts.status.observed_revision != Revision::default())
+        .any(|sts| s.resource_stable(sts))
+}
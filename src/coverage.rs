@@ -0,0 +1,170 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use stateright::report::Reporter;
+use stateright::Model;
+
+use crate::abstract_model::ControllerAction;
+use crate::controller::{Controller, Controllers};
+use crate::vacuity::Vacuity;
+
+/// Tallies of what the checker exercised, counted as the model takes each transition.
+#[derive(Debug)]
+pub struct Coverage {
+    /// The names of the controllers (by index), followed by the client.
+    actors: Vec<String>,
+    /// Operations produced, by actor and kind of operation.
+    operations: Vec<AtomicUsize>,
+    /// Operations that the datastore didn't apply, by actor and kind of operation.
+    rejected: Vec<AtomicUsize>,
+    vacuity: Arc<Vacuity>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct CoverageCounts {
+    /// Operations produced, by the name of the controller (or client) and the kind of operation.
+    pub operations: BTreeMap<(String, &'static str), usize>,
+    /// Operations that the datastore didn't apply, by the name of the controller (or client) and
+    /// the kind of operation.
+    pub rejected: BTreeMap<(String, &'static str), usize>,
+    /// How often each property was evaluated, and on how many of those states its antecedent held
+    /// (if it is an implication).
+    pub properties: BTreeMap<&'static str, PropertyCoverage>,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PropertyCoverage {
+    pub evaluated: usize,
+    pub antecedent_held: Option<usize>,
+}
+
+impl Coverage {
+    pub fn new(controllers: &[Controllers], vacuity: Arc<Vacuity>) -> Self {
+        let mut actors = controllers.iter().map(|c| c.name()).collect::<Vec<_>>();
+        actors.push("Client".to_owned());
+        let slots = actors.len() * ControllerAction::KINDS.len();
+        Self {
+            actors,
+            operations: (0..slots).map(|_| AtomicUsize::new(0)).collect(),
+            rejected: (0..slots).map(|_| AtomicUsize::new(0)).collect(),
+            vacuity,
+        }
+    }
+
+    /// Tally an operation from the controller at the given index, or the client if none.
+    pub fn record(&self, controller: Option<usize>, kind: &'static str, rejected: bool) {
+        let actor = controller.unwrap_or(self.actors.len() - 1);
+        let Some(kind) = ControllerAction::KINDS.iter().position(|k| *k == kind) else {
+            return;
+        };
+        let slot = actor * ControllerAction::KINDS.len() + kind;
+        self.operations[slot].fetch_add(1, Ordering::Relaxed);
+        if rejected {
+            self.rejected[slot].fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// The tallies so far, summing over controllers with the same name.
+    pub fn counts(&self) -> CoverageCounts {
+        let mut counts = CoverageCounts::default();
+        for (slot, operations) in self.operations.iter().enumerate() {
+            let operations = operations.load(Ordering::Relaxed);
+            if operations == 0 {
+                continue;
+            }
+            let actor = &self.actors[slot / ControllerAction::KINDS.len()];
+            let kind = ControllerAction::KINDS[slot % ControllerAction::KINDS.len()];
+            let key = (actor.clone(), kind);
+            *counts.operations.entry(key.clone()).or_default() += operations;
+            let rejected = self.rejected[slot].load(Ordering::Relaxed);
+            if rejected > 0 {
+                *counts.rejected.entry(key).or_default() += rejected;
+            }
+        }
+        counts.properties = self.vacuity.coverage();
+        counts
+    }
+}
+
+/// Prints the coverage as a table and writes it as CSV once checking is done.
+pub struct CoverageReporter {
+    coverage: Arc<Coverage>,
+    writer: csv::Writer<File>,
+}
+
+impl CoverageReporter {
+    /// Create a new reporter writing to the file at the given path, which is created up front.
+    pub fn new(path: &Path, coverage: Arc<Coverage>) -> std::io::Result<Self> {
+        Ok(Self {
+            coverage,
+            writer: csv::Writer::from_writer(File::create(path)?),
+        })
+    }
+}
+
+impl<M: Model> Reporter<M> for CoverageReporter {
+    fn report_checking(&mut self, data: stateright::report::ReportData) {
+        if !data.done {
+            return;
+        }
+        let counts = self.coverage.counts();
+        let writer = &mut self.writer;
+        writer
+            .write_record(["category", "subject", "detail", "count"])
+            .unwrap();
+
+        println!("Operations by controller:");
+        for ((actor, kind), count) in &counts.operations {
+            let rejected = counts
+                .rejected
+                .get(&(actor.clone(), *kind))
+                .copied()
+                .unwrap_or_default();
+            println!(
+                "  {: <20} {: <30} {: >8} ({} rejected)",
+                actor, kind, count, rejected
+            );
+            writer
+                .write_record(["operation", actor, kind, &count.to_string()])
+                .unwrap();
+            writer
+                .write_record(["rejected", actor, kind, &rejected.to_string()])
+                .unwrap();
+        }
+
+        println!("Properties:");
+        for (name, coverage) in &counts.properties {
+            let antecedent = coverage
+                .antecedent_held
+                .map_or_else(|| "-".to_owned(), |c| c.to_string());
+            println!(
+                "  {:?} evaluated={} antecedent_held={}",
+                name, coverage.evaluated, antecedent
+            );
+            writer
+                .write_record([
+                    "property",
+                    name,
+                    "evaluated",
+                    &coverage.evaluated.to_string(),
+                ])
+                .unwrap();
+            writer
+                .write_record(["property", name, "antecedent_held", &antecedent])
+                .unwrap();
+        }
+        writer.flush().unwrap();
+    }
+
+    fn report_discoveries(
+        &mut self,
+        _discoveries: BTreeMap<&'static str, stateright::report::ReportDiscovery<M>>,
+    ) where
+        <M as Model>::Action: std::fmt::Debug,
+        <M as Model>::State: std::fmt::Debug + std::hash::Hash,
+    {
+    }
+}
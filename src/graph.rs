@@ -0,0 +1,114 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::io::Write;
+
+use stateright::{Expectation, Model};
+
+use crate::abstract_model::AbstractModel;
+use crate::state::RawState;
+
+/// Write the states reachable within the given depth as a DOT graph.
+///
+/// Nodes are labelled with a summary of the latest state and edges with the action taken.
+/// States that violate an always property are highlighted.
+pub fn export_dot(
+    model: &AbstractModel,
+    max_depth: usize,
+    out: &mut impl Write,
+) -> std::io::Result<()> {
+    let properties = model
+        .properties()
+        .into_iter()
+        .filter(|p| matches!(p.expectation, Expectation::Always))
+        .collect::<Vec<_>>();
+
+    writeln!(out, "digraph states {{")?;
+    writeln!(out, "  node [shape=box];")?;
+
+    let mut ids = HashMap::new();
+    let mut queue = VecDeque::new();
+    for state in model.init_states() {
+        let id = ids.len();
+        ids.insert(stateright::fingerprint(&state), id);
+        queue.push_back((state, id, 0));
+    }
+    while let Some((state, id, depth)) = queue.pop_front() {
+        let violated = properties
+            .iter()
+            .filter(|p| !(p.condition)(model, &state))
+            .map(|p| p.name)
+            .collect::<Vec<_>>();
+        let mut label = summarise(&state.latest().state);
+        for name in &violated {
+            label.push_str(&format!("\nviolated: {name}"));
+        }
+        let style = if violated.is_empty() {
+            ""
+        } else {
+            ", style=filled, fillcolor=red"
+        };
+        writeln!(out, "  {} [label=\"{}\"{}];", id, escape(&label), style)?;
+
+        if depth >= max_depth {
+            continue;
+        }
+        let mut actions = Vec::new();
+        model.actions(&state, &mut actions);
+        for action in actions {
+            let label = model.format_action(&state, &action);
+            let Some(next_state) = model.next_state(&state, action) else {
+                continue;
+            };
+            let fingerprint = stateright::fingerprint(&next_state);
+            let next_id = match ids.get(&fingerprint) {
+                Some(next_id) => *next_id,
+                None => {
+                    let next_id = ids.len();
+                    ids.insert(fingerprint, next_id);
+                    queue.push_back((next_state, next_id, depth + 1));
+                    next_id
+                }
+            };
+            writeln!(
+                out,
+                "  {} -> {} [label=\"{}\"];",
+                id,
+                next_id,
+                escape(&label)
+            )?;
+        }
+    }
+
+    writeln!(out, "}}")
+}
+
+/// Summarise a state by the number of pods in each phase and the replicas of each replicaset.
+fn summarise(state: &RawState) -> String {
+    let mut phases = BTreeMap::<String, usize>::new();
+    for pod in state.pods.iter() {
+        *phases.entry(format!("{:?}", pod.status.phase)).or_default() += 1;
+    }
+    let mut lines = vec![format!(
+        "pods: {}",
+        phases
+            .iter()
+            .map(|(phase, count)| format!("{phase}={count}"))
+            .collect::<Vec<_>>()
+            .join(" ")
+    )];
+    for rs in state.replicasets.iter() {
+        lines.push(format!(
+            "{}: {}/{}",
+            rs.metadata.name,
+            rs.status.replicas,
+            rs.spec.replicas.unwrap_or_default()
+        ));
+    }
+    lines.join("\n")
+}
+
+fn escape(label: &str) -> String {
+    label
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
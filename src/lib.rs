@@ -4,11 +4,19 @@ pub mod arbitrary_client;
 pub mod controller;
 pub mod controller_manager;
 pub mod controller_properties;
+pub mod coverage;
+pub mod graph;
 pub mod hasher;
+pub mod minimise;
 pub mod model;
+pub mod reduction;
+pub mod replay;
 pub mod report;
 pub mod resources;
+pub mod scenario;
 pub mod serve_cluster;
+pub mod serve_history;
 pub mod serve_test;
 pub mod state;
 pub mod utils;
+pub mod vacuity;
@@ -1,11 +1,23 @@
 use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufWriter;
 use std::io::IsTerminal;
+use std::path::Path;
 
 use clap::Parser;
 use stateright::Checker;
 use stateright::Model;
 use stateright::UniformChooser;
+use themelios::abstract_model::AbstractModel;
+use themelios::abstract_model::QUIESCENCE_PROPERTY;
+use themelios::controller_properties::Properties;
+use themelios::coverage::CoverageReporter;
+use themelios::graph;
+use themelios::minimise::Minimiser;
 use themelios::model;
+use themelios::replay;
+use themelios::report::JointReporter;
+use themelios::report::JsonReporter;
 use themelios::report::StdoutReporter;
 use themelios::resources::Deployment;
 use themelios::resources::DeploymentSpec;
@@ -24,8 +36,9 @@ use themelios::resources::ReplicaSetStatus;
 use themelios::resources::StatefulSet;
 use themelios::resources::StatefulSetSpec;
 use themelios::resources::StatefulSetStatus;
-use themelios::state::history::ConsistencySetup;
+use themelios::scenario::Scenario;
 use themelios::state::RawState;
+use themelios::state::State;
 use themelios::utils;
 use tokio::runtime::Runtime;
 use tower_http::trace::TraceLayer;
@@ -50,6 +63,60 @@ fn main() {
         .with(log_filter)
         .init();
 
+    let model = if let Some(path) = &opts.scenario {
+        match Scenario::load(path) {
+            Ok(scenario) => {
+                let mut cfg = scenario.into_model_cfg();
+                cfg.partial_order_reduction = opts.partial_order_reduction;
+                cfg
+            }
+            Err(err) => {
+                eprintln!("Failed to load scenario: {err}");
+                std::process::exit(1);
+            }
+        }
+    } else {
+        model_from_opts(&opts)
+    };
+    if let opts::SubCmd::Replay {
+        fingerprint_path,
+        actions,
+        property,
+    } = &opts.command
+    {
+        replay(
+            abstract_model(model),
+            fingerprint_path.as_deref(),
+            actions.as_deref(),
+            property.as_deref(),
+        );
+        return;
+    }
+    if let opts::SubCmd::ExportGraph { output, depth } = &opts.command {
+        let model = abstract_model(model);
+        let file = File::create(output).unwrap_or_else(|err| {
+            eprintln!("Failed to create {output:?}: {err}");
+            std::process::exit(1);
+        });
+        if let Err(err) = graph::export_dot(&model, *depth, &mut BufWriter::new(file)) {
+            eprintln!("Failed to write the state graph to {output:?}: {err}");
+            std::process::exit(1);
+        }
+        println!("Wrote state graph to {:?}", output);
+        return;
+    }
+    run(opts, model)
+}
+
+/// Build the model, exiting if its configuration is invalid.
+fn abstract_model(cfg: model::OrchestrationModelCfg) -> AbstractModel {
+    cfg.try_into_abstract_model().unwrap_or_else(|err| {
+        eprintln!("Invalid model configuration: {err}");
+        std::process::exit(1);
+    })
+}
+
+fn model_from_opts(opts: &opts::Opts) -> model::OrchestrationModelCfg {
     let initial_state = RawState::default()
         .with_pods((0..opts.initial_pods).map(|i| Pod {
             metadata: utils::metadata(format!("pod-{i}")),
@@ -146,39 +213,75 @@ fn main() {
             status: NodeStatus::default(),
         }));
 
-    let consistency_level = if opts.session {
-        ConsistencySetup::ResettableSession
-    } else if opts.optimistic_linear {
-        ConsistencySetup::OptimisticLinear
-    } else if opts.causal {
-        ConsistencySetup::Causal
-    } else {
-        // default to synchronous
-        ConsistencySetup::Synchronous
-    };
-    let model = model::OrchestrationModelCfg {
+    model::OrchestrationModelCfg {
         initial_state,
-        consistency_level,
+        consistency_level: opts.consistency_level(),
+        controller_consistency: opts
+            .controller_consistency
+            .iter()
+            .map(|c| (c.controller.clone(), c.value.clone()))
+            .collect(),
+        read_modes: opts
+            .read_mode
+            .iter()
+            .map(|r| (r.controller.clone(), r.value))
+            .collect(),
+        leader_election: opts.leader_election.iter().cloned().collect(),
+        fencing: opts.fencing,
+        quiescence_bound: opts.quiescence_bound,
+        partial_order_reduction: opts.partial_order_reduction,
         schedulers: opts.schedulers,
         nodes: opts.nodes,
         replicaset_controllers: opts.replicaset_controllers,
         deployment_controllers: opts.deployment_controllers,
         statefulset_controllers: opts.statefulset_controllers,
+        daemonset_controllers: opts.daemonset_controllers,
         job_controllers: opts.job_controllers,
+        cronjob_controllers: opts.cronjob_controllers,
+        hpa_controllers: opts.hpa_controllers,
         podgc_controllers: opts.podgc_controllers,
-        properties: Vec::new(),
-    };
-    run(opts, model.into_abstract_model())
+        garbage_collector_controllers: opts.garbage_collector_controllers,
+        clock_ticks: opts.clock_ticks,
+        properties: Properties::default(),
+    }
 }
 
-fn run<M>(opts: opts::Opts, model: M)
-where
-    M: Model + Send + Sync + 'static,
-    M::State: Send + Sync + std::hash::Hash + std::fmt::Debug + Clone,
-    M::Action: Send + Sync + std::hash::Hash + std::fmt::Debug + Clone,
-{
+fn run(opts: opts::Opts, cfg: model::OrchestrationModelCfg) {
     println!("Running with config {:?}", opts);
-    let mut reporter = StdoutReporter::new(&model);
+    let minimiser = Minimiser::new(abstract_model(cfg.clone()));
+    let model = abstract_model(cfg.clone());
+    let vacuity = model.vacuity.clone();
+    let stdout_reporter = StdoutReporter::new(&model)
+        .with_quiescence(QUIESCENCE_PROPERTY, |state| {
+            state.writes_since_perturbation().unwrap_or_default()
+        })
+        .with_minimiser(move |property, actions| {
+            minimiser
+                .minimise(property, actions)
+                .map(|trace| (trace.actions, trace.fingerprint_path))
+        })
+        .with_vacuity(move || vacuity.vacuous());
+    let mut reporter = JointReporter {
+        reporters: vec![Box::new(stdout_reporter)],
+    };
+    if let Some(path) = &opts.json_report {
+        let json_reporter = JsonReporter::new(path, abstract_model(cfg.clone()));
+        reporter
+            .reporters
+            .push(Box::new(json_reporter.unwrap_or_else(|err| {
+                eprintln!("Failed to create the JSON report {path:?}: {err}");
+                std::process::exit(1);
+            })));
+    }
+    if let Some(path) = &opts.coverage {
+        let coverage_reporter = CoverageReporter::new(path, model.coverage.clone());
+        reporter
+            .reporters
+            .push(Box::new(coverage_reporter.unwrap_or_else(|err| {
+                eprintln!("Failed to create the coverage report {path:?}: {err}");
+                std::process::exit(1);
+            })));
+    }
     let threads = opts.threads.unwrap_or_else(num_cpus::get);
     let checker = model
         .checker()
@@ -188,8 +291,20 @@ where
     match opts.command {
         opts::SubCmd::Explore {
             port,
+            history_port,
             fingerprint_path,
         } => {
+            let history_model = abstract_model(cfg);
+            std::thread::spawn(move || {
+                let rt = Runtime::new().unwrap();
+                rt.block_on(async {
+                    let address = format!("127.0.0.1:{history_port}");
+                    info!("Serving history graphs on {address}");
+                    let app = themelios::serve_history::app(history_model);
+                    let listener = tokio::net::TcpListener::bind(address).await.unwrap();
+                    axum::serve(listener, app).await.unwrap();
+                });
+            });
             let path = fingerprint_path
                 .map(|p| format!("/#/steps/{}", p))
                 .unwrap_or_default();
@@ -197,6 +312,11 @@ where
             checker.serve(("127.0.0.1", port));
         }
         opts::SubCmd::CheckDfs => {
+            let checker = if opts.symmetry {
+                checker.symmetry()
+            } else {
+                checker
+            };
             checker.spawn_dfs().report(&mut reporter).join();
         }
         opts::SubCmd::CheckBfs => {
@@ -209,6 +329,9 @@ where
                 .report(&mut reporter)
                 .join();
         }
+        opts::SubCmd::Replay { .. } | opts::SubCmd::ExportGraph { .. } => {
+            unreachable!("run without a checker")
+        }
         opts::SubCmd::ServeTest { port } => {
             let rt = Runtime::new().unwrap();
             rt.block_on(async {
@@ -247,3 +370,59 @@ where
         }
     }
 }
+
+fn replay(
+    model: AbstractModel,
+    fingerprint_path: Option<&str>,
+    actions: Option<&Path>,
+    property: Option<&str>,
+) {
+    let actions = match actions {
+        Some(path) => replay::load_actions(path, property),
+        None => replay::actions_from_fingerprints(
+            &model,
+            fingerprint_path.expect("either a fingerprint path or actions are required"),
+        ),
+    };
+    let replay = match actions.and_then(|actions| replay::replay(&model, &actions)) {
+        Ok(replay) => replay,
+        Err(err) => {
+            eprintln!("Failed to replay: {err}");
+            std::process::exit(1);
+        }
+    };
+
+    println!("Initial state");
+    print_properties(&model, &replay.initial_state);
+    for (i, step) in replay.steps.into_iter().enumerate() {
+        println!(
+            "Step {}: {}",
+            i,
+            model.format_action(&step.last_state, &step.action)
+        );
+        if let Some(diff) = model.format_step(&step.last_state, step.action) {
+            print!("{}", diff);
+        }
+        if let Some(rejection) = step.state.rejection() {
+            println!(
+                "Rejected {} write: {}",
+                rejection.operation, rejection.reason
+            );
+        }
+        print_properties(&model, &step.state);
+    }
+}
+
+fn print_properties(model: &AbstractModel, state: &State) {
+    for property in model.properties() {
+        let status = if (property.condition)(model, state) {
+            "holds"
+        } else {
+            "does not hold"
+        };
+        println!(
+            "  Property {:?} {:?} {}",
+            property.expectation, property.name, status
+        );
+    }
+}
@@ -0,0 +1,126 @@
+use stateright::{Expectation, Model, Property};
+
+use crate::abstract_model::{AbstractModel, Action};
+use crate::state::State;
+
+/// Shrink the traces of discoveries by delta debugging over their actions.
+///
+/// Candidate traces are replayed from the initial state through the model, with controller steps
+/// moved to the latest revision still on offer when removing earlier steps shifts the
+/// revisions, and kept if the property still fails.
+/// Eventually properties only fail on traces that end in a state with no actions enabled or that
+/// go around a cycle without the property holding.
+pub struct Minimiser {
+    model: AbstractModel,
+}
+
+/// A trace that reproduces a discovery.
+#[derive(Clone, Debug)]
+pub struct Trace {
+    pub actions: Vec<Action>,
+    /// The fingerprints of the states along the trace, as taken by `explore`.
+    pub fingerprint_path: String,
+}
+
+impl Minimiser {
+    pub fn new(model: AbstractModel) -> Self {
+        Self { model }
+    }
+
+    /// Find a shorter trace for the discovery of the named property, if it can be reproduced.
+    pub fn minimise(&self, property: &str, actions: &[Action]) -> Option<Trace> {
+        let property = self
+            .model
+            .properties()
+            .into_iter()
+            .find(|p| p.name == property)?;
+
+        let mut current = self.replay(&property, actions)?;
+        let mut granularity = 2;
+        while current.actions.len() >= 2 {
+            let len = current.actions.len();
+            let chunk = len.div_ceil(granularity);
+            let reduced = (0..len).step_by(chunk).find_map(|start| {
+                let mut candidate = current.actions[..start].to_vec();
+                candidate.extend_from_slice(&current.actions[(start + chunk).min(len)..]);
+                self.replay(&property, &candidate)
+            });
+            if let Some(trace) = reduced {
+                current = trace;
+                granularity = (granularity - 1).max(2);
+            } else if granularity >= len {
+                break;
+            } else {
+                granularity = (granularity * 2).min(len);
+            }
+        }
+        Some(current)
+    }
+
+    /// Replay the actions, returning the trace up to where the property is discovered, if it is.
+    fn replay(&self, property: &Property<AbstractModel>, actions: &[Action]) -> Option<Trace> {
+        let mut state = self.model.init_states().into_iter().next()?;
+        let mut fingerprints = vec![stateright::fingerprint(&state)];
+        let mut trace = Trace {
+            actions: Vec::new(),
+            fingerprint_path: fingerprints[0].to_string(),
+        };
+        if self.discovered(property, &state)? {
+            return Some(trace);
+        }
+        let eventually = matches!(property.expectation, Expectation::Eventually);
+        for action in actions {
+            let action = self.enabled(&state, action)?;
+            state = self.model.next_state(&state, action.clone())?;
+            let fingerprint = stateright::fingerprint(&state);
+            trace.actions.push(action);
+            trace.fingerprint_path += &format!("/{}", fingerprint);
+            if self.discovered(property, &state)? {
+                return Some(trace);
+            }
+            if eventually && fingerprints.contains(&fingerprint) {
+                // the property never held around a cycle
+                return Some(trace);
+            }
+            fingerprints.push(fingerprint);
+        }
+        // or it never held up to a state with nothing left to do, rather than the trace just
+        // being cut short
+        (eventually && self.terminal(&state)).then_some(trace)
+    }
+
+    fn terminal(&self, state: &State) -> bool {
+        let mut actions = Vec::new();
+        self.model.actions(state, &mut actions);
+        actions.is_empty()
+    }
+
+    /// Whether the state is a discovery for the property: a counterexample to an always
+    /// property or an example of a sometimes property.
+    /// Returns `None` if the trace can no longer be a discovery, as an eventually property held.
+    fn discovered(&self, property: &Property<AbstractModel>, state: &State) -> Option<bool> {
+        let holds = (property.condition)(&self.model, state);
+        match property.expectation {
+            Expectation::Always => Some(!holds),
+            Expectation::Sometimes => Some(holds),
+            Expectation::Eventually => (!holds).then_some(false),
+        }
+    }
+
+    /// The action as enabled in the state, remapping controller steps to the latest revision on
+    /// offer to that controller.
+    fn enabled(&self, state: &State, action: &Action) -> Option<Action> {
+        let mut actions = Vec::new();
+        self.model.actions(state, &mut actions);
+        if actions.contains(action) {
+            return Some(action.clone());
+        }
+        match action {
+            Action::ControllerStep(_, controller) => actions
+                .into_iter()
+                .rev()
+                .find(|a| matches!(a, Action::ControllerStep(_, c) if c == controller)),
+            _ => None,
+        }
+    }
+}
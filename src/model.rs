@@ -1,15 +1,86 @@
-use stateright::{Expectation, Property};
+use std::collections::{BTreeMap, BTreeSet};
+
+use stateright::Expectation;
 
 use crate::{
     abstract_model::{AbstractModel, AbstractModelCfg},
     controller::{
-        job::JobController, podgc::PodGCController, Controllers, DeploymentController,
-        NodeController, ReplicaSetController, SchedulerController, StatefulSetController,
+        job::JobController, podgc::PodGCController, Controller, Controllers, CronJobController,
+        DaemonSetController, DeploymentController, GarbageCollectorController, HPAController,
+        LeaderElected, NodeController, ReplicaSetController, SchedulerController,
+        StatefulSetController,
+    },
+    controller_properties::{Condition, ControllerProperties, Implication, Properties},
+    state::{
+        history::{ConsistencySetup, ReadMode},
+        RawState,
     },
-    controller_properties::ControllerProperties,
-    state::{history::ConsistencySetup, RawState, State},
 };
 
+/// The lowercase names of the kinds of controllers, used to configure each kind.
+pub const CONTROLLER_KINDS: &[&str] = &[
+    "node",
+    "scheduler",
+    "replicaset",
+    "deployment",
+    "statefulset",
+    "daemonset",
+    "job",
+    "cronjob",
+    "hpa",
+    "podgc",
+    "garbagecollector",
+];
+
+/// Why a model couldn't be built from its configuration.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ModelCfgError {
+    /// A setting was given for a kind of controller that doesn't exist.
+    UnknownController(String),
+    /// A kind of controller was given a consistency level the state can't provide.
+    ConsistencyOverride {
+        controller: String,
+        consistency: ConsistencySetup,
+        datastore: ConsistencySetup,
+    },
+    /// The state was to be replicated to no replicas.
+    NoReplicas,
+}
+
+impl std::fmt::Display for ModelCfgError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ModelCfgError::UnknownController(name) => write!(
+                f,
+                "unknown controller {name:?}, expected one of {}",
+                CONTROLLER_KINDS.join(", ")
+            ),
+            ModelCfgError::ConsistencyOverride {
+                controller,
+                consistency,
+                datastore,
+            } => write!(
+                f,
+                "{controller} controllers cannot use {consistency} consistency with a {datastore} state"
+            ),
+            ModelCfgError::NoReplicas => {
+                write!(f, "replicated consistency needs at least one replica")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ModelCfgError {}
+
+/// Check that the name is of a kind of controller, as given by [`CONTROLLER_KINDS`].
+pub fn check_controller_kind(name: &str) -> Result<(), ModelCfgError> {
+    if CONTROLLER_KINDS.contains(&name) {
+        Ok(())
+    } else {
+        Err(ModelCfgError::UnknownController(name.to_owned()))
+    }
+}
+
 #[derive(derivative::Derivative)]
 #[derivative(Debug)]
 #[derive(Clone, Default)]
@@ -18,6 +89,27 @@ pub struct OrchestrationModelCfg {
     pub initial_state: RawState,
     /// The consistency level of the state.
     pub consistency_level: ConsistencySetup,
+    /// Consistency levels for kinds of controllers, overriding the consistency level of the state.
+    ///
+    /// Keyed by the lowercase controller name, e.g. `scheduler` or `replicaset`.
+    pub controller_consistency: BTreeMap<String, ConsistencySetup>,
+    /// Read modes for kinds of controllers, taking precedence over `controller_consistency`.
+    ///
+    /// Keyed by the lowercase controller name, e.g. `scheduler` or `replicaset`.
+    pub read_modes: BTreeMap<String, ReadMode>,
+    /// Kinds of controllers that only run whilst holding a lease for their kind.
+    ///
+    /// Keyed by the lowercase controller name, e.g. `scheduler` or `replicaset`.
+    pub leader_election: BTreeSet<String>,
+    /// Whether writes from leader-elected controllers are rejected when they no longer hold the
+    /// lease.
+    pub fencing: bool,
+    /// Maximum number of writes controllers can issue after the last perturbation before they
+    /// are considered to be fighting, if checking for quiescence.
+    pub quiescence_bound: Option<usize>,
+    /// Whether to only explore one interleaving of the actions of parts of the cluster that are
+    /// independent of each other, see [`crate::reduction`].
+    pub partial_order_reduction: bool,
     /// The number of schedulers to run.
     pub schedulers: usize,
     /// The number of nodes to run.
@@ -26,14 +118,22 @@ pub struct OrchestrationModelCfg {
     pub replicaset_controllers: usize,
     pub deployment_controllers: usize,
     pub statefulset_controllers: usize,
+    pub daemonset_controllers: usize,
     pub job_controllers: usize,
+    pub cronjob_controllers: usize,
+    pub hpa_controllers: usize,
     pub podgc_controllers: usize,
+    pub garbage_collector_controllers: usize,
+    /// The number of times the clock can tick.
+    pub clock_ticks: u32,
 
     #[derivative(Debug = "ignore")]
-    pub properties: Vec<Property<AbstractModel>>,
+    pub properties: Properties,
 }
 
 impl OrchestrationModelCfg {
+    /// A model with the given number of each controller, leaving out the daemonset, cronjob,
+    /// autoscaler and garbage collector controllers as the command line does by default.
     pub fn new(
         initial_state: RawState,
         consistency_level: ConsistencySetup,
@@ -42,24 +142,59 @@ impl OrchestrationModelCfg {
         Self {
             initial_state,
             consistency_level,
+            controller_consistency: BTreeMap::new(),
+            read_modes: BTreeMap::new(),
+            leader_election: BTreeSet::new(),
+            fencing: false,
+            quiescence_bound: None,
+            partial_order_reduction: false,
             schedulers: controllers,
             nodes: controllers,
             replicaset_controllers: controllers,
             deployment_controllers: controllers,
             statefulset_controllers: controllers,
+            daemonset_controllers: 0,
             job_controllers: controllers,
+            cronjob_controllers: 0,
+            hpa_controllers: 0,
             podgc_controllers: controllers,
-            properties: Vec::new(),
+            garbage_collector_controllers: 0,
+            clock_ticks: 0,
+            properties: Properties::default(),
         }
     }
 
-    pub fn into_abstract_model(mut self) -> AbstractModel {
+    /// Build the model, panicking if its configuration is invalid, see
+    /// [`Self::try_into_abstract_model`].
+    pub fn into_abstract_model(self) -> AbstractModel {
+        self.try_into_abstract_model()
+            .unwrap_or_else(|err| panic!("invalid model configuration: {err}"))
+    }
+
+    /// Build the model, checking its configuration.
+    pub fn try_into_abstract_model(mut self) -> Result<AbstractModel, ModelCfgError> {
+        for name in self
+            .controller_consistency
+            .keys()
+            .chain(self.read_modes.keys())
+            .chain(self.leader_election.iter())
+        {
+            check_controller_kind(name)?;
+        }
+        if self.consistency_level == (ConsistencySetup::Replicated { replicas: 0 }) {
+            return Err(ModelCfgError::NoReplicas);
+        }
+
         self.auto_add_properties();
 
         let mut cfg = AbstractModelCfg {
             controllers: Vec::new(),
             initial_state: self.initial_state,
             consistency_level: self.consistency_level,
+            read_modes: BTreeMap::new(),
+            quiescence_bound: self.quiescence_bound,
+            partial_order_reduction: self.partial_order_reduction,
+            clock_ticks: self.clock_ticks,
             properties: self.properties,
         };
 
@@ -89,35 +224,95 @@ impl OrchestrationModelCfg {
                 .push(Controllers::StatefulSet(StatefulSetController));
         }
 
+        for _ in 0..self.daemonset_controllers {
+            cfg.controllers
+                .push(Controllers::DaemonSet(DaemonSetController));
+        }
+
         for _ in 0..self.job_controllers {
             cfg.controllers.push(Controllers::Job(JobController));
         }
 
+        for _ in 0..self.cronjob_controllers {
+            cfg.controllers
+                .push(Controllers::CronJob(CronJobController));
+        }
+
+        for _ in 0..self.hpa_controllers {
+            cfg.controllers.push(Controllers::HPA(HPAController));
+        }
+
         for _ in 0..self.podgc_controllers {
             cfg.controllers.push(Controllers::PodGC(PodGCController));
         }
 
-        AbstractModel::new(cfg)
+        for _ in 0..self.garbage_collector_controllers {
+            cfg.controllers
+                .push(Controllers::GarbageCollector(GarbageCollectorController));
+        }
+
+        let mut candidates = BTreeMap::<String, usize>::new();
+        cfg.controllers = cfg
+            .controllers
+            .into_iter()
+            .map(|controller| {
+                let name = controller.name().to_lowercase();
+                if !self.leader_election.contains(&name) {
+                    return controller;
+                }
+                let candidate = candidates.entry(name.clone()).or_default();
+                let identity = format!("{name}-{candidate}");
+                *candidate += 1;
+                Controllers::LeaderElected(Box::new(LeaderElected {
+                    inner: controller,
+                    identity,
+                    lease: name,
+                    fencing: self.fencing,
+                }))
+            })
+            .collect();
+
+        for (i, controller) in cfg.controllers.iter().enumerate() {
+            let name = controller.name().to_lowercase();
+            if let Some(read_mode) = self.read_modes.get(&name) {
+                cfg.read_modes.insert(i, *read_mode);
+            } else if let Some(consistency) = self.controller_consistency.get(&name) {
+                if !consistency.can_override(&cfg.consistency_level) {
+                    return Err(ModelCfgError::ConsistencyOverride {
+                        controller: name,
+                        consistency: consistency.clone(),
+                        datastore: cfg.consistency_level,
+                    });
+                }
+                cfg.read_modes.insert(i, consistency.read_mode());
+            }
+        }
+
+        Ok(AbstractModel::new(cfg))
     }
 
     pub fn add_property(
         &mut self,
         expectation: Expectation,
         name: &'static str,
-        condition: fn(&AbstractModel, &State) -> bool,
+        condition: Condition,
     ) {
-        self.properties.push(Property {
-            expectation,
-            name,
-            condition,
-        })
+        self.properties.add(expectation, name, condition)
     }
 
-    pub fn add_properties(
+    /// Add a property that is an implication, see [`Properties::add_implication`].
+    pub fn add_implication(
         &mut self,
-        properties: impl IntoIterator<Item = Property<AbstractModel>>,
+        expectation: Expectation,
+        name: &'static str,
+        implication: Implication,
     ) {
-        self.properties.extend(properties)
+        self.properties
+            .add_implication(expectation, name, implication)
+    }
+
+    pub fn add_properties(&mut self, mut properties: Properties) {
+        self.properties.append(&mut properties)
     }
 
     fn auto_add_properties(&mut self) {
@@ -130,17 +325,32 @@ impl OrchestrationModelCfg {
         if self.statefulset_controllers > 0 {
             self.add_properties(StatefulSetController::properties())
         }
+        if self.daemonset_controllers > 0 {
+            self.add_properties(DaemonSetController::properties())
+        }
         if self.job_controllers > 0 {
             self.add_properties(JobController::properties())
         }
+        if self.cronjob_controllers > 0 {
+            self.add_properties(CronJobController::properties())
+        }
+        if self.hpa_controllers > 0 {
+            self.add_properties(HPAController::properties())
+        }
         if self.podgc_controllers > 0 {
             self.add_properties(PodGCController::properties())
         }
+        if self.garbage_collector_controllers > 0 {
+            self.add_properties(GarbageCollectorController::properties())
+        }
         if self.nodes > 0 {
             self.add_properties(NodeController::properties())
         }
         if self.schedulers > 0 {
             self.add_properties(SchedulerController::properties())
         }
+        if !self.leader_election.is_empty() {
+            self.add_properties(LeaderElected::<Controllers>::properties())
+        }
     }
 }
@@ -1,4 +1,9 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+
 use clap::Parser;
+use themelios::model::check_controller_kind;
+use themelios::state::history::{ConsistencySetup, ReadMode};
 
 #[derive(Parser, Debug)]
 pub struct Opts {
@@ -37,33 +42,151 @@ pub struct Opts {
     #[clap(long, global = true, default_value = "1")]
     pub statefulset_controllers: usize,
 
+    #[clap(long, global = true, default_value = "0")]
+    pub daemonset_controllers: usize,
+
     #[clap(long, global = true, default_value = "1")]
     pub job_controllers: usize,
 
+    #[clap(long, global = true, default_value = "0")]
+    pub cronjob_controllers: usize,
+
+    #[clap(long, global = true, default_value = "0")]
+    pub hpa_controllers: usize,
+
     #[clap(long, global = true, default_value = "1")]
     pub podgc_controllers: usize,
 
+    #[clap(long, global = true, default_value = "0")]
+    pub garbage_collector_controllers: usize,
+
     #[clap(long, short, global = true, default_value = "1")]
     pub schedulers: usize,
 
     #[clap(long, short, global = true, default_value = "1")]
     pub nodes: usize,
 
+    /// The number of times the clock can tick, each a minute, to drive time-based controllers.
+    #[clap(long, global = true, default_value = "0")]
+    pub clock_ticks: u32,
+
     /// Max depth for the check run, 0 is no limit.
     #[clap(long, global = true, default_value = "0")]
     pub max_depth: usize,
 
-    /// Model session consistency for the state.
-    #[clap(long, global = true)]
+    /// Consistency level for the state, one of synchronous, monotonic-session,
+    /// resettable-session, optimistic-linear, causal, replicated-<replicas> or informer.
+    #[clap(long, global = true, default_value = "synchronous")]
+    pub consistency: ConsistencySetup,
+
+    /// Deprecated, use `--consistency resettable-session`.
+    #[clap(long, global = true, hide = true)]
     pub session: bool,
 
-    /// Model optimistic linear consistency for the state.
-    #[clap(long, global = true)]
+    /// Deprecated, use `--consistency optimistic-linear`.
+    #[clap(long, global = true, hide = true)]
     pub optimistic_linear: bool,
 
-    /// Model causal consistency for the state.
-    #[clap(long, global = true)]
+    /// Deprecated, use `--consistency causal`.
+    #[clap(long, global = true, hide = true)]
     pub causal: bool,
+
+    /// Consistency level for a kind of controller, overriding `--consistency`, e.g.
+    /// `scheduler=synchronous`.
+    /// Can be given multiple times.
+    #[clap(long, global = true)]
+    pub controller_consistency: Vec<PerController<ConsistencySetup>>,
+
+    /// Read mode for a kind of controller, one of latest, session-monotonic or any-valid, e.g.
+    /// `replicaset=latest`.
+    /// Takes precedence over `--controller-consistency`.
+    /// Can be given multiple times.
+    #[clap(long, global = true)]
+    pub read_mode: Vec<PerController<ReadMode>>,
+
+    /// Run a kind of controller under leader election, e.g. `scheduler`.
+    /// Can be given multiple times.
+    #[clap(long, global = true, parse(try_from_str = controller_kind))]
+    pub leader_election: Vec<String>,
+
+    /// Reject writes from leader-elected controllers that no longer hold their lease.
+    #[clap(long, global = true)]
+    pub fencing: bool,
+
+    /// Check that controllers stop writing within this many writes after the last perturbation
+    /// (client changes, restarts, partitions and clock ticks), reporting the looping segment if
+    /// not.
+    #[clap(long, global = true)]
+    pub quiescence_bound: Option<usize>,
+
+    /// Treat states that only differ in which of a set of identical controllers did the work as
+    /// the same, only used by depth-first checking.
+    #[clap(long, global = true)]
+    pub symmetry: bool,
+
+    /// Only explore one interleaving of the steps of parts of the cluster that touch unrelated
+    /// resources, rather than every one.
+    #[clap(long, global = true)]
+    pub partial_order_reduction: bool,
+
+    /// Write each discovery as a line of JSON to this file, with the operations and states along
+    /// its path.
+    #[clap(long, global = true)]
+    pub json_report: Option<PathBuf>,
+
+    /// Count the operations each controller made and how often each property's antecedent held,
+    /// printing them when checking finishes and writing them as CSV to this file.
+    #[clap(long, global = true)]
+    pub coverage: Option<PathBuf>,
+
+    /// Load the initial state, controllers and consistency level from Kubernetes YAML manifests,
+    /// either a single (multi-document) file or a directory of them.
+    /// Overrides the resource and controller counts above.
+    #[clap(long, global = true)]
+    pub scenario: Option<PathBuf>,
+}
+
+impl Opts {
+    /// The consistency level for the state, taking the deprecated flags over `--consistency`.
+    pub fn consistency_level(&self) -> ConsistencySetup {
+        if self.session {
+            ConsistencySetup::ResettableSession
+        } else if self.optimistic_linear {
+            ConsistencySetup::OptimisticLinear
+        } else if self.causal {
+            ConsistencySetup::Causal
+        } else {
+            self.consistency.clone()
+        }
+    }
+}
+
+/// A setting for a kind of controller, given as `<controller>=<value>`.
+#[derive(Clone, Debug)]
+pub struct PerController<T> {
+    pub controller: String,
+    pub value: T,
+}
+
+impl<T: FromStr<Err = String>> FromStr for PerController<T> {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (controller, value) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected <controller>=<value>, got {s:?}"))?;
+        Ok(Self {
+            controller: controller_kind(controller)?,
+            value: value.parse()?,
+        })
+    }
+}
+
+/// Parse the name of a kind of controller, rejecting unknown ones rather than ignoring them.
+fn controller_kind(s: &str) -> Result<String, String> {
+    let name = s.to_lowercase();
+    check_controller_kind(&name).map_err(|e| e.to_string())?;
+    Ok(name)
 }
 
 #[derive(clap::Subcommand, Debug)]
@@ -74,6 +197,9 @@ pub enum SubCmd {
         /// Port to serve the UI on.
         #[clap(long, default_value = "8080")]
         port: u16,
+        /// Port to serve the history graphs of explored states on.
+        #[clap(long, default_value = "8081")]
+        history_port: u16,
     },
     CheckDfs,
     CheckBfs,
@@ -81,6 +207,31 @@ pub enum SubCmd {
         #[clap(long)]
         seed: Option<u64>,
     },
+    /// Re-run a path step by step, printing the changes and the properties at each state.
+    #[clap(group(
+        clap::ArgGroup::new("path")
+            .required(true)
+            .args(&["fingerprint_path", "actions"])
+    ))]
+    Replay {
+        /// Path of state fingerprints, as printed for discoveries.
+        fingerprint_path: Option<String>,
+        /// File of exported actions, either a JSON list or a JSON report.
+        #[clap(long)]
+        actions: Option<PathBuf>,
+        /// Property whose discovery to replay from a JSON report, defaulting to the first.
+        #[clap(long, requires = "actions")]
+        property: Option<String>,
+    },
+    /// Write the state space up to a depth as a DOT graph.
+    ExportGraph {
+        /// File to write the graph to.
+        #[clap(long, default_value = "states.dot")]
+        output: PathBuf,
+        /// Depth to explore to.
+        #[clap(long, default_value = "5")]
+        depth: usize,
+    },
     /// Serve an integration test suitable API.
     ServeTest {
         #[clap(long, default_value = "7070")]
@@ -0,0 +1,464 @@
+//! Partial-order reduction over independent parts of the cluster.
+//!
+//! The enabled actions are grouped by what takes them: each controller along with its restarts
+//! and informer, each client change, each datastore replica catching up and the clock. Groups
+//! that could touch the same resources, now or in any later step of theirs, are joined into a
+//! component. Exploring only the actions of a component that is independent of the rest still
+//! reaches every ordering of the other actions from the successor states, so they are left for
+//! later.
+//!
+//! Independent actions taken in either order reach states that differ in the revisions given to
+//! their writes, and so in the uids and generated names that are taken from those revisions. These
+//! are different states, but the properties only compare identifiers with each other, so each is
+//! the other with its identifiers renamed and the properties give the same verdicts on both. Two
+//! provisos keep leaving out the other order from hiding anything the properties would flag:
+//! - every action of the component leaves each property's condition as it was, so the
+//!   properties can't tell which order was taken, even those comparing revisions through
+//!   `resource_stable`.
+//! - every action of the component either commits a write, moving the datastore on to a new
+//!   revision, or is used up by being taken, like a controller moving past the revision it read.
+//!   Restarts put a controller back where it started so keep their component from being chosen,
+//!   leaving no cycle of reduced states that could put the other actions off forever.
+//!
+//! Partitions and heals change which writes any controller can commit, so nothing is reduced
+//! whilst one is enabled.
+
+use std::collections::BTreeSet;
+
+use crate::abstract_model::{AbstractModel, Action, ControllerAction};
+use crate::arbitrary_client::ArbitraryClientAction;
+use crate::controller::Controllers;
+use crate::resources::NamespacePhase;
+use crate::state::State;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ResourceKind {
+    Node,
+    Pod,
+    ReplicaSet,
+    Deployment,
+    StatefulSet,
+    DaemonSet,
+    ControllerRevision,
+    PersistentVolumeClaim,
+    Job,
+    CronJob,
+    HorizontalPodAutoscaler,
+    Lease,
+    Namespace,
+    /// The logical clock, which stamps the times that resources are created and deleted.
+    Clock,
+    /// A replica of the datastore, named by its index.
+    Replica,
+}
+
+/// A resource, with no name for any resource of the kind.
+pub type ResourceRef = (ResourceKind, Option<String>);
+
+/// The resources that an action reads and writes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Footprint {
+    /// The resources read.
+    pub reads: BTreeSet<ResourceRef>,
+    /// The resources written, with no name for resources that have their name generated.
+    pub writes: BTreeSet<ResourceRef>,
+}
+
+impl Footprint {
+    /// Whether the actions with these footprints can be applied in either order with the same
+    /// result.
+    pub fn independent(&self, other: &Self) -> bool {
+        !overlapping(&self.writes, &other.writes)
+            && !overlapping(&self.writes, &other.reads)
+            && !overlapping(&other.writes, &self.reads)
+    }
+}
+
+fn overlapping(a: &BTreeSet<ResourceRef>, b: &BTreeSet<ResourceRef>) -> bool {
+    a.iter().any(|(kind, name)| {
+        b.iter().any(|(other_kind, other_name)| {
+            kind == other_kind && (name.is_none() || other_name.is_none() || name == other_name)
+        })
+    })
+}
+
+fn any_of(kinds: BTreeSet<ResourceKind>) -> BTreeSet<ResourceRef> {
+    kinds.into_iter().map(|kind| (kind, None)).collect()
+}
+
+/// The kinds of resources that a controller reads when stepping.
+///
+/// Independence is judged on these alone, so they have to cover everything a step can depend
+/// on, including the clock for controllers that create or delete resources or read the time.
+pub fn controller_reads(controller: &Controllers) -> BTreeSet<ResourceKind> {
+    use ResourceKind::*;
+    match controller {
+        Controllers::Node(_) => [Node, Pod, Clock].into(),
+        Controllers::Scheduler(_) => [Node, Pod, PersistentVolumeClaim].into(),
+        Controllers::ReplicaSet(_) => [ReplicaSet, Pod, Clock].into(),
+        Controllers::Deployment(_) => [Deployment, ReplicaSet, Clock].into(),
+        Controllers::StatefulSet(_) => [
+            StatefulSet,
+            Pod,
+            ControllerRevision,
+            PersistentVolumeClaim,
+            Clock,
+        ]
+        .into(),
+        Controllers::DaemonSet(_) => [DaemonSet, Node, Pod, ControllerRevision, Clock].into(),
+        Controllers::Job(_) => [Job, Pod, Clock].into(),
+        Controllers::CronJob(_) => [CronJob, Job, Clock].into(),
+        Controllers::HPA(_) => [
+            HorizontalPodAutoscaler,
+            Deployment,
+            ReplicaSet,
+            StatefulSet,
+            Clock,
+        ]
+        .into(),
+        Controllers::PodGC(_) => [Pod, Node, Clock].into(),
+        Controllers::GarbageCollector(_) => [
+            Pod,
+            ReplicaSet,
+            Deployment,
+            StatefulSet,
+            DaemonSet,
+            ControllerRevision,
+            Job,
+            CronJob,
+            Clock,
+        ]
+        .into(),
+        Controllers::LeaderElected(c) => {
+            let mut reads = controller_reads(&c.inner);
+            reads.insert(Lease);
+            reads
+        }
+    }
+}
+
+/// The kinds of resources that a controller can write when stepping.
+pub fn controller_writes(controller: &Controllers) -> BTreeSet<ResourceKind> {
+    use ResourceKind::*;
+    match controller {
+        Controllers::Node(_) => [Node, Pod].into(),
+        Controllers::Scheduler(_) => [Pod].into(),
+        Controllers::ReplicaSet(_) => [ReplicaSet, Pod].into(),
+        Controllers::Deployment(_) => [Deployment, ReplicaSet].into(),
+        Controllers::StatefulSet(_) => {
+            [StatefulSet, Pod, ControllerRevision, PersistentVolumeClaim].into()
+        }
+        Controllers::DaemonSet(_) => [DaemonSet, Pod, ControllerRevision].into(),
+        Controllers::Job(_) => [Job, Pod].into(),
+        Controllers::CronJob(_) => [CronJob, Job].into(),
+        Controllers::HPA(_) => {
+            [HorizontalPodAutoscaler, Deployment, ReplicaSet, StatefulSet].into()
+        }
+        Controllers::PodGC(_) => [Pod].into(),
+        Controllers::GarbageCollector(_) => [
+            Pod,
+            ReplicaSet,
+            Deployment,
+            StatefulSet,
+            DaemonSet,
+            ControllerRevision,
+            Job,
+            CronJob,
+        ]
+        .into(),
+        Controllers::LeaderElected(c) => {
+            let mut writes = controller_writes(&c.inner);
+            writes.insert(Lease);
+            writes
+        }
+    }
+}
+
+/// The resources that a controller action writes.
+pub fn action_writes(action: &ControllerAction) -> BTreeSet<ResourceRef> {
+    fn named(kind: ResourceKind, name: &str) -> ResourceRef {
+        let name = (!name.is_empty()).then(|| name.to_owned());
+        (kind, name)
+    }
+    use ResourceKind::*;
+    match action {
+        ControllerAction::NodeJoin(name, _) => [named(Node, name)].into(),
+        ControllerAction::DeleteNode(n) => [named(Node, &n.metadata.name)].into(),
+        ControllerAction::CreatePod(p)
+        | ControllerAction::SoftDeletePod(p)
+        | ControllerAction::HardDeletePod(p)
+        | ControllerAction::UpdatePod(p) => [named(Pod, &p.metadata.name)].into(),
+        ControllerAction::UpdateDeployment(d)
+        | ControllerAction::RequeueDeployment(d)
+        | ControllerAction::UpdateDeploymentStatus(d)
+        | ControllerAction::DeleteDeployment(d, _) => [named(Deployment, &d.metadata.name)].into(),
+        ControllerAction::CreateReplicaSet(rs)
+        | ControllerAction::UpdateReplicaSet(rs)
+        | ControllerAction::UpdateReplicaSetStatus(rs)
+        | ControllerAction::DeleteReplicaSet(rs, _) => {
+            [named(ReplicaSet, &rs.metadata.name)].into()
+        }
+        ControllerAction::UpdateReplicaSets(rss) => rss
+            .iter()
+            .map(|rs| named(ReplicaSet, &rs.metadata.name))
+            .collect(),
+        ControllerAction::UpdateStatefulSet(sts)
+        | ControllerAction::UpdateStatefulSetStatus(sts) => {
+            [named(StatefulSet, &sts.metadata.name)].into()
+        }
+        ControllerAction::UpdateDaemonSet(ds) | ControllerAction::UpdateDaemonSetStatus(ds) => {
+            [named(DaemonSet, &ds.metadata.name)].into()
+        }
+        ControllerAction::CreateControllerRevision(cr)
+        | ControllerAction::UpdateControllerRevision(cr)
+        | ControllerAction::DeleteControllerRevision(cr) => {
+            [named(ControllerRevision, &cr.metadata.name)].into()
+        }
+        ControllerAction::CreatePersistentVolumeClaim(pvc)
+        | ControllerAction::UpdatePersistentVolumeClaim(pvc) => {
+            [named(PersistentVolumeClaim, &pvc.metadata.name)].into()
+        }
+        ControllerAction::CreateJob(job)
+        | ControllerAction::UpdateJob(job)
+        | ControllerAction::UpdateJobStatus(job)
+        | ControllerAction::DeleteJob(job) => [named(Job, &job.metadata.name)].into(),
+        ControllerAction::UpdateCronJob(cj) | ControllerAction::UpdateCronJobStatus(cj) => {
+            [named(CronJob, &cj.metadata.name)].into()
+        }
+        ControllerAction::UpdateHorizontalPodAutoscalerStatus(hpa) => {
+            [named(HorizontalPodAutoscaler, &hpa.metadata.name)].into()
+        }
+        ControllerAction::CreateLease(lease) | ControllerAction::UpdateLease(lease) => {
+            [named(Lease, &lease.metadata.name)].into()
+        }
+        ControllerAction::DeleteNamespace(ns) => {
+            // everything in the namespace is deleted along with it
+            let mut writes = any_of(
+                [
+                    Pod,
+                    ReplicaSet,
+                    Deployment,
+                    StatefulSet,
+                    DaemonSet,
+                    ControllerRevision,
+                    PersistentVolumeClaim,
+                    Job,
+                    CronJob,
+                    HorizontalPodAutoscaler,
+                    Lease,
+                ]
+                .into(),
+            );
+            writes.insert(named(Namespace, &ns.metadata.name));
+            writes
+        }
+        ControllerAction::Fenced { lease, action, .. } => {
+            // the lease is checked
+            let mut writes = action_writes(action);
+            writes.insert(named(Lease, lease));
+            writes
+        }
+    }
+}
+
+/// Whether any namespace is terminating, so that removing the last resource in it removes it too.
+fn namespaces_terminating(state: &State) -> bool {
+    state
+        .latest()
+        .namespaces
+        .iter()
+        .any(|ns| ns.status.phase == NamespacePhase::Terminating)
+}
+
+/// Everything the controller can touch from here on, through any of its steps, restarts or its
+/// informer.
+fn controller_footprint(model: &AbstractModel, state: &State, controller: usize) -> Footprint {
+    let mut reads = any_of(controller_reads(&model.controllers[controller]));
+    if let Some(replica) = state.replica_for(controller) {
+        reads.insert((ResourceKind::Replica, Some(replica.to_string())));
+    }
+    // creations are rejected in terminating namespaces
+    reads.insert((ResourceKind::Namespace, None));
+    let mut writes = any_of(controller_writes(&model.controllers[controller]));
+    if namespaces_terminating(state) {
+        writes.insert((ResourceKind::Namespace, None));
+    }
+    Footprint { reads, writes }
+}
+
+/// What a client change touches, which is just the resource it changes.
+///
+/// Later changes by the client only differ in the value they write, or act on resources created
+/// by someone else, whose creation already conflicts with anything that reads them.
+fn client_footprint(model: &AbstractModel, state: &State, action: &Action) -> Footprint {
+    let operation = model.operation(state, action);
+    let mut writes = operation.as_ref().map(action_writes).unwrap_or_default();
+    if namespaces_terminating(state) {
+        writes.insert((ResourceKind::Namespace, None));
+    }
+    let mut reads = writes.clone();
+    if let Action::ArbitraryStep(ArbitraryClientAction::SelectNodeDaemonSet(..)) = action {
+        // there is a change for each node
+        reads.insert((ResourceKind::Node, None));
+    }
+    if let Some(
+        ControllerAction::DeleteDeployment(..)
+        | ControllerAction::DeleteReplicaSet(..)
+        | ControllerAction::DeleteNamespace(_),
+    ) = operation
+    {
+        // deleted resources are stamped with the time
+        reads.insert((ResourceKind::Clock, None));
+    }
+    Footprint { reads, writes }
+}
+
+/// Actions taken by one part of the cluster, with everything it can touch, or `None` if that
+/// is everything.
+struct Group {
+    footprint: Option<Footprint>,
+    actions: Vec<usize>,
+}
+
+impl Group {
+    fn dependent(&self, other: &Self) -> bool {
+        match (&self.footprint, &other.footprint) {
+            (Some(a), Some(b)) => !a.independent(b),
+            _ => true,
+        }
+    }
+}
+
+fn groups(model: &AbstractModel, state: &State, actions: &[Action]) -> Vec<Group> {
+    // controllers that can't step yet still could after others write, so always have a group
+    let mut groups = (0..model.controllers.len())
+        .map(|i| Group {
+            footprint: Some(controller_footprint(model, state, i)),
+            actions: Vec::new(),
+        })
+        .collect::<Vec<_>>();
+    for (index, action) in actions.iter().enumerate() {
+        let footprint = match action {
+            Action::ControllerStep(_, i)
+            | Action::ControllerRestart(i)
+            | Action::NodeRestart(i)
+            | Action::InformerEvent(i)
+            | Action::InformerRelist(i) => {
+                groups[*i].actions.push(index);
+                continue;
+            }
+            Action::ArbitraryStep(_) => Some(client_footprint(model, state, action)),
+            Action::Replicate(replica) => {
+                let replica = (ResourceKind::Replica, Some(replica.to_string()));
+                Some(Footprint {
+                    reads: [replica.clone()].into(),
+                    writes: [replica].into(),
+                })
+            }
+            Action::TickClock => {
+                let clock = (ResourceKind::Clock, None);
+                Some(Footprint {
+                    reads: [clock.clone()].into(),
+                    writes: [clock].into(),
+                })
+            }
+            Action::Partition(_) | Action::Heal(_) => None,
+        };
+        groups.push(Group {
+            footprint,
+            actions: vec![index],
+        });
+    }
+    groups
+}
+
+/// Find an ample set of the enabled actions: those of a component that is independent of all
+/// other actions, now and later, and whose actions are invisible to the properties and make
+/// progress.
+///
+/// Returns `None` if no component qualifies, in which case all actions should be explored.
+pub fn ample_set(model: &AbstractModel, state: &State, actions: &[Action]) -> Option<Vec<Action>> {
+    let groups = groups(model, state, actions);
+
+    // join dependent groups into components, each labelled by its first group
+    let mut parent = (0..groups.len()).collect::<Vec<_>>();
+    fn find(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    for a in 0..groups.len() {
+        for b in a + 1..groups.len() {
+            let (root_a, root_b) = (find(&mut parent, a), find(&mut parent, b));
+            if root_a != root_b && groups[a].dependent(&groups[b]) {
+                parent[root_a.max(root_b)] = root_a.min(root_b);
+            }
+        }
+    }
+    let component = (0..groups.len())
+        .map(|i| find(&mut parent, i))
+        .collect::<Vec<_>>();
+
+    let mut tried = BTreeSet::new();
+    for label in component.iter().copied() {
+        if !tried.insert(label) {
+            continue;
+        }
+        let mut ample = groups
+            .iter()
+            .zip(&component)
+            .filter(|(_, c)| **c == label)
+            .flat_map(|(g, _)| g.actions.iter().copied())
+            .collect::<Vec<_>>();
+        if ample.is_empty() {
+            continue;
+        }
+        if ample.len() == actions.len() {
+            // nothing to reduce
+            return None;
+        }
+        ample.sort_unstable();
+        let ample = ample
+            .into_iter()
+            .map(|i| actions[i].clone())
+            .collect::<Vec<_>>();
+        if ample.iter().all(|a| {
+            model
+                .transition(state, a.clone(), None)
+                .map_or(false, |next| {
+                    invisible(model, state, &next) && progresses(model, state, a, &next)
+                })
+        }) {
+            return Some(ample);
+        }
+    }
+    None
+}
+
+/// Whether stepping from the state to the next one leaves alone everything the properties look
+/// at: no write is rejected and each property's condition is unchanged.
+fn invisible(model: &AbstractModel, state: &State, next: &State) -> bool {
+    next.rejection().is_none()
+        && model
+            .properties
+            .iter()
+            .all(|p| (p.condition)(model, state) == (p.condition)(model, next))
+}
+
+/// Whether taking the action moves on in a way that other actions of its component can't undo.
+fn progresses(model: &AbstractModel, state: &State, action: &Action, next: &State) -> bool {
+    match action {
+        Action::ControllerRestart(_) => false,
+        // the informer moves on through the log
+        Action::InformerEvent(_) | Action::InformerRelist(_) => true,
+        _ => next.max_revision() > state.max_revision() || !enabled(model, next, action),
+    }
+}
+
+fn enabled(model: &AbstractModel, state: &State, action: &Action) -> bool {
+    let mut actions = Vec::new();
+    model.enabled_actions(state, &mut actions);
+    actions.contains(action)
+}
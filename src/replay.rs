@@ -0,0 +1,167 @@
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use stateright::Model;
+
+use crate::abstract_model::{AbstractModel, Action};
+use crate::state::State;
+
+/// A path through the model, re-executed from its initial state.
+#[derive(Debug)]
+pub struct Replay {
+    pub initial_state: State,
+    pub steps: Vec<ReplayStep>,
+}
+
+#[derive(Debug)]
+pub struct ReplayStep {
+    pub action: Action,
+    /// The state before the action was taken.
+    pub last_state: State,
+    pub state: State,
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(PathBuf, std::io::Error),
+    Json(PathBuf, serde_json::Error),
+    /// The file doesn't contain a discovery for the requested property.
+    MissingDiscovery(PathBuf, String),
+    InvalidFingerprint(String),
+    /// No state reachable from the previous one has the fingerprint at the given index.
+    UnknownFingerprint(usize, String),
+    /// The action at the given index isn't enabled in the state reached by the earlier ones.
+    DisabledAction(usize, Action),
+}
+
+impl Display for ReplayError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReplayError::Io(path, err) => write!(f, "failed to read {path:?}: {err}"),
+            ReplayError::Json(path, err) => write!(f, "failed to parse {path:?}: {err}"),
+            ReplayError::MissingDiscovery(path, property) => {
+                write!(f, "{path:?} has no discovery for property {property:?}")
+            }
+            ReplayError::InvalidFingerprint(fingerprint) => {
+                write!(f, "invalid fingerprint {fingerprint:?}")
+            }
+            ReplayError::UnknownFingerprint(index, fingerprint) => {
+                write!(
+                    f,
+                    "no state with fingerprint {fingerprint} is reachable at step {index}"
+                )
+            }
+            ReplayError::DisabledAction(index, action) => {
+                write!(f, "action {action:?} at step {index} is not enabled")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+/// Actions as exported, either as a plain list or as a discovery from the JSON reporter.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ExportedActions {
+    Actions(Vec<Action>),
+    Discovery {
+        property: String,
+        steps: Vec<ExportedStep>,
+    },
+}
+
+#[derive(Deserialize)]
+struct ExportedStep {
+    action: Action,
+}
+
+/// Load the actions from a file containing a JSON list of actions, or the lines of a JSON report
+/// where the first discovery (or the first for the given property) is used.
+pub fn load_actions(path: &Path, property: Option<&str>) -> Result<Vec<Action>, ReplayError> {
+    let contents =
+        std::fs::read_to_string(path).map_err(|e| ReplayError::Io(path.to_owned(), e))?;
+    let documents = serde_json::Deserializer::from_str(&contents).into_iter::<ExportedActions>();
+    for document in documents {
+        match document.map_err(|e| ReplayError::Json(path.to_owned(), e))? {
+            ExportedActions::Actions(actions) => return Ok(actions),
+            ExportedActions::Discovery {
+                property: name,
+                steps,
+            } => {
+                if property.map_or(true, |p| p == name) {
+                    return Ok(steps.into_iter().map(|s| s.action).collect());
+                }
+            }
+        }
+    }
+    Err(ReplayError::MissingDiscovery(
+        path.to_owned(),
+        property.unwrap_or_default().to_owned(),
+    ))
+}
+
+/// Find the actions along a path of state fingerprints, separated by `/`, as printed for
+/// discoveries.
+pub fn actions_from_fingerprints(
+    model: &AbstractModel,
+    path: &str,
+) -> Result<Vec<Action>, ReplayError> {
+    let fingerprints = path
+        .split('/')
+        .filter(|f| !f.is_empty())
+        .map(|f| {
+            f.parse::<u64>()
+                .map_err(|_| ReplayError::InvalidFingerprint(f.to_owned()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let Some((first, rest)) = fingerprints.split_first() else {
+        return Ok(Vec::new());
+    };
+
+    let mut state = model
+        .init_states()
+        .into_iter()
+        .find(|s| stateright::fingerprint(s).get() == *first)
+        .ok_or_else(|| ReplayError::UnknownFingerprint(0, first.to_string()))?;
+    let mut actions = Vec::new();
+    for (i, fingerprint) in rest.iter().enumerate() {
+        let mut enabled = Vec::new();
+        model.actions(&state, &mut enabled);
+        let (action, next_state) = enabled
+            .into_iter()
+            .filter_map(|a| model.next_state(&state, a.clone()).map(|s| (a, s)))
+            .find(|(_, s)| stateright::fingerprint(s).get() == *fingerprint)
+            .ok_or_else(|| ReplayError::UnknownFingerprint(i + 1, fingerprint.to_string()))?;
+        actions.push(action);
+        state = next_state;
+    }
+    Ok(actions)
+}
+
+/// Re-execute the actions from the initial state, checking that each is enabled in turn.
+pub fn replay(model: &AbstractModel, actions: &[Action]) -> Result<Replay, ReplayError> {
+    let initial_state = model.init_states().into_iter().next().unwrap();
+    let mut state = initial_state.clone();
+    let mut steps = Vec::new();
+    for (i, action) in actions.iter().enumerate() {
+        let mut enabled = Vec::new();
+        model.actions(&state, &mut enabled);
+        if !enabled.contains(action) {
+            return Err(ReplayError::DisabledAction(i, action.clone()));
+        }
+        let next_state = model
+            .next_state(&state, action.clone())
+            .ok_or_else(|| ReplayError::DisabledAction(i, action.clone()))?;
+        steps.push(ReplayStep {
+            action: action.clone(),
+            last_state: std::mem::replace(&mut state, next_state.clone()),
+            state: next_state,
+        });
+    }
+    Ok(Replay {
+        initial_state,
+        steps,
+    })
+}
@@ -1,7 +1,11 @@
+use crate::abstract_model::{AbstractModel, Action, ControllerAction};
 use crate::state::history::ConsistencySetup;
+use crate::state::{RawState, Rejection};
+use serde::Serialize;
 use stateright::report::Reporter;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs::File;
+use std::io::Write;
 use std::path::Path;
 use sysinfo::ProcessExt;
 use sysinfo::System;
@@ -36,16 +40,26 @@ where
     }
 }
 
-#[derive(Debug, Default)]
-pub struct StdoutReporter {
+/// Shrink the actions of a discovery for the named property, giving the shorter actions and their
+/// fingerprint path.
+type Minimise<A> = dyn Fn(&'static str, &[A]) -> Option<(Vec<A>, String)>;
+
+pub struct StdoutReporter<M: Model> {
     last_total: usize,
     last_unique: usize,
     properties: BTreeMap<&'static str, Expectation>,
+    /// The quiescence property and how to get the number of writes since the last perturbation
+    /// from a state, used to print the looping segment of its counterexamples.
+    quiescence: Option<(&'static str, fn(&M::State) -> usize)>,
+    /// Used to print a minimised trace alongside each discovery.
+    minimiser: Option<Box<Minimise<M::Action>>>,
+    /// Gives the names of the properties that have only held vacuously.
+    vacuous: Option<Box<dyn Fn() -> BTreeSet<&'static str>>>,
 }
 
-impl StdoutReporter {
+impl<M: Model> StdoutReporter<M> {
     /// Create a new reporter.
-    pub fn new<M: Model>(model: &M) -> Self {
+    pub fn new(model: &M) -> Self {
         let properties = model
             .properties()
             .iter()
@@ -55,13 +69,43 @@ impl StdoutReporter {
             last_total: 0,
             last_unique: 0,
             properties,
+            quiescence: None,
+            minimiser: None,
+            vacuous: None,
         }
     }
+
+    /// Print the steps since the last perturbation for counterexamples to the given property.
+    pub fn with_quiescence(
+        mut self,
+        property: &'static str,
+        writes_since_perturbation: fn(&M::State) -> usize,
+    ) -> Self {
+        self.quiescence = Some((property, writes_since_perturbation));
+        self
+    }
+
+    /// Print a minimised trace alongside each discovery.
+    pub fn with_minimiser(
+        mut self,
+        minimiser: impl Fn(&'static str, &[M::Action]) -> Option<(Vec<M::Action>, String)> + 'static,
+    ) -> Self {
+        self.minimiser = Some(Box::new(minimiser));
+        self
+    }
+
+    /// Mark implications that held only because their antecedent never did.
+    pub fn with_vacuity(mut self, vacuous: impl Fn() -> BTreeSet<&'static str> + 'static) -> Self {
+        self.vacuous = Some(Box::new(vacuous));
+        self
+    }
 }
 
-impl<M> Reporter<M> for StdoutReporter
+impl<M> Reporter<M> for StdoutReporter<M>
 where
     M: Model,
+    M::State: Clone,
+    M::Action: Clone,
 {
     fn report_checking(&mut self, data: stateright::report::ReportData) {
         let new_total = data.total_states - self.last_total;
@@ -113,11 +157,17 @@ where
                 property_holds(expectation, discoveries.get(*name).is_some())
             });
 
+        let vacuous = self.vacuous.as_ref().map(|v| v()).unwrap_or_default();
+        let mut vacuous_count = 0;
+
         for (name, expectation) in &self.properties {
-            let status = if property_holds(expectation, discoveries.get(name).is_some()) {
-                "OK"
-            } else {
+            let status = if !property_holds(expectation, discoveries.get(name).is_some()) {
                 "FAILED"
+            } else if vacuous.contains(name) {
+                vacuous_count += 1;
+                "OK (vacuous: antecedent never held)"
+            } else {
+                "OK"
             };
             println!("Property {:?} {:?} {}", expectation, name, status);
             if let Some(discovery) = discoveries.get(name) {
@@ -126,17 +176,80 @@ where
                     "To explore this path try re-running with `explore {}`",
                     discovery.path.encode()
                 );
+                if let Some((property, writes_since_perturbation)) = self.quiescence {
+                    if property == *name {
+                        print_looping_segment(discovery, writes_since_perturbation);
+                    }
+                }
+                if let Some(minimiser) = &self.minimiser {
+                    print_minimised(*name, discovery, minimiser.as_ref());
+                }
             }
         }
 
         println!(
-            "Properties checked. {} succeeded, {} failed",
+            "Properties checked. {} succeeded ({} vacuously), {} failed",
             success.len(),
+            vacuous_count,
             failure.len()
         );
     }
 }
 
+/// Print the actions taken since the last perturbation in the path, which are the ones that
+/// didn't converge.
+fn print_looping_segment<M: Model>(
+    discovery: &stateright::report::ReportDiscovery<M>,
+    writes_since_perturbation: fn(&M::State) -> usize,
+) where
+    M::State: Clone,
+    M::Action: std::fmt::Debug + Clone,
+{
+    let steps = discovery.path.clone().into_vec();
+    let start = steps
+        .iter()
+        .rposition(|(state, _)| writes_since_perturbation(state) == 0)
+        .unwrap_or(0);
+    println!("Looping segment since the last perturbation:");
+    for (i, (_, action)) in steps.iter().enumerate().skip(start) {
+        if let Some(action) = action {
+            println!("  {}: {:?}", i, action);
+        }
+    }
+}
+
+/// Print the shortest trace found that still reproduces the discovery.
+fn print_minimised<M: Model>(
+    name: &'static str,
+    discovery: &stateright::report::ReportDiscovery<M>,
+    minimiser: &Minimise<M::Action>,
+) where
+    M::State: Clone,
+    M::Action: std::fmt::Debug + Clone,
+{
+    let actions = discovery
+        .path
+        .clone()
+        .into_vec()
+        .into_iter()
+        .filter_map(|(_, action)| action)
+        .collect::<Vec<_>>();
+    if let Some((minimised, path)) = minimiser(name, &actions) {
+        println!(
+            "Minimised from {} to {} steps:",
+            actions.len(),
+            minimised.len()
+        );
+        for (i, action) in minimised.iter().enumerate() {
+            println!("  {}: {:?}", i, action);
+        }
+        println!(
+            "To explore the minimised path try re-running with `explore {}`",
+            path
+        );
+    }
+}
+
 fn property_holds(expectation: &Expectation, discovery: bool) -> bool {
     match (expectation, discovery) {
         // counter-example
@@ -224,3 +337,80 @@ where
     {
     }
 }
+
+/// Writes each discovery as a line of JSON, with every step's action, the controller that took it,
+/// the operation it wrote and the resulting state.
+pub struct JsonReporter {
+    writer: File,
+    model: AbstractModel,
+}
+
+#[derive(Serialize)]
+struct JsonDiscovery<'a> {
+    property: &'a str,
+    expectation: String,
+    classification: String,
+    initial_state: RawState,
+    steps: Vec<JsonStep>,
+}
+
+#[derive(Serialize)]
+struct JsonStep {
+    action: Action,
+    controller: Option<String>,
+    operation: Option<ControllerAction>,
+    /// The operation being rejected by the datastore, if it was.
+    rejection: Option<Rejection>,
+    state: RawState,
+}
+
+impl JsonReporter {
+    /// Create a new reporter writing to the file at the given path, using the model to fill in
+    /// the details of each step.
+    pub fn new(path: &Path, model: AbstractModel) -> std::io::Result<Self> {
+        let writer = File::create(path)?;
+        Ok(Self { writer, model })
+    }
+}
+
+impl Reporter<AbstractModel> for JsonReporter {
+    fn report_checking(&mut self, _data: stateright::report::ReportData) {}
+
+    fn report_discoveries(
+        &mut self,
+        discoveries: BTreeMap<&'static str, stateright::report::ReportDiscovery<AbstractModel>>,
+    ) {
+        for (name, discovery) in discoveries {
+            let path = discovery.path.into_vec();
+            let mut steps = Vec::new();
+            for window in path.windows(2) {
+                let (last_state, action) = &window[0];
+                let (state, _) = &window[1];
+                let action = action.clone().unwrap();
+                steps.push(JsonStep {
+                    controller: self.model.actor(&action),
+                    operation: self.model.operation(last_state, &action),
+                    rejection: state.rejection().cloned(),
+                    action,
+                    state: (*state.latest().state).clone(),
+                });
+            }
+            let document = JsonDiscovery {
+                property: name,
+                expectation: self
+                    .model
+                    .properties()
+                    .iter()
+                    .find(|p| p.name == name)
+                    .map(|p| format!("{:?}", p.expectation))
+                    .unwrap_or_default(),
+                classification: discovery.classification.to_string(),
+                initial_state: (*path[0].0.latest().state).clone(),
+                steps,
+            };
+            serde_json::to_writer(&mut self.writer, &document).unwrap();
+            writeln!(self.writer).unwrap();
+        }
+        self.writer.flush().unwrap();
+    }
+}
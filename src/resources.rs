@@ -9,13 +9,21 @@ use std::{
 use crate::state::revision::Revision;
 
 pub trait Meta {
+    /// Whether resources of this kind live outside of namespaces, having an empty namespace.
+    const CLUSTER_SCOPED: bool = false;
+
     fn metadata(&self) -> &Metadata;
     fn metadata_mut(&mut self) -> &mut Metadata;
 }
 
 macro_rules! impl_meta {
     ($r:ident) => {
+        impl_meta!($r, false);
+    };
+    ($r:ident, $cluster_scoped:expr) => {
         impl Meta for $r {
+            const CLUSTER_SCOPED: bool = $cluster_scoped;
+
             fn metadata(&self) -> &Metadata {
                 &self.metadata
             }
@@ -31,9 +39,14 @@ impl_meta!(Job);
 impl_meta!(Deployment);
 impl_meta!(ReplicaSet);
 impl_meta!(StatefulSet);
+impl_meta!(DaemonSet);
+impl_meta!(CronJob);
+impl_meta!(HorizontalPodAutoscaler);
 impl_meta!(ControllerRevision);
 impl_meta!(PersistentVolumeClaim);
-impl_meta!(Node);
+impl_meta!(Node, true);
+impl_meta!(Lease);
+impl_meta!(Namespace, true);
 
 pub trait ObservedGeneration {
     fn observed_generation(&self) -> u64;
@@ -54,6 +67,8 @@ impl_observed_generation!(Job);
 impl_observed_generation!(Deployment);
 impl_observed_generation!(ReplicaSet);
 impl_observed_generation!(StatefulSet);
+impl_observed_generation!(DaemonSet);
+impl_observed_generation!(HorizontalPodAutoscaler);
 // impl_observed_generation!(ControllerRevision);
 // impl_observed_generation!(PersistentVolumeClaim);
 // impl_observed_generation!(Node);
@@ -84,6 +99,7 @@ impl_observed_revision!(Job);
 impl_observed_revision!(Deployment);
 impl_observed_revision!(ReplicaSet);
 impl_observed_revision!(StatefulSet);
+impl_observed_revision!(DaemonSet);
 // impl_observed_revision!(ControllerRevision);
 // impl_observed_revision!(PersistentVolumeClaim);
 // impl_observed_revision!(Node);
@@ -110,8 +126,13 @@ impl_spec!(Job, JobSpec);
 impl_spec!(Deployment, DeploymentSpec);
 impl_spec!(ReplicaSet, ReplicaSetSpec);
 impl_spec!(StatefulSet, StatefulSetSpec);
+impl_spec!(DaemonSet, DaemonSetSpec);
+impl_spec!(CronJob, CronJobSpec);
+impl_spec!(HorizontalPodAutoscaler, HorizontalPodAutoscalerSpec);
 impl_spec!(PersistentVolumeClaim, PersistentVolumeClaimSpec);
 impl_spec!(Node, NodeSpec);
+impl_spec!(Lease, LeaseSpec);
+impl_spec!(Namespace, NamespaceSpec);
 
 impl Spec for ControllerRevision {
     type Spec = ();
@@ -241,10 +262,50 @@ pub struct OwnerReference {
     pub controller: bool,
 }
 
+/// Finalizer set on an owner being deleted in the foreground, removed by the garbage collector once
+/// the dependents blocking its deletion are gone.
+pub const FOREGROUND_DELETION_FINALIZER: &str = "foregroundDeletion";
+/// Finalizer set on an owner being deleted with its dependents orphaned, removed by the garbage
+/// collector once no dependents refer to it.
+pub const ORPHAN_FINALIZER: &str = "orphan";
+
+/// How the deletion of an owner propagates to its dependents.
+#[derive(
+    Clone, Copy, Default, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+)]
+pub enum PropagationPolicy {
+    /// Delete the owner straight away and let the garbage collector delete the dependents.
+    #[default]
+    Background,
+    /// Keep the owner until the garbage collector has deleted the dependents.
+    Foreground,
+    /// Keep the dependents, with the garbage collector removing their references to the owner.
+    Orphan,
+}
+
+impl PropagationPolicy {
+    /// The finalizer that holds the owner while the garbage collector propagates the deletion.
+    pub fn finalizer(&self) -> Option<&'static str> {
+        match self {
+            PropagationPolicy::Background => None,
+            PropagationPolicy::Foreground => Some(FOREGROUND_DELETION_FINALIZER),
+            PropagationPolicy::Orphan => Some(ORPHAN_FINALIZER),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteOptions {
+    #[serde(default)]
+    pub propagation_policy: PropagationPolicy,
+}
+
 #[derive(Default, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct Pod {
     pub metadata: Metadata,
     pub spec: PodSpec,
+    #[serde(default)]
     pub status: PodStatus,
 }
 
@@ -579,6 +640,7 @@ impl SubAssign for ResourceQuantities {
 pub struct Job {
     pub metadata: Metadata,
     pub spec: JobSpec,
+    #[serde(default)]
     pub status: JobStatus,
 }
 
@@ -608,6 +670,7 @@ pub struct JobSpec {
     pub ttl_seconds_after_finished: Option<u64>,
     #[serde(default)]
     pub suspend: bool,
+    #[serde(default)]
     pub selector: LabelSelector,
 
     pub pod_failure_policy: Option<JobPodFailurePolicy>,
@@ -739,10 +802,183 @@ pub struct UncountedTerminatedPods {
     pub succeeded: Vec<String>,
 }
 
+/// Label the API server sets on jobs and their pods to the uid of the job, when generating the
+/// selector for jobs that don't set one.
+pub const CONTROLLER_UID_LABEL: &str = "controller-uid";
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CronJob {
+    pub metadata: Metadata,
+    pub spec: CronJobSpec,
+    #[serde(default)]
+    pub status: CronJobStatus,
+}
+
+impl CronJob {
+    pub const GVK: GroupVersionKind = GroupVersionKind {
+        group: "batch",
+        version: "v1",
+        kind: "CronJob",
+    };
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CronJobSpec {
+    // The schedule in Cron format, see https://en.wikipedia.org/wiki/Cron.
+    pub schedule: String,
+    // Optional deadline in seconds for starting the job if it misses scheduled
+    // time for any reason.  Missed jobs executions will be counted as failed ones.
+    pub starting_deadline_seconds: Option<u64>,
+    // Specifies how to treat concurrent executions of a Job.
+    #[serde(default)]
+    pub concurrency_policy: ConcurrencyPolicy,
+    // This flag tells the controller to suspend subsequent executions, it does
+    // not apply to already started executions.  Defaults to false.
+    #[serde(default)]
+    pub suspend: bool,
+    // Specifies the job that will be created when executing a CronJob.
+    pub job_template: JobTemplateSpec,
+    // The number of successful finished jobs to retain. Value must be non-negative integer.
+    // Defaults to 3.
+    pub successful_jobs_history_limit: Option<u32>,
+    // The number of failed finished jobs to retain. Value must be non-negative integer.
+    // Defaults to 1.
+    pub failed_jobs_history_limit: Option<u32>,
+}
+
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+)]
+pub enum ConcurrencyPolicy {
+    // Allow allows CronJobs to run concurrently.
+    #[default]
+    Allow,
+    // Forbid forbids concurrent runs, skipping next run if previous
+    // hasn't finished yet.
+    Forbid,
+    // Replace cancels currently running job and replaces it with a new one.
+    Replace,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobTemplateSpec {
+    #[serde(default)]
+    pub metadata: Metadata,
+    pub spec: JobSpec,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CronJobStatus {
+    // A list of pointers to currently running jobs.
+    #[serde(default)]
+    pub active: Vec<ObjectReference>,
+    // Information when was the last time the job was successfully scheduled.
+    pub last_schedule_time: Option<Time>,
+    // Information when was the last time the job successfully completed.
+    pub last_successful_time: Option<Time>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ObjectReference {
+    #[serde(default)]
+    pub api_version: String,
+    #[serde(default)]
+    pub kind: String,
+    #[serde(default)]
+    pub namespace: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub uid: String,
+    #[serde(default)]
+    pub resource_version: Revision,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HorizontalPodAutoscaler {
+    pub metadata: Metadata,
+    pub spec: HorizontalPodAutoscalerSpec,
+    #[serde(default)]
+    pub status: HorizontalPodAutoscalerStatus,
+}
+
+impl HorizontalPodAutoscaler {
+    pub const GVK: GroupVersionKind = GroupVersionKind {
+        group: "autoscaling",
+        version: "v1",
+        kind: "HorizontalPodAutoscaler",
+    };
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HorizontalPodAutoscalerSpec {
+    // reference to scaled resource; horizontal pod autoscaler will learn the current resource
+    // consumption and will set the desired number of pods by using its Scale subresource.
+    pub scale_target_ref: CrossVersionObjectReference,
+    // minReplicas is the lower limit for the number of replicas to which the autoscaler
+    // can scale down.  It defaults to 1 pod.
+    pub min_replicas: Option<u32>,
+    // maxReplicas is the upper limit for the number of pods that can be set by the autoscaler;
+    // cannot be smaller than MinReplicas.
+    pub max_replicas: u32,
+    // targetCPUUtilizationPercentage is the target average CPU utilization (represented as a
+    // percentage of requested CPU) over all the pods; if not specified the default autoscaling
+    // policy will be used.
+    #[serde(rename = "targetCPUUtilizationPercentage")]
+    pub target_cpu_utilization_percentage: Option<u32>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrossVersionObjectReference {
+    // kind is the kind of the referent
+    pub kind: String,
+    // name is the name of the referent
+    pub name: String,
+    // apiVersion is the API version of the referent
+    #[serde(default)]
+    pub api_version: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HorizontalPodAutoscalerStatus {
+    // observedGeneration is the most recent generation observed by this autoscaler.
+    #[serde(default)]
+    pub observed_generation: u64,
+    // lastScaleTime is the last time the HorizontalPodAutoscaler scaled the number of pods;
+    // used by the autoscaler to control how often the number of pods is changed.
+    pub last_scale_time: Option<Time>,
+    // currentReplicas is the current number of replicas of pods managed by this autoscaler.
+    #[serde(default)]
+    pub current_replicas: u32,
+    // desiredReplicas is the desired number of replicas of pods managed by this autoscaler.
+    #[serde(default)]
+    pub desired_replicas: u32,
+    // currentCPUUtilizationPercentage is the current average CPU utilization over all pods,
+    // represented as a percentage of requested CPU, e.g. 70 means that an average pod is using
+    // now 70% of its requested CPU.
+    // THEMELIOS: there is no metrics server, so this is set by the client as a synthetic metric.
+    #[serde(rename = "currentCPUUtilizationPercentage")]
+    pub current_cpu_utilization_percentage: Option<u32>,
+
+    // THEMELIOS: added field
+    #[serde(default)]
+    pub observed_revision: Revision,
+}
+
 #[derive(Default, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct ReplicaSet {
     pub metadata: Metadata,
     pub spec: ReplicaSetSpec,
+    #[serde(default)]
     pub status: ReplicaSetStatus,
 }
 
@@ -844,6 +1080,7 @@ pub enum ConditionStatus {
 pub struct Deployment {
     pub metadata: Metadata,
     pub spec: DeploymentSpec,
+    #[serde(default)]
     pub status: DeploymentStatus,
 }
 
@@ -1019,6 +1256,7 @@ pub struct ControllerRevision {
 pub struct StatefulSet {
     pub metadata: Metadata,
     pub spec: StatefulSetSpec,
+    #[serde(default)]
     pub status: StatefulSetStatus,
 }
 
@@ -1033,6 +1271,7 @@ impl StatefulSet {
 #[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct StatefulSetSpec {
+    #[serde(default)]
     pub service_name: String,
     pub selector: LabelSelector,
     pub template: PodTemplateSpec,
@@ -1043,6 +1282,7 @@ pub struct StatefulSetSpec {
     #[serde(default)]
     pub pod_management_policy: PodManagementPolicyType,
     pub revision_history_limit: Option<u32>,
+    #[serde(default)]
     pub volume_claim_templates: Vec<PersistentVolumeClaim>,
     pub min_ready_seconds: Option<u32>,
     #[serde(default)]
@@ -1145,11 +1385,102 @@ pub struct RollingUpdateStatefulSetStrategy {
     pub partition: u32,
 }
 
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DaemonSet {
+    pub metadata: Metadata,
+    pub spec: DaemonSetSpec,
+    #[serde(default)]
+    pub status: DaemonSetStatus,
+}
+
+impl DaemonSet {
+    pub const GVK: GroupVersionKind = GroupVersionKind {
+        group: "apps",
+        version: "v1",
+        kind: "DaemonSet",
+    };
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DaemonSetSpec {
+    pub selector: LabelSelector,
+    pub template: PodTemplateSpec,
+    #[serde(default)]
+    pub update_strategy: DaemonSetUpdateStrategy,
+    #[serde(default)]
+    pub min_ready_seconds: u32,
+    pub revision_history_limit: Option<u32>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DaemonSetUpdateStrategy {
+    #[serde(default)]
+    pub r#type: DaemonSetUpdateStrategyType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rolling_update: Option<RollingUpdateDaemonSet>,
+}
+
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+)]
+pub enum DaemonSetUpdateStrategyType {
+    #[default]
+    RollingUpdate,
+    OnDelete,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RollingUpdateDaemonSet {
+    // The maximum number of nodes with an existing available daemon pod that can have an updated daemon pod during an update. Value can be an absolute number (ex: 5) or a percentage of desired pods (ex: 10%). This can not be 0 if MaxUnavailable is 0. Absolute number is calculated from percentage by rounding up. Defaults to 0.
+    pub max_surge: Option<IntOrString>,
+    // The maximum number of DaemonSet pods that can be unavailable during the update. Value can be an absolute number (ex: 5) or a percentage of total number of DaemonSet pods at the start of the update (ex: 10%). Absolute number is calculated from percentage by rounding up. This cannot be 0 if MaxSurge is 0. Defaults to 1.
+    pub max_unavailable: Option<IntOrString>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DaemonSetStatus {
+    // The number of nodes that should be running the daemon pod.
+    #[serde(default)]
+    pub desired_number_scheduled: u32,
+    // The number of nodes that are running at least 1 daemon pod and are supposed to run the daemon pod.
+    #[serde(default)]
+    pub current_number_scheduled: u32,
+    // The number of nodes that are running the daemon pod, but are not supposed to run the daemon pod.
+    #[serde(default)]
+    pub number_misscheduled: u32,
+    // The number of nodes that should be running the daemon pod and have one or more of the daemon pod running with a Ready Condition.
+    #[serde(default)]
+    pub number_ready: u32,
+    // The total number of nodes that are running updated daemon pod.
+    #[serde(default)]
+    pub updated_number_scheduled: u32,
+    // The number of nodes that should be running the daemon pod and have one or more of the daemon pod running and available (ready for at least spec.minReadySeconds).
+    #[serde(default)]
+    pub number_available: u32,
+    // The number of nodes that should be running the daemon pod and have none of the daemon pod running and available (ready for at least spec.minReadySeconds).
+    #[serde(default)]
+    pub number_unavailable: u32,
+    #[serde(default)]
+    pub collision_count: u32,
+    #[serde(default)]
+    pub observed_generation: u64,
+
+    // THEMELIOS: added field
+    #[serde(default)]
+    pub observed_revision: Revision,
+}
+
 #[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PersistentVolumeClaim {
     pub metadata: Metadata,
     pub spec: PersistentVolumeClaimSpec,
+    #[serde(default)]
     pub status: PersistentVolumeClaimStatus,
 }
 
@@ -1176,10 +1507,70 @@ pub struct PersistentVolumeClaimStatus {
     pub access_modes: Vec<String>,
 }
 
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Lease {
+    pub metadata: Metadata,
+    #[serde(default)]
+    pub spec: LeaseSpec,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LeaseSpec {
+    /// The identity of the current holder of the lease.
+    pub holder_identity: Option<String>,
+    /// The number of transitions of the lease between holders.
+    #[serde(default)]
+    pub lease_transitions: u32,
+
+    // THEMELIOS: added field, time isn't modelled so expiry of the lease is explicit rather than
+    // from the renew time and duration.
+    #[serde(default)]
+    pub expired: bool,
+}
+
+#[derive(Clone, Default, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Namespace {
+    pub metadata: Metadata,
+    #[serde(default)]
+    pub spec: NamespaceSpec,
+    #[serde(default)]
+    pub status: NamespaceStatus,
+}
+
+#[derive(Clone, Default, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct NamespaceSpec {
+    /// Finalizers that must be removed before the namespace is removed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub finalizers: Vec<String>,
+}
+
+#[derive(Clone, Default, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct NamespaceStatus {
+    #[serde(default)]
+    pub phase: NamespacePhase,
+}
+
+#[derive(
+    Clone, Copy, Default, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+)]
+pub enum NamespacePhase {
+    /// The namespace is available for use.
+    #[default]
+    Active,
+    /// The namespace is being deleted, along with the resources in it.
+    Terminating,
+}
+
+/// Label set on each node to its name, used to select individual nodes.
+pub const LABEL_HOSTNAME: &str = "kubernetes.io/hostname";
+
 #[derive(Clone, Default, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct Node {
     pub metadata: Metadata,
     pub spec: NodeSpec,
+    #[serde(default)]
     pub status: NodeStatus,
 }
 
@@ -1370,6 +1761,12 @@ impl From<String> for IntOrString {
 #[serde(transparent)]
 pub struct Time(#[serde(with = "time::serde::rfc3339")] pub time::OffsetDateTime);
 
+impl Default for Time {
+    fn default() -> Self {
+        Time(time::OffsetDateTime::UNIX_EPOCH)
+    }
+}
+
 pub struct GroupVersionKind {
     pub group: &'static str,
     pub version: &'static str,
@@ -0,0 +1,397 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::controller_properties::Properties;
+use crate::model::OrchestrationModelCfg;
+use crate::resources::{
+    CronJob, DaemonSet, Deployment, HorizontalPodAutoscaler, Job, Lease, Meta, Namespace, Node,
+    PersistentVolumeClaim, Pod, ReplicaSet, Spec, StatefulSet,
+};
+use crate::state::history::{ConsistencySetup, ReadMode};
+use crate::state::resources::{namespace_of, Resources};
+use crate::state::RawState;
+use crate::utils;
+
+/// The kind of the header document that configures the model rather than describing a resource.
+pub const SCENARIO_KIND: &str = "Scenario";
+
+/// Header document for a scenario, describing how to run the model over the resources.
+///
+/// ```yaml
+/// apiVersion: themelios/v1
+/// kind: Scenario
+/// consistency: causal
+/// controllerConsistency:
+///   scheduler: synchronous
+/// readModes:
+///   replicaset: session-monotonic
+/// controllers:
+///   nodes: 2
+///   schedulers: 1
+///   replicasets: 1
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScenarioHeader {
+    /// The consistency level of the state.
+    #[serde(default)]
+    pub consistency: ConsistencySetup,
+    /// Consistency levels for kinds of controllers, overriding `consistency`.
+    #[serde(default)]
+    pub controller_consistency: BTreeMap<String, ConsistencySetup>,
+    /// Read modes for kinds of controllers, overriding `controllerConsistency`.
+    #[serde(default)]
+    pub read_modes: BTreeMap<String, ReadMode>,
+    /// Kinds of controllers to run under leader election.
+    #[serde(default)]
+    pub leader_election: BTreeSet<String>,
+    /// Whether to reject writes from leader-elected controllers that have lost their lease.
+    #[serde(default)]
+    pub fencing: bool,
+    /// Maximum number of writes controllers can issue after the last perturbation, if checking
+    /// for quiescence.
+    #[serde(default)]
+    pub quiescence_bound: Option<usize>,
+    /// The number of times the clock can tick.
+    #[serde(default)]
+    pub clock_ticks: u32,
+    /// The number of each controller to run.
+    #[serde(default)]
+    pub controllers: ControllerCounts,
+}
+
+/// Number of each kind of controller to run, any missing are not run.
+///
+/// Node controllers are named `node-<i>` so `Node` documents should use those names for the nodes
+/// to be managed by them.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ControllerCounts {
+    #[serde(default)]
+    pub nodes: usize,
+    #[serde(default)]
+    pub schedulers: usize,
+    #[serde(default)]
+    pub replicasets: usize,
+    #[serde(default)]
+    pub deployments: usize,
+    #[serde(default)]
+    pub statefulsets: usize,
+    #[serde(default)]
+    pub daemonsets: usize,
+    #[serde(default)]
+    pub jobs: usize,
+    #[serde(default)]
+    pub cronjobs: usize,
+    #[serde(default)]
+    pub hpas: usize,
+    #[serde(default)]
+    pub podgc: usize,
+    #[serde(default)]
+    pub garbage_collectors: usize,
+}
+
+/// A scenario loaded from manifests: the initial state and how to run the model over it.
+#[derive(Clone, Debug, Default)]
+pub struct Scenario {
+    pub header: ScenarioHeader,
+    pub initial_state: RawState,
+}
+
+#[derive(Debug)]
+pub enum ScenarioError {
+    Io(PathBuf, std::io::Error),
+    Yaml(PathBuf, serde_yaml::Error),
+    MissingKind(PathBuf),
+    UnknownKind(PathBuf, String),
+    DuplicateHeader(PathBuf),
+    DuplicateResource(PathBuf, String),
+    InvalidNodeName(PathBuf, String),
+    /// A node whose index is beyond the number of node controllers configured, so that none would
+    /// run it.
+    UnservedNode(String, usize),
+}
+
+impl Display for ScenarioError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScenarioError::Io(path, err) => write!(f, "failed to read {path:?}: {err}"),
+            ScenarioError::Yaml(path, err) => write!(f, "failed to parse {path:?}: {err}"),
+            ScenarioError::MissingKind(path) => {
+                write!(f, "document in {path:?} is missing a kind")
+            }
+            ScenarioError::UnknownKind(path, kind) => {
+                write!(f, "document in {path:?} has unsupported kind {kind:?}")
+            }
+            ScenarioError::DuplicateHeader(path) => {
+                write!(f, "{path:?} contains a second {SCENARIO_KIND} document")
+            }
+            ScenarioError::DuplicateResource(path, name) => {
+                write!(f, "{path:?} contains a duplicate resource named {name:?}")
+            }
+            ScenarioError::InvalidNodeName(path, name) => {
+                write!(
+                    f,
+                    "{path:?} contains a node named {name:?}, node names must be of the form `node-<i>`"
+                )
+            }
+            ScenarioError::UnservedNode(name, nodes) => {
+                write!(
+                    f,
+                    "node {name:?} has no node controller to run it, only {nodes} are configured"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ScenarioError {}
+
+impl Scenario {
+    /// Load a scenario from a file, or from all `.yaml` and `.yml` files in a directory (in name
+    /// order).
+    pub fn load(path: &Path) -> Result<Self, ScenarioError> {
+        let mut files = Vec::new();
+        if path.is_dir() {
+            let entries =
+                std::fs::read_dir(path).map_err(|e| ScenarioError::Io(path.to_owned(), e))?;
+            for entry in entries {
+                let entry = entry.map_err(|e| ScenarioError::Io(path.to_owned(), e))?;
+                let file = entry.path();
+                if file
+                    .extension()
+                    .map_or(false, |ext| ext == "yaml" || ext == "yml")
+                {
+                    files.push(file);
+                }
+            }
+            files.sort();
+        } else {
+            files.push(path.to_owned());
+        }
+
+        let mut scenario = Scenario::default();
+        let mut seen_header = false;
+        for file in files {
+            let contents =
+                std::fs::read_to_string(&file).map_err(|e| ScenarioError::Io(file.clone(), e))?;
+            scenario.add_documents(&file, &contents, &mut seen_header)?;
+        }
+        scenario.check_nodes_served()?;
+        Ok(scenario)
+    }
+
+    /// Parse a scenario from a (possibly multi-document) YAML string.
+    pub fn from_yaml(contents: &str) -> Result<Self, ScenarioError> {
+        let mut scenario = Scenario::default();
+        scenario.add_documents(Path::new("<string>"), contents, &mut false)?;
+        scenario.check_nodes_served()?;
+        Ok(scenario)
+    }
+
+    /// Check that every node has a node controller to run it, once the header (which may come
+    /// after the nodes) has been read.
+    fn check_nodes_served(&self) -> Result<(), ScenarioError> {
+        let nodes = self.header.controllers.nodes;
+        for node in self.initial_state.nodes.iter() {
+            let name = &node.metadata.name;
+            if node_controller_index(name).map_or(true, |i| i >= nodes) {
+                return Err(ScenarioError::UnservedNode(name.clone(), nodes));
+            }
+        }
+        Ok(())
+    }
+
+    fn add_documents(
+        &mut self,
+        file: &Path,
+        contents: &str,
+        seen_header: &mut bool,
+    ) -> Result<(), ScenarioError> {
+        for document in serde_yaml::Deserializer::from_str(contents) {
+            let value = serde_yaml::Value::deserialize(document)
+                .map_err(|e| ScenarioError::Yaml(file.to_owned(), e))?;
+            if value.is_null() {
+                // empty document, such as a trailing `---`
+                continue;
+            }
+            let kind = value
+                .get("kind")
+                .and_then(|k| k.as_str())
+                .ok_or_else(|| ScenarioError::MissingKind(file.to_owned()))?
+                .to_owned();
+            let parse_err = |e| ScenarioError::Yaml(file.to_owned(), e);
+            match kind.as_str() {
+                SCENARIO_KIND => {
+                    if *seen_header {
+                        return Err(ScenarioError::DuplicateHeader(file.to_owned()));
+                    }
+                    *seen_header = true;
+                    self.header = serde_yaml::from_value(value).map_err(parse_err)?;
+                }
+                "Pod" => {
+                    let pod =
+                        fill_metadata(serde_yaml::from_value::<Pod>(value).map_err(parse_err)?);
+                    check_unique(file, &self.initial_state.pods, &pod)?;
+                    self.initial_state.set_pods([pod]);
+                }
+                "ReplicaSet" => {
+                    let rs = fill_metadata(
+                        serde_yaml::from_value::<ReplicaSet>(value).map_err(parse_err)?,
+                    );
+                    check_unique(file, &self.initial_state.replicasets, &rs)?;
+                    self.initial_state.set_replicasets([rs]);
+                }
+                "Deployment" => {
+                    let dep = fill_metadata(
+                        serde_yaml::from_value::<Deployment>(value).map_err(parse_err)?,
+                    );
+                    check_unique(file, &self.initial_state.deployments, &dep)?;
+                    self.initial_state.set_deployments([dep]);
+                }
+                "StatefulSet" => {
+                    let sts = fill_metadata(
+                        serde_yaml::from_value::<StatefulSet>(value).map_err(parse_err)?,
+                    );
+                    check_unique(file, &self.initial_state.statefulsets, &sts)?;
+                    self.initial_state.set_statefulsets([sts]);
+                }
+                "DaemonSet" => {
+                    let ds = fill_metadata(
+                        serde_yaml::from_value::<DaemonSet>(value).map_err(parse_err)?,
+                    );
+                    check_unique(file, &self.initial_state.daemonsets, &ds)?;
+                    self.initial_state.set_daemonsets([ds]);
+                }
+                "Job" => {
+                    let job =
+                        fill_metadata(serde_yaml::from_value::<Job>(value).map_err(parse_err)?);
+                    check_unique(file, &self.initial_state.jobs, &job)?;
+                    self.initial_state.set_jobs([job]);
+                }
+                "CronJob" => {
+                    let cj =
+                        fill_metadata(serde_yaml::from_value::<CronJob>(value).map_err(parse_err)?);
+                    check_unique(file, &self.initial_state.cronjobs, &cj)?;
+                    self.initial_state.set_cronjobs([cj]);
+                }
+                "HorizontalPodAutoscaler" => {
+                    let hpa = fill_metadata(
+                        serde_yaml::from_value::<HorizontalPodAutoscaler>(value)
+                            .map_err(parse_err)?,
+                    );
+                    check_unique(file, &self.initial_state.horizontal_pod_autoscalers, &hpa)?;
+                    self.initial_state.set_horizontal_pod_autoscalers([hpa]);
+                }
+                "Node" => {
+                    let node =
+                        fill_metadata(serde_yaml::from_value::<Node>(value).map_err(parse_err)?);
+                    if node_controller_index(&node.metadata.name).is_none() {
+                        return Err(ScenarioError::InvalidNodeName(
+                            file.to_owned(),
+                            node.metadata.name,
+                        ));
+                    }
+                    check_unique(file, &self.initial_state.nodes, &node)?;
+                    self.initial_state.set_nodes([node]);
+                }
+                "PersistentVolumeClaim" => {
+                    let pvc = fill_metadata(
+                        serde_yaml::from_value::<PersistentVolumeClaim>(value)
+                            .map_err(parse_err)?,
+                    );
+                    check_unique(file, &self.initial_state.persistent_volume_claims, &pvc)?;
+                    self.initial_state.set_persistent_volume_claims([pvc]);
+                }
+                "Lease" => {
+                    let lease =
+                        fill_metadata(serde_yaml::from_value::<Lease>(value).map_err(parse_err)?);
+                    check_unique(file, &self.initial_state.leases, &lease)?;
+                    self.initial_state.set_leases([lease]);
+                }
+                "Namespace" => {
+                    let namespace = fill_metadata(
+                        serde_yaml::from_value::<Namespace>(value).map_err(parse_err)?,
+                    );
+                    check_unique(file, &self.initial_state.namespaces, &namespace)?;
+                    self.initial_state.set_namespaces([namespace]);
+                }
+                _ => return Err(ScenarioError::UnknownKind(file.to_owned(), kind)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Build the model configuration for this scenario.
+    pub fn into_model_cfg(self) -> OrchestrationModelCfg {
+        let controllers = self.header.controllers;
+        OrchestrationModelCfg {
+            initial_state: self.initial_state,
+            consistency_level: self.header.consistency,
+            controller_consistency: self.header.controller_consistency,
+            read_modes: self.header.read_modes,
+            leader_election: self.header.leader_election,
+            fencing: self.header.fencing,
+            quiescence_bound: self.header.quiescence_bound,
+            partial_order_reduction: false,
+            schedulers: controllers.schedulers,
+            nodes: controllers.nodes,
+            replicaset_controllers: controllers.replicasets,
+            deployment_controllers: controllers.deployments,
+            statefulset_controllers: controllers.statefulsets,
+            daemonset_controllers: controllers.daemonsets,
+            job_controllers: controllers.jobs,
+            cronjob_controllers: controllers.cronjobs,
+            hpa_controllers: controllers.hpas,
+            podgc_controllers: controllers.podgc,
+            garbage_collector_controllers: controllers.garbage_collectors,
+            clock_ticks: self.header.clock_ticks,
+            properties: Properties::default(),
+        }
+    }
+}
+
+/// Fill in the metadata that manifests typically leave to the API server.
+fn fill_metadata<T: Meta>(mut resource: T) -> T {
+    let namespace = namespace_of(&resource).to_owned();
+    let metadata = resource.metadata_mut();
+    metadata.namespace = namespace;
+    if metadata.uid.is_empty() {
+        metadata.uid = utils::new_uid(&metadata.name);
+    }
+    resource
+}
+
+/// The index of the node controller given the name, `node-<i>`, otherwise no controller would run
+/// the node.
+///
+/// The index must be written the way node controllers are named, without leading zeros.
+fn node_controller_index(name: &str) -> Option<usize> {
+    let i = name.strip_prefix("node-")?;
+    let canonical =
+        !i.is_empty() && i.bytes().all(|b| b.is_ascii_digit()) && (i == "0" || !i.starts_with('0'));
+    if canonical {
+        i.parse().ok()
+    } else {
+        None
+    }
+}
+
+fn check_unique<T: Meta + Spec + Clone>(
+    file: &Path,
+    existing: &Resources<T>,
+    resource: &T,
+) -> Result<(), ScenarioError> {
+    if existing.contains(resource) {
+        let name = match namespace_of(resource) {
+            "" => resource.metadata().name.clone(),
+            namespace => format!("{}/{}", namespace, resource.metadata().name),
+        };
+        Err(ScenarioError::DuplicateResource(file.to_owned(), name))
+    } else {
+        Ok(())
+    }
+}
@@ -8,17 +8,26 @@ use crate::api::SerializableResource;
 use crate::controller::job::JobController;
 use crate::controller::podgc::PodGCController;
 use crate::controller::Controller;
+use crate::controller::CronJobController;
+use crate::controller::DaemonSetController;
 use crate::controller::DeploymentController;
+use crate::controller::GarbageCollectorController;
+use crate::controller::HPAController;
 use crate::controller::NodeController;
 use crate::controller::ReplicaSetController;
 use crate::controller::SchedulerController;
 use crate::controller::StatefulSetController;
+use crate::resources::DeleteOptions;
 use crate::resources::Deployment;
+use crate::resources::Namespace;
 use crate::resources::Node;
 use crate::resources::Pod;
 use crate::resources::ReplicaSet;
 use crate::resources::Scale;
+use crate::state::resources::ResourceError;
+use crate::state::resources::DEFAULT_NAMESPACE;
 use crate::state::StateView;
+use crate::utils;
 use axum::extract::Path;
 use axum::extract::State;
 use axum::routing::delete;
@@ -43,9 +52,60 @@ use tracing::{debug, info, warn};
 
 type AppState = Arc<Mutex<StateView>>;
 
+/// A response, or the Kubernetes status describing why the request failed.
+type ApiResult<T> = Result<(StatusCode, Json<T>), (StatusCode, Json<Status>)>;
+
+/// The Kubernetes status for a request on the named resource that failed.
+fn error_status(err: ResourceError, name: &str) -> (StatusCode, Json<Status>) {
+    let (code, reason) = match err {
+        ResourceError::AlreadyExists => (StatusCode::CONFLICT, "AlreadyExists"),
+        ResourceError::NotFound => (StatusCode::NOT_FOUND, "NotFound"),
+        ResourceError::UidMismatch | ResourceError::StaleResourceVersion => {
+            (StatusCode::CONFLICT, "Conflict")
+        }
+        ResourceError::Terminating => (StatusCode::UNPROCESSABLE_ENTITY, "Invalid"),
+        ResourceError::NamespaceTerminating => (StatusCode::FORBIDDEN, "Forbidden"),
+    };
+    (
+        code,
+        Json(Status {
+            code: Some(code.as_u16().into()),
+            details: None,
+            message: Some(format!("{:?}: {}", name, err)),
+            metadata: ListMeta::default(),
+            reason: Some(reason.to_owned()),
+            status: Some("Failure".to_owned()),
+        }),
+    )
+}
+
+fn success_status() -> (StatusCode, Json<Status>) {
+    (
+        StatusCode::OK,
+        Json(Status {
+            code: None,
+            details: None,
+            message: None,
+            metadata: ListMeta::default(),
+            reason: None,
+            status: Some("Success".to_owned()),
+        }),
+    )
+}
+
 pub async fn run(address: String) -> (Arc<AtomicBool>, Vec<JoinHandle<()>>) {
     let trace_layer = TraceLayer::new_for_http();
-    let state = Arc::new(Mutex::new(StateView::default()));
+    let mut view = StateView::default();
+    let default_namespace = Namespace {
+        metadata: utils::metadata(DEFAULT_NAMESPACE.to_owned()),
+        ..Default::default()
+    };
+    let revision = view.revision.clone().increment();
+    view.namespaces
+        .create(default_namespace, revision.clone(), utils::now())
+        .unwrap();
+    view.revision = revision;
+    let state = Arc::new(Mutex::new(view));
     let shutdown = Arc::new(AtomicBool::new(false));
     let mut handles = Vec::new();
 
@@ -61,10 +121,14 @@ pub async fn run(address: String) -> (Arc<AtomicBool>, Vec<JoinHandle<()>>) {
 
     run_controller!(DeploymentController);
     run_controller!(StatefulSetController);
+    run_controller!(DaemonSetController);
     run_controller!(JobController);
+    run_controller!(CronJobController);
+    run_controller!(HPAController);
     run_controller!(ReplicaSetController);
     run_controller!(SchedulerController);
     run_controller!(PodGCController);
+    run_controller!(GarbageCollectorController);
 
     let state2 = Arc::clone(&state);
     let sd = Arc::clone(&shutdown);
@@ -118,11 +182,13 @@ async fn controller_loop<C: Controller>(state: AppState, controller: C, shutdown
         }
 
         debug!(name = controller.name(), "Checking for steps");
+        s.now = utils::now();
         if let Some(operation) = controller.step(&s, &mut cstate) {
             info!(name = controller.name(), "Got operation to perform");
             let revision = s.revision.clone();
-            if !s.apply_operation(operation.into(), revision.increment()) {
-                warn!(name = controller.name(), "Failed to apply operation");
+            let now = s.now;
+            if let Err(reason) = s.apply_operation(operation.into(), revision.increment(), now) {
+                warn!(name = controller.name(), %reason, "Failed to apply operation");
             }
         }
         last_revision = s.revision.clone();
@@ -150,18 +216,21 @@ fn apis() -> Router<AppState> {
 
 fn core_v1() -> Router<AppState> {
     Router::new()
-        // .route("/namespaces", get(list_namespaces))
         .nest("/namespaces", namespaces_core_v1())
+        .nest("/nodes", nodes_router())
 }
 
 fn namespaces_core_v1() -> Router<AppState> {
-    Router::new().nest("/default", resources_core_v1())
+    Router::new()
+        .route("/", get(list_namespaces))
+        .route("/", post(create_namespace))
+        .route("/:namespace", get(get_namespace))
+        .route("/:namespace", delete(delete_namespace))
+        .nest("/:namespace", resources_core_v1())
 }
 
 fn resources_core_v1() -> Router<AppState> {
-    Router::new()
-        .nest("/pods", pods_router())
-        .nest("/nodes", nodes_router())
+    Router::new().nest("/pods", pods_router())
 }
 
 fn pods_router() -> Router<AppState> {
@@ -177,13 +246,11 @@ fn nodes_router() -> Router<AppState> {
 }
 
 fn apps_v1() -> Router<AppState> {
-    Router::new()
-        // .route("/namespaces", get(list_namespaces))
-        .nest("/namespaces", namespaces_apps_v1())
+    Router::new().nest("/namespaces", namespaces_apps_v1())
 }
 
 fn namespaces_apps_v1() -> Router<AppState> {
-    Router::new().nest("/default", resources_apps_v1())
+    Router::new().nest("/:namespace", resources_apps_v1())
 }
 
 fn resources_apps_v1() -> Router<AppState> {
@@ -205,13 +272,14 @@ fn deployments_router() -> Router<AppState> {
 #[tracing::instrument(skip_all)]
 async fn list_deployments(
     State(state): State<AppState>,
+    Path(namespace): Path<String>,
 ) -> (StatusCode, Json<List<SerializableResource<Deployment>>>) {
     info!("Got list request for deployments");
     let state = state.lock().await;
     let deployments = List {
         items: state
             .deployments
-            .iter()
+            .in_namespace(&namespace)
             .map(|d| SerializableResource::new(d.clone()))
             .collect(),
         metadata: ListMeta {
@@ -227,11 +295,11 @@ async fn list_deployments(
 #[tracing::instrument(skip_all)]
 async fn get_deployment(
     State(state): State<AppState>,
-    Path(name): Path<String>,
+    Path((namespace, name)): Path<(String, String)>,
 ) -> (StatusCode, Json<SerializableResource<Deployment>>) {
     info!("Got get request for deployment");
     let state = state.lock().await;
-    if let Some(deployment) = state.deployments.get(&name) {
+    if let Some(deployment) = state.deployments.get(&namespace, &name) {
         (
             StatusCode::OK,
             Json(SerializableResource::new(deployment.clone())),
@@ -247,71 +315,94 @@ async fn get_deployment(
 #[tracing::instrument(skip_all)]
 async fn create_deployment(
     State(state): State<AppState>,
-    Json(deployment): Json<Deployment>,
-) -> (StatusCode, Json<SerializableResource<Deployment>>) {
+    Path(namespace): Path<String>,
+    Json(mut deployment): Json<Deployment>,
+) -> ApiResult<SerializableResource<Deployment>> {
     info!("Got create request for deployment");
+    deployment.metadata.namespace = namespace.clone();
     let mut s = state.lock().await;
-    s.revision = s.revision.clone().increment();
-    let revision = s.revision.clone();
+    let revision = s.revision.clone().increment();
     let deployment_name = deployment.metadata.name.clone();
-    s.deployments.create(deployment, revision).unwrap();
-    let deployment = s.deployments.get(&deployment_name).unwrap().clone();
-    (StatusCode::OK, Json(SerializableResource::new(deployment)))
+    s.deployments
+        .create(deployment, revision.clone(), utils::now())
+        .map_err(|err| error_status(err, &deployment_name))?;
+    s.revision = revision;
+    let deployment = s
+        .deployments
+        .get(&namespace, &deployment_name)
+        .unwrap()
+        .clone();
+    Ok((StatusCode::OK, Json(SerializableResource::new(deployment))))
 }
 
 #[tracing::instrument(skip_all)]
 async fn update_deployment(
     State(state): State<AppState>,
-    Json(deployment): Json<Deployment>,
-) -> (StatusCode, Json<SerializableResource<Deployment>>) {
+    Path((namespace, _name)): Path<(String, String)>,
+    Json(mut deployment): Json<Deployment>,
+) -> ApiResult<SerializableResource<Deployment>> {
     info!("Got create request for deployment");
+    deployment.metadata.namespace = namespace.clone();
     let mut s = state.lock().await;
-    s.revision = s.revision.clone().increment();
-    let revision = s.revision.clone();
+    let revision = s.revision.clone().increment();
     let deployment_name = deployment.metadata.name.clone();
-    s.deployments.update(deployment, revision).unwrap();
-    let deployment = s.deployments.get(&deployment_name).unwrap().clone();
-    (StatusCode::OK, Json(SerializableResource::new(deployment)))
+    s.deployments
+        .update(deployment, revision.clone())
+        .map_err(|err| error_status(err, &deployment_name))?;
+    s.revision = revision;
+    let deployment = s
+        .deployments
+        .get(&namespace, &deployment_name)
+        .unwrap()
+        .clone();
+    Ok((StatusCode::OK, Json(SerializableResource::new(deployment))))
 }
 
 #[tracing::instrument(skip_all)]
 async fn scale_deployment(
     State(state): State<AppState>,
-    Path(name): Path<String>,
+    Path((namespace, name)): Path<(String, String)>,
     Json(scale): Json<Scale>,
-) -> (StatusCode, Json<SerializableResource<Deployment>>) {
+) -> ApiResult<SerializableResource<Deployment>> {
     info!("Got scale request for deployment");
     let mut s = state.lock().await;
-    s.revision = s.revision.clone().increment();
-    let revision = s.revision.clone();
-    let mut deployment = s.deployments.get(&name).unwrap().clone();
+    let revision = s.revision.clone().increment();
+    let mut deployment = s
+        .deployments
+        .get(&namespace, &name)
+        .cloned()
+        .ok_or_else(|| error_status(ResourceError::NotFound, &name))?;
     deployment.spec.replicas = scale.spec.replicas;
-    s.deployments.update(deployment, revision).unwrap();
-    let deployment = s.deployments.get(&name).unwrap().clone();
-    (StatusCode::OK, Json(SerializableResource::new(deployment)))
+    s.deployments
+        .update(deployment, revision.clone())
+        .map_err(|err| error_status(err, &name))?;
+    s.revision = revision;
+    let deployment = s.deployments.get(&namespace, &name).unwrap().clone();
+    Ok((StatusCode::OK, Json(SerializableResource::new(deployment))))
 }
 
 #[tracing::instrument(skip_all)]
 async fn delete_deployment(
     State(state): State<AppState>,
-    Path(name): Path<String>,
+    Path((namespace, name)): Path<(String, String)>,
+    options: Option<Json<DeleteOptions>>,
 ) -> (StatusCode, Json<Status>) {
-    info!("Got create request for deployment");
+    info!("Got delete request for deployment");
+    let policy = options.map(|o| o.0.propagation_policy).unwrap_or_default();
     let mut s = state.lock().await;
-    s.revision = s.revision.clone().increment();
-    let deployment = s.deployments.get(&name).unwrap().clone();
-    s.deployments.remove(&deployment);
-    (
-        StatusCode::OK,
-        Json(Status {
-            code: None,
-            details: None,
-            message: None,
-            metadata: ListMeta::default(),
-            reason: None,
-            status: Some("Success".to_owned()),
-        }),
-    )
+    let Some(deployment) = s.deployments.get(&namespace, &name).cloned() else {
+        return error_status(ResourceError::NotFound, &name);
+    };
+    let revision = s.revision.clone().increment();
+    if let Err(err) = s
+        .deployments
+        .delete(&deployment, policy, revision.clone(), utils::now())
+    {
+        return error_status(err, &name);
+    }
+    s.remove_empty_namespaces();
+    s.revision = revision;
+    success_status()
 }
 
 fn replicasets_router() -> Router<AppState> {
@@ -326,13 +417,14 @@ fn replicasets_router() -> Router<AppState> {
 #[tracing::instrument(skip_all)]
 async fn list_replicasets(
     State(state): State<AppState>,
+    Path(namespace): Path<String>,
 ) -> (StatusCode, Json<List<SerializableResource<ReplicaSet>>>) {
     info!("Got list request for replicasets");
     let state = state.lock().await;
     let replicasets = List {
         items: state
             .replicasets
-            .iter()
+            .in_namespace(&namespace)
             .map(|d| SerializableResource::new(d.clone()))
             .collect(),
         metadata: ListMeta {
@@ -348,11 +440,11 @@ async fn list_replicasets(
 #[tracing::instrument(skip_all)]
 async fn get_replicaset(
     State(state): State<AppState>,
-    Path(name): Path<String>,
+    Path((namespace, name)): Path<(String, String)>,
 ) -> (StatusCode, Json<SerializableResource<ReplicaSet>>) {
     info!("Got get request for replicaset");
     let state = state.lock().await;
-    if let Some(replicaset) = state.replicasets.get(&name) {
+    if let Some(replicaset) = state.replicasets.get(&namespace, &name) {
         (
             StatusCode::OK,
             Json(SerializableResource::new(replicaset.clone())),
@@ -368,54 +460,71 @@ async fn get_replicaset(
 #[tracing::instrument(skip_all)]
 async fn create_replicaset(
     State(state): State<AppState>,
-    Json(replicaset): Json<ReplicaSet>,
-) -> (StatusCode, Json<ReplicaSet>) {
+    Path(namespace): Path<String>,
+    Json(mut replicaset): Json<ReplicaSet>,
+) -> ApiResult<ReplicaSet> {
     info!("Got create request for replicaset");
+    replicaset.metadata.namespace = namespace.clone();
     let mut s = state.lock().await;
-    s.revision = s.revision.clone().increment();
-    let revision = s.revision.clone();
+    let revision = s.revision.clone().increment();
     let replicaset_name = replicaset.metadata.name.clone();
-    s.replicasets.create(replicaset, revision).unwrap();
-    let replicaset = s.replicasets.get(&replicaset_name).unwrap().clone();
-    (StatusCode::OK, Json(replicaset))
+    s.replicasets
+        .create(replicaset, revision.clone(), utils::now())
+        .map_err(|err| error_status(err, &replicaset_name))?;
+    s.revision = revision;
+    let replicaset = s
+        .replicasets
+        .get(&namespace, &replicaset_name)
+        .unwrap()
+        .clone();
+    Ok((StatusCode::OK, Json(replicaset)))
 }
 
 #[tracing::instrument(skip_all)]
 async fn update_replicaset(
     State(state): State<AppState>,
-    Json(replicaset): Json<ReplicaSet>,
-) -> (StatusCode, Json<ReplicaSet>) {
+    Path((namespace, _name)): Path<(String, String)>,
+    Json(mut replicaset): Json<ReplicaSet>,
+) -> ApiResult<ReplicaSet> {
     info!("Got create request for replicaset");
+    replicaset.metadata.namespace = namespace.clone();
     let mut s = state.lock().await;
-    s.revision = s.revision.clone().increment();
-    let revision = s.revision.clone();
+    let revision = s.revision.clone().increment();
     let replicaset_name = replicaset.metadata.name.clone();
-    s.replicasets.update(replicaset, revision).unwrap();
-    let replicaset = s.replicasets.get(&replicaset_name).unwrap().clone();
-    (StatusCode::OK, Json(replicaset))
+    s.replicasets
+        .update(replicaset, revision.clone())
+        .map_err(|err| error_status(err, &replicaset_name))?;
+    s.revision = revision;
+    let replicaset = s
+        .replicasets
+        .get(&namespace, &replicaset_name)
+        .unwrap()
+        .clone();
+    Ok((StatusCode::OK, Json(replicaset)))
 }
 
 #[tracing::instrument(skip_all)]
 async fn delete_replicaset(
     State(state): State<AppState>,
-    Path(name): Path<String>,
+    Path((namespace, name)): Path<(String, String)>,
+    options: Option<Json<DeleteOptions>>,
 ) -> (StatusCode, Json<Status>) {
-    info!("Got create request for replicaset");
+    info!("Got delete request for replicaset");
+    let policy = options.map(|o| o.0.propagation_policy).unwrap_or_default();
     let mut s = state.lock().await;
-    s.revision = s.revision.clone().increment();
-    let replicaset = s.replicasets.get(&name).unwrap().clone();
-    s.replicasets.remove(&replicaset);
-    (
-        StatusCode::OK,
-        Json(Status {
-            code: None,
-            details: None,
-            message: None,
-            metadata: ListMeta::default(),
-            reason: None,
-            status: Some("Success".to_owned()),
-        }),
-    )
+    let Some(replicaset) = s.replicasets.get(&namespace, &name).cloned() else {
+        return error_status(ResourceError::NotFound, &name);
+    };
+    let revision = s.revision.clone().increment();
+    if let Err(err) = s
+        .replicasets
+        .delete(&replicaset, policy, revision.clone(), utils::now())
+    {
+        return error_status(err, &name);
+    }
+    s.remove_empty_namespaces();
+    s.revision = revision;
+    success_status()
 }
 
 #[tracing::instrument(skip_all)]
@@ -451,7 +560,11 @@ async fn list_core_v1() -> (StatusCode, Json<APIResourceList>) {
     info!("Got request for api v1 versions");
     let apiversions = APIResourceList {
         group_version: "v1".to_owned(),
-        resources: vec![Pod::api_resource(), Node::api_resource()],
+        resources: vec![
+            Pod::api_resource(),
+            Node::api_resource(),
+            Namespace::api_resource(),
+        ],
     };
     (StatusCode::OK, Json(apiversions))
 }
@@ -473,13 +586,14 @@ async fn list_apps_v1() -> (StatusCode, Json<APIResourceList>) {
 #[tracing::instrument(skip_all)]
 async fn list_pods(
     State(state): State<AppState>,
+    Path(namespace): Path<String>,
 ) -> (StatusCode, Json<List<SerializableResource<Pod>>>) {
     info!("Got list request for pods");
     let state = state.lock().await;
     let pods = List {
         items: state
             .pods
-            .iter()
+            .in_namespace(&namespace)
             .map(|p| SerializableResource::new(p.clone()))
             .collect(),
         metadata: ListMeta {
@@ -495,11 +609,11 @@ async fn list_pods(
 #[tracing::instrument(skip_all)]
 async fn get_pod(
     State(state): State<AppState>,
-    Path(name): Path<String>,
+    Path((namespace, name)): Path<(String, String)>,
 ) -> (StatusCode, Json<SerializableResource<Pod>>) {
     info!("Got get request for pods");
     let state = state.lock().await;
-    if let Some(pod) = state.pods.get(&name) {
+    if let Some(pod) = state.pods.get(&namespace, &name) {
         (StatusCode::OK, Json(SerializableResource::new(pod.clone())))
     } else {
         (
@@ -512,24 +626,19 @@ async fn get_pod(
 #[tracing::instrument(skip_all)]
 async fn delete_pod(
     State(state): State<AppState>,
-    Path(name): Path<String>,
+    Path((namespace, name)): Path<(String, String)>,
 ) -> (StatusCode, Json<Status>) {
     info!("Got delete request for pods");
     let mut state = state.lock().await;
+    let Some(pod) = state.pods.get(&namespace, &name).cloned() else {
+        return error_status(ResourceError::NotFound, &name);
+    };
+    if let Err(err) = state.pods.remove(&pod) {
+        return error_status(err, &name);
+    }
+    state.remove_empty_namespaces();
     state.revision = state.revision.clone().increment();
-    let pod = state.pods.get(&name).unwrap().clone();
-    state.pods.remove(&pod);
-    (
-        StatusCode::OK,
-        Json(Status {
-            code: None,
-            details: None,
-            message: None,
-            metadata: ListMeta::default(),
-            reason: None,
-            status: Some("Success".to_owned()),
-        }),
-    )
+    success_status()
 }
 
 #[tracing::instrument(skip_all)]
@@ -561,7 +670,7 @@ async fn get_node(
 ) -> (StatusCode, Json<SerializableResource<Node>>) {
     info!("Got get request for nodes");
     let state = state.lock().await;
-    if let Some(node) = state.nodes.get(&name) {
+    if let Some(node) = state.nodes.get("", &name) {
         (
             StatusCode::OK,
             Json(SerializableResource::new(node.clone())),
@@ -574,6 +683,76 @@ async fn get_node(
     }
 }
 
+#[tracing::instrument(skip_all)]
+async fn list_namespaces(
+    State(state): State<AppState>,
+) -> (StatusCode, Json<List<SerializableResource<Namespace>>>) {
+    info!("Got list request for namespaces");
+    let state = state.lock().await;
+    let namespaces = List {
+        items: state
+            .namespaces
+            .iter()
+            .map(|n| SerializableResource::new(n.clone()))
+            .collect(),
+        metadata: ListMeta {
+            continue_: None,
+            remaining_item_count: None,
+            resource_version: Some(state.revision.to_string()),
+            self_link: None,
+        },
+    };
+    (StatusCode::OK, Json(namespaces))
+}
+
+#[tracing::instrument(skip_all)]
+async fn get_namespace(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> ApiResult<SerializableResource<Namespace>> {
+    info!("Got get request for namespace");
+    let state = state.lock().await;
+    let namespace = state
+        .namespaces
+        .get("", &name)
+        .cloned()
+        .ok_or_else(|| error_status(ResourceError::NotFound, &name))?;
+    Ok((StatusCode::OK, Json(SerializableResource::new(namespace))))
+}
+
+#[tracing::instrument(skip_all)]
+async fn create_namespace(
+    State(state): State<AppState>,
+    Json(namespace): Json<Namespace>,
+) -> ApiResult<SerializableResource<Namespace>> {
+    info!("Got create request for namespace");
+    let mut s = state.lock().await;
+    let revision = s.revision.clone().increment();
+    let name = namespace.metadata.name.clone();
+    s.namespaces
+        .create(namespace, revision.clone(), utils::now())
+        .map_err(|err| error_status(err, &name))?;
+    s.revision = revision;
+    let namespace = s.namespaces.get("", &name).unwrap().clone();
+    Ok((StatusCode::OK, Json(SerializableResource::new(namespace))))
+}
+
+/// Start deleting the namespace along with everything in it.
+#[tracing::instrument(skip_all)]
+async fn delete_namespace(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> (StatusCode, Json<Status>) {
+    info!("Got delete request for namespace");
+    let mut s = state.lock().await;
+    let revision = s.revision.clone().increment();
+    if let Err(err) = s.delete_namespace(&name, revision.clone(), utils::now()) {
+        return error_status(err, &name);
+    }
+    s.revision = revision;
+    success_status()
+}
+
 #[tracing::instrument(skip_all)]
 async fn fallback(method: Method, uri: Uri) -> StatusCode {
     warn!(%method, %uri, "No matching handler for request");
@@ -0,0 +1,70 @@
+use std::sync::Arc;
+
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use serde::Deserialize;
+
+use crate::abstract_model::AbstractModel;
+use crate::controller::Controller;
+use crate::replay::{self, Replay};
+
+/// Serve the history graphs of states in the model, alongside the explorer.
+///
+/// States are found by the same fingerprint paths as the explorer uses, e.g.
+/// `/history/<fingerprint>/<fingerprint>?format=dot`.
+pub fn app(model: AbstractModel) -> Router {
+    Router::new()
+        .route("/history/*path", get(history))
+        .with_state(Arc::new(model))
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum HistoryFormat {
+    #[default]
+    Json,
+    Dot,
+}
+
+#[derive(Debug, Deserialize)]
+struct HistoryQuery {
+    #[serde(default)]
+    format: HistoryFormat,
+}
+
+async fn history(
+    State(model): State<Arc<AbstractModel>>,
+    Path(path): Path<String>,
+    Query(query): Query<HistoryQuery>,
+) -> Response {
+    let replay = replay::actions_from_fingerprints(&model, &path)
+        .and_then(|actions| replay::replay(&model, &actions));
+    let state = match replay {
+        Ok(Replay {
+            initial_state,
+            steps,
+        }) => steps.into_iter().last().map_or(initial_state, |s| s.state),
+        Err(err) => return (StatusCode::NOT_FOUND, err.to_string()).into_response(),
+    };
+    let Some(graph) = state.history_graph() else {
+        return (
+            StatusCode::NOT_FOUND,
+            "only causal histories have a graph".to_owned(),
+        )
+            .into_response();
+    };
+    match query.format {
+        HistoryFormat::Json => Json(graph).into_response(),
+        HistoryFormat::Dot => {
+            let names = model
+                .controllers
+                .iter()
+                .map(|c| c.name())
+                .collect::<Vec<_>>();
+            graph.to_dot(&names).into_response()
+        }
+    }
+}
@@ -21,6 +21,7 @@ use crate::resources::{
 };
 use crate::state::RawState;
 use crate::state::StateView;
+use crate::utils;
 
 pub fn app() -> Router {
     Router::new()
@@ -197,7 +198,9 @@ async fn scheduler(
             pods: pods.into(),
             persistent_volume_claims: payload.persistent_volume_claims.into(),
             ..Default::default()
-        },
+        }
+        .into(),
+        now: utils::now(),
         ..Default::default()
     };
     let mut local_state = SchedulerControllerState::default();
@@ -224,7 +227,9 @@ async fn deployment(
             deployments: vec![payload.deployment].into(),
             replicasets: payload.replicasets.into(),
             ..Default::default()
-        },
+        }
+        .into(),
+        now: utils::now(),
         ..Default::default()
     };
     let mut local_state = DeploymentControllerState::default();
@@ -288,7 +293,9 @@ async fn replicaset(
             replicasets: replicasets.into(),
             pods: payload.pods.into(),
             ..Default::default()
-        },
+        }
+        .into(),
+        now: utils::now(),
         ..Default::default()
     };
     let mut local_state = ReplicaSetControllerState::default();
@@ -326,7 +333,9 @@ async fn statefulset(
             pods: payload.pods.into(),
             persistent_volume_claims: payload.persistent_volume_claims.into(),
             ..Default::default()
-        },
+        }
+        .into(),
+        now: utils::now(),
         ..Default::default()
     };
     let mut local_state = StatefulSetControllerState::default();
@@ -385,7 +394,9 @@ async fn job(Json(payload): Json<JobRequest>) -> Result<Json<JobResponse>, Error
             jobs: vec![payload.job].into(),
             pods: payload.pods.into(),
             ..Default::default()
-        },
+        }
+        .into(),
+        now: utils::now(),
         ..Default::default()
     };
     let mut local_state = JobControllerState::default();
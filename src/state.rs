@@ -1,19 +1,26 @@
 use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
 use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use stateright::Representative;
 
 use crate::controller::ControllerStates;
 use crate::resources::{
-    ConditionStatus, ControllerRevision, Job, Meta, NodeCondition, NodeConditionType,
-    ObservedGeneration, PersistentVolumeClaim,
+    ConditionStatus, ControllerRevision, CronJob, DaemonSet, HorizontalPodAutoscaler, Job, Lease,
+    Meta, Namespace, NamespacePhase, NodeCondition, NodeConditionType, ObservedGeneration,
+    PersistentVolumeClaim, PropagationPolicy, Time, CONTROLLER_UID_LABEL, LABEL_HOSTNAME,
 };
-use crate::utils::{self, now};
+use crate::utils;
 use crate::{
     abstract_model::{Change, ControllerAction},
     resources::{Deployment, Node, Pod, ReplicaSet, StatefulSet},
 };
 
-use self::history::{ConsistencySetup, History, StateHistory};
-use self::resources::Resources;
+use self::history::graph::HistoryGraph;
+use self::history::{ConsistencySetup, History, ReadMode, StateHistory};
+use self::resources::{namespace_of, ResourceError, Resources};
 use self::revision::Revision;
 
 pub mod history;
@@ -27,6 +34,75 @@ pub struct State {
     states: StateHistory,
 
     controller_states: Vec<ControllerStates>,
+
+    /// The writes issued by controllers since the last perturbation, when tracking quiescence.
+    ///
+    /// This has to be part of the state for loops of writes to be unrolled up to the bound, but
+    /// it stops counting just past the bound so that it only multiplies the states by the bound.
+    quiescence: Option<Quiescence>,
+
+    /// The write that the datastore rejected in the step to this state, if any.
+    rejection: Option<Rejection>,
+
+    /// The leases that a candidate has written under after it no longer held them.
+    ///
+    /// Kept out of the datastore state as it is only for checking, and only a flag so that
+    /// further writes don't each make a new state.
+    split_brain_leases: BTreeSet<String>,
+
+    /// The scalable resources, by kind, namespace and name, whose replicas were last set by an
+    /// autoscaler rather than a client or another controller.
+    autoscaled: BTreeSet<(String, String, String)>,
+
+    /// The number of times the clock has ticked.
+    clock: u32,
+}
+
+/// The writes issued by controllers since the last perturbation, up to one past the bound.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Quiescence {
+    writes: usize,
+    bound: usize,
+}
+
+/// How far the clock moves on each tick, the granularity of cron schedules.
+pub const CLOCK_TICK: time::Duration = time::Duration::MINUTE;
+
+/// Why the datastore rejected a write.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum RejectionReason {
+    /// The resources couldn't take the change.
+    Resource(ResourceError),
+    /// The write was fenced on a lease that the writer no longer holds.
+    LeaseNotHeld,
+    /// The replica the write was sent to could not reach a majority to commit it.
+    NoQuorum,
+}
+
+impl From<ResourceError> for RejectionReason {
+    fn from(value: ResourceError) -> Self {
+        RejectionReason::Resource(value)
+    }
+}
+
+impl std::fmt::Display for RejectionReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RejectionReason::Resource(err) => write!(f, "{}", err),
+            RejectionReason::LeaseNotHeld => write!(f, "lease not held"),
+            RejectionReason::NoQuorum => write!(f, "no quorum"),
+        }
+    }
+}
+
+/// A write that the datastore rejected.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize)]
+pub struct Rejection {
+    /// The controller that made the write, or `None` for clients.
+    pub controller: Option<usize>,
+    /// The kind of operation that was written.
+    pub operation: &'static str,
+    pub reason: RejectionReason,
 }
 
 impl State {
@@ -34,12 +110,168 @@ impl State {
         Self {
             states: StateHistory::new(consistency_level, initial_state),
             controller_states: Vec::new(),
+            quiescence: None,
+            rejection: None,
+            split_brain_leases: BTreeSet::new(),
+            autoscaled: BTreeSet::new(),
+            clock: 0,
         }
     }
 
-    /// Record a change for this state from a given controller.
+    /// The number of times the clock has ticked.
+    pub fn clock_ticks(&self) -> u32 {
+        self.clock
+    }
+
+    /// Move the clock on by a tick.
+    pub fn tick_clock(&mut self) {
+        self.clock += 1;
+    }
+
+    /// The current time, starting from the epoch and moved on by each tick of the clock.
+    pub fn now(&self) -> Time {
+        Time(time::OffsetDateTime::UNIX_EPOCH + CLOCK_TICK * self.clock)
+    }
+
+    /// Set the time that controllers see in the view to the current time.
+    fn with_now<'a>(&self, mut view: Cow<'a, StateView>) -> Cow<'a, StateView> {
+        let now = self.now();
+        if view.now != now {
+            view.to_mut().now = now;
+        }
+        view
+    }
+
+    /// Start counting the writes that controllers issue between perturbations, up to one past
+    /// the bound.
+    pub fn track_quiescence(&mut self, bound: usize) {
+        self.quiescence = Some(Quiescence { writes: 0, bound });
+    }
+
+    /// The number of writes issued by controllers since the last perturbation, if tracking
+    /// quiescence, saturating at one past the bound.
+    pub fn writes_since_perturbation(&self) -> Option<usize> {
+        self.quiescence.as_ref().map(|q| q.writes)
+    }
+
+    /// Record a write issued by a controller.
+    pub fn record_controller_write(&mut self) {
+        if let Some(q) = &mut self.quiescence {
+            q.writes = (q.writes + 1).min(q.bound + 1);
+        }
+    }
+
+    /// Record a perturbation to the system from the environment.
+    pub fn record_perturbation(&mut self) {
+        if let Some(q) = &mut self.quiescence {
+            q.writes = 0;
+        }
+    }
+
+    /// Record a change for this state from a given controller, noting if the datastore rejected
+    /// it or let it through from a candidate that had lost its lease, and who it scaled resources
+    /// for.
     pub fn push_change(&mut self, change: Change) {
-        self.states.add_change(change)
+        let controller = change.controller;
+        let operation = change.operation.kind();
+        let scaled = self.scaled_by(&change.operation);
+        let autoscaler = controller
+            .and_then(|c| self.controller_states.get(c))
+            .map_or(false, is_autoscaler);
+        let now = self.now();
+        self.rejection = match self.states.add_change(change, now) {
+            Ok(lost_lease) => {
+                self.split_brain_leases.extend(lost_lease);
+                for target in scaled {
+                    if autoscaler {
+                        self.autoscaled.insert(target);
+                    } else {
+                        self.autoscaled.remove(&target);
+                    }
+                }
+                None
+            }
+            Err(reason) => Some(Rejection {
+                controller,
+                operation,
+                reason,
+            }),
+        };
+    }
+
+    /// The write that the datastore rejected in the step to this state, if any.
+    pub fn rejection(&self) -> Option<&Rejection> {
+        self.rejection.as_ref()
+    }
+
+    /// The leases that a candidate has written under after it no longer held them.
+    pub fn split_brain_leases(&self) -> &BTreeSet<String> {
+        &self.split_brain_leases
+    }
+
+    /// Whether the replicas of the scalable resource were last set by an autoscaler.
+    pub fn autoscaled(&self, kind: &str, namespace: &str, name: &str) -> bool {
+        self.autoscaled
+            .contains(&(kind.to_owned(), namespace.to_owned(), name.to_owned()))
+    }
+
+    /// The scalable resources, by kind, namespace and name, whose replicas the operation changes
+    /// from the latest state.
+    fn scaled_by(&self, operation: &ControllerAction) -> Vec<(String, String, String)> {
+        let latest = self.latest();
+        let scaled = |kind: &str, namespace: &str, name: &str, replicas, current: Option<u32>| {
+            current
+                .map_or(false, |c| c != replicas)
+                .then(|| (kind.to_owned(), namespace.to_owned(), name.to_owned()))
+        };
+        let replicaset = |rs: &ReplicaSet| {
+            let namespace = namespace_of(rs);
+            let current = latest.replicasets.get(namespace, &rs.metadata.name);
+            scaled(
+                "ReplicaSet",
+                namespace,
+                &rs.metadata.name,
+                rs.spec.replicas.unwrap_or(1),
+                current.map(|c| c.spec.replicas.unwrap_or(1)),
+            )
+        };
+        match operation {
+            ControllerAction::UpdateDeployment(d) => {
+                let namespace = namespace_of(d);
+                let current = latest.deployments.get(namespace, &d.metadata.name);
+                scaled(
+                    "Deployment",
+                    namespace,
+                    &d.metadata.name,
+                    d.spec.replicas,
+                    current.map(|c| c.spec.replicas),
+                )
+                .into_iter()
+                .collect()
+            }
+            ControllerAction::UpdateReplicaSet(rs) => replicaset(rs).into_iter().collect(),
+            ControllerAction::UpdateReplicaSets(rss) => rss.iter().filter_map(replicaset).collect(),
+            ControllerAction::UpdateStatefulSet(sts) => {
+                let namespace = namespace_of(sts);
+                let current = latest.statefulsets.get(namespace, &sts.metadata.name);
+                scaled(
+                    "StatefulSet",
+                    namespace,
+                    &sts.metadata.name,
+                    sts.spec.replicas.unwrap_or(1),
+                    current.map(|c| c.spec.replicas.unwrap_or(1)),
+                )
+                .into_iter()
+                .collect()
+            }
+            ControllerAction::Fenced { action, .. } => self.scaled_by(action),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Forget the rejected write, as the state has been stepped from.
+    pub fn clear_rejection(&mut self) {
+        self.rejection = None;
     }
 
     /// Get the maximum revision for this change.
@@ -49,16 +281,119 @@ impl State {
 
     /// Get a view for a specific revision in the change history.
     pub fn view_at(&self, revision: &Revision) -> Cow<StateView> {
-        self.states.state_at(revision)
+        self.with_now(self.states.state_at(revision))
+    }
+
+    /// Get the view that a controller reading with the given mode sees at a revision, which is its
+    /// informer cache if it has one at that revision.
+    ///
+    /// A cache part way through receiving the events of a change has the revision of that change,
+    /// so reading at that revision gives the partially applied state.
+    pub fn view_for(
+        &self,
+        controller: usize,
+        read_mode: ReadMode,
+        revision: &Revision,
+    ) -> Cow<StateView> {
+        match self.states.informer() {
+            Some(i)
+                if read_mode != ReadMode::Latest && &i.cache(controller).revision == revision =>
+            {
+                self.with_now(Cow::Borrowed(i.cache(controller)))
+            }
+            _ => self.view_at(revision),
+        }
     }
 
-    /// Get all the possible revisions under the given consistency level.
-    pub fn revisions(&self, min_revision: Option<&Revision>) -> Vec<Revision> {
-        self.states.valid_revisions(min_revision)
+    /// Record that a controller has read its informer cache, if it has one.
+    pub fn observe(&mut self, controller: usize) {
+        if let Some(i) = self.states.informer_mut() {
+            i.observe(controller)
+        }
+    }
+
+    /// Controllers whose informers have watch events yet to receive.
+    pub fn pending_informers(&self) -> Vec<usize> {
+        self.states
+            .informer()
+            .map_or_else(Vec::new, |i| i.pending_informers())
+    }
+
+    /// Deliver the next watch event to the controller's informer.
+    pub fn deliver_event(&mut self, controller: usize) {
+        if let Some(i) = self.states.informer_mut() {
+            i.deliver_event(controller)
+        }
+    }
+
+    /// Relist the controller's informer, replacing its cache with the latest state.
+    pub fn relist(&mut self, controller: usize) {
+        if let Some(i) = self.states.informer_mut() {
+            i.relist(controller)
+        }
+    }
+
+    /// Get all the possible revisions for a controller reading with the given mode, under the
+    /// consistency level of the state.
+    pub fn revisions(
+        &self,
+        controller: usize,
+        read_mode: ReadMode,
+        min_revision: Option<&Revision>,
+    ) -> Vec<Revision> {
+        self.states
+            .valid_revisions_with(controller, read_mode, min_revision)
+    }
+
+    /// The replica of the datastore that the controller reads through, if it is replicated.
+    pub fn replica_for(&self, controller: usize) -> Option<usize> {
+        self.states.replicated().map(|r| r.replica_for(controller))
+    }
+
+    /// Replicas of the datastore that can apply more of the log, if it is replicated.
+    pub fn lagging_replicas(&self) -> Vec<usize> {
+        self.states
+            .replicated()
+            .map_or_else(Vec::new, |r| r.lagging_replicas())
+    }
+
+    /// Replicas of the datastore that can be partitioned, if it is replicated.
+    pub fn partitionable_replicas(&self) -> Vec<usize> {
+        self.states
+            .replicated()
+            .map_or_else(Vec::new, |r| r.partitionable_replicas())
+    }
+
+    /// Replicas of the datastore that are partitioned, if it is replicated.
+    pub fn partitioned_replicas(&self) -> Vec<usize> {
+        self.states
+            .replicated()
+            .map_or_else(Vec::new, |r| r.partitioned_replicas())
+    }
+
+    pub fn replicate(&mut self, replica: usize) {
+        if let Some(r) = self.states.replicated_mut() {
+            r.replicate(replica)
+        }
+    }
+
+    pub fn partition(&mut self, replica: usize) {
+        if let Some(r) = self.states.replicated_mut() {
+            r.partition(replica)
+        }
+    }
+
+    pub fn heal(&mut self, replica: usize) {
+        if let Some(r) = self.states.replicated_mut() {
+            r.heal(replica)
+        }
     }
 
     pub fn add_controller(&mut self, controller_state: ControllerStates) {
         self.controller_states.push(controller_state);
+        if let Some(i) = self.states.informer_mut() {
+            i.add_informer()
+        }
     }
 
     pub fn update_controller(&mut self, controller: usize, controller_state: ControllerStates) {
@@ -69,40 +404,152 @@ impl State {
         &self.controller_states[controller]
     }
 
+    /// The graph of states in the history, if it is causal.
+    pub fn history_graph(&self) -> Option<HistoryGraph> {
+        self.states.causal().map(|c| c.graph())
+    }
+
     pub fn latest(&self) -> Cow<StateView> {
-        self.states.state_at(&self.max_revision())
+        self.view_at(&self.max_revision())
+    }
+}
+
+/// Whether the controller is an autoscaler, leader-elected or not.
+fn is_autoscaler(controller: &ControllerStates) -> bool {
+    match controller {
+        ControllerStates::HPA(_) => true,
+        ControllerStates::LeaderElected(c) => is_autoscaler(&c.inner),
+        _ => false,
+    }
+}
+
+impl Representative for State {
+    /// Sort the states of interchangeable controllers, those of the same kind that are next to
+    /// each other, so that states which only differ in which of them did the work are explored
+    /// once.
+    ///
+    /// Node controllers are bound to their node by name, so nodes are sorted along with their
+    /// controllers and renamed to match, moving the pods bound to them too. Pods themselves are
+    /// left alone as their names carry the revision they were created at. Leader-elected
+    /// controllers are bound to their identity so are left in place, as are all controllers when
+    /// reads depend on which replica a controller is connected to.
+    fn representative(&self) -> Self {
+        if self.states.replicated().is_some() {
+            return self.clone();
+        }
+        let latest = self.latest();
+        let sort_key = |i: usize| {
+            (
+                stateright::fingerprint(&self.controller_states[i]),
+                self.states
+                    .informer()
+                    .map(|inf| stateright::fingerprint(inf.cache(i))),
+            )
+        };
+        // the k-th node controller runs node-k
+        let node_sort_key = |i: usize, k: usize| {
+            let name = format!("node-{k}");
+            (
+                stateright::fingerprint(&self.controller_states[i]),
+                stateright::fingerprint(&latest.anonymous_node(&name)),
+                self.states
+                    .informer()
+                    .map(|inf| stateright::fingerprint(&inf.cache(i).anonymous_node(&name))),
+            )
+        };
+        let mut order: Vec<usize> = (0..self.controller_states.len()).collect();
+        let mut renames = BTreeMap::new();
+        let mut start = 0;
+        while start < order.len() {
+            let kind = std::mem::discriminant(&self.controller_states[start]);
+            let mut end = start + 1;
+            while end < order.len() && std::mem::discriminant(&self.controller_states[end]) == kind
+            {
+                end += 1;
+            }
+            match self.controller_states[start] {
+                ControllerStates::LeaderElected(_) => {}
+                ControllerStates::Node(_) => {
+                    order[start..end].sort_by_key(|i| node_sort_key(*i, i - start));
+                    for (k, i) in order[start..end].iter().enumerate() {
+                        if *i != start + k {
+                            renames.insert(format!("node-{}", i - start), format!("node-{k}"));
+                        }
+                    }
+                }
+                _ => order[start..end].sort_by_key(|i| sort_key(*i)),
+            }
+            start = end;
+        }
+
+        let mut state = self.clone();
+        state.controller_states = order
+            .iter()
+            .map(|i| self.controller_states[*i].clone())
+            .collect();
+        if let Some(inf) = state.states.informer_mut() {
+            inf.permute_informers(&order);
+        }
+        if let Some(rejection) = &mut state.rejection {
+            rejection.controller = rejection
+                .controller
+                .map(|c| order.iter().position(|i| *i == c).unwrap());
+        }
+        if !renames.is_empty() {
+            state.states.map_states(&mut |s| s.rename_nodes(&renames));
+        }
+        state
     }
 }
 
 #[derive(derivative::Derivative)]
-#[derivative(PartialEq, Hash)]
-#[derive(Default, Clone, Debug, Eq, PartialOrd, Ord)]
+#[derivative(PartialEq, Hash, PartialOrd, Ord)]
+#[derive(Default, Clone, Debug, Eq)]
 pub struct StateView {
     // Ignore the revision field as we just care whether the rest of the state is the same.
-    #[derivative(PartialEq = "ignore", Hash = "ignore")]
+    #[derivative(
+        PartialEq = "ignore",
+        Hash = "ignore",
+        PartialOrd = "ignore",
+        Ord = "ignore"
+    )]
     pub revision: Revision,
-    pub state: RawState,
+    /// The time when the view is read, rather than part of the state.
+    #[derivative(
+        PartialEq = "ignore",
+        Hash = "ignore",
+        PartialOrd = "ignore",
+        Ord = "ignore"
+    )]
+    pub now: Time,
+    /// Shared so that views of the same state read at different times don't copy it.
+    pub state: Arc<RawState>,
 }
 
 impl From<RawState> for StateView {
     fn from(value: RawState) -> Self {
         StateView {
-            state: value,
+            state: Arc::new(value),
             ..Default::default()
         }
     }
 }
 
-#[derive(Default, Clone, Debug, Eq, PartialOrd, Ord, PartialEq, Hash)]
+#[derive(Default, Clone, Debug, Eq, PartialOrd, Ord, PartialEq, Hash, Serialize)]
 pub struct RawState {
     pub nodes: Resources<Node>,
     pub pods: Resources<Pod>,
     pub replicasets: Resources<ReplicaSet>,
     pub deployments: Resources<Deployment>,
     pub statefulsets: Resources<StatefulSet>,
+    pub daemonsets: Resources<DaemonSet>,
     pub controller_revisions: Resources<ControllerRevision>,
     pub persistent_volume_claims: Resources<PersistentVolumeClaim>,
     pub jobs: Resources<Job>,
+    pub cronjobs: Resources<CronJob>,
+    pub horizontal_pod_autoscalers: Resources<HorizontalPodAutoscaler>,
+    pub leases: Resources<Lease>,
+    pub namespaces: Resources<Namespace>,
 }
 
 impl RawState {
@@ -114,7 +561,7 @@ impl RawState {
     pub fn set_pods(&mut self, pods: impl IntoIterator<Item = Pod>) -> &mut Self {
         for pod in pods {
             let revision = pod.metadata.resource_version.clone();
-            self.pods.create(pod, revision).unwrap();
+            self.pods.create(pod, revision, Time::default()).unwrap();
         }
         self
     }
@@ -130,7 +577,9 @@ impl RawState {
     ) -> &mut Self {
         for replicaset in replicasets {
             let revision = replicaset.metadata.resource_version.clone();
-            self.replicasets.create(replicaset, revision).unwrap();
+            self.replicasets
+                .create(replicaset, revision, Time::default())
+                .unwrap();
         }
         self
     }
@@ -146,7 +595,9 @@ impl RawState {
     ) -> &mut Self {
         for deployment in deployments {
             let revision = deployment.metadata.resource_version.clone();
-            self.deployments.create(deployment, revision).unwrap();
+            self.deployments
+                .create(deployment, revision, Time::default())
+                .unwrap();
         }
         self
     }
@@ -165,7 +616,24 @@ impl RawState {
     ) -> &mut Self {
         for statefulset in statefulsets {
             let revision = statefulset.metadata.resource_version.clone();
-            self.statefulsets.create(statefulset, revision).unwrap();
+            self.statefulsets
+                .create(statefulset, revision, Time::default())
+                .unwrap();
+        }
+        self
+    }
+
+    pub fn with_daemonsets(mut self, daemonsets: impl IntoIterator<Item = DaemonSet>) -> Self {
+        self.set_daemonsets(daemonsets);
+        self
+    }
+
+    pub fn set_daemonsets(&mut self, daemonsets: impl IntoIterator<Item = DaemonSet>) -> &mut Self {
+        for daemonset in daemonsets {
+            let revision = daemonset.metadata.resource_version.clone();
+            self.daemonsets
+                .create(daemonset, revision, Time::default())
+                .unwrap();
         }
         self
     }
@@ -178,7 +646,43 @@ impl RawState {
     pub fn set_jobs(&mut self, jobs: impl IntoIterator<Item = Job>) -> &mut Self {
         for job in jobs {
             let revision = job.metadata.resource_version.clone();
-            self.jobs.create(job, revision).unwrap();
+            self.jobs.create(job, revision, Time::default()).unwrap();
+        }
+        self
+    }
+
+    pub fn with_cronjobs(mut self, cronjobs: impl IntoIterator<Item = CronJob>) -> Self {
+        self.set_cronjobs(cronjobs);
+        self
+    }
+
+    pub fn set_cronjobs(&mut self, cronjobs: impl IntoIterator<Item = CronJob>) -> &mut Self {
+        for cronjob in cronjobs {
+            let revision = cronjob.metadata.resource_version.clone();
+            self.cronjobs
+                .create(cronjob, revision, Time::default())
+                .unwrap();
+        }
+        self
+    }
+
+    pub fn with_horizontal_pod_autoscalers(
+        mut self,
+        hpas: impl IntoIterator<Item = HorizontalPodAutoscaler>,
+    ) -> Self {
+        self.set_horizontal_pod_autoscalers(hpas);
+        self
+    }
+
+    pub fn set_horizontal_pod_autoscalers(
+        &mut self,
+        hpas: impl IntoIterator<Item = HorizontalPodAutoscaler>,
+    ) -> &mut Self {
+        for hpa in hpas {
+            let revision = hpa.metadata.resource_version.clone();
+            self.horizontal_pod_autoscalers
+                .create(hpa, revision, Time::default())
+                .unwrap();
         }
         self
     }
@@ -191,11 +695,257 @@ impl RawState {
     pub fn set_nodes(&mut self, nodes: impl IntoIterator<Item = Node>) -> &mut Self {
         for node in nodes {
             let revision = node.metadata.resource_version.clone();
-            self.nodes.create(node, revision).unwrap();
+            self.nodes.create(node, revision, Time::default()).unwrap();
         }
         self
     }
 
+    pub fn with_persistent_volume_claims(
+        mut self,
+        pvcs: impl IntoIterator<Item = PersistentVolumeClaim>,
+    ) -> Self {
+        self.set_persistent_volume_claims(pvcs);
+        self
+    }
+
+    pub fn set_persistent_volume_claims(
+        &mut self,
+        pvcs: impl IntoIterator<Item = PersistentVolumeClaim>,
+    ) -> &mut Self {
+        for pvc in pvcs {
+            let revision = pvc.metadata.resource_version.clone();
+            self.persistent_volume_claims
+                .create(pvc, revision, Time::default())
+                .unwrap();
+        }
+        self
+    }
+
+    pub fn with_leases(mut self, leases: impl IntoIterator<Item = Lease>) -> Self {
+        self.set_leases(leases);
+        self
+    }
+
+    pub fn set_leases(&mut self, leases: impl IntoIterator<Item = Lease>) -> &mut Self {
+        for lease in leases {
+            let revision = lease.metadata.resource_version.clone();
+            self.leases
+                .create(lease, revision, Time::default())
+                .unwrap();
+        }
+        self
+    }
+
+    pub fn with_namespaces(mut self, namespaces: impl IntoIterator<Item = Namespace>) -> Self {
+        self.set_namespaces(namespaces);
+        self
+    }
+
+    pub fn set_namespaces(&mut self, namespaces: impl IntoIterator<Item = Namespace>) -> &mut Self {
+        for namespace in namespaces {
+            let revision = namespace.metadata.resource_version.clone();
+            self.namespaces
+                .create(namespace, revision, Time::default())
+                .unwrap();
+        }
+        self
+    }
+
+    /// Start deleting the namespace: mark it as terminating, so that nothing new can be created in
+    /// it, and delete the resources in it, respecting their finalizers.
+    ///
+    /// The namespace itself is removed once it is empty.
+    pub fn delete_namespace(
+        &mut self,
+        namespace: &str,
+        revision: Revision,
+        now: Time,
+    ) -> Result<(), ResourceError> {
+        let Some(mut ns) = self.namespaces.get("", namespace).cloned() else {
+            return Err(ResourceError::NotFound);
+        };
+        if ns.status.phase != NamespacePhase::Terminating {
+            ns.status.phase = NamespacePhase::Terminating;
+            ns.metadata.deletion_timestamp = Some(now);
+            self.namespaces.update(ns, revision.clone())?;
+        }
+        // pods are removed by their node once terminated
+        let pods = self
+            .pods
+            .in_namespace(namespace)
+            .filter(|p| p.metadata.deletion_timestamp.is_none())
+            .cloned()
+            .collect::<Vec<_>>();
+        for mut pod in pods {
+            pod.metadata.deletion_timestamp = Some(now);
+            self.pods.update(pod, revision.clone())?;
+        }
+        macro_rules! delete_all {
+            ($($kind:ident),*) => {
+                $(
+                    let resources = self
+                        .$kind
+                        .in_namespace(namespace)
+                        .filter(|r| r.metadata.deletion_timestamp.is_none())
+                        .cloned()
+                        .collect::<Vec<_>>();
+                    for resource in resources {
+                        self.$kind
+                            .delete(&resource, PropagationPolicy::Background, revision.clone(), now)?;
+                    }
+                )*
+            };
+        }
+        delete_all!(
+            replicasets,
+            deployments,
+            statefulsets,
+            daemonsets,
+            controller_revisions,
+            persistent_volume_claims,
+            jobs,
+            cronjobs,
+            horizontal_pod_autoscalers,
+            leases
+        );
+        self.remove_empty_namespaces();
+        Ok(())
+    }
+
+    /// Remove the terminating namespaces that have nothing left in them and no finalizers.
+    pub fn remove_empty_namespaces(&mut self) {
+        let empty = self
+            .namespaces
+            .iter()
+            .filter(|ns| {
+                ns.status.phase == NamespacePhase::Terminating
+                    && ns.spec.finalizers.is_empty()
+                    && ns.metadata.finalizers.is_empty()
+                    && self.namespace_is_empty(&ns.metadata.name)
+            })
+            .map(|ns| ns.metadata.name.clone())
+            .collect::<BTreeSet<_>>();
+        if !empty.is_empty() {
+            self.namespaces
+                .retain(|ns| !empty.contains(&ns.metadata.name));
+        }
+    }
+
+    fn namespace_is_empty(&self, namespace: &str) -> bool {
+        self.pods.in_namespace(namespace).next().is_none()
+            && self.replicasets.in_namespace(namespace).next().is_none()
+            && self.deployments.in_namespace(namespace).next().is_none()
+            && self.statefulsets.in_namespace(namespace).next().is_none()
+            && self.daemonsets.in_namespace(namespace).next().is_none()
+            && self
+                .controller_revisions
+                .in_namespace(namespace)
+                .next()
+                .is_none()
+            && self
+                .persistent_volume_claims
+                .in_namespace(namespace)
+                .next()
+                .is_none()
+            && self.jobs.in_namespace(namespace).next().is_none()
+            && self.cronjobs.in_namespace(namespace).next().is_none()
+            && self
+                .horizontal_pod_autoscalers
+                .in_namespace(namespace)
+                .next()
+                .is_none()
+            && self.leases.in_namespace(namespace).next().is_none()
+    }
+
+    /// Whether the namespace is being deleted, so nothing new can be created in it.
+    fn namespace_terminating(&self, namespace: &str) -> bool {
+        self.namespaces
+            .get("", namespace)
+            .map_or(false, |ns| ns.status.phase == NamespacePhase::Terminating)
+    }
+
+    /// Rename nodes, along with the pods bound to them and daemonsets selecting them by hostname.
+    pub fn rename_nodes(&mut self, renames: &BTreeMap<String, String>) {
+        let rename = |name: &mut String| {
+            if let Some(new_name) = renames.get(name) {
+                *name = new_name.clone();
+            }
+        };
+        self.nodes = self
+            .nodes
+            .iter()
+            .map(|node| {
+                let mut node = node.clone();
+                if node.metadata.uid == node.metadata.name {
+                    rename(&mut node.metadata.uid);
+                }
+                rename(&mut node.metadata.name);
+                if let Some(hostname) = node.metadata.labels.get_mut(LABEL_HOSTNAME) {
+                    rename(hostname);
+                }
+                node
+            })
+            .collect();
+        self.pods = self
+            .pods
+            .iter()
+            .map(|pod| {
+                let mut pod = pod.clone();
+                if let Some(node_name) = &mut pod.spec.node_name {
+                    rename(node_name);
+                }
+                if let Some(hostname) = pod.spec.node_selector.get_mut(LABEL_HOSTNAME) {
+                    rename(hostname);
+                }
+                pod
+            })
+            .collect();
+        self.daemonsets = self
+            .daemonsets
+            .iter()
+            .map(|ds| {
+                let mut ds = ds.clone();
+                if let Some(hostname) = ds.spec.template.spec.node_selector.get_mut(LABEL_HOSTNAME)
+                {
+                    rename(hostname);
+                }
+                ds
+            })
+            .collect();
+    }
+
+    /// The node, the pods bound to it and the daemonsets selecting it, without its name, to
+    /// compare nodes by.
+    fn anonymous_node(&self, name: &str) -> (Option<Node>, Vec<Pod>, Vec<&str>) {
+        let node = self.nodes.get("", name).map(|node| {
+            let mut node = node.clone();
+            node.metadata.name.clear();
+            node.metadata.uid.clear();
+            node.metadata.labels.remove(LABEL_HOSTNAME);
+            node
+        });
+        let pods = self
+            .pods_for_node(name)
+            .into_iter()
+            .map(|pod| {
+                let mut pod = pod.clone();
+                pod.spec.node_name = None;
+                pod.spec.node_selector.remove(LABEL_HOSTNAME);
+                pod
+            })
+            .collect();
+        let daemonsets = self
+            .daemonsets
+            .iter()
+            .filter(|ds| {
+                let selector = &ds.spec.template.spec.node_selector;
+                selector.get(LABEL_HOSTNAME).map_or(false, |n| n == name)
+            })
+            .map(|ds| ds.metadata.name.as_str())
+            .collect();
+        (node, pods, daemonsets)
+    }
+
     pub fn pods_for_node(&self, node: &str) -> Vec<&Pod> {
         self.pods
             .iter()
@@ -203,16 +953,59 @@ impl RawState {
             .collect()
     }
 
+    /// Apply a single watch event to move this state towards the target at the given revision,
+    /// returning whether there was a difference to apply.
+    ///
+    /// The oldest change or deletion across all kinds is applied first, so events arrive in commit
+    /// order rather than grouped by kind.
+    pub fn apply_watch_event(&mut self, target: &Self, revision: &Revision) -> bool {
+        macro_rules! apply_oldest {
+            ($($field:ident),*) => {{
+                let next = [$(self.$field.next_watch_event(&target.$field, revision)),*];
+                let Some(oldest) = next.into_iter().flatten().min() else {
+                    return false;
+                };
+                $(
+                    if self.$field.next_watch_event(&target.$field, revision).as_ref() == Some(&oldest) {
+                        return self.$field.apply_watch_event(&target.$field, revision);
+                    }
+                )*
+                unreachable!()
+            }};
+        }
+        apply_oldest!(
+            nodes,
+            pods,
+            replicasets,
+            deployments,
+            statefulsets,
+            daemonsets,
+            controller_revisions,
+            persistent_volume_claims,
+            jobs,
+            cronjobs,
+            horizontal_pod_autoscalers,
+            leases,
+            namespaces
+        )
+    }
+
     pub fn merge(&mut self, other: &Self) {
         self.nodes.merge(&other.nodes);
         self.pods.merge(&other.pods);
         self.replicasets.merge(&other.replicasets);
         self.deployments.merge(&other.deployments);
         self.statefulsets.merge(&other.statefulsets);
+        self.daemonsets.merge(&other.daemonsets);
         self.controller_revisions.merge(&other.controller_revisions);
         self.persistent_volume_claims
             .merge(&other.persistent_volume_claims);
         self.jobs.merge(&other.jobs);
+        self.cronjobs.merge(&other.cronjobs);
+        self.horizontal_pod_autoscalers
+            .merge(&other.horizontal_pod_autoscalers);
+        self.leases.merge(&other.leases);
+        self.namespaces.merge(&other.namespaces);
     }
 }
 
@@ -226,151 +1019,238 @@ impl Deref for StateView {
 
 impl DerefMut for StateView {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.state
+        Arc::make_mut(&mut self.state)
     }
 }
 
 impl StateView {
-    /// Apply the operation to the state, using the new revision.
+    /// Apply the operation to the state, using the new revision and stamping the resources it
+    /// creates or deletes with the given time.
     ///
-    /// On success it applies the new revision and returns true.
-    /// On failure it does nothing and returns false.
-    #[must_use]
-    pub fn apply_operation(&mut self, operation: ControllerAction, new_revision: Revision) -> bool {
+    /// On success it applies the new revision and returns the lease the operation was fenced on if
+    /// the writer no longer held it.
+    /// On failure it does nothing and returns why the operation was rejected.
+    pub fn apply_operation(
+        &mut self,
+        operation: ControllerAction,
+        new_revision: Revision,
+        now: Time,
+    ) -> Result<Option<String>, RejectionReason> {
+        let lost_lease = match &operation {
+            ControllerAction::Fenced {
+                namespace,
+                lease,
+                holder,
+                transitions,
+                ..
+            } if !self.lease_held(namespace, lease, holder, *transitions) => Some(lease.clone()),
+            _ => None,
+        };
         let mut s = self.clone();
-        match s.apply_operation_inner(operation, new_revision.clone()) {
-            Ok(()) => {
-                s.revision = new_revision;
-                *self = s;
-                true
-            }
-            Err(()) => {
-                // don't update our self, basically abort the transaction so no changes
-                false
-            }
-        }
+        // on failure don't update our self, basically abort the transaction so no changes
+        s.apply_operation_inner(operation, new_revision.clone(), now)?;
+        s.remove_empty_namespaces();
+        s.revision = new_revision;
+        *self = s;
+        Ok(lost_lease)
+    }
+
+    /// Whether the lease in the namespace is held by the holder, and hasn't changed hands since
+    /// they acquired it.
+    fn lease_held(&self, namespace: &str, lease: &str, holder: &str, transitions: u32) -> bool {
+        self.leases.get(namespace, lease).map_or(false, |l| {
+            l.spec.holder_identity.as_deref() == Some(holder)
+                && l.spec.lease_transitions == transitions
+        })
     }
 
     fn apply_operation_inner(
         &mut self,
         operation: ControllerAction,
         new_revision: Revision,
-    ) -> Result<(), ()> {
+        now: Time,
+    ) -> Result<(), RejectionReason> {
+        if let Some(namespace) = created_namespace(&operation) {
+            if self.namespace_terminating(namespace) {
+                return Err(ResourceError::NamespaceTerminating.into());
+            }
+        }
         match operation {
             ControllerAction::NodeJoin(name, capacity) => {
-                self.nodes
-                    .create(
-                        Node {
-                            metadata: utils::metadata(name.clone()),
-                            spec: crate::resources::NodeSpec {
-                                taints: Vec::new(),
-                                unschedulable: false,
-                            },
-                            status: crate::resources::NodeStatus {
-                                capacity: capacity.clone(),
-                                allocatable: Some(capacity.clone()),
-                                conditions: vec![NodeCondition {
-                                    r#type: NodeConditionType::Ready,
-                                    status: ConditionStatus::True,
-                                    ..Default::default()
-                                }],
-                            },
+                let mut metadata = utils::metadata(name.clone());
+                metadata
+                    .labels
+                    .insert(LABEL_HOSTNAME.to_owned(), name.clone());
+                self.nodes.create(
+                    Node {
+                        metadata,
+                        spec: crate::resources::NodeSpec {
+                            taints: Vec::new(),
+                            unschedulable: false,
+                        },
+                        status: crate::resources::NodeStatus {
+                            capacity: capacity.clone(),
+                            allocatable: Some(capacity.clone()),
+                            conditions: vec![NodeCondition {
+                                r#type: NodeConditionType::Ready,
+                                status: ConditionStatus::True,
+                                ..Default::default()
+                            }],
                         },
-                        new_revision,
-                    )
-                    .map_err(|_| ())?;
+                    },
+                    new_revision,
+                    now,
+                )?;
             }
             ControllerAction::DeleteNode(name) => {
-                self.nodes.remove(&name);
+                self.nodes.remove(&name)?;
             }
             ControllerAction::CreatePod(mut pod) => {
                 pod.metadata.uid = self.revision.to_string();
                 self.fill_name(&mut pod);
-                self.pods.create(pod, new_revision).map_err(|_| ())?;
+                self.pods.create(pod, new_revision, now)?;
             }
             ControllerAction::UpdatePod(pod) => {
-                self.pods.update(pod, new_revision).map_err(|_| ())?;
+                self.pods.update(pod, new_revision)?;
             }
             ControllerAction::SoftDeletePod(mut pod) => {
                 // marked for deletion
-                pod.metadata.deletion_timestamp = Some(now());
-                self.pods.update(pod, new_revision).map_err(|_| ())?;
+                pod.metadata.deletion_timestamp = Some(now);
+                self.pods.update(pod, new_revision)?;
             }
             ControllerAction::HardDeletePod(pod) => {
-                self.pods.remove(&pod);
+                self.pods.remove(&pod)?;
             }
             ControllerAction::UpdateDeployment(dep) => {
-                self.deployments.update(dep, new_revision).map_err(|_| ())?;
+                self.deployments.update_finalizing(dep, new_revision)?;
             }
             ControllerAction::RequeueDeployment(_dep) => {
                 // skip
             }
             ControllerAction::UpdateDeploymentStatus(dep) => {
-                self.deployments.update(dep, new_revision).map_err(|_| ())?;
+                self.deployments.update(dep, new_revision)?;
+            }
+            ControllerAction::DeleteDeployment(dep, policy) => {
+                self.deployments.delete(&dep, policy, new_revision, now)?;
             }
             ControllerAction::CreateReplicaSet(mut rs) => {
                 rs.metadata.uid = self.revision.to_string();
                 self.fill_name(&mut rs);
-                self.replicasets.create(rs, new_revision).map_err(|_| ())?;
+                self.replicasets.create(rs, new_revision, now)?;
             }
             ControllerAction::UpdateReplicaSet(rs) => {
-                self.replicasets.update(rs, new_revision).map_err(|_| ())?;
+                self.replicasets.update_finalizing(rs, new_revision)?;
             }
             ControllerAction::UpdateReplicaSetStatus(rs) => {
-                self.replicasets.update(rs, new_revision).map_err(|_| ())?;
+                self.replicasets.update(rs, new_revision)?;
             }
             ControllerAction::UpdateReplicaSets(rss) => {
                 for rs in rss {
-                    self.replicasets
-                        .update(rs, new_revision.clone())
-                        .map_err(|_| ())?;
+                    self.replicasets.update(rs, new_revision.clone())?;
                 }
             }
             ControllerAction::UpdateStatefulSet(sts) => {
-                self.statefulsets
-                    .update(sts, new_revision)
-                    .map_err(|_| ())?;
+                self.statefulsets.update_finalizing(sts, new_revision)?;
             }
             ControllerAction::UpdateStatefulSetStatus(sts) => {
-                self.statefulsets
-                    .update(sts, new_revision)
-                    .map_err(|_| ())?;
+                self.statefulsets.update(sts, new_revision)?;
+            }
+            ControllerAction::UpdateDaemonSet(ds) => {
+                self.daemonsets.update_finalizing(ds, new_revision)?;
+            }
+            ControllerAction::UpdateDaemonSetStatus(ds) => {
+                self.daemonsets.update(ds, new_revision)?;
             }
             ControllerAction::CreateControllerRevision(mut cr) => {
                 cr.metadata.uid = self.revision.to_string();
                 self.fill_name(&mut cr);
-                self.controller_revisions
-                    .create(cr, new_revision)
-                    .map_err(|_| ())?;
+                self.controller_revisions.create(cr, new_revision, now)?;
             }
             ControllerAction::UpdateControllerRevision(cr) => {
                 self.controller_revisions
-                    .update(cr, new_revision)
-                    .map_err(|_| ())?;
+                    .update_finalizing(cr, new_revision)?;
             }
             ControllerAction::DeleteControllerRevision(cr) => {
-                self.controller_revisions.remove(&cr);
+                self.controller_revisions.delete(
+                    &cr,
+                    PropagationPolicy::Background,
+                    new_revision,
+                    now,
+                )?;
             }
-            ControllerAction::DeleteReplicaSet(rs) => {
-                self.replicasets.remove(&rs);
+            ControllerAction::DeleteReplicaSet(rs, policy) => {
+                self.replicasets.delete(&rs, policy, new_revision, now)?;
             }
             ControllerAction::CreatePersistentVolumeClaim(mut pvc) => {
                 pvc.metadata.uid = self.revision.to_string();
                 self.fill_name(&mut pvc);
                 self.persistent_volume_claims
-                    .create(pvc, new_revision)
-                    .map_err(|_| ())?;
+                    .create(pvc, new_revision, now)?;
             }
             ControllerAction::UpdatePersistentVolumeClaim(pvc) => {
-                self.persistent_volume_claims
-                    .update(pvc, new_revision)
-                    .map_err(|_| ())?;
+                self.persistent_volume_claims.update(pvc, new_revision)?;
             }
             ControllerAction::UpdateJobStatus(job) => {
-                self.jobs.update(job, new_revision).map_err(|_| ())?;
+                self.jobs.update(job, new_revision)?;
             }
             ControllerAction::UpdateJob(job) => {
-                self.jobs.update(job, new_revision).map_err(|_| ())?;
+                self.jobs.update_finalizing(job, new_revision)?;
+            }
+            ControllerAction::CreateJob(mut job) => {
+                job.metadata.uid = self.revision.to_string();
+                self.fill_name(&mut job);
+                // THEMELIOS: the API server generates the selector for jobs that don't set one
+                // manually, so that jobs created from the same template don't share pods
+                if job.spec.selector.match_labels.is_empty() {
+                    let uid = job.metadata.uid.clone();
+                    job.spec
+                        .selector
+                        .match_labels
+                        .insert(CONTROLLER_UID_LABEL.to_owned(), uid.clone());
+                    job.spec
+                        .template
+                        .metadata
+                        .labels
+                        .insert(CONTROLLER_UID_LABEL.to_owned(), uid);
+                }
+                self.jobs.create(job, new_revision, now)?;
+            }
+            ControllerAction::DeleteJob(job) => {
+                self.jobs
+                    .delete(&job, PropagationPolicy::Background, new_revision, now)?;
+            }
+            ControllerAction::UpdateCronJob(cj) => {
+                self.cronjobs.update_finalizing(cj, new_revision)?;
+            }
+            ControllerAction::UpdateCronJobStatus(cj) => {
+                self.cronjobs.update(cj, new_revision)?;
+            }
+            ControllerAction::UpdateHorizontalPodAutoscalerStatus(hpa) => {
+                self.horizontal_pod_autoscalers.update(hpa, new_revision)?;
+            }
+            ControllerAction::CreateLease(lease) => {
+                self.leases.create(lease, new_revision, now)?;
+            }
+            ControllerAction::UpdateLease(lease) => {
+                self.leases.update(lease, new_revision)?;
+            }
+            ControllerAction::DeleteNamespace(ns) => {
+                self.delete_namespace(&ns.metadata.name, new_revision, now)?;
+            }
+            ControllerAction::Fenced {
+                namespace,
+                lease,
+                holder,
+                transitions,
+                enforce,
+                action,
+            } => {
+                // a write let through from a candidate that has lost the lease is a split brain,
+                // which is returned from `apply_operation` for the model to record
+                if enforce && !self.lease_held(&namespace, &lease, &holder, transitions) {
+                    return Err(RejectionReason::LeaseNotHeld);
+                }
+                self.apply_operation_inner(*action, new_revision, now)?;
             }
         }
         Ok(())
@@ -406,6 +1286,19 @@ impl StateView {
 
     pub fn merge(&mut self, other: &Self) {
         self.revision.merge(&other.revision);
-        self.state.merge(&other.state);
+        Arc::make_mut(&mut self.state).merge(&other.state);
+    }
+}
+
+/// The namespace that the operation creates a resource in, if it creates one.
+fn created_namespace(operation: &ControllerAction) -> Option<&str> {
+    match operation {
+        ControllerAction::CreatePod(pod) => Some(namespace_of(pod)),
+        ControllerAction::CreateReplicaSet(rs) => Some(namespace_of(rs)),
+        ControllerAction::CreateControllerRevision(cr) => Some(namespace_of(cr)),
+        ControllerAction::CreatePersistentVolumeClaim(pvc) => Some(namespace_of(pvc)),
+        ControllerAction::CreateJob(job) => Some(namespace_of(job)),
+        ControllerAction::CreateLease(lease) => Some(namespace_of(lease)),
+        _ => None,
     }
 }
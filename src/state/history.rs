@@ -2,27 +2,36 @@ use std::{
     borrow::Cow,
     fmt::Display,
     ops::{Deref, DerefMut},
+    str::FromStr,
     sync::Arc,
 };
 
+use serde::Deserialize;
+
 use crate::abstract_model::Change;
+use crate::resources::Time;
 
 use self::{
     causal::CausalHistory, synchronous::SynchronousHistory,
     monotonic_session::MonotonicSessionHistory, optimistic::OptimisticLinearHistory,
-    resettable_session::ResettableSessionHistory,
+    resettable_session::ResettableSessionHistory, informer::InformerHistory,
+    replicated::ReplicatedHistory,
 };
 
-use super::{revision::Revision, RawState, StateView};
+use super::{revision::Revision, RawState, RejectionReason, StateView};
 
 pub mod causal;
 pub mod synchronous;
 pub mod monotonic_session;
 pub mod optimistic;
 pub mod resettable_session;
+pub mod graph;
+pub mod informer;
+pub mod replicated;
 
 /// Consistency level for viewing the state with.
-#[derive(Default, Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Default, Clone, Debug, PartialEq, Eq, Hash, Deserialize)]
+#[serde(try_from = "String")]
 pub enum ConsistencySetup {
     /// Always work off the latest state.
     /// Linearizable reads.
@@ -45,32 +54,155 @@ pub enum ConsistencySetup {
     OptimisticLinear,
     /// Apply changes to a causal graph.
     Causal,
+    /// Commit changes to a linear log that is replicated to a number of replicas, which can be
+    /// partitioned.
+    /// Serializable reads from the connected replica.
+    /// Linearizable writes, rejected from a minority replica.
+    Replicated { replicas: usize },
+    /// Each controller reads from its own informer cache, which receives watch events one at a
+    /// time.
+    /// Cached reads.
+    /// Linearizable writes.
+    Informer,
 }
 
 impl Display for ConsistencySetup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConsistencySetup::Synchronous => write!(f, "synchronous"),
+            ConsistencySetup::MonotonicSession => write!(f, "monotonic-session"),
+            ConsistencySetup::ResettableSession => write!(f, "resettable-session"),
+            ConsistencySetup::OptimisticLinear => write!(f, "optimistic-linear"),
+            ConsistencySetup::Causal => write!(f, "causal"),
+            ConsistencySetup::Replicated { replicas } => write!(f, "replicated-{replicas}"),
+            ConsistencySetup::Informer => write!(f, "informer"),
+        }
+    }
+}
+
+impl FromStr for ConsistencySetup {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "synchronous" => Ok(ConsistencySetup::Synchronous),
+            "monotonic-session" => Ok(ConsistencySetup::MonotonicSession),
+            "resettable-session" => Ok(ConsistencySetup::ResettableSession),
+            "optimistic-linear" => Ok(ConsistencySetup::OptimisticLinear),
+            "causal" => Ok(ConsistencySetup::Causal),
+            "informer" => Ok(ConsistencySetup::Informer),
+            _ => {
+                if let Some(replicas) = s.strip_prefix("replicated-") {
+                    match replicas.parse() {
+                        Ok(0) => {
+                            return Err(
+                                "replicated consistency needs at least one replica".to_owned()
+                            )
+                        }
+                        Ok(replicas) => return Ok(ConsistencySetup::Replicated { replicas }),
+                        Err(_) => {}
+                    }
+                }
+                Err(format!(
+                    "unknown consistency level {s:?}, expected one of synchronous, monotonic-session, resettable-session, optimistic-linear, causal, replicated-<replicas> or informer"
+                ))
+            }
+        }
+    }
+}
+
+impl TryFrom<String> for ConsistencySetup {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl ConsistencySetup {
+    /// Whether a client can use this consistency level when the datastore runs with the given one.
+    ///
+    /// Clients can always ask for reads at least as strong as the datastore's linear ones (latest,
+    /// or monotonic from their session), but the optimistic and causal levels change how writes
+    /// are applied so must match the datastore.
+    pub fn can_override(&self, datastore: &ConsistencySetup) -> bool {
+        self == datastore
+            || matches!(
+                self,
+                ConsistencySetup::Synchronous | ConsistencySetup::MonotonicSession
+            )
+    }
+
+    /// The read mode a client gets when asking for this consistency level.
+    pub fn read_mode(&self) -> ReadMode {
+        match self {
+            ConsistencySetup::Synchronous => ReadMode::Latest,
+            ConsistencySetup::MonotonicSession => ReadMode::SessionMonotonic,
+            ConsistencySetup::ResettableSession
+            | ConsistencySetup::OptimisticLinear
+            | ConsistencySetup::Causal
+            | ConsistencySetup::Replicated { .. }
+            | ConsistencySetup::Informer => ReadMode::AnyValid,
+        }
+    }
+}
+
+/// How a client reads from the history, independent of how the datastore applies writes.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReadMode {
+    /// Only read the latest state, like a quorum read.
+    Latest,
+    /// Read any state that derives from the last one seen, or the latest when no session is
+    /// present.
+    SessionMonotonic,
+    /// Read any state that is valid under the consistency level of the datastore.
+    #[default]
+    AnyValid,
+}
+
+impl Display for ReadMode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
             "{}",
             match self {
-                ConsistencySetup::Synchronous => "synchronous",
-                ConsistencySetup::MonotonicSession => "monotonic-session",
-                ConsistencySetup::ResettableSession => "resettable-session",
-                ConsistencySetup::OptimisticLinear => "optimistic-linear",
-                ConsistencySetup::Causal => "causal",
+                ReadMode::Latest => "latest",
+                ReadMode::SessionMonotonic => "session-monotonic",
+                ReadMode::AnyValid => "any-valid",
             }
         )
     }
 }
 
+impl FromStr for ReadMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "latest" => Ok(ReadMode::Latest),
+            "session-monotonic" => Ok(ReadMode::SessionMonotonic),
+            "any-valid" => Ok(ReadMode::AnyValid),
+            _ => Err(format!(
+                "unknown read mode {s:?}, expected one of latest, session-monotonic or any-valid"
+            )),
+        }
+    }
+}
+
 pub trait History {
-    fn add_change(&mut self, change: Change);
+    /// Apply the change to the history at the given time, returning the lease it was fenced on if
+    /// the writer no longer held it, or return why the datastore rejected it.
+    fn add_change(&mut self, change: Change, now: Time) -> Result<Option<String>, RejectionReason>;
 
     fn max_revision(&self) -> Revision;
 
     fn state_at(&self, revision: &Revision) -> Cow<'_, StateView>;
 
     fn valid_revisions(&self, min_revision: Option<&Revision>) -> Vec<Revision>;
+
+    /// Apply the function to every state in the history, such as to rename resources throughout.
+    fn map_states(&mut self, f: &mut dyn FnMut(&mut RawState));
 }
 
 #[derive(Clone, PartialEq, Eq, Hash, Debug)]
@@ -88,6 +220,12 @@ pub enum StateHistory {
     /// Optimistic writes.
     OptimisticLinear(OptimisticLinearHistory),
     Causal(CausalHistory),
+    /// Serializable reads from a replica.
+    /// Linearizable writes, rejected from a minority replica.
+    Replicated(ReplicatedHistory),
+    /// Cached reads.
+    /// Linearizable writes.
+    Informer(InformerHistory),
 }
 
 impl Default for StateHistory {
@@ -112,18 +250,100 @@ impl StateHistory {
                 Self::OptimisticLinear(OptimisticLinearHistory::new(initial_state))
             }
             ConsistencySetup::Causal => Self::Causal(CausalHistory::new(initial_state)),
+            ConsistencySetup::Replicated { replicas } => {
+                Self::Replicated(ReplicatedHistory::new(initial_state, replicas))
+            }
+            ConsistencySetup::Informer => Self::Informer(InformerHistory::new(initial_state)),
+        }
+    }
+}
+
+impl StateHistory {
+    /// Get the valid revisions for a controller reading with the given mode.
+    pub fn valid_revisions_with(
+        &self,
+        controller: usize,
+        read_mode: ReadMode,
+        min_revision: Option<&Revision>,
+    ) -> Vec<Revision> {
+        match self {
+            StateHistory::Replicated(s) => {
+                // reads depend on which replica the controller is connected to
+                return s.valid_revisions_for(controller, read_mode, min_revision);
+            }
+            StateHistory::Informer(s) if read_mode != ReadMode::Latest => {
+                // reads come from the controller's cache, unless it asks for a quorum read
+                return s.valid_revisions_for(controller);
+            }
+            _ => {}
+        }
+        match (read_mode, min_revision) {
+            (ReadMode::Latest, _) | (ReadMode::SessionMonotonic, None) => {
+                // only the latest state, if it hasn't been seen yet
+                let max = self.max_revision();
+                if min_revision == Some(&max) {
+                    Vec::new()
+                } else {
+                    vec![max]
+                }
+            }
+            (ReadMode::SessionMonotonic, Some(_)) | (ReadMode::AnyValid, _) => {
+                self.valid_revisions(min_revision)
+            }
+        }
+    }
+
+    /// The replicated history, if the state is replicated.
+    pub fn replicated(&self) -> Option<&ReplicatedHistory> {
+        match self {
+            StateHistory::Replicated(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// The replicated history, if the state is replicated.
+    pub fn replicated_mut(&mut self) -> Option<&mut ReplicatedHistory> {
+        match self {
+            StateHistory::Replicated(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// The causal history, if the state is causally consistent.
+    pub fn causal(&self) -> Option<&CausalHistory> {
+        match self {
+            StateHistory::Causal(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// The informer history, if controllers read through informers.
+    pub fn informer(&self) -> Option<&InformerHistory> {
+        match self {
+            StateHistory::Informer(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// The informer history, if controllers read through informers.
+    pub fn informer_mut(&mut self) -> Option<&mut InformerHistory> {
+        match self {
+            StateHistory::Informer(s) => Some(s),
+            _ => None,
         }
     }
 }
 
 impl History for StateHistory {
-    fn add_change(&mut self, change: Change) {
+    fn add_change(&mut self, change: Change, now: Time) -> Result<Option<String>, RejectionReason> {
         match self {
-            StateHistory::Synchronous(s) => s.add_change(change),
-            StateHistory::MonotonicSession(s) => s.add_change(change),
-            StateHistory::ResettableSession(s) => s.add_change(change),
-            StateHistory::OptimisticLinear(s) => s.add_change(change),
-            StateHistory::Causal(s) => s.add_change(change),
+            StateHistory::Synchronous(s) => s.add_change(change, now),
+            StateHistory::MonotonicSession(s) => s.add_change(change, now),
+            StateHistory::ResettableSession(s) => s.add_change(change, now),
+            StateHistory::OptimisticLinear(s) => s.add_change(change, now),
+            StateHistory::Causal(s) => s.add_change(change, now),
+            StateHistory::Replicated(s) => s.add_change(change, now),
+            StateHistory::Informer(s) => s.add_change(change, now),
         }
     }
 
@@ -134,6 +354,8 @@ impl History for StateHistory {
             StateHistory::ResettableSession(s) => s.max_revision(),
             StateHistory::OptimisticLinear(s) => s.max_revision(),
             StateHistory::Causal(s) => s.max_revision(),
+            StateHistory::Replicated(s) => s.max_revision(),
+            StateHistory::Informer(s) => s.max_revision(),
         }
     }
 
@@ -144,6 +366,8 @@ impl History for StateHistory {
             StateHistory::ResettableSession(s) => s.state_at(revision),
             StateHistory::OptimisticLinear(s) => s.state_at(revision),
             StateHistory::Causal(s) => s.state_at(revision),
+            StateHistory::Replicated(s) => s.state_at(revision),
+            StateHistory::Informer(s) => s.state_at(revision),
         }
     }
 
@@ -154,6 +378,20 @@ impl History for StateHistory {
             StateHistory::ResettableSession(s) => s.valid_revisions(min_revision),
             StateHistory::OptimisticLinear(s) => s.valid_revisions(min_revision),
             StateHistory::Causal(s) => s.valid_revisions(min_revision),
+            StateHistory::Replicated(s) => s.valid_revisions(min_revision),
+            StateHistory::Informer(s) => s.valid_revisions(min_revision),
+        }
+    }
+
+    fn map_states(&mut self, f: &mut dyn FnMut(&mut RawState)) {
+        match self {
+            StateHistory::Synchronous(s) => s.map_states(f),
+            StateHistory::MonotonicSession(s) => s.map_states(f),
+            StateHistory::ResettableSession(s) => s.map_states(f),
+            StateHistory::OptimisticLinear(s) => s.map_states(f),
+            StateHistory::Causal(s) => s.map_states(f),
+            StateHistory::Replicated(s) => s.map_states(f),
+            StateHistory::Informer(s) => s.map_states(f),
         }
     }
 }
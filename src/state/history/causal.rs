@@ -4,10 +4,14 @@ use bit_set::BitSet;
 
 use crate::{
     abstract_model::Change,
-    state::{revision::Revision, RawState, StateView},
+    resources::Time,
+    state::{revision::Revision, RawState, RejectionReason, StateView},
 };
 
-use super::{History, StatesVec};
+use super::{
+    graph::{HistoryGraph, HistoryNode},
+    History, StatesVec,
+};
 
 #[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub struct CausalHistory {
@@ -16,9 +20,14 @@ pub struct CausalHistory {
     heads: BTreeSet<usize>,
 }
 
-#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+#[derive(derivative::Derivative)]
+#[derivative(PartialEq, Hash)]
+#[derive(Clone, Eq, Debug)]
 struct CausalState {
     state: StateView,
+    /// The controller that made the change producing this state, only kept for debugging.
+    #[derivative(PartialEq = "ignore", Hash = "ignore")]
+    controller: Option<usize>,
     predecessors: Vec<usize>,
     successors: Vec<usize>,
     concurrent: BitSet<usize>,
@@ -31,6 +40,7 @@ impl CausalHistory {
         Self {
             states: StatesVec(imbl::vector![Arc::new(CausalState {
                 state: initial_state.into(),
+                controller: None,
                 predecessors: Vec::new(),
                 successors: Vec::new(),
                 concurrent: BitSet::default(),
@@ -38,10 +48,29 @@ impl CausalHistory {
             heads,
         }
     }
+
+    /// The graph of states, with which controller made each change and which states were
+    /// merged for it.
+    pub fn graph(&self) -> HistoryGraph {
+        let nodes = self
+            .states
+            .iter()
+            .enumerate()
+            .map(|(index, s)| HistoryNode {
+                index,
+                revision: s.state.revision.clone(),
+                controller: s.controller,
+                predecessors: s.predecessors.clone(),
+                concurrent: s.concurrent.iter().collect(),
+                head: self.heads.contains(&index),
+            })
+            .collect();
+        HistoryGraph { nodes }
+    }
 }
 
 impl History for CausalHistory {
-    fn add_change(&mut self, change: Change) {
+    fn add_change(&mut self, change: Change, now: Time) -> Result<Option<String>, RejectionReason> {
         let mut new_state = self.state_at(&change.revision).into_owned();
 
         let max_rev = self
@@ -52,35 +81,42 @@ impl History for CausalHistory {
             .revision
             .clone()
             .increment();
-        if new_state.apply_operation(change.operation, max_rev) {
-            // find the dependencies of the change
-            let predecessors = change.revision.components().to_owned();
-            let new_index = self.states.len();
-
-            let concurrent = self
-                .concurrent_many(&predecessors)
-                .collect::<BitSet<usize>>();
-            for c in &concurrent {
-                Arc::make_mut(&mut self.states[c])
-                    .concurrent
-                    .insert(new_index);
-            }
+        let lost_lease = new_state.apply_operation(change.operation, max_rev, now)?;
+        // find the dependencies of the change
+        let predecessors = change.revision.components().to_owned();
+        let new_index = self.states.len();
+
+        let concurrent = self
+            .concurrent_many(&predecessors)
+            .collect::<BitSet<usize>>();
+        for c in &concurrent {
+            Arc::make_mut(&mut self.states[c])
+                .concurrent
+                .insert(new_index);
+        }
 
-            for &p in &predecessors {
-                Arc::make_mut(&mut self.states[p])
-                    .successors
-                    .push(new_index);
-                self.heads.remove(&p);
-            }
+        for &p in &predecessors {
+            Arc::make_mut(&mut self.states[p])
+                .successors
+                .push(new_index);
+            self.heads.remove(&p);
+        }
 
-            self.heads.insert(new_index);
+        self.heads.insert(new_index);
 
-            self.states.push_back(Arc::new(CausalState {
-                state: new_state,
-                predecessors,
-                successors: Vec::new(),
-                concurrent,
-            }));
+        self.states.push_back(Arc::new(CausalState {
+            state: new_state,
+            controller: change.controller,
+            predecessors,
+            successors: Vec::new(),
+            concurrent,
+        }));
+        Ok(lost_lease)
+    }
+
+    fn map_states(&mut self, f: &mut dyn FnMut(&mut RawState)) {
+        for state in self.states.iter_mut() {
+            f(&mut Arc::make_mut(state).state);
         }
     }
 
@@ -0,0 +1,61 @@
+use serde::Serialize;
+
+use crate::state::revision::Revision;
+
+/// The graph of states kept by a history, for debugging.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct HistoryGraph {
+    pub nodes: Vec<HistoryNode>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct HistoryNode {
+    pub index: usize,
+    pub revision: Revision,
+    /// The controller that made the change producing this state, if any.
+    pub controller: Option<usize>,
+    /// The states that were merged to produce the state the change was made against.
+    pub predecessors: Vec<usize>,
+    /// The states that were made without seeing this one.
+    pub concurrent: Vec<usize>,
+    /// Whether no later state has been made from this one.
+    pub head: bool,
+}
+
+impl HistoryGraph {
+    /// Render the graph in DOT, naming the controllers that made each change with the given
+    /// names (by index).
+    pub fn to_dot(&self, controller_names: &[String]) -> String {
+        let mut lines = vec![
+            "digraph history {".to_owned(),
+            "  node [shape=box];".to_owned(),
+        ];
+        for node in &self.nodes {
+            let mut label = node.revision.to_string();
+            match node.controller {
+                Some(c) => {
+                    let name = controller_names.get(c).map_or("", |n| n.as_str());
+                    label.push_str(&format!("\\n{} ({})", name, c))
+                }
+                None if node.index == 0 => label.push_str("\\ninitial"),
+                None => label.push_str("\\nclient"),
+            }
+            if node.predecessors.len() > 1 {
+                let merged = node
+                    .predecessors
+                    .iter()
+                    .map(|p| p.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                label.push_str(&format!("\\nmerge of {}", merged));
+            }
+            let style = if node.head { ", peripheries=2" } else { "" };
+            lines.push(format!("  {} [label=\"{}\"{}];", node.index, label, style));
+            for p in &node.predecessors {
+                lines.push(format!("  {} -> {};", p, node.index));
+            }
+        }
+        lines.push("}".to_owned());
+        lines.join("\n")
+    }
+}
@@ -0,0 +1,148 @@
+use std::{borrow::Cow, sync::Arc};
+
+use crate::{
+    abstract_model::Change,
+    resources::Time,
+    state::{revision::Revision, RawState, RejectionReason, StateView},
+};
+
+use super::{History, StatesVec};
+
+/// A linear history where each controller reads from its own informer cache.
+///
+/// Caches are advanced towards the latest state one watch event (a single resource being applied
+/// or deleted) at a time, so controllers can observe partially-applied changes.
+/// A relist replaces the cache with the latest state, like the `Restarted` event from a kube
+/// watcher after the watch is dropped or the controller restarts.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct InformerHistory {
+    states: StatesVec,
+    informers: Vec<Informer>,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+struct Informer {
+    /// The index of the state in the log that the cache is receiving events for.
+    target: usize,
+    /// The state as seen through the events received so far.
+    cache: Arc<StateView>,
+    /// Whether the cache has changed since the controller last read it.
+    fresh: bool,
+}
+
+impl InformerHistory {
+    pub fn new(initial_state: RawState) -> Self {
+        Self {
+            states: StatesVec(imbl::vector![Arc::new(initial_state.into())]),
+            informers: Vec::new(),
+        }
+    }
+
+    /// Add an informer for a new controller, starting from a list of the latest state.
+    pub fn add_informer(&mut self) {
+        self.informers.push(Informer {
+            target: self.states.len() - 1,
+            cache: Arc::clone(self.states.last().unwrap()),
+            fresh: true,
+        });
+    }
+
+    /// The cached state that the controller's informer has built up.
+    pub fn cache(&self, controller: usize) -> &StateView {
+        &self.informers[controller].cache
+    }
+
+    /// Controllers whose informers have watch events yet to receive.
+    pub fn pending_informers(&self) -> Vec<usize> {
+        let last = self.states.len() - 1;
+        self.informers
+            .iter()
+            .enumerate()
+            .filter(|(_, i)| i.target < last || i.cache.state != self.states[i.target].state)
+            .map(|(c, _)| c)
+            .collect()
+    }
+
+    /// Deliver the next watch event to the controller's informer.
+    pub fn deliver_event(&mut self, controller: usize) {
+        let last = self.states.len() - 1;
+        let informer = &mut self.informers[controller];
+        // skip over changes that the cache has already caught up with
+        while informer.target < last && informer.cache.state == self.states[informer.target].state {
+            informer.target += 1;
+        }
+        let target = &self.states[informer.target];
+        let cache = Arc::make_mut(&mut informer.cache);
+        if cache.apply_watch_event(&target.state, &target.revision) {
+            cache.revision = target.revision.clone();
+            informer.fresh = true;
+        }
+    }
+
+    /// Replace the controller's cache with the latest state.
+    pub fn relist(&mut self, controller: usize) {
+        let informer = &mut self.informers[controller];
+        informer.target = self.states.len() - 1;
+        informer.cache = Arc::clone(self.states.last().unwrap());
+        informer.fresh = true;
+    }
+
+    /// Reorder the informers, such that the informer at `order[i]` moves to `i`.
+    pub fn permute_informers(&mut self, order: &[usize]) {
+        self.informers = order.iter().map(|i| self.informers[*i].clone()).collect();
+    }
+
+    /// Record that the controller has read its cache.
+    pub fn observe(&mut self, controller: usize) {
+        self.informers[controller].fresh = false;
+    }
+
+    /// Get the valid revisions for a controller reading from its cache.
+    ///
+    /// Controllers are triggered by events so can only read their cache once it has changed.
+    pub fn valid_revisions_for(&self, controller: usize) -> Vec<Revision> {
+        let informer = &self.informers[controller];
+        if informer.fresh {
+            vec![informer.cache.revision.clone()]
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+impl History for InformerHistory {
+    fn add_change(&mut self, change: Change, now: Time) -> Result<Option<String>, RejectionReason> {
+        let mut new_state = (**self.states.last().unwrap()).clone();
+        let new_revision = self.max_revision().increment();
+        let lost_lease = new_state.apply_operation(change.operation, new_revision, now)?;
+        self.states.push_back(Arc::new(new_state));
+        Ok(lost_lease)
+    }
+
+    fn map_states(&mut self, f: &mut dyn FnMut(&mut RawState)) {
+        for state in self.states.iter_mut() {
+            f(Arc::make_mut(state));
+        }
+        for informer in &mut self.informers {
+            f(Arc::make_mut(&mut informer.cache));
+        }
+    }
+
+    fn max_revision(&self) -> Revision {
+        self.states.last().unwrap().revision.clone()
+    }
+
+    fn state_at(&self, revision: &Revision) -> Cow<StateView> {
+        let index = revision.components().first().unwrap();
+        Cow::Borrowed(&self.states[*index])
+    }
+
+    fn valid_revisions(&self, min_revision: Option<&Revision>) -> Vec<Revision> {
+        let max = self.max_revision();
+        if min_revision.map_or(true, |min| &max > min) {
+            vec![max]
+        } else {
+            Vec::new()
+        }
+    }
+}
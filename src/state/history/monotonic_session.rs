@@ -2,7 +2,8 @@ use std::{borrow::Cow, sync::Arc};
 
 use crate::{
     abstract_model::Change,
-    state::{revision::Revision, RawState, StateView},
+    resources::Time,
+    state::{revision::Revision, RawState, RejectionReason, StateView},
 };
 
 use super::{History, StatesVec};
@@ -21,15 +22,18 @@ impl MonotonicSessionHistory {
 }
 
 impl History for MonotonicSessionHistory {
-    fn add_change(&mut self, change: Change) {
+    fn add_change(&mut self, change: Change, now: Time) -> Result<Option<String>, RejectionReason> {
         let mut new_state = (**self.states.last().unwrap()).clone();
         let new_revision = self.max_revision().increment();
-        if new_state.apply_operation(change.operation, new_revision) {
-            // operation succeeded, add the new state to the list of states
-            self.states.push_back(Arc::new(new_state));
-        } else {
-            // operation did not succeed, however client state may have changed so just return the
-            // max revision still
+        let lost_lease = new_state.apply_operation(change.operation, new_revision, now)?;
+        // operation succeeded, add the new state to the list of states
+        self.states.push_back(Arc::new(new_state));
+        Ok(lost_lease)
+    }
+
+    fn map_states(&mut self, f: &mut dyn FnMut(&mut RawState)) {
+        for state in self.states.iter_mut() {
+            f(Arc::make_mut(state));
         }
     }
 
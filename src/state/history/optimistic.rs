@@ -2,7 +2,8 @@ use std::{borrow::Cow, sync::Arc};
 
 use crate::{
     abstract_model::Change,
-    state::{revision::Revision, RawState, StateView},
+    resources::Time,
+    state::{revision::Revision, RawState, RejectionReason, StateView},
 };
 
 use super::{History, StatesVec};
@@ -35,17 +36,23 @@ impl OptimisticLinearHistory {
 }
 
 impl History for OptimisticLinearHistory {
-    fn add_change(&mut self, change: Change) {
+    fn add_change(&mut self, change: Change, now: Time) -> Result<Option<String>, RejectionReason> {
         // find the state for the revision that the change operated on, we'll treat this as the
         // committed one if they didn't operate on the latest (optimistic)
         let index = change.revision.components().first().unwrap();
         let mut new_state = self.states[*index].state.clone();
         let new_revision = self.max_revision().increment();
-        if new_state.apply_operation(change.operation, new_revision) {
-            self.states.push_back(Arc::new(HistoryState {
-                state: new_state,
-                parent: *index,
-            }));
+        let lost_lease = new_state.apply_operation(change.operation, new_revision, now)?;
+        self.states.push_back(Arc::new(HistoryState {
+            state: new_state,
+            parent: *index,
+        }));
+        Ok(lost_lease)
+    }
+
+    fn map_states(&mut self, f: &mut dyn FnMut(&mut RawState)) {
+        for state in self.states.iter_mut() {
+            f(&mut Arc::make_mut(state).state);
         }
     }
 
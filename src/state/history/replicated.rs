@@ -0,0 +1,167 @@
+use std::{borrow::Cow, collections::BTreeSet, sync::Arc};
+
+use crate::{
+    abstract_model::Change,
+    resources::Time,
+    state::{revision::Revision, RawState, RejectionReason, StateView},
+};
+
+use super::{History, ReadMode, StatesVec};
+
+/// A datastore made of etcd-like replicas.
+///
+/// Committed changes form a single linear log, which each replica applies in order through
+/// explicit replication steps.
+/// Controller `i` is connected to replica `i % replicas`, reading from what that replica has
+/// applied.
+/// Replicas can be partitioned from the rest, splitting them into two sides.
+/// Only a side with a majority of the replicas can commit writes, replicate and serve quorum reads,
+/// writes through replicas on the other side are rejected.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct ReplicatedHistory {
+    /// The committed log of states.
+    states: StatesVec,
+    /// The index in the log that each replica has applied up to.
+    applied: Vec<usize>,
+    /// Replicas that are currently cut off from the rest, which may be the majority.
+    partitioned: BTreeSet<usize>,
+}
+
+impl ReplicatedHistory {
+    pub fn new(initial_state: RawState, replicas: usize) -> Self {
+        assert!(replicas > 0, "need at least one replica");
+        Self {
+            states: StatesVec(imbl::vector![Arc::new(initial_state.into())]),
+            applied: vec![0; replicas],
+            partitioned: BTreeSet::new(),
+        }
+    }
+
+    /// The replica that the given controller is connected to.
+    pub fn replica_for(&self, controller: usize) -> usize {
+        controller % self.applied.len()
+    }
+
+    /// Whether the replica is on the side of the partition with a majority of the replicas, so
+    /// writes and quorum reads through it can succeed.
+    fn in_majority(&self, replica: usize) -> bool {
+        let side = if self.partitioned.contains(&replica) {
+            self.partitioned.len()
+        } else {
+            self.applied.len() - self.partitioned.len()
+        };
+        side > self.applied.len() / 2
+    }
+
+    /// Replicas that have not yet applied the whole log and are able to catch up.
+    pub fn lagging_replicas(&self) -> Vec<usize> {
+        let last = self.states.len() - 1;
+        (0..self.applied.len())
+            .filter(|r| self.applied[*r] < last && self.in_majority(*r))
+            .collect()
+    }
+
+    /// Replicas that can be partitioned from the rest, leaving at least one connected, whichever
+    /// side ends up with the majority.
+    pub fn partitionable_replicas(&self) -> Vec<usize> {
+        let connected = self.applied.len() - self.partitioned.len();
+        if connected > 1 {
+            (0..self.applied.len())
+                .filter(|r| !self.partitioned.contains(r))
+                .collect()
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Replicas that are currently partitioned.
+    pub fn partitioned_replicas(&self) -> Vec<usize> {
+        self.partitioned.iter().copied().collect()
+    }
+
+    /// Apply the next entry in the log to the replica.
+    pub fn replicate(&mut self, replica: usize) {
+        if self.in_majority(replica) && self.applied[replica] + 1 < self.states.len() {
+            self.applied[replica] += 1;
+        }
+    }
+
+    pub fn partition(&mut self, replica: usize) {
+        self.partitioned.insert(replica);
+    }
+
+    pub fn heal(&mut self, replica: usize) {
+        self.partitioned.remove(&replica);
+    }
+
+    /// Get the valid revisions for a controller reading through its replica with the given mode.
+    pub fn valid_revisions_for(
+        &self,
+        controller: usize,
+        read_mode: ReadMode,
+        min_revision: Option<&Revision>,
+    ) -> Vec<Revision> {
+        let replica = self.replica_for(controller);
+        let revision = match (read_mode, min_revision) {
+            (ReadMode::Latest, _) | (ReadMode::SessionMonotonic, None) => {
+                // a quorum read, which needs the replica to be able to reach a majority
+                if !self.in_majority(replica) {
+                    return Vec::new();
+                }
+                self.max_revision()
+            }
+            (ReadMode::SessionMonotonic, Some(_)) | (ReadMode::AnyValid, _) => {
+                // a serializable read, served from whatever the replica has applied
+                self.states[self.applied[replica]].revision.clone()
+            }
+        };
+        if min_revision.map_or(true, |min| &revision > min) {
+            vec![revision]
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+impl History for ReplicatedHistory {
+    fn add_change(&mut self, change: Change, now: Time) -> Result<Option<String>, RejectionReason> {
+        if let Some(controller) = change.controller {
+            if !self.in_majority(self.replica_for(controller)) {
+                // the replica can't commit the write so it gets rejected
+                return Err(RejectionReason::NoQuorum);
+            }
+        }
+        let mut new_state = (**self.states.last().unwrap()).clone();
+        let new_revision = self.max_revision().increment();
+        let lost_lease = new_state.apply_operation(change.operation, new_revision, now)?;
+        self.states.push_back(Arc::new(new_state));
+        Ok(lost_lease)
+    }
+
+    fn map_states(&mut self, f: &mut dyn FnMut(&mut RawState)) {
+        for state in self.states.iter_mut() {
+            f(Arc::make_mut(state));
+        }
+    }
+
+    fn max_revision(&self) -> Revision {
+        self.states.last().unwrap().revision.clone()
+    }
+
+    fn state_at(&self, revision: &Revision) -> Cow<StateView> {
+        let index = revision.components().first().unwrap();
+        Cow::Borrowed(&self.states[*index])
+    }
+
+    fn valid_revisions(&self, min_revision: Option<&Revision>) -> Vec<Revision> {
+        // any state that some replica currently serves
+        let mut indices = self.applied.clone();
+        indices.sort();
+        indices.dedup();
+        indices
+            .into_iter()
+            .map(|i| self.states[i].revision.clone())
+            .filter(|r| min_revision.map_or(true, |min| r > min))
+            .collect()
+    }
+}
@@ -2,7 +2,8 @@ use std::{borrow::Cow, sync::Arc};
 
 use crate::{
     abstract_model::Change,
-    state::{revision::Revision, RawState, StateView},
+    resources::Time,
+    state::{revision::Revision, RawState, RejectionReason, StateView},
 };
 
 use super::{History, StatesVec};
@@ -21,11 +22,17 @@ impl SynchronousHistory {
 }
 
 impl History for SynchronousHistory {
-    fn add_change(&mut self, change: Change) {
+    fn add_change(&mut self, change: Change, now: Time) -> Result<Option<String>, RejectionReason> {
         let mut new_state = (**self.states.last().unwrap()).clone();
         let new_revision = self.max_revision().increment();
-        if new_state.apply_operation(change.operation, new_revision) {
-            self.states.push_back(Arc::new(new_state));
+        let lost_lease = new_state.apply_operation(change.operation, new_revision, now)?;
+        self.states.push_back(Arc::new(new_state));
+        Ok(lost_lease)
+    }
+
+    fn map_states(&mut self, f: &mut dyn FnMut(&mut RawState)) {
+        for state in self.states.iter_mut() {
+            f(Arc::make_mut(state));
         }
     }
 
@@ -1,43 +1,115 @@
 use std::sync::Arc;
 
-use tracing::warn;
+use serde::{Deserialize, Serialize};
 
-use crate::{
-    resources::{LabelSelector, Meta, Spec},
-    utils::now,
-};
+use crate::resources::{LabelSelector, Meta, PropagationPolicy, Spec, Time};
 
 use super::revision::Revision;
 
-/// A data structure that ensures the resources are unique by name, and kept in sorted order for
-/// efficient lookup and deterministic ordering.
+/// A data structure that ensures the resources are unique by namespace and name, and kept in
+/// sorted order for efficient lookup and deterministic ordering.
+///
+/// Cluster-scoped resources have an empty namespace.
 #[derive(derivative::Derivative)]
 #[derivative(PartialEq, Hash)]
 #[derive(Clone, Debug, Eq, PartialOrd, Ord)]
 pub struct Resources<T>(imbl::Vector<Arc<T>>);
 
+/// Why a change to the resources failed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum ResourceError {
+    /// A resource with the same name already exists.
+    AlreadyExists,
+    /// No resource with the name exists.
+    NotFound,
+    /// The resource has a different uid to the stored one, so was for an older incarnation.
+    UidMismatch,
+    /// The resource was based on an older resource version than the stored one.
+    StaleResourceVersion,
+    /// The resource is being deleted, so only its finalizers can be removed.
+    Terminating,
+    /// The namespace is being deleted, so nothing new can be created in it.
+    NamespaceTerminating,
+}
+
+impl std::fmt::Display for ResourceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResourceError::AlreadyExists => write!(f, "already exists"),
+            ResourceError::NotFound => write!(f, "not found"),
+            ResourceError::UidMismatch => write!(f, "uid does not match the stored resource"),
+            ResourceError::StaleResourceVersion => {
+                write!(f, "resource version is older than the stored resource")
+            }
+            ResourceError::Terminating => {
+                write!(
+                    f,
+                    "resource is terminating, only removing finalizers is allowed"
+                )
+            }
+            ResourceError::NamespaceTerminating => {
+                write!(f, "namespace is terminating, nothing can be created in it")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ResourceError {}
+
+impl<T: Serialize> Serialize for Resources<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_seq(self.0.iter().map(|r| r.as_ref()))
+    }
+}
+
 impl<T> Default for Resources<T> {
     fn default() -> Self {
         Self(Default::default())
     }
 }
 
+/// The namespace that the resource is stored in: none for cluster-scoped kinds, otherwise its own or
+/// the default namespace if it doesn't have one.
+pub fn namespace_of<T: Meta>(res: &T) -> &str {
+    if T::CLUSTER_SCOPED {
+        ""
+    } else if res.metadata().namespace.is_empty() {
+        DEFAULT_NAMESPACE
+    } else {
+        &res.metadata().namespace
+    }
+}
+
+/// The namespace that namespaced resources are put in when they don't give one.
+pub const DEFAULT_NAMESPACE: &str = "default";
+
 impl<T: Meta + Spec + Clone> Resources<T> {
-    pub fn upsert(&mut self, res: T, revision: Revision)
+    pub fn upsert(&mut self, res: T, revision: Revision, now: Time)
     where
         T: PartialEq,
     {
-        match self.create(res.clone(), revision.clone()) {
+        match self.create(res.clone(), revision.clone(), now) {
             Ok(_) => {}
             Err(_) => {
-                self.update(res, revision).map_err(|_| ()).unwrap();
+                self.update(res, revision).unwrap();
             }
         }
     }
 
-    pub fn create(&mut self, mut res: T, revision: Revision) -> Result<(), T> {
-        if self.has(&res.metadata().name) {
-            return Err(res);
+    /// Create the resource, stamping it with the given time if it wasn't created before.
+    pub fn create(
+        &mut self,
+        mut res: T,
+        revision: Revision,
+        now: Time,
+    ) -> Result<(), ResourceError> {
+        // set the namespace
+        res.metadata_mut().namespace = namespace_of(&res).to_owned();
+        if self.has(&res.metadata().namespace, &res.metadata().name) {
+            return Err(ResourceError::AlreadyExists);
         }
         // set the uid if not set already
         if res.metadata().uid.is_empty() {
@@ -49,94 +121,98 @@ impl<T: Meta + Spec + Clone> Resources<T> {
         }
         // set the creation timestamp
         if res.metadata().creation_timestamp.is_none() {
-            res.metadata_mut().creation_timestamp = Some(now());
-        }
-        // set the namespace
-        if res.metadata().namespace.is_empty() {
-            res.metadata_mut().namespace = "default".to_owned();
+            res.metadata_mut().creation_timestamp = Some(now);
         }
         // set resource version to mod revision as per https://github.com/kubernetes/community/blob/master/contributors/devel/sig-architecture/api-conventions.md#concurrency-control-and-consistency
         res.metadata_mut().resource_version = revision;
-        let pos = self.get_insertion_pos(&res.metadata().name);
+        let pos = self.get_insertion_pos(&res.metadata().namespace, &res.metadata().name);
         self.0.insert(pos, Arc::new(res));
         Ok(())
     }
 
-    pub fn update(&mut self, mut res: T, revision: Revision) -> Result<(), T>
+    pub fn update(&mut self, mut res: T, revision: Revision) -> Result<(), ResourceError>
     where
         T: PartialEq,
     {
-        if let Some(existing_pos) = self.get_pos(&res.metadata().name) {
-            let existing = &self.0[existing_pos];
-            if existing.metadata().deletion_timestamp.is_some() {
-                // can only remove finalizers on terminating resources
-                let mut ex = (**existing).clone();
-                ex.metadata_mut().finalizers.clear();
-                let mut r = res.clone();
-                r.metadata_mut().finalizers.clear();
-                if r != ex {
-                    warn!("Tried to update resource that is terminating, only removing finalizers is allowed");
-                    return Err(res);
-                }
-            }
-            if existing.metadata().uid != res.metadata().uid {
-                // TODO: update this to have some conflict-reconciliation thing?
-                warn!(
-                    "Different uids! {} vs {}",
-                    existing.metadata().uid,
-                    res.metadata().uid
-                );
-                Err(res)
-            } else if existing.metadata().resource_version > res.metadata().resource_version {
-                // ignore changes to resources when resource version is specified but the resource
-                // being inserted is old
-                let existing = &existing.metadata().resource_version;
-                let new = &res.metadata().resource_version;
-                warn!(?existing, ?new, "Old resource");
-                Err(res)
-            } else {
-                // set resource version to mod revision as per https://github.com/kubernetes/community/blob/master/contributors/devel/sig-architecture/api-conventions.md#concurrency-control-and-consistency
-                // Update the generation of the resource if the spec (desired state) has changed.
-                let mut new_meta_without_generation = res.metadata().clone();
-                new_meta_without_generation.generation = 0;
-                let mut existing_meta_without_generation = existing.metadata().clone();
-                existing_meta_without_generation.generation = 0;
-                if res.spec() != existing.spec() ||
-                // TODO: this should be able to be removed now that we have the
-                // observed_revision field
-                // THEMELIOS: changing metadata does not change generation normally, but this
-                // eliminates the way to check for stability (that a controller has observed the
-                // updates)
-                    new_meta_without_generation != existing_meta_without_generation
-                {
-                    res.metadata_mut().generation += 1;
-                }
-                res.metadata_mut().resource_version = revision;
-                self.0[existing_pos] = Arc::new(res);
-                Ok(())
+        res.metadata_mut().namespace = namespace_of(&res).to_owned();
+        let Some(existing_pos) = self.get_pos(&res.metadata().namespace, &res.metadata().name)
+        else {
+            return Err(ResourceError::NotFound);
+        };
+        let existing = &self.0[existing_pos];
+        if existing.metadata().deletion_timestamp.is_some() {
+            // can only remove finalizers on terminating resources
+            let mut ex = (**existing).clone();
+            ex.metadata_mut().finalizers.clear();
+            let mut r = res.clone();
+            r.metadata_mut().finalizers.clear();
+            if r != ex {
+                return Err(ResourceError::Terminating);
             }
+        }
+        if existing.metadata().uid != res.metadata().uid {
+            // TODO: update this to have some conflict-reconciliation thing?
+            Err(ResourceError::UidMismatch)
+        } else if existing.metadata().resource_version > res.metadata().resource_version {
+            // ignore changes to resources when resource version is specified but the resource
+            // being inserted is old
+            Err(ResourceError::StaleResourceVersion)
         } else {
-            Err(res)
+            // set resource version to mod revision as per https://github.com/kubernetes/community/blob/master/contributors/devel/sig-architecture/api-conventions.md#concurrency-control-and-consistency
+            // Update the generation of the resource if the spec (desired state) has changed.
+            let mut new_meta_without_generation = res.metadata().clone();
+            new_meta_without_generation.generation = 0;
+            let mut existing_meta_without_generation = existing.metadata().clone();
+            existing_meta_without_generation.generation = 0;
+            if res.spec() != existing.spec() ||
+            // TODO: this should be able to be removed now that we have the
+            // observed_revision field
+            // THEMELIOS: changing metadata does not change generation normally, but this
+            // eliminates the way to check for stability (that a controller has observed the
+            // updates)
+                new_meta_without_generation != existing_meta_without_generation
+            {
+                res.metadata_mut().generation += 1;
+            }
+            res.metadata_mut().resource_version = revision;
+            self.0[existing_pos] = Arc::new(res);
+            Ok(())
         }
     }
 
-    fn get_insertion_pos(&self, k: &str) -> usize {
-        match self.0.binary_search_by(|v| k.cmp(&v.metadata().name)) {
+    fn get_insertion_pos(&self, namespace: &str, name: &str) -> usize {
+        match self.search(namespace, name) {
             Ok(p) => p,
             Err(p) => p,
         }
     }
 
-    fn get_pos(&self, k: &str) -> Option<usize> {
-        self.0.binary_search_by(|v| k.cmp(&v.metadata().name)).ok()
+    fn get_pos(&self, namespace: &str, name: &str) -> Option<usize> {
+        self.search(namespace, name).ok()
+    }
+
+    fn search(&self, namespace: &str, name: &str) -> Result<usize, usize> {
+        self.0.binary_search_by(|v| {
+            let meta = v.metadata();
+            (namespace, name).cmp(&(meta.namespace.as_str(), meta.name.as_str()))
+        })
+    }
+
+    fn get_pos_of(&self, res: &T) -> Option<usize> {
+        self.get_pos(namespace_of(res), &res.metadata().name)
     }
 
-    pub fn has(&self, name: &str) -> bool {
-        self.get(name).is_some()
+    pub fn has(&self, namespace: &str, name: &str) -> bool {
+        self.get(namespace, name).is_some()
     }
 
-    pub fn get(&self, name: &str) -> Option<&T> {
-        self.get_pos(name)
+    /// Whether there is a resource stored with the same namespace and name as this one.
+    pub fn contains(&self, res: &T) -> bool {
+        self.get_pos_of(res).is_some()
+    }
+
+    pub fn get(&self, namespace: &str, name: &str) -> Option<&T> {
+        self.get_pos(namespace, name)
             .and_then(|p| self.0.get(p).map(|r| r.as_ref()))
     }
 
@@ -146,15 +222,70 @@ impl<T: Meta + Spec + Clone> Resources<T> {
         }
     }
 
-    pub fn remove(&mut self, res: &T) -> Option<T> {
+    pub fn remove(&mut self, res: &T) -> Result<T, ResourceError> {
+        let Some(existing_pos) = self.get_pos_of(res) else {
+            return Err(ResourceError::NotFound);
+        };
         // in order to remove a resource it must have the same uid.
-        if let Some(existing_pos) = self.get_pos(&res.metadata().name) {
-            let existing = &self.0[existing_pos];
-            if existing.metadata().uid == res.metadata().uid {
-                return Some((*self.0.remove(existing_pos)).clone());
+        if self.0[existing_pos].metadata().uid != res.metadata().uid {
+            return Err(ResourceError::UidMismatch);
+        }
+        Ok((*self.0.remove(existing_pos)).clone())
+    }
+
+    /// Delete the resource, removing it straight away unless it has finalizers (including the one
+    /// for the propagation policy), in which case it is marked as terminating from the given time
+    /// until they are removed.
+    pub fn delete(
+        &mut self,
+        res: &T,
+        policy: PropagationPolicy,
+        revision: Revision,
+        now: Time,
+    ) -> Result<(), ResourceError> {
+        let Some(existing_pos) = self.get_pos_of(res) else {
+            return Err(ResourceError::NotFound);
+        };
+        let mut existing = (*self.0[existing_pos]).clone();
+        if existing.metadata().uid != res.metadata().uid {
+            return Err(ResourceError::UidMismatch);
+        }
+        if let Some(finalizer) = policy.finalizer() {
+            let finalizers = &mut existing.metadata_mut().finalizers;
+            if !finalizers.iter().any(|f| f == finalizer) {
+                finalizers.push(finalizer.to_owned());
             }
         }
-        None
+        if existing.metadata().finalizers.is_empty() {
+            self.0.remove(existing_pos);
+        } else {
+            if existing.metadata().deletion_timestamp.is_none() {
+                existing.metadata_mut().deletion_timestamp = Some(now);
+            }
+            existing.metadata_mut().resource_version = revision;
+            self.0[existing_pos] = Arc::new(existing);
+        }
+        Ok(())
+    }
+
+    /// Update the resource, removing it if it was terminating and the update removed its last
+    /// finalizer.
+    ///
+    /// Pods aren't updated this way as they are removed by their node once terminated.
+    pub fn update_finalizing(&mut self, res: T, revision: Revision) -> Result<(), ResourceError>
+    where
+        T: PartialEq,
+    {
+        let namespace = namespace_of(&res).to_owned();
+        let name = res.metadata().name.clone();
+        self.update(res, revision)?;
+        if let Some(pos) = self.get_pos(&namespace, &name) {
+            let metadata = self.0[pos].metadata();
+            if metadata.deletion_timestamp.is_some() && metadata.finalizers.is_empty() {
+                self.0.remove(pos);
+            }
+        }
+        Ok(())
     }
 
     pub fn retain(&mut self, f: impl Fn(&T) -> bool) {
@@ -169,27 +300,141 @@ impl<T: Meta + Spec + Clone> Resources<T> {
         self.0.is_empty()
     }
 
-    pub fn for_controller<'a>(&'a self, uid: &'a str) -> impl Iterator<Item = &T> + 'a {
+    /// The resources in the namespace.
+    pub fn in_namespace<'a>(&'a self, namespace: &'a str) -> impl Iterator<Item = &T> + 'a {
         self.0
             .iter()
-            .filter(move |t| t.metadata().owner_references.iter().any(|or| or.uid == uid))
+            .filter(move |t| t.metadata().namespace == namespace)
             .map(|r| r.as_ref())
     }
 
-    pub fn matching<'a>(&'a self, selector: &'a LabelSelector) -> impl Iterator<Item = &T> + 'a {
-        self.0
-            .iter()
+    /// The resources in the namespace controlled by the resource with the given uid.
+    pub fn for_controller<'a>(
+        &'a self,
+        namespace: &'a str,
+        uid: &'a str,
+    ) -> impl Iterator<Item = &T> + 'a {
+        self.in_namespace(namespace).filter(move |t| {
+            t.metadata()
+                .owner_references
+                .iter()
+                .any(|or| or.controller && or.uid == uid)
+        })
+    }
+
+    /// The resources in the namespace with any owner reference to the resource with the given
+    /// uid, whether or not it is their controller.
+    pub fn owned_by<'a>(
+        &'a self,
+        namespace: &'a str,
+        uid: &'a str,
+    ) -> impl Iterator<Item = &T> + 'a {
+        self.in_namespace(namespace)
+            .filter(move |t| t.metadata().owner_references.iter().any(|or| or.uid == uid))
+    }
+
+    /// The resources in the namespace with labels matching the selector.
+    pub fn matching<'a>(
+        &'a self,
+        namespace: &'a str,
+        selector: &'a LabelSelector,
+    ) -> impl Iterator<Item = &T> + 'a {
+        self.in_namespace(namespace)
             .filter(move |t| selector.matches(&t.metadata().labels))
-            .map(|r| r.as_ref())
     }
 
     pub fn to_vec(&self) -> Vec<&T> {
         self.iter().collect()
     }
 
+    /// The order of the next watch event that would move these resources towards the target at
+    /// the given revision, used to deliver events across kinds in commit order.
+    ///
+    /// Events are ordered by the revision they were committed at: a changed resource by its
+    /// resource version, a resource that was replaced by one with the same name by the version of
+    /// the replacement, and any other deletion by the target revision, the latest it could have
+    /// been deleted at. A deletion comes before a change at the same revision, so that a resource
+    /// that was deleted and recreated is removed before its replacement is added.
+    pub fn next_watch_event(&self, target: &Self, revision: &Revision) -> Option<(Revision, bool)>
+    where
+        T: PartialEq,
+    {
+        let changed = self
+            .next_changed(target)
+            .map(|resource| (resource.metadata().resource_version.clone(), true));
+        let deleted = self
+            .next_deleted(target, revision)
+            .map(|(_, deleted_at)| (deleted_at, false));
+        changed.into_iter().chain(deleted).min()
+    }
+
+    /// Apply a single watch event to move these resources towards the target at the given
+    /// revision, returning whether there was a difference to apply.
+    ///
+    /// Events are applied in the order given by [`Resources::next_watch_event`].
+    pub fn apply_watch_event(&mut self, target: &Self, revision: &Revision) -> bool
+    where
+        T: PartialEq,
+    {
+        match self.next_watch_event(target, revision) {
+            Some((_, true)) => {
+                let resource = Arc::clone(self.next_changed(target).unwrap());
+                match self.search(&resource.metadata().namespace, &resource.metadata().name) {
+                    Ok(pos) => self.0[pos] = resource,
+                    Err(pos) => self.0.insert(pos, resource),
+                }
+                true
+            }
+            Some((_, false)) => {
+                let (pos, _) = self.next_deleted(target, revision).unwrap();
+                self.0.remove(pos);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The resource in the target that differs from ours with the oldest resource version.
+    fn next_changed<'a>(&self, target: &'a Self) -> Option<&'a Arc<T>>
+    where
+        T: PartialEq,
+    {
+        target
+            .0
+            .iter()
+            .filter(|resource| {
+                self.get_pos(&resource.metadata().namespace, &resource.metadata().name)
+                    .map_or(true, |pos| self.0[pos] != **resource)
+            })
+            .min_by_key(|resource| &resource.metadata().resource_version)
+    }
+
+    /// The position of our resource that was deleted earliest on the way to the target at the
+    /// given revision, along with the revision it was deleted at.
+    ///
+    /// A resource whose name the target has with a different uid was deleted no later than its
+    /// replacement was created.
+    fn next_deleted(&self, target: &Self, revision: &Revision) -> Option<(usize, Revision)> {
+        self.0
+            .iter()
+            .enumerate()
+            .filter_map(
+                |(pos, r)| match target.get(&r.metadata().namespace, &r.metadata().name) {
+                    None => Some((pos, revision.clone())),
+                    Some(t) if t.metadata().uid != r.metadata().uid => {
+                        Some((pos, t.metadata().resource_version.clone()))
+                    }
+                    Some(_) => None,
+                },
+            )
+            .min_by(|(_, a), (_, b)| a.cmp(b))
+    }
+
     pub fn merge(&mut self, other: &Self) {
         for resource in &other.0 {
-            if let Some(existing_pos) = self.get_pos(&resource.metadata().name) {
+            if let Some(existing_pos) =
+                self.get_pos(&resource.metadata().namespace, &resource.metadata().name)
+            {
                 let existing = &self.0[existing_pos];
                 let new_revision = &resource.metadata().resource_version;
                 let existing_revision = &existing.metadata().resource_version;
@@ -197,7 +442,8 @@ impl<T: Meta + Spec + Clone> Resources<T> {
                     self.0[existing_pos] = Arc::clone(resource);
                 }
             } else {
-                let pos = self.get_insertion_pos(&resource.metadata().name);
+                let pos = self
+                    .get_insertion_pos(&resource.metadata().namespace, &resource.metadata().name);
                 self.0.insert(pos, Arc::clone(resource));
             }
         }
@@ -209,7 +455,7 @@ impl<T: Meta + Spec + Clone> From<Vec<T>> for Resources<T> {
         let mut rv = Resources::default();
         for v in value {
             let revision = v.metadata().resource_version.clone();
-            rv.create(v, revision).map_err(|_| ()).unwrap();
+            rv.create(v, revision, Time::default()).unwrap();
         }
         rv
     }
@@ -220,7 +466,7 @@ impl<T: Meta + Spec + Clone> FromIterator<T> for Resources<T> {
         let mut rv = Resources::default();
         for v in iter {
             let revision = v.metadata().resource_version.clone();
-            rv.create(v, revision).map_err(|_| ()).unwrap();
+            rv.create(v, revision, Time::default()).unwrap();
         }
         rv
     }
@@ -13,11 +13,15 @@ pub fn new_uid(name: &str) -> String {
     name.to_owned()
 }
 
+/// The wall-clock time, for serving real clusters whose time the model can't step.
+///
+/// The model reads the time from the clock on its state instead.
 #[cfg(feature = "serve")]
 pub fn now() -> Time {
     Time(OffsetDateTime::now_utc())
 }
 
+/// A fixed time, as the model reads the time from the clock on its state instead.
 #[cfg(not(feature = "serve"))]
 pub fn now() -> Time {
     Time(OffsetDateTime::UNIX_EPOCH)
@@ -28,7 +32,7 @@ pub fn metadata(name: String) -> Metadata {
     Metadata {
         name,
         generate_name: String::new(),
-        namespace: "default".to_owned(),
+        namespace: String::new(),
         creation_timestamp: None,
         deletion_timestamp: None,
         generation: 0,
@@ -0,0 +1,81 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::abstract_model::AbstractModel;
+use crate::controller_properties::{Condition, Properties};
+use crate::coverage::PropertyCoverage;
+use crate::state::State;
+
+/// How often the antecedent of each property of a model that is an implication has held.
+///
+/// An implication whose antecedent never held passes vacuously, and so says nothing about the
+/// states that were checked.
+#[derive(derivative::Derivative)]
+#[derivative(Debug)]
+pub struct Vacuity {
+    names: Vec<&'static str>,
+    /// The antecedent of the property (by index), if it is an implication.
+    #[derivative(Debug = "ignore")]
+    antecedents: Vec<Option<Condition>>,
+    /// How many states the properties were evaluated on.
+    evaluated: AtomicUsize,
+    /// On how many states the antecedent of the property (by index) held.
+    fired: Vec<AtomicUsize>,
+}
+
+impl Vacuity {
+    pub fn new(properties: &Properties) -> Self {
+        Self {
+            names: properties.properties.iter().map(|(p, _)| p.name).collect(),
+            antecedents: properties.properties.iter().map(|(_, a)| *a).collect(),
+            evaluated: AtomicUsize::new(0),
+            fired: properties
+                .properties
+                .iter()
+                .map(|_| AtomicUsize::new(0))
+                .collect(),
+        }
+    }
+
+    /// Record that the properties were evaluated on the state, along with which antecedents held
+    /// on it.
+    pub fn record(&self, model: &AbstractModel, state: &State) {
+        self.evaluated.fetch_add(1, Ordering::Relaxed);
+        for (antecedent, fired) in self.antecedents.iter().zip(&self.fired) {
+            if antecedent.is_some_and(|a| a(model, state)) {
+                fired.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// The names of the implications that have not yet had their antecedent hold.
+    pub fn vacuous(&self) -> BTreeSet<&'static str> {
+        self.names
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| {
+                self.antecedents[*i].is_some() && self.fired[*i].load(Ordering::Relaxed) == 0
+            })
+            .map(|(_, name)| *name)
+            .collect()
+    }
+
+    /// How often each property was evaluated, and how often its antecedent held if it is an
+    /// implication.
+    pub fn coverage(&self) -> BTreeMap<&'static str, PropertyCoverage> {
+        let evaluated = self.evaluated.load(Ordering::Relaxed);
+        self.names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let coverage = PropertyCoverage {
+                    evaluated,
+                    antecedent_held: self.antecedents[i]
+                        .is_some()
+                        .then(|| self.fired[i].load(Ordering::Relaxed)),
+                };
+                (*name, coverage)
+            })
+            .collect()
+    }
+}
@@ -10,6 +10,7 @@ use std::path::PathBuf;
 use std::sync::atomic::AtomicU64;
 use std::sync::Arc;
 use std::time::Duration;
+use themelios::abstract_model::QUIESCENCE_PROPERTY;
 use themelios::model::OrchestrationModelCfg;
 use themelios::report::CSVReporter;
 use themelios::report::JointReporter;
@@ -81,6 +82,7 @@ fn check(model: OrchestrationModelCfg, test_name: &str, should_succeed: bool, ma
     let consistency = model.consistency_level.clone();
     let controllers = model.nodes;
     let am = model.into_abstract_model();
+    let vacuity = am.vacuity.clone();
     let report_dir =
         PathBuf::from(std::env::var("MCO_REPORT_PATH").unwrap_or_else(|_| "testout".to_owned()));
     if !report_dir.exists() {
@@ -99,7 +101,13 @@ fn check(model: OrchestrationModelCfg, test_name: &str, should_succeed: bool, ma
     let depths2 = depths.clone();
     let mut reporter = JointReporter {
         reporters: vec![
-            Box::new(StdoutReporter::new(&am)),
+            Box::new(
+                StdoutReporter::new(&am)
+                    .with_quiescence(QUIESCENCE_PROPERTY, |state| {
+                        state.writes_since_perturbation().unwrap_or_default()
+                    })
+                    .with_vacuity(move || vacuity.vacuous()),
+            ),
             Box::new(CSVReporter::new(
                 &report_path,
                 consistency,
@@ -123,6 +131,14 @@ fn check(model: OrchestrationModelCfg, test_name: &str, should_succeed: bool, ma
             info!(check_mode, "Running checking");
             checker.spawn_dfs().report(&mut reporter).check_properties()
         }
+        "dfs-symmetry" => {
+            info!(check_mode, "Running checking");
+            checker
+                .symmetry()
+                .spawn_dfs()
+                .report(&mut reporter)
+                .check_properties()
+        }
         "bfs" => {
             info!(check_mode, "Running checking");
             checker.spawn_bfs().report(&mut reporter).check_properties()
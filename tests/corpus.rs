@@ -0,0 +1,208 @@
+//! Replays the saved traces in `tests/corpus`, checking that each property still has the
+//! outcome it is expected to have along its trace regardless of how the checker explores.
+//!
+//! The actions of a trace can be taken from the steps of a discovery in a JSON report, and the
+//! counterexamples for the known-failing scenario table entries are recorded by the ignored
+//! `record_known_failing` test.
+
+use serde::Deserialize;
+use stateright::{Checker, Expectation, HasDiscoveries, Model, UniformChooser};
+use std::path::Path;
+use std::time::Duration;
+use themelios::abstract_model::Action;
+use themelios::model::OrchestrationModelCfg;
+use themelios::replay;
+use themelios::scenario::Scenario;
+use themelios::state::history::ConsistencySetup;
+
+/// The scenario table entries that are known to fail, by scenario, consistency and how many of
+/// each controller they run, to record counterexamples for.
+const KNOWN_FAILING: [(&str, ConsistencySetup, usize); 3] = [
+    (
+        "overlapping-rss.yaml",
+        ConsistencySetup::ResettableSession,
+        2,
+    ),
+    ("overlapping-rss.yaml", ConsistencySetup::Causal, 2),
+    (
+        "leader-elected-rs.yaml",
+        ConsistencySetup::ResettableSession,
+        1,
+    ),
+];
+
+/// A saved trace, with the model it was found in and the outcome expected for a property along
+/// it.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CorpusTrace {
+    /// Scenario to build the model from, relative to `tests/scenarios`.
+    scenario: String,
+    consistency: ConsistencySetup,
+    /// How many of each of the scenario's controllers to run.
+    controllers: usize,
+    /// Whether to fence leader-elected controllers, on top of the scenario.
+    #[serde(default)]
+    fencing: bool,
+    property: String,
+    expected: Outcome,
+    actions: Vec<Action>,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum Outcome {
+    /// An always property holds at every state, or a sometimes or eventually property holds at
+    /// some state.
+    Holds,
+    /// An always property is violated at some state, a sometimes property never holds, or an
+    /// eventually property never holds up to a state with nothing left to do or around a cycle.
+    Fails,
+}
+
+/// The model for a scenario, with every kind of controller it configures scaled up.
+fn model(
+    scenario: &str,
+    consistency: ConsistencySetup,
+    controllers: usize,
+) -> Result<OrchestrationModelCfg, String> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/scenarios")
+        .join(scenario);
+    let mut model = Scenario::load(&path)
+        .map_err(|e| e.to_string())?
+        .into_model_cfg();
+    model.consistency_level = consistency;
+    model.schedulers *= controllers;
+    model.nodes *= controllers;
+    model.replicaset_controllers *= controllers;
+    model.deployment_controllers *= controllers;
+    model.statefulset_controllers *= controllers;
+    model.daemonset_controllers *= controllers;
+    model.job_controllers *= controllers;
+    model.cronjob_controllers *= controllers;
+    model.hpa_controllers *= controllers;
+    model.podgc_controllers *= controllers;
+    model.garbage_collector_controllers *= controllers;
+    Ok(model)
+}
+
+fn check_trace(path: &Path) -> Result<(), String> {
+    let contents = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let trace: CorpusTrace = serde_json::from_str(&contents).map_err(|e| e.to_string())?;
+
+    let mut model = model(&trace.scenario, trace.consistency, trace.controllers)?;
+    model.fencing |= trace.fencing;
+    let model = model.into_abstract_model();
+
+    let property = stateright::Model::properties(&model)
+        .into_iter()
+        .find(|p| p.name == trace.property)
+        .ok_or_else(|| format!("no property named {:?}", trace.property))?;
+    let replay = replay::replay(&model, &trace.actions).map_err(|e| e.to_string())?;
+    let states = std::iter::once(&replay.initial_state)
+        .chain(replay.steps.iter().map(|s| &s.state))
+        .collect::<Vec<_>>();
+    let mut holds_at = states
+        .iter()
+        .map(|state| (property.condition)(&model, state));
+    let holds = match property.expectation {
+        Expectation::Always => holds_at.all(|h| h),
+        Expectation::Sometimes => holds_at.any(|h| h),
+        Expectation::Eventually => {
+            if holds_at.any(|h| h) {
+                true
+            } else {
+                // never holding is only a counterexample if the trace can't go on to make it hold
+                let last = states.last().unwrap();
+                let mut enabled = Vec::new();
+                stateright::Model::actions(&model, last, &mut enabled);
+                let cycle = states[..states.len() - 1].contains(last);
+                if !enabled.is_empty() && !cycle {
+                    return Err(format!(
+                        "trace for eventually property {:?} ends without a terminal state or cycle",
+                        trace.property
+                    ));
+                }
+                false
+            }
+        }
+    };
+    let outcome = if holds {
+        Outcome::Holds
+    } else {
+        Outcome::Fails
+    };
+    if outcome == trace.expected {
+        Ok(())
+    } else {
+        Err(format!(
+            "expected {:?} to be {:?} but was {:?}",
+            trace.property, trace.expected, outcome
+        ))
+    }
+}
+
+#[test_log::test]
+fn test_corpus() {
+    let corpus = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus");
+    let mut traces = std::fs::read_dir(corpus)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().map_or(false, |ext| ext == "json"))
+        .collect::<Vec<_>>();
+    traces.sort();
+    assert!(!traces.is_empty());
+
+    let failures = traces
+        .iter()
+        .filter_map(|path| check_trace(path).err().map(|e| format!("{path:?}: {e}")))
+        .collect::<Vec<_>>();
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+/// The name of the file in the corpus holding the counterexample for a known failure.
+fn trace_file(scenario: &str, consistency: &ConsistencySetup, controllers: usize) -> String {
+    let scenario = scenario.trim_end_matches(".yaml");
+    format!("{scenario}-{consistency}-{controllers}.json")
+}
+
+/// Record a counterexample for each of the known failures into the corpus, to be run after
+/// changing the model in a way that changes the traces.
+#[test_log::test]
+#[ignore = "records the corpus rather than checking it"]
+fn record_known_failing() {
+    let corpus = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus");
+    for (scenario, consistency, controllers) in KNOWN_FAILING {
+        let model = model(scenario, consistency.clone(), controllers)
+            .unwrap()
+            .into_abstract_model();
+        let checker = model
+            .checker()
+            .finish_when(HasDiscoveries::AnyFailures)
+            .target_max_depth(100)
+            .timeout(Duration::from_secs(60))
+            .spawn_simulation(0, UniformChooser)
+            .join();
+        let (property, discovery) = checker
+            .discoveries()
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| panic!("no failure found for {scenario} {consistency}"));
+        let actions = discovery
+            .into_vec()
+            .into_iter()
+            .filter_map(|(_, action)| action)
+            .collect::<Vec<_>>();
+        let trace = serde_json::json!({
+            "scenario": scenario,
+            "consistency": consistency.to_string(),
+            "controllers": controllers,
+            "property": property,
+            "expected": "fails",
+            "actions": actions,
+        });
+        let path = corpus.join(trace_file(scenario, &consistency, controllers));
+        std::fs::write(&path, serde_json::to_string_pretty(&trace).unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,40 @@
+use stateright::Checker;
+use stateright::Model;
+use std::path::Path;
+use themelios::scenario::Scenario;
+
+#[test_log::test]
+fn test_coverage() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/scenarios/overlapping-rss.yaml");
+    let model = Scenario::load(&path)
+        .unwrap()
+        .into_model_cfg()
+        .into_abstract_model();
+    let coverage = model.coverage.clone();
+    model.checker().target_max_depth(4).spawn_bfs().join();
+    let counts = coverage.counts();
+
+    assert!(counts
+        .operations
+        .contains_key(&("Node".to_owned(), "NodeJoin")));
+    assert!(counts
+        .operations
+        .contains_key(&("ReplicaSet".to_owned(), "CreatePod")));
+    for (key, rejected) in &counts.rejected {
+        assert!(counts.operations[key] >= *rejected);
+    }
+
+    // every property is evaluated on every state
+    let evaluated = counts
+        .properties
+        .values()
+        .map(|p| p.evaluated)
+        .collect::<Vec<_>>();
+    assert!(evaluated[0] > 0);
+    assert!(evaluated.iter().all(|e| *e == evaluated[0]));
+    // the replicaset properties only check stable replicasets
+    assert!(counts
+        .properties
+        .values()
+        .any(|p| p.antecedent_held.is_some_and(|h| h <= p.evaluated)));
+}
@@ -0,0 +1,176 @@
+use common::run;
+use common::test_table;
+use common::test_table_panic;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use stdext::function_name;
+use themelios::abstract_model::{Change, ControllerAction};
+use themelios::controller::{Controller, CronJobController};
+use themelios::controller_properties::Properties;
+use themelios::model::OrchestrationModelCfg;
+use themelios::resources::ConcurrencyPolicy;
+use themelios::resources::Container;
+use themelios::resources::CronJob;
+use themelios::resources::Metadata;
+use themelios::resources::PodSpec;
+use themelios::resources::PodTemplateSpec;
+use themelios::state::history::ConsistencySetup;
+use themelios::state::{RawState, State};
+use themelios::utils;
+
+mod common;
+
+fn model(
+    cronjobs: impl IntoIterator<Item = CronJob>,
+    clock_ticks: u32,
+    consistency: ConsistencySetup,
+    controllers: usize,
+) -> OrchestrationModelCfg {
+    let initial_state = RawState::default().with_cronjobs(cronjobs);
+    OrchestrationModelCfg {
+        initial_state,
+        consistency_level: consistency,
+        controller_consistency: BTreeMap::new(),
+        read_modes: BTreeMap::new(),
+        leader_election: BTreeSet::new(),
+        fencing: false,
+        quiescence_bound: None,
+        partial_order_reduction: false,
+        schedulers: 0,
+        nodes: 0,
+        replicaset_controllers: 0,
+        deployment_controllers: 0,
+        statefulset_controllers: 0,
+        daemonset_controllers: 0,
+        job_controllers: 0,
+        cronjob_controllers: controllers,
+        hpa_controllers: 0,
+        podgc_controllers: 0,
+        garbage_collector_controllers: 0,
+        clock_ticks,
+        properties: Properties::default(),
+    }
+}
+
+fn new_cronjob(name: &str, concurrency_policy: ConcurrencyPolicy) -> CronJob {
+    let mut cj = CronJob {
+        metadata: utils::metadata(name.to_owned()),
+        ..Default::default()
+    };
+    cj.spec.schedule = "* * * * *".to_owned();
+    cj.spec.concurrency_policy = concurrency_policy;
+    cj.spec.job_template.spec.template = PodTemplateSpec {
+        metadata: Metadata::default(),
+        spec: PodSpec {
+            containers: vec![Container {
+                name: "fake".to_owned(),
+                image: "fake".to_owned(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        },
+    };
+    cj
+}
+
+/// Step the cronjob controller on the latest state, applying the change it makes.
+fn step(state: &mut State) -> Option<ControllerAction> {
+    let mut local = Default::default();
+    let operation: ControllerAction = CronJobController.step(&state.latest(), &mut local)?.into();
+    state.push_change(Change {
+        revision: state.max_revision(),
+        controller: Some(0),
+        operation: operation.clone(),
+    });
+    assert_eq!(state.rejection(), None);
+    Some(operation)
+}
+
+test_table! {
+    test_forbid_cronjob,
+    synchronous_1(ConsistencySetup::Synchronous, 1),
+    synchronous_2(ConsistencySetup::Synchronous, 2),
+    monotonic_session_1(ConsistencySetup::MonotonicSession, 1),
+    monotonic_session_2(ConsistencySetup::MonotonicSession, 2),
+    optimistic_linear_1(ConsistencySetup::OptimisticLinear, 1),
+    optimistic_linear_2(ConsistencySetup::OptimisticLinear, 2),
+    causal_1(ConsistencySetup::Causal, 1),
+    causal_2(ConsistencySetup::Causal, 2),
+    informer_1(ConsistencySetup::Informer, 1),
+}
+
+// a restarted controller can read from before the last job was created and start another for a
+// later schedule
+test_table_panic! {
+    test_forbid_cronjob,
+    resettable_session_1(ConsistencySetup::ResettableSession, 1),
+    resettable_session_2(ConsistencySetup::ResettableSession, 2),
+}
+
+// writes don't reach the replica the controller reads from until it is replicated to, so a
+// restarted controller can read from before the job it created and start another for a later
+// schedule
+test_table_panic! {
+    test_forbid_cronjob,
+    replicated_3_1(ConsistencySetup::Replicated { replicas: 3 }, 1),
+    replicated_3_2(ConsistencySetup::Replicated { replicas: 3 }, 2),
+}
+
+// the other controller's informer may not have received the job that one created yet, so it
+// starts another for a later schedule
+test_table_panic! {
+    test_forbid_cronjob,
+    informer_2(ConsistencySetup::Informer, 2),
+}
+
+fn test_forbid_cronjob(consistency: ConsistencySetup, controllers: usize) -> OrchestrationModelCfg {
+    let cj = new_cronjob("foo", ConcurrencyPolicy::Forbid);
+    model([cj], 2, consistency, controllers)
+}
+
+test_table! {
+    test_allow_cronjob_quiescence,
+    synchronous_1(ConsistencySetup::Synchronous, 1),
+    synchronous_2(ConsistencySetup::Synchronous, 2),
+}
+
+// each tick starts another job, creating it and recording it in the status, which would exceed the
+// bound if ticks weren't perturbations
+fn test_allow_cronjob_quiescence(
+    consistency: ConsistencySetup,
+    controllers: usize,
+) -> OrchestrationModelCfg {
+    let cj = new_cronjob("foo", ConcurrencyPolicy::Allow);
+    let mut model = model([cj], 2, consistency, controllers);
+    model.quiescence_bound = Some(2);
+    model
+}
+
+#[test_log::test]
+fn test_cronjob_runs_on_schedule() {
+    let cj = new_cronjob("foo", ConcurrencyPolicy::Forbid);
+    let mut state = State::new(
+        RawState::default().with_cronjobs([cj]),
+        ConsistencySetup::Synchronous,
+    );
+
+    // nothing is due until the clock reaches the first scheduled minute
+    assert_eq!(step(&mut state), None);
+
+    state.tick_clock();
+    let Some(ControllerAction::CreateJob(job)) = step(&mut state) else {
+        panic!("expected a job to be created");
+    };
+    assert_eq!(job.metadata.name, "foo-1");
+    let Some(ControllerAction::UpdateCronJobStatus(cj)) = step(&mut state) else {
+        panic!("expected the job to be recorded as active");
+    };
+    assert_eq!(cj.status.active.len(), 1);
+    assert_eq!(cj.status.last_schedule_time, Some(state.now()));
+    assert_eq!(step(&mut state), None);
+
+    // the previous job is still running so the next one is forbidden
+    state.tick_clock();
+    assert_eq!(step(&mut state), None);
+    assert_eq!(state.latest().jobs.len(), 1);
+}
@@ -0,0 +1,164 @@
+use common::run;
+use common::test_table;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use stdext::function_name;
+use themelios::abstract_model::{Change, ControllerAction};
+use themelios::controller::{Controller, DaemonSetController};
+use themelios::controller_properties::Properties;
+use themelios::model::OrchestrationModelCfg;
+use themelios::resources::Container;
+use themelios::resources::DaemonSet;
+use themelios::resources::Metadata;
+use themelios::resources::Node;
+use themelios::resources::PodSpec;
+use themelios::resources::PodTemplateSpec;
+use themelios::resources::LABEL_HOSTNAME;
+use themelios::state::history::ConsistencySetup;
+use themelios::state::{RawState, State};
+use themelios::utils;
+
+mod common;
+
+fn model(
+    daemonsets: impl IntoIterator<Item = DaemonSet>,
+    nodes: usize,
+    consistency: ConsistencySetup,
+    controllers: usize,
+) -> OrchestrationModelCfg {
+    let initial_state = RawState::default().with_daemonsets(daemonsets);
+    OrchestrationModelCfg {
+        initial_state,
+        consistency_level: consistency,
+        controller_consistency: BTreeMap::new(),
+        read_modes: BTreeMap::new(),
+        leader_election: BTreeSet::new(),
+        fencing: false,
+        quiescence_bound: None,
+        partial_order_reduction: false,
+        schedulers: 0,
+        nodes,
+        replicaset_controllers: 0,
+        deployment_controllers: 0,
+        statefulset_controllers: 0,
+        daemonset_controllers: controllers,
+        job_controllers: 0,
+        cronjob_controllers: 0,
+        hpa_controllers: 0,
+        podgc_controllers: controllers,
+        garbage_collector_controllers: 0,
+        clock_ticks: 0,
+        properties: Properties::default(),
+    }
+}
+
+fn new_daemonset(name: &str) -> DaemonSet {
+    let mut d = DaemonSet {
+        metadata: utils::metadata(name.to_owned()),
+        ..Default::default()
+    };
+    let mut test_labels = BTreeMap::new();
+    test_labels.insert("name".to_owned(), "test".to_owned());
+    d.spec.selector.match_labels = test_labels.clone();
+    d.spec.template = PodTemplateSpec {
+        metadata: Metadata {
+            labels: test_labels,
+            ..Default::default()
+        },
+        spec: PodSpec {
+            containers: vec![Container {
+                name: "fake".to_owned(),
+                image: "fake".to_owned(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        },
+    };
+    d
+}
+
+fn new_node(name: &str) -> Node {
+    let mut node = Node {
+        metadata: utils::metadata(name.to_owned()),
+        ..Default::default()
+    };
+    node.metadata
+        .labels
+        .insert(LABEL_HOSTNAME.to_owned(), name.to_owned());
+    node
+}
+
+/// Step the daemonset controller on the latest state, applying the change it makes.
+fn step(state: &mut State) -> Option<ControllerAction> {
+    let mut local = Default::default();
+    let operation: ControllerAction = DaemonSetController
+        .step(&state.latest(), &mut local)?
+        .into();
+    state.push_change(Change {
+        revision: state.max_revision(),
+        controller: Some(0),
+        operation: operation.clone(),
+    });
+    assert_eq!(state.rejection(), None);
+    Some(operation)
+}
+
+test_table! {
+    test_simple_daemonset_launches_pods,
+    synchronous_1(ConsistencySetup::Synchronous, 1),
+    synchronous_2(ConsistencySetup::Synchronous, 2),
+    monotonic_session_1(ConsistencySetup::MonotonicSession, 1),
+    monotonic_session_2(ConsistencySetup::MonotonicSession, 2),
+    resettable_session_1(ConsistencySetup::ResettableSession, 1),
+    resettable_session_2(ConsistencySetup::ResettableSession, 2),
+    optimistic_linear_1(ConsistencySetup::OptimisticLinear, 1),
+    optimistic_linear_2(ConsistencySetup::OptimisticLinear, 2),
+    causal_1(ConsistencySetup::Causal, 1),
+    causal_2(ConsistencySetup::Causal, 2),
+}
+
+// TestSimpleDaemonSetLaunchesPods
+fn test_simple_daemonset_launches_pods(
+    consistency: ConsistencySetup,
+    controllers: usize,
+) -> OrchestrationModelCfg {
+    let ds = new_daemonset("foo");
+    model([ds], 2, consistency, controllers)
+}
+
+#[test_log::test]
+fn test_node_selector_limits_daemon_pods() {
+    let mut ds = new_daemonset("foo");
+    ds.spec
+        .template
+        .spec
+        .node_selector
+        .insert(LABEL_HOSTNAME.to_owned(), "node-1".to_owned());
+    let mut state = State::new(
+        RawState::default()
+            .with_nodes([new_node("node-0"), new_node("node-1")])
+            .with_daemonsets([ds]),
+        ConsistencySetup::Synchronous,
+    );
+
+    assert!(matches!(
+        step(&mut state),
+        Some(ControllerAction::CreateControllerRevision(_))
+    ));
+    let Some(ControllerAction::CreatePod(pod)) = step(&mut state) else {
+        panic!("expected a daemon pod to be created");
+    };
+    assert_eq!(pod.spec.node_name.as_deref(), Some("node-1"));
+    assert!(matches!(
+        step(&mut state),
+        Some(ControllerAction::UpdateDaemonSetStatus(_))
+    ));
+    assert_eq!(step(&mut state), None);
+
+    let latest = state.latest();
+    assert_eq!(latest.pods.len(), 1);
+    let ds = latest.daemonsets.iter().next().unwrap();
+    assert_eq!(ds.status.desired_number_scheduled, 1);
+    assert_eq!(ds.status.current_number_scheduled, 1);
+    assert_eq!(ds.status.number_misscheduled, 0);
+}
@@ -0,0 +1,192 @@
+use themelios::abstract_model::{Change, ControllerAction};
+use themelios::controller::{Controller, GarbageCollectorController};
+use themelios::resources::{
+    Deployment, OwnerReference, Pod, PropagationPolicy, ReplicaSet, FOREGROUND_DELETION_FINALIZER,
+    ORPHAN_FINALIZER,
+};
+use themelios::state::history::ConsistencySetup;
+use themelios::state::resources::DEFAULT_NAMESPACE;
+use themelios::state::{RawState, State};
+use themelios::utils;
+
+fn owner_reference(kind: &str, name: &str) -> OwnerReference {
+    OwnerReference {
+        api_version: "apps/v1".to_owned(),
+        kind: kind.to_owned(),
+        name: name.to_owned(),
+        uid: name.to_owned(),
+        block_owner_deletion: true,
+        controller: true,
+    }
+}
+
+fn replicaset(name: &str, owner: Option<&str>) -> ReplicaSet {
+    let mut rs = ReplicaSet {
+        metadata: utils::metadata(name.to_owned()),
+        ..Default::default()
+    };
+    rs.metadata.uid = name.to_owned();
+    rs.metadata.owner_references = owner
+        .map(|o| vec![owner_reference("Deployment", o)])
+        .unwrap_or_default();
+    rs
+}
+
+fn pod(name: &str, owner: &str) -> Pod {
+    let mut pod = Pod {
+        metadata: utils::metadata(name.to_owned()),
+        ..Default::default()
+    };
+    pod.metadata.owner_references = vec![owner_reference("ReplicaSet", owner)];
+    pod
+}
+
+/// Step the garbage collector on the latest state, applying the change it makes.
+fn step(state: &mut State) -> Option<ControllerAction> {
+    let mut local = Default::default();
+    let operation: ControllerAction = GarbageCollectorController
+        .step(&state.latest(), &mut local)?
+        .into();
+    state.push_change(Change {
+        revision: state.max_revision(),
+        controller: Some(0),
+        operation: operation.clone(),
+    });
+    assert_eq!(state.rejection(), None);
+    Some(operation)
+}
+
+#[test_log::test]
+fn test_dangling_dependents_are_deleted() {
+    let mut state = State::new(
+        RawState::default().with_pods([pod("pod", "missing")]),
+        ConsistencySetup::Synchronous,
+    );
+    assert!(matches!(
+        step(&mut state),
+        Some(ControllerAction::SoftDeletePod(_))
+    ));
+    let latest = state.latest();
+    let pod = latest.pods.get(DEFAULT_NAMESPACE, "pod").unwrap();
+    assert!(pod.metadata.deletion_timestamp.is_some());
+    // nothing more for the collector to do, the node removes the pod
+    assert_eq!(step(&mut state), None);
+}
+
+#[test_log::test]
+fn test_foreground_deletion() {
+    let mut deployment = Deployment {
+        metadata: utils::metadata("dep".to_owned()),
+        ..Default::default()
+    };
+    deployment.metadata.uid = "dep".to_owned();
+    let mut raw = RawState::default()
+        .with_deployments([deployment])
+        .with_replicasets([replicaset("rs", Some("dep"))]);
+    let dep = raw
+        .deployments
+        .get(DEFAULT_NAMESPACE, "dep")
+        .unwrap()
+        .clone();
+    raw.deployments
+        .delete(
+            &dep,
+            PropagationPolicy::Foreground,
+            Default::default(),
+            Default::default(),
+        )
+        .unwrap();
+    let dep = raw.deployments.get(DEFAULT_NAMESPACE, "dep").unwrap();
+    assert_eq!(dep.metadata.finalizers, vec![FOREGROUND_DELETION_FINALIZER]);
+    let mut state = State::new(raw, ConsistencySetup::Synchronous);
+
+    // the dependent goes first
+    assert!(matches!(
+        step(&mut state),
+        Some(ControllerAction::DeleteReplicaSet(..))
+    ));
+    assert!(state.latest().replicasets.is_empty());
+    assert!(state.latest().deployments.has(DEFAULT_NAMESPACE, "dep"));
+
+    // then the owner once its finalizer is removed
+    assert!(matches!(
+        step(&mut state),
+        Some(ControllerAction::UpdateDeployment(_))
+    ));
+    assert!(state.latest().deployments.is_empty());
+    assert_eq!(step(&mut state), None);
+}
+
+#[test_log::test]
+fn test_orphan_deletion() {
+    let mut raw = RawState::default()
+        .with_replicasets([replicaset("rs", None)])
+        .with_pods([pod("pod", "rs")]);
+    let rs = raw
+        .replicasets
+        .get(DEFAULT_NAMESPACE, "rs")
+        .unwrap()
+        .clone();
+    raw.replicasets
+        .delete(
+            &rs,
+            PropagationPolicy::Orphan,
+            Default::default(),
+            Default::default(),
+        )
+        .unwrap();
+    let rs = raw.replicasets.get(DEFAULT_NAMESPACE, "rs").unwrap();
+    assert_eq!(rs.metadata.finalizers, vec![ORPHAN_FINALIZER]);
+    let mut state = State::new(raw, ConsistencySetup::Synchronous);
+
+    // the dependent has its reference removed
+    assert!(matches!(
+        step(&mut state),
+        Some(ControllerAction::UpdatePod(_))
+    ));
+    let latest = state.latest();
+    let pod = latest.pods.get(DEFAULT_NAMESPACE, "pod").unwrap();
+    assert!(pod.metadata.owner_references.is_empty());
+    assert!(pod.metadata.deletion_timestamp.is_none());
+
+    // then the owner goes, leaving the pod
+    assert!(matches!(
+        step(&mut state),
+        Some(ControllerAction::UpdateReplicaSet(_))
+    ));
+    assert!(state.latest().replicasets.is_empty());
+    assert_eq!(state.latest().pods.len(), 1);
+    assert_eq!(step(&mut state), None);
+}
+
+#[test_log::test]
+fn test_foreground_deletion_of_non_controller_owner() {
+    let mut dependent = pod("pod", "rs");
+    dependent.metadata.owner_references[0].controller = false;
+    let mut raw = RawState::default()
+        .with_replicasets([replicaset("rs", None)])
+        .with_pods([dependent]);
+    let rs = raw
+        .replicasets
+        .get(DEFAULT_NAMESPACE, "rs")
+        .unwrap()
+        .clone();
+    raw.replicasets
+        .delete(
+            &rs,
+            PropagationPolicy::Foreground,
+            Default::default(),
+            Default::default(),
+        )
+        .unwrap();
+    let mut state = State::new(raw, ConsistencySetup::Synchronous);
+
+    // dependents are found by any reference to the owner, not just the controller one
+    assert!(matches!(
+        step(&mut state),
+        Some(ControllerAction::SoftDeletePod(_))
+    ));
+    let latest = state.latest();
+    let pod = latest.pods.get(DEFAULT_NAMESPACE, "pod").unwrap();
+    assert!(pod.metadata.deletion_timestamp.is_some());
+}
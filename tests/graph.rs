@@ -0,0 +1,57 @@
+use stateright::Model;
+use std::path::Path;
+use themelios::abstract_model::Action;
+use themelios::graph;
+use themelios::scenario::Scenario;
+use themelios::state::history::ConsistencySetup;
+
+#[test_log::test]
+fn test_export_dot() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/scenarios/overlapping-rss.yaml");
+    let model = Scenario::load(&path)
+        .unwrap()
+        .into_model_cfg()
+        .into_abstract_model();
+    let mut out = Vec::new();
+    graph::export_dot(&model, 2, &mut out).unwrap();
+    let dot = String::from_utf8(out).unwrap();
+    assert!(dot.starts_with("digraph states {"));
+    assert!(dot.contains("0 [label=\"pods: \\ntest-overlapping-rss-1: 0/1"));
+    assert!(dot.contains("0 -> 1 [label=\"ControllerStep"));
+    assert!(dot.trim_end().ends_with('}'));
+}
+
+#[test_log::test]
+fn test_causal_history_graph() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/scenarios/overlapping-rss.yaml");
+    let mut model = Scenario::load(&path).unwrap().into_model_cfg();
+    model.consistency_level = ConsistencySetup::Causal;
+    let model = model.into_abstract_model();
+
+    // let the node join and then the replicaset controller create a pod having seen it
+    let mut state = model.init_states().remove(0);
+    for controller in [0, 2] {
+        let mut actions = Vec::new();
+        model.actions(&state, &mut actions);
+        let step = actions
+            .into_iter()
+            .find(|a| matches!(a, Action::ControllerStep(_, c) if *c == controller))
+            .unwrap();
+        state = model.next_state(&state, step).unwrap();
+    }
+
+    let graph = state.history_graph().unwrap();
+    assert_eq!(graph.nodes.len(), 3);
+    assert_eq!(graph.nodes[1].controller, Some(0));
+    assert_eq!(graph.nodes[2].controller, Some(2));
+    assert_eq!(graph.nodes[2].predecessors, vec![1]);
+    assert!(graph.nodes[1].concurrent.is_empty());
+    assert!(!graph.nodes[1].head && graph.nodes[2].head);
+    assert!(graph
+        .to_dot(&[
+            "Node".to_owned(),
+            "Scheduler".to_owned(),
+            "ReplicaSet".to_owned()
+        ])
+        .contains("2 [label=\"2\\nReplicaSet (2)\", peripheries=2];"));
+}
@@ -0,0 +1,247 @@
+use common::run;
+use common::test_table;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use stdext::function_name;
+use themelios::abstract_model::{Change, ControllerAction};
+use themelios::controller::{Controller, ControllerStates, HPAController};
+use themelios::controller_properties::Properties;
+use themelios::model::OrchestrationModelCfg;
+use themelios::resources::Container;
+use themelios::resources::CrossVersionObjectReference;
+use themelios::resources::Deployment;
+use themelios::resources::DeploymentSpec;
+use themelios::resources::HorizontalPodAutoscaler;
+use themelios::resources::HorizontalPodAutoscalerSpec;
+use themelios::resources::Metadata;
+use themelios::resources::PodSpec;
+use themelios::resources::PodTemplateSpec;
+use themelios::state::history::ConsistencySetup;
+use themelios::state::resources::DEFAULT_NAMESPACE;
+use themelios::state::{RawState, State};
+use themelios::utils;
+
+mod common;
+
+fn model(
+    hpas: impl IntoIterator<Item = HorizontalPodAutoscaler>,
+    deployments: impl IntoIterator<Item = Deployment>,
+    consistency: ConsistencySetup,
+    controllers: usize,
+) -> OrchestrationModelCfg {
+    let initial_state = RawState::default()
+        .with_horizontal_pod_autoscalers(hpas)
+        .with_deployments(deployments);
+    OrchestrationModelCfg {
+        initial_state,
+        consistency_level: consistency,
+        controller_consistency: BTreeMap::new(),
+        read_modes: BTreeMap::new(),
+        leader_election: BTreeSet::new(),
+        fencing: false,
+        quiescence_bound: None,
+        partial_order_reduction: false,
+        schedulers: 0,
+        nodes: 0,
+        replicaset_controllers: 0,
+        deployment_controllers: 0,
+        statefulset_controllers: 0,
+        daemonset_controllers: 0,
+        job_controllers: 0,
+        cronjob_controllers: 0,
+        hpa_controllers: controllers,
+        podgc_controllers: 0,
+        garbage_collector_controllers: 0,
+        clock_ticks: 0,
+        properties: Properties::default(),
+    }
+}
+
+fn new_deployment(name: &str, replicas: u32) -> Deployment {
+    let mut d = Deployment {
+        metadata: utils::metadata(name.to_owned()),
+        spec: DeploymentSpec {
+            replicas,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut test_labels = BTreeMap::new();
+    test_labels.insert("name".to_owned(), "test".to_owned());
+    d.spec.selector.match_labels = test_labels.clone();
+    d.spec.template = PodTemplateSpec {
+        metadata: Metadata {
+            labels: test_labels,
+            ..Default::default()
+        },
+        spec: PodSpec {
+            containers: vec![Container {
+                name: "fake".to_owned(),
+                image: "fake".to_owned(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        },
+    };
+    d
+}
+
+fn new_hpa(
+    name: &str,
+    target: &str,
+    min_replicas: u32,
+    max_replicas: u32,
+) -> HorizontalPodAutoscaler {
+    HorizontalPodAutoscaler {
+        metadata: utils::metadata(name.to_owned()),
+        spec: HorizontalPodAutoscalerSpec {
+            scale_target_ref: CrossVersionObjectReference {
+                kind: "Deployment".to_owned(),
+                name: target.to_owned(),
+                api_version: "apps/v1".to_owned(),
+            },
+            min_replicas: Some(min_replicas),
+            max_replicas,
+            target_cpu_utilization_percentage: None,
+        },
+        ..Default::default()
+    }
+}
+
+/// Step the autoscaler on the latest state, applying the change it makes.
+fn step(state: &mut State) -> Option<ControllerAction> {
+    let mut local = Default::default();
+    let operation: ControllerAction = HPAController.step(&state.latest(), &mut local)?.into();
+    state.push_change(Change {
+        revision: state.max_revision(),
+        controller: Some(0),
+        operation: operation.clone(),
+    });
+    assert_eq!(state.rejection(), None);
+    Some(operation)
+}
+
+/// Set the cpu utilization of the only autoscaler, as the client would.
+fn set_utilization(state: &mut State, utilization: u32) {
+    let mut hpa = state
+        .latest()
+        .horizontal_pod_autoscalers
+        .iter()
+        .next()
+        .unwrap()
+        .clone();
+    hpa.status.current_cpu_utilization_percentage = Some(utilization);
+    state.push_change(Change {
+        revision: state.max_revision(),
+        controller: None,
+        operation: ControllerAction::UpdateHorizontalPodAutoscalerStatus(hpa),
+    });
+    assert_eq!(state.rejection(), None);
+}
+
+fn deployment_replicas(state: &State) -> u32 {
+    state
+        .latest()
+        .deployments
+        .iter()
+        .next()
+        .unwrap()
+        .spec
+        .replicas
+}
+
+test_table! {
+    test_autoscale_deployment,
+    synchronous_1(ConsistencySetup::Synchronous, 1),
+    synchronous_2(ConsistencySetup::Synchronous, 2),
+    monotonic_session_1(ConsistencySetup::MonotonicSession, 1),
+    monotonic_session_2(ConsistencySetup::MonotonicSession, 2),
+    resettable_session_1(ConsistencySetup::ResettableSession, 1),
+    resettable_session_2(ConsistencySetup::ResettableSession, 2),
+    optimistic_linear_1(ConsistencySetup::OptimisticLinear, 1),
+    optimistic_linear_2(ConsistencySetup::OptimisticLinear, 2),
+    causal_1(ConsistencySetup::Causal, 1),
+    causal_2(ConsistencySetup::Causal, 2),
+}
+
+fn test_autoscale_deployment(
+    consistency: ConsistencySetup,
+    controllers: usize,
+) -> OrchestrationModelCfg {
+    let d = new_deployment("web", 1);
+    let hpa = new_hpa("web", "web", 1, 3);
+    model([hpa], [d], consistency, controllers)
+}
+
+#[test_log::test]
+fn test_scale_up_to_max_replicas() {
+    let d = new_deployment("web", 1);
+    let hpa = new_hpa("web", "web", 1, 3);
+    let mut state = State::new(
+        RawState::default()
+            .with_horizontal_pod_autoscalers([hpa])
+            .with_deployments([d]),
+        ConsistencySetup::Synchronous,
+    );
+
+    // without a metric the autoscaler just records the current replicas
+    let Some(ControllerAction::UpdateHorizontalPodAutoscalerStatus(hpa)) = step(&mut state) else {
+        panic!("expected the autoscaler to record its status");
+    };
+    assert_eq!(hpa.status.desired_replicas, 1);
+    assert_eq!(step(&mut state), None);
+
+    // doubling the load doubles the replicas, until they reach the max
+    set_utilization(&mut state, 160);
+    while step(&mut state).is_some() {}
+    assert_eq!(deployment_replicas(&state), 3);
+    let hpa = state
+        .latest()
+        .horizontal_pod_autoscalers
+        .iter()
+        .next()
+        .unwrap()
+        .clone();
+    assert_eq!(hpa.status.current_replicas, 3);
+    assert_eq!(hpa.status.desired_replicas, 3);
+}
+
+#[test_log::test]
+fn test_client_scale_within_bounds_is_kept() {
+    let d = new_deployment("web", 1);
+    let hpa = new_hpa("web", "web", 1, 3);
+    let mut state = State::new(
+        RawState::default()
+            .with_horizontal_pod_autoscalers([hpa])
+            .with_deployments([d]),
+        ConsistencySetup::Synchronous,
+    );
+    state.add_controller(ControllerStates::HPA(Default::default()));
+    set_utilization(&mut state, 80);
+    while step(&mut state).is_some() {}
+
+    // the client scales up and the load stays on target, so the autoscaler leaves it alone
+    let mut d = state.latest().deployments.iter().next().unwrap().clone();
+    d.spec.replicas = 2;
+    state.push_change(Change {
+        revision: state.max_revision(),
+        controller: None,
+        operation: ControllerAction::UpdateDeployment(d),
+    });
+    while step(&mut state).is_some() {}
+    assert_eq!(deployment_replicas(&state), 2);
+    assert!(!state.autoscaled("Deployment", DEFAULT_NAMESPACE, "web"));
+
+    // scaling past the max is brought back within bounds
+    let mut d = state.latest().deployments.iter().next().unwrap().clone();
+    d.spec.replicas = 5;
+    state.push_change(Change {
+        revision: state.max_revision(),
+        controller: None,
+        operation: ControllerAction::UpdateDeployment(d),
+    });
+    while step(&mut state).is_some() {}
+    assert_eq!(deployment_replicas(&state), 3);
+    // and the autoscaler was the last to set them
+    assert!(state.autoscaled("Deployment", DEFAULT_NAMESPACE, "web"));
+}
@@ -0,0 +1,152 @@
+use themelios::abstract_model::{Change, ControllerAction};
+use themelios::controller::ControllerStates;
+use themelios::resources::{Node, Pod, ReplicaSet};
+use themelios::state::history::{ConsistencySetup, ReadMode};
+use themelios::state::resources::DEFAULT_NAMESPACE;
+use themelios::state::revision::Revision;
+use themelios::state::{RawState, State, StateView};
+use themelios::utils;
+
+fn write(state: &mut State, operation: ControllerAction) {
+    state.push_change(Change {
+        revision: state.max_revision(),
+        controller: None,
+        operation,
+    });
+    assert_eq!(state.rejection(), None);
+}
+
+#[test_log::test]
+fn test_events_delivered_in_commit_order() {
+    let node = Node {
+        metadata: utils::metadata("node-0".to_owned()),
+        ..Default::default()
+    };
+    let mut state = State::new(
+        RawState::default().with_nodes([node.clone()]),
+        ConsistencySetup::Informer,
+    );
+    state.add_controller(ControllerStates::ReplicaSet(Default::default()));
+
+    let pod = Pod {
+        metadata: utils::metadata("pod".to_owned()),
+        ..Default::default()
+    };
+    write(&mut state, ControllerAction::CreatePod(pod));
+    write(&mut state, ControllerAction::DeleteNode(node));
+    assert_eq!(state.pending_informers(), vec![0]);
+
+    // the pod was created before the node was deleted, even though nodes come first by kind
+    state.deliver_event(0);
+    let revisions = state.revisions(0, ReadMode::AnyValid, None);
+    assert_eq!(revisions.len(), 1);
+    let view = state.view_for(0, ReadMode::AnyValid, &revisions[0]);
+    assert_eq!(view.pods.len(), 1);
+    assert_eq!(view.nodes.len(), 1);
+
+    state.deliver_event(0);
+    let view = state.view_for(0, ReadMode::AnyValid, &state.max_revision());
+    assert_eq!(view.pods.len(), 1);
+    assert_eq!(view.nodes.len(), 0);
+    assert!(state.pending_informers().is_empty());
+}
+
+#[test_log::test]
+fn test_partially_delivered_change() {
+    let replicasets = ["rs-a", "rs-b"].map(|name| ReplicaSet {
+        metadata: utils::metadata(name.to_owned()),
+        ..Default::default()
+    });
+    let mut state = State::new(
+        RawState::default().with_replicasets(replicasets),
+        ConsistencySetup::Informer,
+    );
+    state.add_controller(ControllerStates::ReplicaSet(Default::default()));
+
+    // scale both replicasets in a single change
+    let scaled = state
+        .latest()
+        .replicasets
+        .iter()
+        .map(|rs| {
+            let mut rs = rs.clone();
+            rs.spec.replicas = Some(2);
+            rs
+        })
+        .collect();
+    let initial = state.max_revision();
+    write(&mut state, ControllerAction::UpdateReplicaSets(scaled));
+    let revision = state.max_revision();
+
+    // the controller observes the change part way through its events
+    state.deliver_event(0);
+    assert_eq!(
+        state.revisions(0, ReadMode::AnyValid, None),
+        vec![revision.clone()]
+    );
+    let scaled_count = |view: &StateView| {
+        ["rs-a", "rs-b"]
+            .iter()
+            .filter(|name| {
+                view.replicasets
+                    .get(DEFAULT_NAMESPACE, name)
+                    .unwrap()
+                    .spec
+                    .replicas
+                    == Some(2)
+            })
+            .count()
+    };
+    assert_eq!(
+        scaled_count(&state.view_for(0, ReadMode::AnyValid, &revision)),
+        1
+    );
+    // whereas a quorum read sees the whole change
+    assert_eq!(
+        scaled_count(&state.view_for(0, ReadMode::Latest, &revision)),
+        2
+    );
+    // and reading at a revision the cache isn't at gives that revision
+    assert_eq!(
+        scaled_count(&state.view_for(0, ReadMode::AnyValid, &initial)),
+        0
+    );
+
+    state.deliver_event(0);
+    assert_eq!(
+        scaled_count(&state.view_for(0, ReadMode::AnyValid, &revision)),
+        2
+    );
+    assert!(state.pending_informers().is_empty());
+}
+
+#[test_log::test]
+fn test_deleted_and_recreated_delivered_in_order() {
+    let pod = |revision: usize| {
+        let mut pod = Pod {
+            metadata: utils::metadata("pod".to_owned()),
+            ..Default::default()
+        };
+        pod.metadata.resource_version = Revision::from(vec![revision]);
+        pod
+    };
+    let mut cache = RawState::default().with_pods([pod(1)]);
+    let target = RawState::default().with_pods([pod(3)]);
+    let revision = Revision::from(vec![3]);
+
+    // the pod was deleted before the one with the same name was created in its place
+    assert!(cache.apply_watch_event(&target, &revision));
+    assert!(cache.pods.is_empty());
+
+    assert!(cache.apply_watch_event(&target, &revision));
+    assert_eq!(
+        cache
+            .pods
+            .get(DEFAULT_NAMESPACE, "pod")
+            .unwrap()
+            .metadata
+            .uid,
+        "3"
+    );
+    assert!(!cache.apply_watch_event(&target, &revision));
+}
@@ -2,16 +2,22 @@ use common::run;
 use common::test_table;
 use common::test_table_panic;
 use std::collections::BTreeMap;
+use std::collections::BTreeSet;
 use stdext::function_name;
+use themelios::abstract_model::{Change, ControllerAction};
+use themelios::controller::{Controller, JobController};
+use themelios::controller_properties::Properties;
 use themelios::model::OrchestrationModelCfg;
+use themelios::resources::ConditionStatus;
 use themelios::resources::Container;
 use themelios::resources::Job;
+use themelios::resources::JobConditionType;
 use themelios::resources::JobSpec;
 use themelios::resources::Metadata;
 use themelios::resources::PodSpec;
 use themelios::resources::PodTemplateSpec;
 use themelios::state::history::ConsistencySetup;
-use themelios::state::RawState;
+use themelios::state::{RawState, State};
 use themelios::utils;
 
 mod common;
@@ -25,14 +31,25 @@ fn model(
     OrchestrationModelCfg {
         initial_state,
         consistency_level: consistency,
+        controller_consistency: BTreeMap::new(),
+        read_modes: BTreeMap::new(),
+        leader_election: BTreeSet::new(),
+        fencing: false,
+        quiescence_bound: None,
+        partial_order_reduction: false,
         schedulers: controllers,
         nodes: controllers,
         replicaset_controllers: 0,
         deployment_controllers: 0,
         statefulset_controllers: 0,
+        daemonset_controllers: 0,
         job_controllers: controllers,
+        cronjob_controllers: 0,
+        hpa_controllers: 0,
         podgc_controllers: controllers,
-        properties: Vec::new(),
+        garbage_collector_controllers: 0,
+        clock_ticks: 0,
+        properties: Properties::default(),
     }
 }
 
@@ -130,3 +147,48 @@ test_table_panic! {
 // func TestSuspendJob(t *testing.T) {
 // func TestSuspendJobControllerRestart(t *testing.T) {
 // func TestNodeSelectorUpdate(t *testing.T) {
+
+/// Step the job controller on the latest state until it has nothing left to do.
+fn step_until_done(state: &mut State) {
+    let mut local = Default::default();
+    for _ in 0..20 {
+        let Some(operation) = JobController.step(&state.latest(), &mut local) else {
+            return;
+        };
+        state.push_change(Change {
+            revision: state.max_revision(),
+            controller: Some(0),
+            operation: ControllerAction::from(operation),
+        });
+        assert_eq!(state.rejection(), None);
+    }
+    panic!("job controller didn't settle");
+}
+
+fn failed_reason(state: &State) -> Option<String> {
+    let job = state.latest().jobs.iter().next().unwrap().clone();
+    job.status
+        .conditions
+        .into_iter()
+        .find(|c| c.r#type == JobConditionType::Failed && c.status == ConditionStatus::True)
+        .map(|c| c.reason)
+}
+
+#[test_log::test]
+fn test_active_deadline() {
+    let mut job = new_job("deadline", "");
+    job.spec.active_deadline_seconds = Some(30);
+    let mut state = State::new(
+        RawState::default().with_jobs([job]),
+        ConsistencySetup::Synchronous,
+    );
+
+    // the job starts at the current time so is within its deadline
+    step_until_done(&mut state);
+    assert_eq!(failed_reason(&state), None);
+
+    // once the clock has moved past the deadline the job fails
+    state.tick_clock();
+    step_until_done(&mut state);
+    assert_eq!(failed_reason(&state), Some("DeadlineExceeded".to_owned()));
+}
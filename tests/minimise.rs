@@ -0,0 +1,131 @@
+use stateright::Checker;
+use stateright::HasDiscoveries;
+use stateright::Model;
+use stateright::Property;
+use stateright::UniformChooser;
+use std::path::Path;
+use std::time::Duration;
+use themelios::abstract_model::{AbstractModel, Action};
+use themelios::controller::Controllers;
+use themelios::minimise::Minimiser;
+use themelios::report::JsonReporter;
+use themelios::scenario::Scenario;
+use themelios::state::history::ConsistencySetup;
+use themelios::state::State;
+
+// TestOverlappingRSs with two of each controller, which fails under resettable sessions
+#[test_log::test]
+fn test_minimise_overlapping_rss() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/scenarios/overlapping-rss.yaml");
+    let mut model = Scenario::load(&path).unwrap().into_model_cfg();
+    model.consistency_level = ConsistencySetup::ResettableSession;
+    model.schedulers *= 2;
+    model.nodes *= 2;
+    model.replicaset_controllers *= 2;
+    model.podgc_controllers *= 2;
+
+    let minimiser = Minimiser::new(model.clone().into_abstract_model());
+    let report = std::env::temp_dir().join("themelios-test-minimise-overlapping-rss.jsonl");
+    let mut reporter = JsonReporter::new(&report, model.clone().into_abstract_model()).unwrap();
+    let checker = model
+        .into_abstract_model()
+        .checker()
+        .finish_when(HasDiscoveries::AnyFailures)
+        .target_max_depth(100)
+        .timeout(Duration::from_secs(60))
+        .spawn_simulation(0, UniformChooser)
+        .report(&mut reporter);
+
+    let (property, discovery) = checker.discoveries().into_iter().next().unwrap();
+    let actions = discovery
+        .into_vec()
+        .into_iter()
+        .filter_map(|(_, action)| action)
+        .collect::<Vec<_>>();
+    let trace = minimiser.minimise(property, &actions).unwrap();
+    assert!(trace.actions.len() <= actions.len());
+    assert_eq!(
+        trace.fingerprint_path.split('/').count(),
+        trace.actions.len() + 1
+    );
+
+    // the minimised trace still reproduces the discovery
+    assert!(minimiser.minimise(property, &trace.actions).is_some());
+
+    // the JSON report has the discovery before it was minimised
+    let reported = std::fs::read_to_string(&report).unwrap();
+    let document = reported
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .find(|d| d["property"] == *property)
+        .unwrap();
+    assert_eq!(document["steps"].as_array().unwrap().len(), actions.len());
+}
+
+fn overlapping_rss(nodes: usize) -> AbstractModel {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/scenarios/overlapping-rss.yaml");
+    let mut model = Scenario::load(&path).unwrap().into_model_cfg();
+    model.nodes = nodes;
+    let mut model = model.into_abstract_model();
+    model
+        .properties
+        .push(Property::always("no pods", |_, s: &State| {
+            s.latest().pods.is_empty()
+        }));
+    model
+        .properties
+        .push(Property::eventually("some pods", |_, s: &State| {
+            !s.latest().pods.is_empty()
+        }));
+    model
+}
+
+/// Step the controller at the given index, from the state reached by the earlier actions.
+fn step(model: &AbstractModel, actions: &mut Vec<Action>, controller: usize) {
+    let mut state = model.init_states().remove(0);
+    for action in actions.iter() {
+        state = model.next_state(&state, action.clone()).unwrap();
+    }
+    let mut enabled = Vec::new();
+    model.actions(&state, &mut enabled);
+    let action = enabled
+        .into_iter()
+        .find(|a| matches!(a, Action::ControllerStep(_, c) if *c == controller))
+        .unwrap();
+    actions.push(action);
+}
+
+// Nodes joining don't matter to the replicaset controller creating the first pod
+#[test_log::test]
+fn test_minimise_known_trace() {
+    let model = overlapping_rss(3);
+    let replicaset_controller = model
+        .controllers
+        .iter()
+        .position(|c| matches!(c, Controllers::ReplicaSet(_)))
+        .unwrap();
+    let mut actions = Vec::new();
+    for node in 0..3 {
+        step(&model, &mut actions, node);
+    }
+    step(&model, &mut actions, replicaset_controller);
+
+    let minimiser = Minimiser::new(model);
+    let trace = minimiser.minimise("no pods", &actions).unwrap();
+    assert_eq!(trace.actions.len(), 1);
+    assert!(matches!(
+        trace.actions[0],
+        Action::ControllerStep(_, c) if c == replicaset_controller
+    ));
+}
+
+// A trace that is only cut short isn't a counterexample to an eventually property
+#[test_log::test]
+fn test_minimise_eventually_cut_short() {
+    let model = overlapping_rss(1);
+    let mut actions = Vec::new();
+    step(&model, &mut actions, 0);
+
+    let minimiser = Minimiser::new(model);
+    assert!(minimiser.minimise("some pods", &actions).is_none());
+}
@@ -0,0 +1,138 @@
+use themelios::abstract_model::ControllerAction;
+use themelios::resources::{LabelSelector, Namespace, NamespacePhase, Pod, ReplicaSet};
+use themelios::state::resources::{ResourceError, DEFAULT_NAMESPACE};
+use themelios::state::{RawState, StateView};
+use themelios::utils;
+
+fn pod(namespace: &str, name: &str) -> Pod {
+    let mut metadata = utils::metadata(name.to_owned());
+    metadata.namespace = namespace.to_owned();
+    metadata.labels.insert("app".to_owned(), "test".to_owned());
+    Pod {
+        metadata,
+        ..Default::default()
+    }
+}
+
+fn replicaset(namespace: &str, name: &str) -> ReplicaSet {
+    let mut metadata = utils::metadata(name.to_owned());
+    metadata.namespace = namespace.to_owned();
+    ReplicaSet {
+        metadata,
+        ..Default::default()
+    }
+}
+
+fn namespace(name: &str) -> Namespace {
+    Namespace {
+        metadata: utils::metadata(name.to_owned()),
+        ..Default::default()
+    }
+}
+
+#[test_log::test]
+fn test_same_name_in_namespaces() {
+    let mut state = RawState::default().with_pods([pod("", "pod"), pod("other", "pod")]);
+    assert_eq!(state.pods.len(), 2);
+
+    // pods without a namespace go in the default one
+    let default = state.pods.get(DEFAULT_NAMESPACE, "pod").unwrap();
+    assert_eq!(default.metadata.namespace, DEFAULT_NAMESPACE);
+    assert!(state.pods.has("other", "pod"));
+    assert!(!state.pods.has("missing", "pod"));
+
+    let selector = LabelSelector {
+        match_labels: [("app".to_owned(), "test".to_owned())].into(),
+    };
+    assert_eq!(state.pods.matching("other", &selector).count(), 1);
+    assert_eq!(state.pods.in_namespace(DEFAULT_NAMESPACE).count(), 1);
+
+    assert_eq!(
+        state
+            .pods
+            .create(pod("other", "pod"), Default::default(), Default::default()),
+        Err(ResourceError::AlreadyExists)
+    );
+}
+
+#[test_log::test]
+fn test_delete_namespace() {
+    let mut finalized = replicaset("other", "finalized");
+    finalized.metadata.finalizers.push("test".to_owned());
+    let mut state = StateView::from(
+        RawState::default()
+            .with_namespaces([namespace(DEFAULT_NAMESPACE), namespace("other")])
+            .with_pods([pod("", "a"), pod("other", "a"), pod("other", "b")])
+            .with_replicasets([finalized, replicaset("other", "plain")]),
+    );
+    // namespaces are cluster-scoped
+    assert!(state.namespaces.has("", "other"));
+
+    let other = state.namespaces.get("", "other").unwrap().clone();
+    let revision = state.revision.clone().increment();
+    state
+        .apply_operation(
+            ControllerAction::DeleteNamespace(other),
+            revision,
+            Default::default(),
+        )
+        .unwrap();
+    let other = state.namespaces.get("", "other").unwrap();
+    assert_eq!(other.status.phase, NamespacePhase::Terminating);
+    // pods are left for their node to remove and finalizers hold up the rest
+    assert!(state
+        .pods
+        .in_namespace("other")
+        .all(|p| p.metadata.deletion_timestamp.is_some()));
+    assert!(!state.replicasets.has("other", "plain"));
+    let mut finalized = state.replicasets.get("other", "finalized").unwrap().clone();
+    assert!(finalized.metadata.deletion_timestamp.is_some());
+    let default = state.pods.get(DEFAULT_NAMESPACE, "a").unwrap();
+    assert!(default.metadata.deletion_timestamp.is_none());
+
+    // nothing new can be created in it
+    let revision = state.revision.clone().increment();
+    assert_eq!(
+        state.apply_operation(
+            ControllerAction::CreatePod(pod("other", "c")),
+            revision,
+            Default::default()
+        ),
+        Err(ResourceError::NamespaceTerminating.into())
+    );
+
+    // the namespace goes once everything in it has
+    finalized.metadata.finalizers.clear();
+    let revision = state.revision.clone().increment();
+    state
+        .apply_operation(
+            ControllerAction::UpdateReplicaSet(finalized),
+            revision,
+            Default::default(),
+        )
+        .unwrap();
+    let pods = state
+        .pods
+        .in_namespace("other")
+        .cloned()
+        .collect::<Vec<_>>();
+    for pod in pods {
+        assert!(state.namespaces.has("", "other"));
+        let revision = state.revision.clone().increment();
+        state
+            .apply_operation(
+                ControllerAction::HardDeletePod(pod),
+                revision,
+                Default::default(),
+            )
+            .unwrap();
+    }
+    assert!(!state.namespaces.has("", "other"));
+    assert!(state.pods.has(DEFAULT_NAMESPACE, "a"));
+
+    let revision = state.revision.clone().increment();
+    assert_eq!(
+        state.delete_namespace("other", revision, Default::default()),
+        Err(ResourceError::NotFound)
+    );
+}
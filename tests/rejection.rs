@@ -0,0 +1,110 @@
+use themelios::abstract_model::{Change, ControllerAction};
+use themelios::resources::Pod;
+use themelios::state::history::ConsistencySetup;
+use themelios::state::resources::{ResourceError, DEFAULT_NAMESPACE};
+use themelios::state::{RawState, Rejection, RejectionReason, State};
+use themelios::utils;
+
+fn write(state: &mut State, operation: ControllerAction) {
+    state.push_change(Change {
+        revision: state.max_revision(),
+        controller: Some(1),
+        operation,
+    });
+}
+
+#[test_log::test]
+fn test_rejected_writes() {
+    let pod = Pod {
+        metadata: utils::metadata("pod".to_owned()),
+        ..Default::default()
+    };
+    let mut state = State::new(
+        RawState::default().with_pods([pod.clone()]),
+        ConsistencySetup::Synchronous,
+    );
+    assert_eq!(state.rejection(), None);
+
+    write(&mut state, ControllerAction::CreatePod(pod.clone()));
+    assert_eq!(
+        state.rejection(),
+        Some(&Rejection {
+            controller: Some(1),
+            operation: "CreatePod",
+            reason: RejectionReason::Resource(ResourceError::AlreadyExists),
+        })
+    );
+
+    let mut other = pod.clone();
+    other.metadata.uid = "other".to_owned();
+    write(&mut state, ControllerAction::UpdatePod(other));
+    assert_eq!(
+        state.rejection().map(|r| r.reason),
+        Some(RejectionReason::Resource(ResourceError::UidMismatch))
+    );
+
+    let mut updated = state
+        .latest()
+        .pods
+        .get(DEFAULT_NAMESPACE, "pod")
+        .unwrap()
+        .clone();
+    updated.spec.node_name = Some("node".to_owned());
+    write(&mut state, ControllerAction::UpdatePod(updated));
+    assert_eq!(state.rejection(), None);
+
+    // the original pod is now behind the stored one
+    let mut stale = pod;
+    stale.spec.node_name = Some("other-node".to_owned());
+    write(&mut state, ControllerAction::UpdatePod(stale));
+    assert_eq!(
+        state.rejection().map(|r| r.reason),
+        Some(RejectionReason::Resource(
+            ResourceError::StaleResourceVersion
+        ))
+    );
+
+    let mut missing = Pod {
+        metadata: utils::metadata("missing".to_owned()),
+        ..Default::default()
+    };
+    write(&mut state, ControllerAction::HardDeletePod(missing.clone()));
+    assert_eq!(
+        state.rejection().map(|r| r.reason),
+        Some(RejectionReason::Resource(ResourceError::NotFound))
+    );
+
+    missing.metadata.name = "pod".to_owned();
+    write(&mut state, ControllerAction::HardDeletePod(missing));
+    assert_eq!(
+        state.rejection().map(|r| r.reason),
+        Some(RejectionReason::Resource(ResourceError::UidMismatch))
+    );
+}
+
+#[test_log::test]
+fn test_split_brain_writes_saturate() {
+    let mut state = State::new(RawState::default(), ConsistencySetup::Synchronous);
+    let fenced = |name: &str| ControllerAction::Fenced {
+        namespace: DEFAULT_NAMESPACE.to_owned(),
+        lease: "replicaset".to_owned(),
+        holder: "replicaset-0".to_owned(),
+        transitions: 0,
+        enforce: false,
+        action: Box::new(ControllerAction::CreatePod(Pod {
+            metadata: utils::metadata(name.to_owned()),
+            ..Default::default()
+        })),
+    };
+
+    // the lease isn't held, so the write is let through but recorded
+    write(&mut state, fenced("pod-0"));
+    assert_eq!(state.rejection(), None);
+    let recorded = state.split_brain_leases().clone();
+    assert_eq!(recorded, ["replicaset".to_owned()].into());
+
+    // further writes under the lease don't record anything new
+    write(&mut state, fenced("pod-1"));
+    assert_eq!(state.rejection(), None);
+    assert_eq!(*state.split_brain_leases(), recorded);
+}
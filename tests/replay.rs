@@ -0,0 +1,77 @@
+use stateright::Checker;
+use stateright::Expectation;
+use stateright::HasDiscoveries;
+use stateright::Model;
+use stateright::UniformChooser;
+use std::path::Path;
+use std::time::Duration;
+use themelios::model::OrchestrationModelCfg;
+use themelios::replay;
+use themelios::report::JsonReporter;
+use themelios::scenario::Scenario;
+use themelios::state::history::ConsistencySetup;
+
+// TestOverlappingRSs with two of each controller, which fails under resettable sessions
+fn model() -> OrchestrationModelCfg {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/scenarios/overlapping-rss.yaml");
+    let mut model = Scenario::load(&path).unwrap().into_model_cfg();
+    model.consistency_level = ConsistencySetup::ResettableSession;
+    model.schedulers *= 2;
+    model.nodes *= 2;
+    model.replicaset_controllers *= 2;
+    model.podgc_controllers *= 2;
+    model
+}
+
+#[test_log::test]
+fn test_replay_discovery() {
+    let report = std::env::temp_dir().join("themelios-test-replay-discovery.jsonl");
+    let mut reporter = JsonReporter::new(&report, model().into_abstract_model()).unwrap();
+    let checker = model()
+        .into_abstract_model()
+        .checker()
+        .finish_when(HasDiscoveries::AnyFailures)
+        .target_max_depth(100)
+        .timeout(Duration::from_secs(60))
+        .spawn_simulation(0, UniformChooser)
+        .report(&mut reporter);
+    let model = model().into_abstract_model();
+    // a counterexample, rather than an example of a sometimes property
+    let (property, discovery) = model
+        .properties()
+        .into_iter()
+        .filter(|p| matches!(p.expectation, Expectation::Always))
+        .find_map(|p| checker.discovery(p.name).map(|d| (p, d)))
+        .unwrap();
+    let fingerprint_path = discovery.encode();
+    let actions = discovery
+        .into_vec()
+        .into_iter()
+        .filter_map(|(_, action)| action)
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        replay::actions_from_fingerprints(&model, &fingerprint_path).unwrap(),
+        actions
+    );
+
+    // the JSON report has a step for each action of the discovery
+    let reported = std::fs::read_to_string(&report).unwrap();
+    let document = reported
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .find(|d| d["property"] == property.name)
+        .unwrap();
+    assert_eq!(document["steps"].as_array().unwrap().len(), actions.len());
+
+    let exported = serde_json::to_string(&actions).unwrap();
+    let file = std::env::temp_dir().join("themelios-test-replay-discovery.json");
+    std::fs::write(&file, exported).unwrap();
+    let loaded = replay::load_actions(&file, None).unwrap();
+    assert_eq!(loaded, actions);
+
+    let replay = replay::replay(&model, &loaded).unwrap();
+    assert_eq!(replay.steps.len(), actions.len());
+    let last_state = &replay.steps.last().unwrap().state;
+    assert!(!(property.condition)(&model, last_state));
+}
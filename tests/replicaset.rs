@@ -1,17 +1,31 @@
 use common::run;
 use common::test_table;
 use common::test_table_panic;
+use stateright::{Checker, Model};
 use std::collections::BTreeMap;
+use std::collections::BTreeSet;
 use stdext::function_name;
+use themelios::abstract_model::{Change, ControllerAction};
+use themelios::controller::util::new_controller_ref;
+use themelios::controller::{Controller, ReplicaSetController};
+use themelios::controller_properties::Properties;
+use themelios::model::ModelCfgError;
 use themelios::model::OrchestrationModelCfg;
+use themelios::resources::ConditionStatus;
 use themelios::resources::Container;
+use themelios::resources::CronJob;
 use themelios::resources::Metadata;
+use themelios::resources::Pod;
+use themelios::resources::PodCondition;
+use themelios::resources::PodConditionType;
+use themelios::resources::PodPhase;
 use themelios::resources::PodSpec;
 use themelios::resources::PodTemplateSpec;
 use themelios::resources::ReplicaSet;
 use themelios::resources::ReplicaSetSpec;
 use themelios::state::history::ConsistencySetup;
-use themelios::state::RawState;
+use themelios::state::history::ReadMode;
+use themelios::state::{RawState, State};
 use themelios::utils;
 
 mod common;
@@ -25,14 +39,25 @@ fn model(
     OrchestrationModelCfg {
         initial_state,
         consistency_level: consistency,
+        controller_consistency: BTreeMap::new(),
+        read_modes: BTreeMap::new(),
+        leader_election: BTreeSet::new(),
+        fencing: false,
+        quiescence_bound: None,
+        partial_order_reduction: false,
         schedulers: controllers,
         nodes: controllers,
         replicaset_controllers: controllers,
         deployment_controllers: 0,
         statefulset_controllers: 0,
+        daemonset_controllers: 0,
         job_controllers: 0,
+        cronjob_controllers: 0,
+        hpa_controllers: 0,
         podgc_controllers: controllers,
-        properties: Vec::new(),
+        garbage_collector_controllers: 0,
+        clock_ticks: 0,
+        properties: Properties::default(),
     }
 }
 
@@ -84,11 +109,14 @@ test_table! {
     test_spec_replicas_change,
     synchronous_1(ConsistencySetup::Synchronous, 1),
     synchronous_2(ConsistencySetup::Synchronous, 2),
+    synchronous_3(ConsistencySetup::Synchronous, 3),
     monotonic_session_1(ConsistencySetup::MonotonicSession, 1),
     monotonic_session_2(ConsistencySetup::MonotonicSession, 2),
     resettable_session_1(ConsistencySetup::ResettableSession, 1),
     optimistic_linear_1(ConsistencySetup::OptimisticLinear, 1),
     causal_1(ConsistencySetup::Causal, 1),
+    informer_1(ConsistencySetup::Informer, 1),
+    replicated_3_1(ConsistencySetup::Replicated { replicas: 3 }, 1),
 }
 
 test_table_panic! {
@@ -98,6 +126,74 @@ test_table_panic! {
     causal_2(ConsistencySetup::Causal, 2),
 }
 
+// TestSpecReplicasChange, checking that the controllers settle after each change
+fn test_spec_replicas_change_quiescence(
+    consistency: ConsistencySetup,
+    controllers: usize,
+) -> OrchestrationModelCfg {
+    let mut model = test_spec_replicas_change(consistency, controllers);
+    model.quiescence_bound = Some(30);
+    model
+}
+
+test_table! {
+    test_spec_replicas_change_quiescence,
+    synchronous_1(ConsistencySetup::Synchronous, 1),
+    synchronous_2(ConsistencySetup::Synchronous, 2),
+}
+
+// TestSpecReplicasChange alongside a cronjob, whose controller touches unrelated resources, finding
+// the same discoveries with and without partial-order reduction
+fn test_spec_replicas_change_reduced(consistency: ConsistencySetup) {
+    let explore = |partial_order_reduction: bool| {
+        let mut model = test_spec_replicas_change(consistency.clone(), 1);
+        model.schedulers = 0;
+        model.nodes = 0;
+        model.podgc_controllers = 0;
+        let mut cronjob = CronJob {
+            metadata: utils::metadata("test-cronjob".to_owned()),
+            ..Default::default()
+        };
+        cronjob.spec.schedule = "* * * * *".to_owned();
+        model.initial_state.set_cronjobs([cronjob]);
+        model.cronjob_controllers = 1;
+        model.partial_order_reduction = partial_order_reduction;
+        let checker = model
+            .into_abstract_model()
+            .checker()
+            .target_max_depth(10)
+            .spawn_bfs()
+            .join();
+        let discoveries = checker.discoveries().into_keys().collect::<BTreeSet<_>>();
+        (discoveries, checker.unique_state_count())
+    };
+    let (full, full_states) = explore(false);
+    let (reduced, reduced_states) = explore(true);
+    assert_eq!(full, reduced);
+    // the cronjob controller isn't stepped in between the replicaset's writes
+    assert!(reduced_states < full_states);
+}
+
+#[test_log::test]
+fn test_spec_replicas_change_reduced_synchronous() {
+    test_spec_replicas_change_reduced(ConsistencySetup::Synchronous)
+}
+
+#[test_log::test]
+fn test_spec_replicas_change_reduced_resettable_session() {
+    test_spec_replicas_change_reduced(ConsistencySetup::ResettableSession)
+}
+
+#[test_log::test]
+fn test_spec_replicas_change_reduced_causal() {
+    test_spec_replicas_change_reduced(ConsistencySetup::Causal)
+}
+
+#[test_log::test]
+fn test_spec_replicas_change_reduced_informer() {
+    test_spec_replicas_change_reduced(ConsistencySetup::Informer)
+}
+
 // TestOverlappingRSs
 fn test_overlapping_rss(
     consistency: ConsistencySetup,
@@ -109,6 +205,33 @@ fn test_overlapping_rss(
     model([replicaset_1, replicaset_2], consistency, controllers)
 }
 
+// TestOverlappingRSs, whose pods are created with names and uids from the revisions they are
+// written at, finding the same discoveries with and without partial-order reduction
+fn test_overlapping_rss_reduced(consistency: ConsistencySetup, controllers: usize) {
+    let explore = |partial_order_reduction: bool| {
+        let mut model = test_overlapping_rss(consistency.clone(), controllers);
+        model.partial_order_reduction = partial_order_reduction;
+        let checker = model
+            .into_abstract_model()
+            .checker()
+            .target_max_depth(10)
+            .spawn_bfs()
+            .join();
+        checker.discoveries().into_keys().collect::<BTreeSet<_>>()
+    };
+    assert_eq!(explore(false), explore(true));
+}
+
+#[test_log::test]
+fn test_overlapping_rss_reduced_synchronous() {
+    test_overlapping_rss_reduced(ConsistencySetup::Synchronous, 1)
+}
+
+#[test_log::test]
+fn test_overlapping_rss_reduced_resettable_session() {
+    test_overlapping_rss_reduced(ConsistencySetup::ResettableSession, 2)
+}
+
 test_table! {
     test_overlapping_rss,
     synchronous_1(ConsistencySetup::Synchronous, 1),
@@ -118,6 +241,7 @@ test_table! {
     resettable_session_1(ConsistencySetup::ResettableSession, 1),
     optimistic_linear_1(ConsistencySetup::OptimisticLinear, 1),
     causal_1(ConsistencySetup::Causal, 1),
+    replicated_3_1(ConsistencySetup::Replicated { replicas: 3 }, 1),
 }
 
 test_table_panic! {
@@ -127,6 +251,118 @@ test_table_panic! {
     causal_2(ConsistencySetup::Causal, 2),
 }
 
+// TestOverlappingRSs, with every controller reading the latest state regardless of the consistency
+// level of the datastore
+fn test_overlapping_rss_synchronous_controllers(
+    consistency: ConsistencySetup,
+    controllers: usize,
+) -> OrchestrationModelCfg {
+    let mut model = test_overlapping_rss(consistency, controllers);
+    for controller in ["node", "scheduler", "replicaset", "podgc"] {
+        model
+            .controller_consistency
+            .insert(controller.to_owned(), ConsistencySetup::Synchronous);
+    }
+    model
+}
+
+test_table! {
+    test_overlapping_rss_synchronous_controllers,
+    resettable_session_2(ConsistencySetup::ResettableSession, 2),
+    causal_2(ConsistencySetup::Causal, 2),
+}
+
+#[test]
+fn test_controller_consistency_cannot_override() {
+    let mut model = test_overlapping_rss(ConsistencySetup::Synchronous, 1);
+    model
+        .controller_consistency
+        .insert("replicaset".to_owned(), ConsistencySetup::Causal);
+    assert!(matches!(
+        model.try_into_abstract_model(),
+        Err(ModelCfgError::ConsistencyOverride { .. })
+    ));
+}
+
+#[test]
+fn test_unknown_controller() {
+    let mut model = test_overlapping_rss(ConsistencySetup::Synchronous, 1);
+    model
+        .read_modes
+        .insert("replicasets".to_owned(), ReadMode::Latest);
+    assert!(matches!(
+        model.try_into_abstract_model(),
+        Err(ModelCfgError::UnknownController(name)) if name == "replicasets"
+    ));
+}
+
+#[test]
+fn test_no_replicas() {
+    let model = test_overlapping_rss(ConsistencySetup::Replicated { replicas: 0 }, 1);
+    assert!(matches!(
+        model.try_into_abstract_model(),
+        Err(ModelCfgError::NoReplicas)
+    ));
+    assert!("replicated-0".parse::<ConsistencySetup>().is_err());
+}
+
+// TestOverlappingRSs on a resettable session datastore, where only the read mode of the
+// controllers decides whether a restarted controller can read from before its last write
+fn test_overlapping_rss_read_mode(
+    read_mode: ReadMode,
+    controllers: usize,
+) -> OrchestrationModelCfg {
+    let mut model = test_overlapping_rss(ConsistencySetup::ResettableSession, controllers);
+    for controller in ["node", "scheduler", "replicaset", "podgc"] {
+        model.read_modes.insert(controller.to_owned(), read_mode);
+    }
+    model
+}
+
+test_table! {
+    test_overlapping_rss_read_mode,
+    session_monotonic_2(ReadMode::SessionMonotonic, 2),
+}
+
+test_table_panic! {
+    test_overlapping_rss_read_mode,
+    any_valid_2(ReadMode::AnyValid, 2),
+}
+
+// TestOverlappingRSs, with duplicate controllers running under leader election
+fn test_overlapping_rss_leader_elected(
+    consistency: ConsistencySetup,
+    controllers: usize,
+) -> OrchestrationModelCfg {
+    let mut model = test_overlapping_rss(consistency, controllers);
+    for controller in ["scheduler", "replicaset", "podgc"] {
+        model.leader_election.insert(controller.to_owned());
+    }
+    model
+}
+
+test_table_panic! {
+    test_overlapping_rss_leader_elected,
+    resettable_session_2(ConsistencySetup::ResettableSession, 2),
+}
+
+// TestOverlappingRSs, with duplicate controllers running under leader election and their writes
+// fenced by the lease
+fn test_overlapping_rss_leader_elected_fenced(
+    consistency: ConsistencySetup,
+    controllers: usize,
+) -> OrchestrationModelCfg {
+    let mut model = test_overlapping_rss_leader_elected(consistency, controllers);
+    model.fencing = true;
+    model
+}
+
+test_table! {
+    test_overlapping_rss_leader_elected_fenced,
+    synchronous_2(ConsistencySetup::Synchronous, 2),
+    resettable_session_2(ConsistencySetup::ResettableSession, 2),
+}
+
 // TESTS TO DO
 // TestAdoption
 // TestDeletingAndFailedPods
@@ -140,3 +376,58 @@ test_table_panic! {
 // TestReplicaSetsAppsV1DefaultGCPolicy
 //
 // TestRSSelectorImmutability: ignored as just tests API server
+
+/// Step the replicaset controller on the latest state until it has nothing left to do.
+fn step_until_done(state: &mut State) {
+    let mut local = Default::default();
+    for _ in 0..10 {
+        let Some(operation) = ReplicaSetController.step(&state.latest(), &mut local) else {
+            return;
+        };
+        state.push_change(Change {
+            revision: state.max_revision(),
+            controller: Some(0),
+            operation: ControllerAction::from(operation),
+        });
+        assert_eq!(state.rejection(), None);
+    }
+    panic!("replicaset controller didn't settle");
+}
+
+#[test_log::test]
+fn test_min_ready_seconds() {
+    let mut rs = new_replicaset("min-ready", "", 1);
+    rs.spec.min_ready_seconds = 30;
+    let mut pod = Pod {
+        metadata: utils::metadata("min-ready-pod".to_owned()),
+        spec: rs.spec.template.spec.clone(),
+        ..Default::default()
+    };
+    pod.metadata.labels = rs.spec.template.metadata.labels.clone();
+    pod.metadata.owner_references = vec![new_controller_ref(&rs.metadata, &ReplicaSet::GVK)];
+    pod.status.phase = PodPhase::Running;
+    pod.status.conditions.push(PodCondition {
+        status: ConditionStatus::True,
+        r#type: PodConditionType::Ready,
+        last_probe_time: None,
+        last_transition_time: Some(utils::now()),
+        message: None,
+        reason: None,
+    });
+    let mut state = State::new(
+        RawState::default().with_replicasets([rs]).with_pods([pod]),
+        ConsistencySetup::Synchronous,
+    );
+
+    // the pod has only just become ready so isn't available yet
+    step_until_done(&mut state);
+    let rs = state.latest().replicasets.iter().next().unwrap().clone();
+    assert_eq!(rs.status.ready_replicas, 1);
+    assert_eq!(rs.status.available_replicas, 0);
+
+    // once the clock has moved past min_ready_seconds the pod counts as available
+    state.tick_clock();
+    step_until_done(&mut state);
+    let rs = state.latest().replicasets.iter().next().unwrap().clone();
+    assert_eq!(rs.status.available_replicas, 1);
+}
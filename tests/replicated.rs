@@ -0,0 +1,216 @@
+use themelios::abstract_model::{Change, ControllerAction};
+use themelios::resources::{Pod, ReplicaSet, ReplicaSetSpec};
+use themelios::state::history::{ConsistencySetup, ReadMode};
+use themelios::state::resources::DEFAULT_NAMESPACE;
+use themelios::state::{RawState, RejectionReason, State};
+use themelios::utils;
+
+fn state() -> State {
+    State::new(
+        RawState::default(),
+        ConsistencySetup::Replicated { replicas: 3 },
+    )
+}
+
+fn pod(name: &str) -> Pod {
+    Pod {
+        metadata: utils::metadata(name.to_owned()),
+        ..Default::default()
+    }
+}
+
+/// Write through the replica that the controller is connected to.
+fn write(state: &mut State, controller: usize, operation: ControllerAction) {
+    state.push_change(Change {
+        revision: state.max_revision(),
+        controller: Some(controller),
+        operation,
+    });
+}
+
+#[test_log::test]
+fn test_partition_minority() {
+    let mut state = state();
+    assert_eq!(state.partitionable_replicas(), vec![0, 1, 2]);
+
+    state.partition(0);
+    assert_eq!(state.partitioned_replicas(), vec![0]);
+    // the minority can't commit writes
+    write(&mut state, 0, ControllerAction::CreatePod(pod("pod-0")));
+    assert_eq!(
+        state.rejection().map(|r| r.reason),
+        Some(RejectionReason::NoQuorum)
+    );
+    // or serve quorum reads
+    assert!(state.revisions(0, ReadMode::Latest, None).is_empty());
+
+    // but the majority can
+    write(&mut state, 1, ControllerAction::CreatePod(pod("pod-1")));
+    assert_eq!(state.rejection(), None);
+    assert_eq!(
+        state.revisions(1, ReadMode::Latest, None),
+        vec![state.max_revision()]
+    );
+    // and only the majority can catch up
+    assert_eq!(state.lagging_replicas(), vec![1, 2]);
+}
+
+#[test_log::test]
+fn test_partition_majority() {
+    let mut state = state();
+    state.partition(1);
+    state.partition(2);
+    assert_eq!(state.partitioned_replicas(), vec![1, 2]);
+    // the last connected replica is left on its own
+    assert!(state.partitionable_replicas().is_empty());
+
+    // so the partitioned replicas form the majority
+    write(&mut state, 0, ControllerAction::CreatePod(pod("pod-0")));
+    assert_eq!(
+        state.rejection().map(|r| r.reason),
+        Some(RejectionReason::NoQuorum)
+    );
+    write(&mut state, 2, ControllerAction::CreatePod(pod("pod-2")));
+    assert_eq!(state.rejection(), None);
+    assert_eq!(state.lagging_replicas(), vec![1, 2]);
+}
+
+#[test_log::test]
+fn test_heal() {
+    let mut state = state();
+    state.partition(0);
+    write(&mut state, 1, ControllerAction::CreatePod(pod("pod-1")));
+    assert_eq!(state.rejection(), None);
+
+    state.heal(0);
+    assert!(state.partitioned_replicas().is_empty());
+    // the healed replica catches up on what it missed
+    assert_eq!(state.lagging_replicas(), vec![0, 1, 2]);
+    state.replicate(0);
+    assert_eq!(
+        state.revisions(0, ReadMode::AnyValid, None),
+        vec![state.max_revision()]
+    );
+
+    write(&mut state, 0, ControllerAction::CreatePod(pod("pod-0")));
+    assert_eq!(state.rejection(), None);
+    assert_eq!(state.latest().pods.len(), 2);
+}
+
+// Being able to scale whilst partitioned, from the README: a controller on the majority side acts
+// on a scale up whilst the replica of a controller on the minority side keeps serving the old
+// state.
+#[test_log::test]
+fn test_scale_whilst_partitioned() {
+    let replicaset = ReplicaSet {
+        metadata: utils::metadata("rs".to_owned()),
+        spec: ReplicaSetSpec {
+            replicas: Some(1),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut state = State::new(
+        RawState::default().with_replicasets([replicaset]),
+        ConsistencySetup::Replicated { replicas: 3 },
+    );
+    let initial = state.max_revision();
+    state.partition(0);
+
+    // a client scales the replicaset up
+    let mut scaled = state
+        .latest()
+        .replicasets
+        .get(DEFAULT_NAMESPACE, "rs")
+        .unwrap()
+        .clone();
+    scaled.spec.replicas = Some(2);
+    state.push_change(Change {
+        revision: state.max_revision(),
+        controller: None,
+        operation: ControllerAction::UpdateReplicaSet(scaled),
+    });
+    assert_eq!(state.rejection(), None);
+
+    // the controller on the minority side can only see the old state and can't act on it
+    assert_eq!(state.revisions(0, ReadMode::AnyValid, None), vec![initial]);
+    write(&mut state, 0, ControllerAction::CreatePod(pod("rs-a")));
+    assert_eq!(
+        state.rejection().map(|r| r.reason),
+        Some(RejectionReason::NoQuorum)
+    );
+
+    // whilst the one on the majority side sees the scale up and creates the pod
+    state.replicate(1);
+    let revisions = state.revisions(1, ReadMode::AnyValid, None);
+    assert_eq!(revisions.len(), 1);
+    let view = state.view_at(&revisions[0]);
+    assert_eq!(
+        view.replicasets
+            .get(DEFAULT_NAMESPACE, "rs")
+            .unwrap()
+            .spec
+            .replicas,
+        Some(2)
+    );
+    write(&mut state, 1, ControllerAction::CreatePod(pod("rs-b")));
+    assert_eq!(state.rejection(), None);
+    assert_eq!(state.latest().pods.len(), 1);
+}
+
+// Whether scaling whilst partitioned converges once the partition heals: after the healed replica
+// catches up, every controller reads the scale up and the one that was cut off can act on it.
+#[test_log::test]
+fn test_converge_after_heal() {
+    let replicaset = ReplicaSet {
+        metadata: utils::metadata("rs".to_owned()),
+        spec: ReplicaSetSpec {
+            replicas: Some(1),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut state = State::new(
+        RawState::default().with_replicasets([replicaset]),
+        ConsistencySetup::Replicated { replicas: 3 },
+    );
+    state.partition(0);
+
+    let mut scaled = state
+        .latest()
+        .replicasets
+        .get(DEFAULT_NAMESPACE, "rs")
+        .unwrap()
+        .clone();
+    scaled.spec.replicas = Some(2);
+    state.push_change(Change {
+        revision: state.max_revision(),
+        controller: None,
+        operation: ControllerAction::UpdateReplicaSet(scaled),
+    });
+    assert_eq!(state.rejection(), None);
+
+    state.heal(0);
+    while let Some(replica) = state.lagging_replicas().first().copied() {
+        state.replicate(replica);
+    }
+
+    // every replica has converged on the scale up
+    for controller in 0..3 {
+        let revisions = state.revisions(controller, ReadMode::AnyValid, None);
+        assert_eq!(revisions, vec![state.max_revision()]);
+        let view = state.view_at(&revisions[0]);
+        assert_eq!(
+            view.replicasets
+                .get(DEFAULT_NAMESPACE, "rs")
+                .unwrap()
+                .spec
+                .replicas,
+            Some(2)
+        );
+    }
+    // so the controller that was cut off can now create the pod
+    write(&mut state, 0, ControllerAction::CreatePod(pod("rs-a")));
+    assert_eq!(state.rejection(), None);
+    assert_eq!(state.latest().pods.len(), 1);
+}
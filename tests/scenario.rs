@@ -0,0 +1,119 @@
+use common::run;
+use common::test_table;
+use common::test_table_panic;
+use std::path::Path;
+use stdext::function_name;
+use themelios::model::OrchestrationModelCfg;
+use themelios::scenario::Scenario;
+use themelios::scenario::ScenarioError;
+use themelios::state::history::ConsistencySetup;
+use themelios::state::resources::DEFAULT_NAMESPACE;
+
+mod common;
+
+fn model(path: &str, consistency: ConsistencySetup, controllers: usize) -> OrchestrationModelCfg {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/scenarios")
+        .join(path);
+    let mut model = Scenario::load(&path).unwrap().into_model_cfg();
+    // scale each of the configured controllers up
+    model.consistency_level = consistency;
+    model.schedulers *= controllers;
+    model.nodes *= controllers;
+    model.replicaset_controllers *= controllers;
+    model.deployment_controllers *= controllers;
+    model.statefulset_controllers *= controllers;
+    model.daemonset_controllers *= controllers;
+    model.job_controllers *= controllers;
+    model.cronjob_controllers *= controllers;
+    model.hpa_controllers *= controllers;
+    model.podgc_controllers *= controllers;
+    model.garbage_collector_controllers *= controllers;
+    model
+}
+
+#[test]
+fn test_load_scenario_header() {
+    let scenario = Scenario::from_yaml(
+        "kind: Scenario\nconsistency: monotonic-session\ncontrollers:\n  nodes: 2\n  deployments: 1\n",
+    )
+    .unwrap();
+    let model = scenario.into_model_cfg();
+    assert_eq!(model.consistency_level, ConsistencySetup::MonotonicSession);
+    assert_eq!(model.nodes, 2);
+    assert_eq!(model.deployment_controllers, 1);
+    assert_eq!(model.replicaset_controllers, 0);
+}
+
+#[test]
+fn test_load_scenario_default_namespace() {
+    let scenario = Scenario::from_yaml(
+        "kind: Scenario\ncontrollers:\n  nodes: 1\n---\nkind: Pod\nmetadata:\n  name: pod\nspec: {}\n---\nkind: Pod\nmetadata:\n  name: other\n  namespace: other\nspec: {}\n---\nkind: Node\nmetadata:\n  name: node-0\nspec: {}\n",
+    )
+    .unwrap();
+    let pods = scenario.initial_state.pods.to_vec();
+    assert_eq!(pods.len(), 2);
+    assert!(pods
+        .iter()
+        .any(|p| p.metadata.name == "pod" && p.metadata.namespace == DEFAULT_NAMESPACE));
+    assert!(pods
+        .iter()
+        .any(|p| p.metadata.name == "other" && p.metadata.namespace == "other"));
+    // nodes are cluster-scoped so don't get a namespace
+    let nodes = scenario.initial_state.nodes.to_vec();
+    assert_eq!(nodes[0].metadata.namespace, "");
+}
+
+#[test]
+fn test_load_scenario_node_names() {
+    let header = "kind: Scenario\ncontrollers:\n  nodes: 13\n";
+    for name in ["node-0", "node-12"] {
+        let yaml = format!("{header}---\nkind: Node\nmetadata:\n  name: {name}\nspec: {{}}\n");
+        assert!(Scenario::from_yaml(&yaml).is_ok(), "{name}");
+    }
+    for name in ["node", "node-", "node-a", "worker-0", "node-01", "node-+1"] {
+        let yaml = format!("{header}---\nkind: Node\nmetadata:\n  name: {name}\nspec: {{}}\n");
+        assert!(
+            matches!(
+                Scenario::from_yaml(&yaml),
+                Err(ScenarioError::InvalidNodeName(_, n)) if n == name
+            ),
+            "{name}"
+        );
+    }
+}
+
+#[test]
+fn test_load_scenario_unserved_node() {
+    // the header can come after the nodes
+    let yaml = "kind: Node\nmetadata:\n  name: node-2\nspec: {}\n---\nkind: Scenario\ncontrollers:\n  nodes: 2\n";
+    assert!(matches!(
+        Scenario::from_yaml(yaml),
+        Err(ScenarioError::UnservedNode(n, 2)) if n == "node-2"
+    ));
+    let yaml = "kind: Node\nmetadata:\n  name: node-1\nspec: {}\n---\nkind: Scenario\ncontrollers:\n  nodes: 2\n";
+    assert!(Scenario::from_yaml(yaml).is_ok());
+}
+
+// TestOverlappingRSs, from a manifest
+fn test_overlapping_rss_scenario(
+    consistency: ConsistencySetup,
+    controllers: usize,
+) -> OrchestrationModelCfg {
+    model("overlapping-rss.yaml", consistency, controllers)
+}
+
+test_table! {
+    test_overlapping_rss_scenario,
+    synchronous_1(ConsistencySetup::Synchronous, 1),
+    synchronous_2(ConsistencySetup::Synchronous, 2),
+    monotonic_session_1(ConsistencySetup::MonotonicSession, 1),
+    resettable_session_1(ConsistencySetup::ResettableSession, 1),
+    causal_1(ConsistencySetup::Causal, 1),
+}
+
+test_table_panic! {
+    test_overlapping_rss_scenario,
+    resettable_session_2(ConsistencySetup::ResettableSession, 2),
+    causal_2(ConsistencySetup::Causal, 2),
+}
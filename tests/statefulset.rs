@@ -2,7 +2,9 @@ use common::run;
 use common::test_table;
 use common::test_table_panic;
 use std::collections::BTreeMap;
+use std::collections::BTreeSet;
 use stdext::function_name;
+use themelios::controller_properties::Properties;
 use themelios::model::OrchestrationModelCfg;
 use themelios::resources::Container;
 use themelios::resources::Metadata;
@@ -27,14 +29,25 @@ fn model(
     OrchestrationModelCfg {
         initial_state,
         consistency_level: consistency,
+        controller_consistency: BTreeMap::new(),
+        read_modes: BTreeMap::new(),
+        leader_election: BTreeSet::new(),
+        fencing: false,
+        quiescence_bound: None,
+        partial_order_reduction: false,
         schedulers: controllers,
         nodes,
         replicaset_controllers: 0,
         deployment_controllers: 0,
         statefulset_controllers: controllers,
+        daemonset_controllers: 0,
         job_controllers: 0,
+        cronjob_controllers: 0,
+        hpa_controllers: 0,
         podgc_controllers: controllers,
-        properties: Vec::new(),
+        garbage_collector_controllers: 0,
+        clock_ticks: 0,
+        properties: Properties::default(),
     }
 }
 
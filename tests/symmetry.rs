@@ -0,0 +1,152 @@
+use stateright::{Checker, Model, Representative};
+use std::collections::{BTreeMap, BTreeSet};
+use themelios::abstract_model::{Change, ControllerAction};
+use themelios::controller::{ControllerStates, NodeControllerState};
+use themelios::controller_properties::Properties;
+use themelios::model::OrchestrationModelCfg;
+use themelios::resources::{
+    ContainerState, ContainerStateRunning, Node, Pod, ReplicaSet, ReplicaSetSpec, LABEL_HOSTNAME,
+};
+use themelios::state::history::ConsistencySetup;
+use themelios::state::resources::DEFAULT_NAMESPACE;
+use themelios::state::{RawState, State};
+use themelios::utils;
+
+fn node(name: &str) -> Node {
+    let mut metadata = utils::metadata(name.to_owned());
+    metadata
+        .labels
+        .insert(LABEL_HOSTNAME.to_owned(), name.to_owned());
+    Node {
+        metadata,
+        ..Default::default()
+    }
+}
+
+/// Two nodes with a pod running on one of them, and a rejected write from that node's controller.
+fn state_with_pod_on(node_index: usize) -> State {
+    let mut pod = Pod {
+        metadata: utils::metadata("pod".to_owned()),
+        ..Default::default()
+    };
+    pod.spec.node_name = Some(format!("node-{node_index}"));
+    let mut state = State::new(
+        RawState::default()
+            .with_nodes([node("node-0"), node("node-1")])
+            .with_pods([pod]),
+        ConsistencySetup::Synchronous,
+    );
+    for i in 0..2 {
+        let mut node_state = NodeControllerState::default();
+        if i == node_index {
+            node_state.running.insert(
+                "pod".to_owned(),
+                ContainerState::Running(ContainerStateRunning { started_at: None }),
+            );
+        }
+        state.add_controller(ControllerStates::Node(node_state));
+    }
+    state.add_controller(ControllerStates::ReplicaSet(Default::default()));
+
+    let missing = Pod {
+        metadata: utils::metadata("missing".to_owned()),
+        ..Default::default()
+    };
+    state.push_change(Change {
+        revision: state.max_revision(),
+        controller: Some(node_index),
+        operation: ControllerAction::UpdatePod(missing),
+    });
+    assert_eq!(state.rejection().unwrap().controller, Some(node_index));
+    state
+}
+
+#[test_log::test]
+fn test_representative_mirrored_nodes() {
+    let on_first = state_with_pod_on(0);
+    let on_second = state_with_pod_on(1);
+    assert_ne!(on_first, on_second);
+
+    let representative = on_first.representative();
+    assert_eq!(representative, on_second.representative());
+
+    // the pod moved along with its node and the controller running it
+    let view = representative.latest();
+    let pod = view.pods.get(DEFAULT_NAMESPACE, "pod").unwrap();
+    let node_index = match pod.spec.node_name.as_deref() {
+        Some("node-0") => 0,
+        Some("node-1") => 1,
+        other => panic!("pod bound to unexpected node {other:?}"),
+    };
+    let ControllerStates::Node(node_state) = representative.get_controller(node_index) else {
+        panic!("expected a node controller");
+    };
+    assert!(node_state.running.contains_key("pod"));
+    assert_eq!(
+        representative.rejection().unwrap().controller,
+        Some(node_index)
+    );
+    let node = view.nodes.get("", &format!("node-{node_index}")).unwrap();
+    assert_eq!(
+        node.metadata.labels.get(LABEL_HOSTNAME),
+        Some(&format!("node-{node_index}"))
+    );
+    assert_eq!(view.nodes.len(), 2);
+}
+
+fn model() -> OrchestrationModelCfg {
+    let replicaset = ReplicaSet {
+        metadata: utils::metadata("rs".to_owned()),
+        spec: ReplicaSetSpec {
+            replicas: Some(2),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    OrchestrationModelCfg {
+        initial_state: RawState::default().with_replicasets([replicaset]),
+        consistency_level: ConsistencySetup::Synchronous,
+        controller_consistency: BTreeMap::new(),
+        read_modes: BTreeMap::new(),
+        leader_election: BTreeSet::new(),
+        fencing: false,
+        quiescence_bound: None,
+        partial_order_reduction: false,
+        schedulers: 1,
+        nodes: 2,
+        replicaset_controllers: 2,
+        deployment_controllers: 0,
+        statefulset_controllers: 0,
+        daemonset_controllers: 0,
+        job_controllers: 0,
+        cronjob_controllers: 0,
+        hpa_controllers: 0,
+        podgc_controllers: 0,
+        garbage_collector_controllers: 0,
+        clock_ticks: 0,
+        properties: Properties::default(),
+    }
+}
+
+#[test_log::test]
+fn test_symmetry_dfs() {
+    let full = model()
+        .into_abstract_model()
+        .checker()
+        .target_max_depth(8)
+        .spawn_dfs()
+        .join();
+    let reduced = model()
+        .into_abstract_model()
+        .checker()
+        .target_max_depth(8)
+        .symmetry()
+        .spawn_dfs()
+        .join();
+
+    // the same properties fail, whilst nodes joining in either order are only explored once
+    let full_discoveries = full.discoveries().into_keys().collect::<BTreeSet<_>>();
+    let reduced_discoveries = reduced.discoveries().into_keys().collect::<BTreeSet<_>>();
+    assert_eq!(full_discoveries, reduced_discoveries);
+    assert!(reduced.unique_state_count() < full.unique_state_count());
+}
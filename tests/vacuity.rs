@@ -0,0 +1,62 @@
+use stateright::Checker;
+use stateright::Expectation;
+use stateright::Model;
+use std::path::Path;
+use themelios::implication;
+use themelios::scenario::Scenario;
+use themelios::utils::LogicalBoolExt;
+
+#[test_log::test]
+fn test_vacuity() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/scenarios/overlapping-rss.yaml");
+    let mut model = Scenario::load(&path).unwrap().into_model_cfg();
+    model.add_implication(
+        Expectation::Always,
+        "test: pods with an empty name don't exist",
+        implication!(
+            |_model, state| {
+                let s = state.latest();
+                s.pods.iter().any(|p| p.metadata.name.is_empty())
+            },
+            // only checked where the antecedent holds, which it never does
+            |_model, _state| false,
+        ),
+    );
+    model.add_implication(
+        Expectation::Always,
+        "test: pods have a name",
+        implication!(
+            |_model, state| {
+                let s = state.latest();
+                !s.pods.is_empty()
+            },
+            |_model, state| {
+                let s = state.latest();
+                s.pods.iter().all(|p| !p.metadata.name.is_empty())
+            },
+        ),
+    );
+    // implies on its own doesn't make a property an implication
+    model.add_property(
+        Expectation::Always,
+        "test: pods with an empty name don't exist, without an antecedent",
+        |_model, state| {
+            let s = state.latest();
+            s.pods
+                .iter()
+                .all(|p| p.metadata.name.is_empty().implies(false))
+        },
+    );
+    let model = model.into_abstract_model();
+    let vacuity = model.vacuity.clone();
+    let checker = model.checker().target_max_depth(4).spawn_bfs().join();
+    assert!(checker.discoveries().is_empty());
+
+    let vacuous = vacuity.vacuous();
+    assert!(vacuous.contains("test: pods with an empty name don't exist"));
+    // pods get created so the antecedent held
+    assert!(!vacuous.contains("test: pods have a name"));
+    // properties that aren't implications are never vacuous
+    assert!(!vacuous.contains("test: pods with an empty name don't exist, without an antecedent"));
+    assert!(!vacuous.contains("all resources have unique names"));
+}